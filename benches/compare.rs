@@ -0,0 +1,118 @@
+//! `cargo bench`: builds every program in benches/programs with
+//! `bolt --release` and its hand-written C twin with the same gcc flags,
+//! checks that both print the same thing, and reports the fastest of a few
+//! runs of each with the Bolt/C ratio. `cargo bench -- fib` runs only the
+//! programs whose name contains "fib".
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+
+// The flags `bolt --release` passes to gcc, so only the C source differs
+const GCC_FLAGS: [&str; 2] = ["-O2", "-DNDEBUG"];
+
+fn programs(dir: &Path, filters: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("Can't read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bolt"))
+        .map(|path| path.file_stem().unwrap().to_string_lossy().to_string())
+        .filter(|name| filters.is_empty() || filters.iter().any(|f| name.contains(f.as_str())))
+        .collect();
+    names.sort();
+    names
+}
+
+fn build(command: &mut Command, what: &str) {
+    let output = command
+        .output()
+        .unwrap_or_else(|e| panic!("Can't start the build of {}: {}", what, e));
+    if !output.status.success() {
+        panic!(
+            "Building {} failed:\n{}{}",
+            what,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+// The fastest of RUNS runs, and what the program printed
+fn time(executable: &Path) -> (Duration, String) {
+    let mut fastest = Duration::MAX;
+    let mut printed = String::new();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let output = Command::new(executable)
+            .output()
+            .unwrap_or_else(|e| panic!("Can't run {}: {}", executable.display(), e));
+        fastest = fastest.min(start.elapsed());
+        if !output.status.success() {
+            panic!("{} exited with {}", executable.display(), output.status);
+        }
+        printed = String::from_utf8_lossy(&output.stdout).to_string();
+    }
+    (fastest, printed)
+}
+
+fn main() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dir = root.join("benches").join("programs");
+    // cargo passes `--bench`; anything else is a name filter
+    let filters: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+
+    println!(
+        "{:<12} {:>10} {:>10} {:>8}",
+        "program", "bolt (ms)", "C (ms)", "bolt/C"
+    );
+    for name in programs(&dir, &filters) {
+        let c_source = dir.join(format!("{}.c", name));
+        if !c_source.exists() {
+            panic!("{}.bolt has no {}.c to compare with", name, name);
+        }
+
+        let bolt_name = format!("bench_{}", name);
+        build(
+            Command::new(env!("CARGO_BIN_EXE_bolt"))
+                .arg(dir.join(format!("{}.bolt", name)))
+                .args(["--release", "-o", &bolt_name])
+                .current_dir(root),
+            &format!("{}.bolt", name),
+        );
+        let bolt_executable: PathBuf = root.join("out").join("release").join(&bolt_name);
+        let c_executable = root
+            .join("out")
+            .join("release")
+            .join(format!("bench_{}_c", name));
+        build(
+            Command::new("gcc")
+                .arg(&c_source)
+                .arg("-o")
+                .arg(&c_executable)
+                .args(GCC_FLAGS),
+            &format!("{}.c", name),
+        );
+
+        let (bolt_time, bolt_output) = time(&bolt_executable);
+        let (c_time, c_output) = time(&c_executable);
+        if bolt_output != c_output {
+            panic!(
+                "{}: the Bolt program printed\n{}\nbut the C program printed\n{}",
+                name, bolt_output, c_output
+            );
+        }
+        println!(
+            "{:<12} {:>10.1} {:>10.1} {:>7.2}x",
+            name,
+            bolt_time.as_secs_f64() * 1000.0,
+            c_time.as_secs_f64() * 1000.0,
+            bolt_time.as_secs_f64() / c_time.as_secs_f64()
+        );
+    }
+}
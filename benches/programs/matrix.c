@@ -0,0 +1,27 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+int main(void) {
+    int n = 300;
+    int* a = malloc(sizeof(int) * n * n);
+    int* b = malloc(sizeof(int) * n * n);
+    for (int i = 0; i < n * n; i++) {
+        a[i] = (i * 3 + 7) % 13;
+        b[i] = (i * 5 + 7) % 13;
+    }
+
+    int checksum = 0;
+    for (int i = 0; i < n; i++) {
+        for (int j = 0; j < n; j++) {
+            int sum = 0;
+            for (int k = 0; k < n; k++) {
+                sum += a[i * n + k] * b[k * n + j];
+            }
+            checksum = (checksum + sum * (i + j + 1)) % 1000007;
+        }
+    }
+    printf("%d\n", checksum);
+    free(a);
+    free(b);
+    return 0;
+}
@@ -0,0 +1,18 @@
+#include <stdio.h>
+#include <string.h>
+
+int main(void) {
+    int total = 0;
+    int matches = 0;
+    char line[64];
+    for (int i = 0; i < 200000; i++) {
+        snprintf(line, sizeof line, "item-%d:%d", i, i % 97);
+        total += (int)strlen(line);
+        if (strstr(line, "42") != NULL) {
+            matches++;
+        }
+        total += (int)(strchr(line, ':') - line);
+    }
+    printf("%d\n%d\n", total, matches);
+    return 0;
+}
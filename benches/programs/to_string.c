@@ -0,0 +1,15 @@
+#include <stdio.h>
+#include <string.h>
+
+int main(void) {
+    int total = 0;
+    char digits[16];
+    char pair[32];
+    for (int i = 0; i < 1000000; i++) {
+        snprintf(digits, sizeof digits, "%d", i);
+        snprintf(pair, sizeof pair, "%d,%d", i % 1000, i % 7);
+        total += (int)strlen(digits) + (int)strlen(pair);
+    }
+    printf("%d\n", total);
+    return 0;
+}
@@ -59,7 +59,9 @@ function activate(context) {
             { scheme: 'untitled', language: 'bolt' }
         ],
         synchronize: {
-            fileEvents: vscode.workspace.createFileSystemWatcher('**/*.bolt')
+            fileEvents: vscode.workspace.createFileSystemWatcher('**/*.bolt'),
+            // Sends the bolt.* settings on startup and whenever they change
+            configurationSection: 'bolt'
         },
         outputChannelName: 'Bolt Language Server',
         revealOutputChannelOn: 4, // Never automatically reveal
@@ -0,0 +1,592 @@
+/*
+ * Generated by bolt 0.1.0 (commit 869e65d2c79c) from /root/crate/examples/text_processor.bolt
+ * at 2026-10-17 10:56:04 UTC
+ */
+
+#include <stdio.h>
+#include <stdint.h>
+#include <string.h>
+#include <stdlib.h>
+#include <stdarg.h>
+#include <time.h>
+#include <limits.h>
+
+#define BOLT_SMALL_STRING 24 // bytes, including the NUL
+#define BOLT_SMALL_SLAB 1024 // slots per slab
+
+typedef struct {
+    char bytes[BOLT_SMALL_STRING];
+} BoltSmallString;
+
+BoltSmallString* bolt_small_slab = NULL;
+int bolt_small_used = BOLT_SMALL_SLAB;
+
+void* bolt_string_alloc(size_t size) {
+    if (size > BOLT_SMALL_STRING) return malloc(size);
+    if (bolt_small_used == BOLT_SMALL_SLAB) {
+        bolt_small_slab = malloc(sizeof(BoltSmallString) * BOLT_SMALL_SLAB);
+        bolt_small_used = 0;
+    }
+    return bolt_small_slab[bolt_small_used++].bytes;
+}
+
+uint32_t bolt_random_state[4];
+int bolt_random_seeded = 0;
+
+uint64_t bolt_splitmix64(uint64_t* x) {
+    uint64_t z = (*x += 0x9E3779B97F4A7C15ULL);
+    z = (z ^ (z >> 30)) * 0xBF58476D1CE4E5B9ULL;
+    z = (z ^ (z >> 27)) * 0x94D049BB133111EBULL;
+    return z ^ (z >> 31);
+}
+
+void bolt_random_seed(uint64_t seed) {
+    uint64_t low = bolt_splitmix64(&seed);
+    uint64_t high = bolt_splitmix64(&seed);
+    bolt_random_state[0] = (uint32_t)low;
+    bolt_random_state[1] = (uint32_t)(low >> 32);
+    bolt_random_state[2] = (uint32_t)high;
+    bolt_random_state[3] = (uint32_t)(high >> 32);
+    bolt_random_seeded = 1;
+}
+
+uint32_t bolt_random_next(void) {
+    if (!bolt_random_seeded) {
+        bolt_random_seed((uint64_t)time(NULL) ^ (uint64_t)clock() ^ (uint64_t)(uintptr_t)&bolt_random_seeded);
+    }
+    uint32_t* s = bolt_random_state;
+    uint32_t product = s[1] * 5;
+    uint32_t result = ((product << 7) | (product >> 25)) * 9;
+    uint32_t t = s[1] << 9;
+    s[2] ^= s[0];
+    s[3] ^= s[1];
+    s[1] ^= s[2];
+    s[0] ^= s[3];
+    s[2] ^= t;
+    s[3] = (s[3] << 11) | (s[3] >> 21);
+    return result;
+}
+
+// Uniform in 0..bound-1 for bound <= 2^32, by multiplying instead of %
+uint32_t bolt_random_below(uint64_t bound) {
+    return (uint32_t)(((uint64_t)bolt_random_next() * bound) >> 32);
+}
+
+// Uniform in [0, 1) from 53 bits, all a double holds; two statements so
+// the draws happen in a fixed order
+double bolt_random_float(void) {
+    uint64_t high = bolt_random_next() >> 5;
+    uint64_t low = bolt_random_next() >> 6;
+    return (double)((high << 26) | low) / 9007199254740992.0;
+}
+
+// Bytes from the operating system, for identifiers that must not repeat
+// across runs; the generator above fills in if they can't be read
+void bolt_random_bytes(unsigned char* bytes, size_t count) {
+    FILE* source = fopen("/dev/urandom", "rb");
+    size_t filled = source ? fread(bytes, 1, count, source) : 0;
+    if (source) fclose(source);
+    for (size_t i = filled; i < count; i++) bytes[i] = (unsigned char)bolt_random_next();
+}
+
+_Noreturn void bolt_exit(int status) {
+    exit(status);
+}
+
+_Noreturn void bolt_panic(const char* location, const char* kind, const char* message) {
+    fflush(stdout);
+    fprintf(stderr, "%s: %s: %s\n", location, kind, message);
+    exit(1);
+}
+
+#ifdef DEBUG
+int bolt_add_int(int a, int b, const char* location) {
+    int result;
+    if (__builtin_add_overflow(a, b, &result)) bolt_panic(location, "runtime error", "integer overflow");
+    return result;
+}
+
+int bolt_sub_int(int a, int b, const char* location) {
+    int result;
+    if (__builtin_sub_overflow(a, b, &result)) bolt_panic(location, "runtime error", "integer overflow");
+    return result;
+}
+
+int bolt_mul_int(int a, int b, const char* location) {
+    int result;
+    if (__builtin_mul_overflow(a, b, &result)) bolt_panic(location, "runtime error", "integer overflow");
+    return result;
+}
+
+int bolt_div_int(int a, int b, const char* location) {
+    if (b == 0) bolt_panic(location, "runtime error", "division by zero");
+    if (a == INT_MIN && b == -1) bolt_panic(location, "runtime error", "integer overflow");
+    return a / b;
+}
+
+int bolt_mod_int(int a, int b, const char* location) {
+    if (b == 0) bolt_panic(location, "runtime error", "division by zero");
+    if (a == INT_MIN && b == -1) bolt_panic(location, "runtime error", "integer overflow");
+    return a % b;
+}
+
+_Noreturn void bolt_index_error(long long index, long long length, const char* location) {
+    char message[96];
+    snprintf(message, sizeof message, "index %lld is out of bounds for length %lld", index, length);
+    bolt_panic(location, "runtime error", message);
+}
+
+#define bolt_checked(operation, op, a, b) ({ __auto_type _bolt_left = (a); __auto_type _bolt_right = (b); _Generic(_bolt_left op _bolt_right, int: bolt_##operation##_int(_bolt_left, _bolt_right, __func__), default: _bolt_left op _bolt_right); })
+#define bolt_add(a, b) bolt_checked(add, +, a, b)
+#define bolt_sub(a, b) bolt_checked(sub, -, a, b)
+#define bolt_mul(a, b) bolt_checked(mul, *, a, b)
+#define bolt_div(a, b) bolt_checked(div, /, a, b)
+#define bolt_mod(a, b) bolt_checked(mod, %, a, b)
+#define bolt_neg(a) bolt_sub(0, a)
+#define bolt_index(index, length) ({ __auto_type _bolt_at = (index); if (_bolt_at < 0 || _bolt_at >= (length)) bolt_index_error(_bolt_at, (length), __func__); _bolt_at; })
+#define bolt_non_null(pointer) ({ __auto_type _bolt_pointer = (pointer); if (_bolt_pointer == NULL) bolt_panic(__func__, "runtime error", "null pointer dereference"); _bolt_pointer; })
+#else
+#define bolt_add(a, b) ((a) + (b))
+#define bolt_sub(a, b) ((a) - (b))
+#define bolt_mul(a, b) ((a) * (b))
+#define bolt_div(a, b) ((a) / (b))
+#define bolt_mod(a, b) ((a) % (b))
+#define bolt_neg(a) (-(a))
+#define bolt_index(index, length) (index)
+#define bolt_non_null(pointer) (pointer)
+#endif
+
+char* string_concat(const char* str1, const char* str2) {
+    size_t len1 = strlen(str1);
+    size_t len2 = strlen(str2);
+    char* result = bolt_string_alloc(len1 + len2 + 1);
+    strcpy(result, str1);
+    strcat(result, str2);
+    return result;
+}
+
+char* bolt_format(const char* format, ...) {
+    va_list args;
+    va_start(args, format);
+    va_list measure;
+    va_copy(measure, args);
+    int length = vsnprintf(NULL, 0, format, measure);
+    va_end(measure);
+    char* result = bolt_string_alloc(length + 1);
+    vsnprintf(result, length + 1, format, args);
+    va_end(args);
+    return result;
+}
+
+char* string_slice(const char* s, int start, int end) {
+    int length = (int)strlen(s);
+#ifdef DEBUG
+    if (start < 0 || end > length || start > end) {
+        fprintf(stderr, "Slice [%d..%d] is out of bounds for length %d\n", start, end, length);
+        exit(1);
+    }
+#endif
+    if (start < 0) start = 0;
+    if (end > length) end = length;
+    if (end < start) end = start;
+    char* result = bolt_string_alloc(end - start + 1);
+    memcpy(result, s + start, end - start);
+    result[end - start] = '\0';
+    return result;
+}
+
+char* toString(int value) {
+    char digits[16]; // enough for any 32-bit int
+    int length = snprintf(digits, sizeof digits, "%d", value);
+    char* result = bolt_string_alloc(length + 1);
+    memcpy(result, digits, length + 1);
+    return result;
+}
+
+char* floatToString(double value) {
+    int length = snprintf(NULL, 0, "%f", value);
+    char* result = bolt_string_alloc(length + 1);
+    snprintf(result, length + 1, "%f", value);
+    return result;
+}
+
+int bolt_argc;
+char** bolt_argv;
+
+char** getArgs() {
+    return bolt_argv;
+}
+
+int getArgsLength() {
+    return bolt_argc;
+}
+
+int naturalCompare(const char* a, const char* b) {
+    while (*a && *b) {
+        if (*a >= '0' && *a <= '9' && *b >= '0' && *b <= '9') {
+            while (*a == '0') a++;
+            while (*b == '0') b++;
+            const char* a_end = a;
+            const char* b_end = b;
+            while (*a_end >= '0' && *a_end <= '9') a_end++;
+            while (*b_end >= '0' && *b_end <= '9') b_end++;
+            if (a_end - a != b_end - b) return a_end - a < b_end - b ? -1 : 1;
+            int order = strncmp(a, b, a_end - a);
+            if (order != 0) return order < 0 ? -1 : 1;
+            a = a_end;
+            b = b_end;
+        } else {
+            if (*a != *b) return (unsigned char)*a < (unsigned char)*b ? -1 : 1;
+            a++;
+            b++;
+        }
+    }
+    return *a ? 1 : (*b ? -1 : 0);
+}
+
+int fileExists(const char* path) {
+    FILE* file = fopen(path, "r");
+    if (file) {
+        fclose(file);
+        return 1;
+    }
+    return 0;
+}
+
+char* trim(const char* str) {
+    const char* start = str;
+    const char* end = str + strlen(str) - 1;
+    while (*start && (*start == ' ' || *start == '\t' || *start == '\n' || *start == '\r')) start++;
+    while (end > start && (*end == ' ' || *end == '\t' || *end == '\n' || *end == '\r')) end--;
+    size_t len = end - start + 1;
+    char* result = bolt_string_alloc(len + 1);
+    strncpy(result, start, len);
+    result[len] = '\0';
+    return result;
+}
+
+int compare(const char* a, const char* b) {
+    int order = strcmp(a, b);
+    return order < 0 ? -1 : (order > 0 ? 1 : 0);
+}
+
+#ifndef BOLT_TEMP_RUNTIME
+#define BOLT_TEMP_RUNTIME
+#include <unistd.h>
+#include <dirent.h>
+#include <sys/stat.h>
+
+// Paths tempFile and tempDir made that are still there
+char** bolt_temp_paths = NULL;
+int bolt_temp_count = 0;
+
+const char* bolt_temp_root(void) {
+    const char* root = getenv("TMPDIR");
+    return root && *root ? root : "/tmp";
+}
+
+// A file, or a directory and everything in it
+int bolt_remove_tree(const char* path) {
+    struct stat info;
+    if (lstat(path, &info) != 0) return 0;
+    if (!S_ISDIR(info.st_mode)) return unlink(path) == 0;
+    DIR* dir = opendir(path);
+    if (dir) {
+        struct dirent* entry;
+        while ((entry = readdir(dir)) != NULL) {
+            if (strcmp(entry->d_name, ".") == 0 || strcmp(entry->d_name, "..") == 0) continue;
+            size_t size = strlen(path) + strlen(entry->d_name) + 2;
+            char* child = malloc(size);
+            snprintf(child, size, "%s/%s", path, entry->d_name);
+            bolt_remove_tree(child);
+            free(child);
+        }
+        closedir(dir);
+    }
+    return rmdir(path) == 0;
+}
+
+void bolt_temp_cleanup(void) {
+    for (int i = 0; i < bolt_temp_count; i++) bolt_remove_tree(bolt_temp_paths[i]);
+    bolt_temp_count = 0;
+}
+
+// Remembers a new path, hooking the cleanup into exit the first time
+char* bolt_temp_register(char* path) {
+    if (bolt_temp_paths == NULL) atexit(bolt_temp_cleanup);
+    bolt_temp_paths = realloc(bolt_temp_paths, sizeof(char*) * (bolt_temp_count + 1));
+    bolt_temp_paths[bolt_temp_count++] = path;
+    return path;
+}
+#endif
+
+char* tempDir(void) {
+    const char* root = bolt_temp_root();
+    size_t size = strlen(root) + strlen("/bolt-XXXXXX") + 1;
+    char* path = malloc(size);
+    snprintf(path, size, "%s/bolt-XXXXXX", root);
+    if (mkdtemp(path) == NULL) {
+        free(path);
+        return "";
+    }
+    return bolt_temp_register(path);
+}
+
+int writeFile(const char* path, const char* content) {
+    FILE* file = fopen(path, "w");
+    if (!file) return 0;
+    fputs(content, file);
+    fclose(file);
+    return 1;
+}
+
+char* concat(const char* str1, const char* str2) {
+    return string_concat(str1, str2);
+}
+
+int contains(const char* str, const char* substr) {
+    return strstr(str, substr) != NULL ? 1 : 0;
+}
+
+#ifndef BOLT_TEMP_RUNTIME
+#define BOLT_TEMP_RUNTIME
+#include <unistd.h>
+#include <dirent.h>
+#include <sys/stat.h>
+
+// Paths tempFile and tempDir made that are still there
+char** bolt_temp_paths = NULL;
+int bolt_temp_count = 0;
+
+const char* bolt_temp_root(void) {
+    const char* root = getenv("TMPDIR");
+    return root && *root ? root : "/tmp";
+}
+
+// A file, or a directory and everything in it
+int bolt_remove_tree(const char* path) {
+    struct stat info;
+    if (lstat(path, &info) != 0) return 0;
+    if (!S_ISDIR(info.st_mode)) return unlink(path) == 0;
+    DIR* dir = opendir(path);
+    if (dir) {
+        struct dirent* entry;
+        while ((entry = readdir(dir)) != NULL) {
+            if (strcmp(entry->d_name, ".") == 0 || strcmp(entry->d_name, "..") == 0) continue;
+            size_t size = strlen(path) + strlen(entry->d_name) + 2;
+            char* child = malloc(size);
+            snprintf(child, size, "%s/%s", path, entry->d_name);
+            bolt_remove_tree(child);
+            free(child);
+        }
+        closedir(dir);
+    }
+    return rmdir(path) == 0;
+}
+
+void bolt_temp_cleanup(void) {
+    for (int i = 0; i < bolt_temp_count; i++) bolt_remove_tree(bolt_temp_paths[i]);
+    bolt_temp_count = 0;
+}
+
+// Remembers a new path, hooking the cleanup into exit the first time
+char* bolt_temp_register(char* path) {
+    if (bolt_temp_paths == NULL) atexit(bolt_temp_cleanup);
+    bolt_temp_paths = realloc(bolt_temp_paths, sizeof(char*) * (bolt_temp_count + 1));
+    bolt_temp_paths[bolt_temp_count++] = path;
+    return path;
+}
+#endif
+
+int removeTemp(const char* path) {
+    for (int i = 0; i < bolt_temp_count; i++) {
+        if (strcmp(bolt_temp_paths[i], path) == 0) {
+            bolt_temp_paths[i] = bolt_temp_paths[--bolt_temp_count];
+            return bolt_remove_tree(path);
+        }
+    }
+    return 0;
+}
+
+int indexOf(const char* str, const char* substr) {
+    char* pos = strstr(str, substr);
+    return pos ? (int)(pos - str) : -1;
+}
+
+#ifndef BOLT_TEMP_RUNTIME
+#define BOLT_TEMP_RUNTIME
+#include <unistd.h>
+#include <dirent.h>
+#include <sys/stat.h>
+
+// Paths tempFile and tempDir made that are still there
+char** bolt_temp_paths = NULL;
+int bolt_temp_count = 0;
+
+const char* bolt_temp_root(void) {
+    const char* root = getenv("TMPDIR");
+    return root && *root ? root : "/tmp";
+}
+
+// A file, or a directory and everything in it
+int bolt_remove_tree(const char* path) {
+    struct stat info;
+    if (lstat(path, &info) != 0) return 0;
+    if (!S_ISDIR(info.st_mode)) return unlink(path) == 0;
+    DIR* dir = opendir(path);
+    if (dir) {
+        struct dirent* entry;
+        while ((entry = readdir(dir)) != NULL) {
+            if (strcmp(entry->d_name, ".") == 0 || strcmp(entry->d_name, "..") == 0) continue;
+            size_t size = strlen(path) + strlen(entry->d_name) + 2;
+            char* child = malloc(size);
+            snprintf(child, size, "%s/%s", path, entry->d_name);
+            bolt_remove_tree(child);
+            free(child);
+        }
+        closedir(dir);
+    }
+    return rmdir(path) == 0;
+}
+
+void bolt_temp_cleanup(void) {
+    for (int i = 0; i < bolt_temp_count; i++) bolt_remove_tree(bolt_temp_paths[i]);
+    bolt_temp_count = 0;
+}
+
+// Remembers a new path, hooking the cleanup into exit the first time
+char* bolt_temp_register(char* path) {
+    if (bolt_temp_paths == NULL) atexit(bolt_temp_cleanup);
+    bolt_temp_paths = realloc(bolt_temp_paths, sizeof(char*) * (bolt_temp_count + 1));
+    bolt_temp_paths[bolt_temp_count++] = path;
+    return path;
+}
+#endif
+
+char* tempFile(const char* prefix) {
+    const char* root = bolt_temp_root();
+    size_t size = strlen(root) + strlen(prefix) + 8;
+    char* path = malloc(size);
+    snprintf(path, size, "%s/%sXXXXXX", root, prefix);
+    int file = mkstemp(path);
+    if (file < 0) {
+        free(path);
+        return "";
+    }
+    close(file);
+    return bolt_temp_register(path);
+}
+
+int appendFile(const char* path, const char* content) {
+    FILE* file = fopen(path, "a");
+    if (!file) return 0;
+    fputs(content, file);
+    fclose(file);
+    return 1;
+}
+
+char* readFile(const char* path) {
+    FILE* file = fopen(path, "r");
+    if (!file) return "";
+    fseek(file, 0, SEEK_END);
+    long length = ftell(file);
+    fseek(file, 0, SEEK_SET);
+    char* content = malloc(length + 1);
+    fread(content, 1, length, file);
+    content[length] = '\0';
+    fclose(file);
+    return content;
+}
+
+int length(const char* str) {
+    return strlen(str);
+}
+
+int deleteFile(const char* path) {
+    return remove(path) == 0 ? 1 : 0;
+}
+
+int bolt_main() {
+    printf("%s\n", "=== Bolt Text Processor ===");
+    printf("%s\n", "");
+    createSampleFiles();
+    processFile("document1.txt");
+    processFile("document2.txt");
+    createSummaryReport();
+    cleanup();
+    printf("%s\n", "Text processing complete!");
+    return 0;
+}
+
+int createSampleFiles() {
+    printf("%s\n", "Creating sample files...");
+    char doc1[] = "  Hello, Bolt!  This is the first document.  It contains some text with whitespace.  ";
+    char doc2[] = "Welcome to Bolt programming language! This is document number two. Bolt makes native functions easy!";
+    writeFile("document1.txt", doc1);
+    writeFile("document2.txt", doc2);
+    printf("%s\n", "Sample files created.");
+    return 0;
+}
+
+int processFile(char* filename) {
+    printf("%s\n", concat("Processing: ", filename));
+    if ((!fileExists(filename))) {
+        printf("%s\n", "File does not exist!");
+        return 1;
+    }
+    char* content = readFile(filename);
+    int original_length = length(content);
+    char* cleaned = trim(content);
+    int cleaned_length = length(cleaned);
+    int has_bolt = contains(cleaned, "Bolt");
+    int has_document = contains(cleaned, "document");
+    int bolt_position = indexOf(cleaned, "Bolt");
+    char* report = createAnalysisReport(filename, original_length, cleaned_length, has_bolt, has_document, bolt_position);
+    char* cleaned_filename = concat("cleaned_", filename);
+    writeFile(cleaned_filename, cleaned);
+    appendFile("analysis_report.txt", report);
+    printf("%s\n", concat("Processed ", filename));
+    return 0;
+}
+
+char* createAnalysisReport(char* filename, int original_len, int cleaned_len, int has_bolt, int has_doc, int bolt_pos) {
+    char* header = concat("=== Analysis Report for ", concat(filename, " ===\n"));
+    char* length_info = concat("Original length: ", concat(toString(original_len), "\n"));
+    char* cleaned_info = concat("Cleaned length: ", concat(toString(cleaned_len), "\n"));
+    char* bolt_info = ((has_bolt) ? concat("Contains 'Bolt': Yes (position ", concat(toString(bolt_pos), ")\n")) : "Contains 'Bolt': No\n");
+    char* doc_info = ((has_doc) ? "Contains 'document': Yes\n" : "Contains 'document': No\n");
+    char separator[] = "\n";
+    char* report = concat(header, concat(length_info, concat(cleaned_info, concat(bolt_info, concat(doc_info, separator)))));
+    return report;
+}
+
+int createSummaryReport() {
+    printf("%s\n", "Creating summary report...");
+    char summary_header[] = "=== SUMMARY REPORT ===\n";
+    char summary_content[] = "Text processing completed successfully.\nAll files have been analyzed and cleaned.\nSee individual file reports above.\n\n";
+    char* summary = concat(summary_header, summary_content);
+    appendFile("analysis_report.txt", summary);
+    printf("%s\n", "Final Analysis Report:");
+    printf("%s\n", "---------------------");
+    char* final_report = readFile("analysis_report.txt");
+    printf("%s\n", final_report);
+    return 0;
+}
+
+int cleanup() {
+    printf("%s\n", "Cleaning up files...");
+    deleteFile("document1.txt");
+    deleteFile("document2.txt");
+    deleteFile("cleaned_document1.txt");
+    deleteFile("cleaned_document2.txt");
+    deleteFile("analysis_report.txt");
+    printf("%s\n", "Cleanup complete.");
+    return 0;
+}
+
+int main(int argc, char* argv[]) {
+    bolt_argc = argc;
+    bolt_argv = argv;
+    int status = bolt_main();
+    return status;
+}
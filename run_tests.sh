@@ -27,8 +27,15 @@ for test_file in tests/*.bolt; do
         
         echo "🧪 Testing $basename..."
         
+        # Extra compiler flags for this test, e.g. --arena
+        flags_file="tests/${basename}.flags"
+        extra_flags=""
+        if [ -f "$flags_file" ]; then
+            extra_flags=$(cat "$flags_file")
+        fi
+
         # Compile the test (debug mode for better error messages)
-        ./target/debug/bolt "$test_file" -o "$output_exe" > /dev/null 2>&1
+        ./target/debug/bolt "$test_file" -o "$output_exe" $extra_flags > /dev/null 2>&1
         
         if [ $? -ne 0 ]; then
             echo "❌ $basename: Compilation failed"
@@ -1,7 +1,21 @@
+//! The syntax tree the parser produces and every later stage consumes.
+//!
+//! Tools outside this crate (editors, formatters, linters) can depend on it.
+//! Every enum and struct here is `#[non_exhaustive]`, so new variants and
+//! fields arrive in patch releases: match with a `_` arm and `..`, build
+//! nodes with the constructors below (`Expression::call`, `Parameter::new`,
+//! ...) and walk trees with `crate::visit::Visitor`. Removing or renaming
+//! anything, or changing a field's type, waits for the next minor version;
+//! CONTRIBUTING.md has the full policy.
+
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Type {
     String,
     Integer,
+    Float,
     Bool,
     #[allow(dead_code)] // For future typed arrays
     Array(Box<Type>), // Array of some type
@@ -15,9 +29,154 @@ pub enum Type {
     },
     // Type parameters like T, K, V
     TypeParameter(String),
+    // Function types like (Integer, Integer) -> Integer
+    Function {
+        params: Vec<Type>,
+        return_type: Box<Type>,
+    },
+    // Fixed-width integers like Int8 or UInt64
+    SizedInt(IntKind),
+    // Tuples like (Integer, String), fields accessed as .0, .1, ...
+    Tuple(Vec<Type>),
+}
+
+/// The fixed-width integer types, mapped to `<stdint.h>` types in C
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum IntKind {
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+}
+
+impl IntKind {
+    pub const ALL: [IntKind; 8] = [
+        IntKind::Int8,
+        IntKind::Int16,
+        IntKind::Int32,
+        IntKind::Int64,
+        IntKind::UInt8,
+        IntKind::UInt16,
+        IntKind::UInt32,
+        IntKind::UInt64,
+    ];
+
+    /// The kind named by a Bolt type name such as "UInt16"
+    pub fn from_name(name: &str) -> Option<IntKind> {
+        IntKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntKind::Int8 => "Int8",
+            IntKind::Int16 => "Int16",
+            IntKind::Int32 => "Int32",
+            IntKind::Int64 => "Int64",
+            IntKind::UInt8 => "UInt8",
+            IntKind::UInt16 => "UInt16",
+            IntKind::UInt32 => "UInt32",
+            IntKind::UInt64 => "UInt64",
+        }
+    }
+
+    pub fn c_type(self) -> &'static str {
+        match self {
+            IntKind::Int8 => "int8_t",
+            IntKind::Int16 => "int16_t",
+            IntKind::Int32 => "int32_t",
+            IntKind::Int64 => "int64_t",
+            IntKind::UInt8 => "uint8_t",
+            IntKind::UInt16 => "uint16_t",
+            IntKind::UInt32 => "uint32_t",
+            IntKind::UInt64 => "uint64_t",
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            IntKind::Int8 | IntKind::Int16 | IntKind::Int32 | IntKind::Int64
+        )
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            IntKind::Int8 | IntKind::UInt8 => 8,
+            IntKind::Int16 | IntKind::UInt16 => 16,
+            IntKind::Int32 | IntKind::UInt32 => 32,
+            IntKind::Int64 | IntKind::UInt64 => 64,
+        }
+    }
+
+    /// Smallest and largest representable values
+    pub fn range(self) -> (i128, i128) {
+        let bits = self.bits();
+        if self.is_signed() {
+            (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+        } else {
+            (0, (1i128 << bits) - 1)
+        }
+    }
+}
+
+impl Type {
+    /// `Name[T, ...]`, e.g. `Map[String, Integer]`
+    pub fn generic(name: &str, type_params: Vec<Type>) -> Type {
+        Type::Generic {
+            name: name.to_string(),
+            type_params,
+        }
+    }
+
+    /// `T?`, written `Optional[T]` in full
+    pub fn optional(inner: Type) -> Type {
+        Type::generic("Optional", vec![inner])
+    }
+
+    /// `^T`
+    pub fn pointer(target: Type) -> Type {
+        Type::Pointer(Box::new(target))
+    }
+}
+
+// Types print the way they are written in Bolt source
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::String => write!(f, "String"),
+            Type::Integer => write!(f, "Integer"),
+            Type::Float => write!(f, "Float"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Array(element) => write!(f, "[]{}", element),
+            Type::Pointer(inner) => write!(f, "^{}", inner),
+            Type::Custom(name) | Type::TypeParameter(name) => write!(f, "{}", name),
+            Type::Generic { name, type_params } => {
+                let params: Vec<String> = type_params.iter().map(|t| t.to_string()).collect();
+                write!(f, "{}[{}]", name, params.join(", "))
+            }
+            Type::Function {
+                params,
+                return_type,
+            } => {
+                let params: Vec<String> = params.iter().map(|t| t.to_string()).collect();
+                write!(f, "({}) -> {}", params.join(", "), return_type)
+            }
+            Type::SizedInt(kind) => write!(f, "{}", kind.name()),
+            Type::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(|t| t.to_string()).collect();
+                write!(f, "({})", elements.join(", "))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Field {
     #[allow(dead_code)] // For future struct definitions
     pub name: String,
@@ -26,18 +185,76 @@ pub struct Field {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct StructField {
     pub name: String,
     pub value: Expression,
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Parameter {
     pub name: String,
     pub param_type: Type,
+    pub default: Option<Expression>, // `= value`, used where a call leaves the argument out
+}
+
+// A variable bound to an asm operand, with its GCC constraint ("+r" for
+// outputs and "r" for inputs unless one is written, as in `out("=a": x)`)
+impl Field {
+    pub fn new(name: &str, field_type: Type) -> Field {
+        Field {
+            name: name.to_string(),
+            field_type,
+        }
+    }
+}
+
+impl StructField {
+    pub fn new(name: &str, value: Expression) -> StructField {
+        StructField {
+            name: name.to_string(),
+            value,
+        }
+    }
+}
+
+impl Parameter {
+    pub fn new(name: &str, param_type: Type) -> Parameter {
+        Parameter {
+            name: name.to_string(),
+            param_type,
+            default: None,
+        }
+    }
+
+    /// The same parameter with `= value`
+    pub fn with_default(self, default: Expression) -> Parameter {
+        Parameter {
+            default: Some(default),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AsmOperand {
+    pub constraint: String,
+    pub variable: String,
+}
+
+impl AsmOperand {
+    pub fn new(constraint: &str, variable: &str) -> AsmOperand {
+        AsmOperand {
+            constraint: constraint.to_string(),
+            variable: variable.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct NativeFunction {
     pub name: String,
     pub params: Vec<Parameter>,
@@ -45,16 +262,64 @@ pub struct NativeFunction {
     pub exported: bool,
 }
 
+/// A method signature an interface requires, e.g. `fun toString(): String`
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct InterfaceMethod {
+    pub name: String,
+    pub params: Vec<Parameter>,
+    pub return_type: Option<Type>,
+}
+
+impl InterfaceMethod {
+    pub fn new(name: &str, params: Vec<Parameter>, return_type: Option<Type>) -> InterfaceMethod {
+        InterfaceMethod {
+            name: name.to_string(),
+            params,
+            return_type,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ExternFunction {
     pub name: String,
     pub params: Vec<Parameter>,
     pub return_type: Option<Type>,
     pub exported: bool,
     pub library: Option<String>, // e.g., "math" for -lmath
+    pub variadic: bool,          // `...` after the parameters, as in printf
+}
+
+impl NativeFunction {
+    /// An unexported function; set `exported` to export it
+    pub fn new(name: &str, params: Vec<Parameter>, return_type: Option<Type>) -> NativeFunction {
+        NativeFunction {
+            name: name.to_string(),
+            params,
+            return_type,
+            exported: false,
+        }
+    }
+}
+
+impl ExternFunction {
+    /// An unexported function needing no extra library
+    pub fn new(name: &str, params: Vec<Parameter>, return_type: Option<Type>) -> ExternFunction {
+        ExternFunction {
+            name: name.to_string(),
+            params,
+            return_type,
+            exported: false,
+            library: None,
+            variadic: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Statement {
     VarDecl {
         name: String,
@@ -68,18 +333,39 @@ pub enum Statement {
         type_annotation: Option<Type>,
         value: Expression,
     },
+    // `const NAME: Type = value` at module level, evaluated at compile time
+    ConstDecl {
+        name: String,
+        type_annotation: Option<Type>,
+        value: Expression,
+        exported: bool, // `export const`, importable by other modules
+    },
     #[allow(dead_code)] // For future struct definitions
     TypeDef {
         name: String,
         type_params: Vec<String>, // Generic parameters like ["T", "K", "V"]
         fields: Vec<Field>,
+        implements: Vec<String>, // Interfaces the type claims to implement
+    },
+    Interface {
+        name: String,
+        methods: Vec<InterfaceMethod>,
     },
     If {
         condition: Expression,
         then_body: Vec<Statement>,
         else_body: Option<Vec<Statement>>,
     },
+    // if (val name := optional) { ... } else { ... }: the then branch runs
+    // with the unwrapped value bound to `name` when the optional is present
+    IfPresent {
+        name: String,
+        value: Expression,
+        then_body: Vec<Statement>,
+        else_body: Option<Vec<Statement>>,
+    },
     ForIn {
+        index: Option<String>, // `for i, item in items` also counts from 0
         variable: String,
         iterable: Expression,
         body: Vec<Statement>,
@@ -88,10 +374,14 @@ pub enum Statement {
         condition: Expression,
         body: Vec<Statement>,
     },
+    DoWhile {
+        body: Vec<Statement>, // Runs once before the condition is first checked
+        condition: Expression,
+    },
     ForLoop {
         init: Option<Box<Statement>>,
         condition: Option<Expression>,
-        update: Option<Expression>,
+        update: Option<Box<Statement>>, // e.g. `i = i + 1`
         body: Vec<Statement>,
     },
     Import {
@@ -102,15 +392,34 @@ pub enum Statement {
     Export {
         item: String, // Single item export
     },
+    // A method `fun (p: Person) greet()` is named `Person_greet`, with the
+    // receiver kept out of `params`
     Function {
         name: String,
+        type_params: Vec<String>, // Generic parameters like ["T"] in `fun first[T](...)`
+        receiver: Option<Parameter>,
         params: Vec<Parameter>,
         return_type: Option<Type>,
         body: Vec<Statement>,
         exported: bool,
     },
     Return(Option<Expression>),
+    Defer {
+        body: Vec<Statement>, // Runs when the enclosing block exits, including early returns
+    },
     Expression(Expression),
+    // `panic("message")`: stops the program, reporting where (`file:line`)
+    Panic {
+        message: Expression,
+        location: String,
+    },
+    // `assert(condition, "message")`: panics when the condition is false.
+    // A runtime check, so release builds leave it out unless --strict
+    Assert {
+        condition: Expression,
+        message: Expression,
+        location: String,
+    },
     Assignment {
         variable: String,
         value: Expression,
@@ -119,23 +428,111 @@ pub enum Statement {
         language: String, // e.g., "C"
         functions: Vec<NativeFunction>,
     },
+    // `unsafe { ... }`, a block where low-level escape hatches like asm are allowed
+    Unsafe {
+        body: Vec<Statement>,
+    },
+    // asm "addq %1, %0" out(total) in(step) clobber("cc"): GCC extended asm,
+    // emitted verbatim by the C backend (the interpreter rejects it)
+    Asm {
+        template: String,
+        outputs: Vec<AsmOperand>,
+        inputs: Vec<AsmOperand>,
+        clobbers: Vec<String>,
+    },
     ExternBlock {
         language: String, // e.g., "C"
         functions: Vec<ExternFunction>,
     },
 }
 
+// Builders for the statements tools most often generate; other variants
+// are built directly
+impl Statement {
+    /// `val name := value`
+    pub fn val(name: &str, value: Expression) -> Statement {
+        Statement::ValDecl {
+            name: name.to_string(),
+            type_annotation: None,
+            value,
+        }
+    }
+
+    /// `var name := value`
+    pub fn var(name: &str, value: Expression) -> Statement {
+        Statement::VarDecl {
+            name: name.to_string(),
+            type_annotation: None,
+            value,
+        }
+    }
+
+    /// `variable = value`
+    pub fn assign(variable: &str, value: Expression) -> Statement {
+        Statement::Assignment {
+            variable: variable.to_string(),
+            value,
+        }
+    }
+
+    /// `fun name(params): return_type { body }`, not generic or exported
+    pub fn function(
+        name: &str,
+        params: Vec<Parameter>,
+        return_type: Option<Type>,
+        body: Vec<Statement>,
+    ) -> Statement {
+        Statement::Function {
+            name: name.to_string(),
+            type_params: Vec::new(),
+            receiver: None,
+            params,
+            return_type,
+            body,
+            exported: false,
+        }
+    }
+
+    /// `if condition { then_body }`, with an else branch if given
+    pub fn if_else(
+        condition: Expression,
+        then_body: Vec<Statement>,
+        else_body: Option<Vec<Statement>>,
+    ) -> Statement {
+        Statement::If {
+            condition,
+            then_body,
+            else_body,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Expression {
     StringLiteral(String),
-    IntegerLiteral(i64),
+    IntegerLiteral(i128),
+    FloatLiteral(f64),
     BoolLiteral(bool),
+    NoneLiteral, // `none`, an absent Optional[T]
     ArrayLiteral(Vec<Expression>),
+    TupleLiteral(Vec<Expression>), // (1, "one")
     Identifier(String),
     FunctionCall {
         name: String,
         args: Vec<Expression>,
     },
+    // `name: value` among a call's arguments, matched to the parameter by name
+    NamedArgument {
+        name: String,
+        value: Box<Expression>,
+    },
+    // person.greet(), a call to the method of the object's type
+    MethodCall {
+        object: Box<Expression>,
+        method: String,
+        args: Vec<Expression>,
+    },
     NamespacedFunctionCall {
         namespace: String,
         function: String,
@@ -155,28 +552,160 @@ pub enum Expression {
         type_args: Option<Vec<Type>>, // For generic constructors like Array[Integer]
         fields: Vec<StructField>,
     },
+    // {"one": 1, "two": 2}, or Map[String, Integer] { ... } with the types written out
+    MapLiteral {
+        type_args: Option<Vec<Type>>, // [K, V] of the constructor form
+        entries: Vec<(Expression, Expression)>,
+    },
     FieldAccess {
         object: Box<Expression>,
         field: String,
     },
+    // `config?.name`: the field of an optional struct, itself optional
+    OptionalFieldAccess {
+        object: Box<Expression>,
+        field: String,
+    },
+    // `port ?? 8080`: the optional's value, or the fallback (evaluated only
+    // then) when it is none
+    Coalesce {
+        value: Box<Expression>,
+        fallback: Box<Expression>,
+    },
     ArrayAccess {
         array: Box<Expression>,
         index: Box<Expression>,
     },
+    // `items[start..end]`: a Slice[T] view of an array, or a copy of part of a string
+    Slice {
+        object: Box<Expression>,
+        start: Box<Expression>,
+        end: Box<Expression>, // Exclusive; `a..=b` is parsed with end b + 1
+    },
     AddressOf {
         operand: Box<Expression>,
     },
+    // `value as Float`, a C cast; the checker decides which conversions are legal
+    Cast {
+        value: Box<Expression>,
+        target: Type,
+    },
     Dereference {
         operand: Box<Expression>,
     },
+    // `alloc[T](count)`, or `realloc[T](block, count)` resizing an earlier
+    // one: heap memory for count values of T, as a ^T (malloc/realloc in C)
+    Allocate {
+        element: Type,
+        block: Option<Box<Expression>>, // What realloc resizes; None for alloc
+        count: Box<Expression>,
+    },
+    // Parenthesized expression, kept so codegen never re-associates it
+    Grouping(Box<Expression>),
+    // "Hello, ${name}!", lowered to string_concat/toString calls
+    StringInterpolation(Vec<InterpolationPart>),
+    // (x: Integer) -> x * 2, lifted to a C function; it can't capture locals
+    Lambda {
+        params: Vec<Parameter>,
+        return_type: Option<Type>,
+        body: Box<Expression>,
+    },
+    // 0..10 or 0..=10; only iterated by for-in, as a counting loop
+    Range {
+        start: Box<Expression>,
+        end: Box<Expression>,
+        inclusive: bool,
+    },
+    // if a > b { a } else { b }, as a value; lowered to a C ternary
+    If {
+        condition: Box<Expression>,
+        then_value: Box<Expression>,
+        else_value: Box<Expression>,
+    },
+}
+
+// Builders for the expressions tools most often generate; other variants
+// are built directly
+impl Expression {
+    pub fn string(value: &str) -> Expression {
+        Expression::StringLiteral(value.to_string())
+    }
+
+    pub fn identifier(name: &str) -> Expression {
+        Expression::Identifier(name.to_string())
+    }
+
+    /// `name(args)`
+    pub fn call(name: &str, args: Vec<Expression>) -> Expression {
+        Expression::FunctionCall {
+            name: name.to_string(),
+            args,
+        }
+    }
+
+    /// `object.method(args)`
+    pub fn method_call(object: Expression, method: &str, args: Vec<Expression>) -> Expression {
+        Expression::MethodCall {
+            object: Box::new(object),
+            method: method.to_string(),
+            args,
+        }
+    }
+
+    /// `left operator right`, without parentheses
+    pub fn binary(left: Expression, operator: BinaryOperator, right: Expression) -> Expression {
+        Expression::BinaryOp {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn unary(operator: UnaryOperator, operand: Expression) -> Expression {
+        Expression::UnaryOp {
+            operator,
+            operand: Box::new(operand),
+        }
+    }
+
+    /// `object.field`
+    pub fn field(object: Expression, field: &str) -> Expression {
+        Expression::FieldAccess {
+            object: Box::new(object),
+            field: field.to_string(),
+        }
+    }
+
+    /// `array[index]`
+    pub fn index(array: Expression, index: Expression) -> Expression {
+        Expression::ArrayAccess {
+            array: Box::new(array),
+            index: Box::new(index),
+        }
+    }
+
+    /// `(inner)`
+    pub fn grouping(inner: Expression) -> Expression {
+        Expression::Grouping(Box::new(inner))
+    }
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum InterpolationPart {
+    Literal(String),
+    Expression(Expression),
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum UnaryOperator {
     Not,
+    Negate, // -x
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum BinaryOperator {
     Add,
     Subtract,
@@ -194,6 +723,13 @@ pub enum BinaryOperator {
 }
 
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
+
+impl Program {
+    pub fn new(statements: Vec<Statement>) -> Program {
+        Program { statements }
+    }
+}
@@ -0,0 +1,3824 @@
+use crate::ast::{
+    BinaryOperator, Expression, Field, InterfaceMethod, Parameter, Program, Statement, Type,
+    UnaryOperator,
+};
+use crate::const_eval::{evaluate_constants_with, Constant};
+use crate::symbol_table::{format_pieces, method_function_name, ARRAY_METHODS, FORMAT_FUNCTIONS};
+use crate::visit::{walk_expression, walk_statement, walk_statements, Visitor};
+use std::collections::{HashMap, HashSet};
+
+/// Whether a type is a non-owning view (`Slice[T]` or `StrView`)
+pub fn is_view_type(t: &Type) -> bool {
+    match t {
+        Type::Generic { name, .. } => name == "Slice",
+        Type::Custom(name) => name == "StrView" || name.starts_with("Slice_"),
+        _ => false,
+    }
+}
+
+// A view anywhere inside a type, e.g. the StrView in Array[StrView]
+fn contains_view(t: &Type) -> bool {
+    if is_view_type(t) {
+        return true;
+    }
+    match t {
+        Type::Array(inner) | Type::Pointer(inner) => contains_view(inner),
+        Type::Generic { type_params, .. } | Type::Tuple(type_params) => {
+            type_params.iter().any(contains_view)
+        }
+        _ => false,
+    }
+}
+
+/// Prefix of the C names codegen makes up for loop counters and other
+/// temporaries. User names starting with it could collide with them.
+pub const RESERVED_PREFIX: &str = "_bolt_";
+
+/// Generic types whose instances codegen names `Base_Args` (`Optional_Integer`,
+/// `Tuple_Integer_String`) even when the program doesn't declare them.
+const BUILTIN_GENERICS: [&str; 5] = ["Array", "Slice", "Optional", "Map", "Tuple"];
+
+/// A finding that doesn't stop compilation by itself. Which of them are
+/// shown, and which fail the build, is up to the `[lints]` levels in
+/// bolt.toml (see `lint::LintConfig`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub lint: &'static str, // One of lint::LINTS
+    pub message: String,
+}
+
+impl Warning {
+    fn new(lint: &'static str, message: String) -> Self {
+        Warning { lint, message }
+    }
+}
+
+/// The lints that only need the syntax tree:
+/// - reserved-name: declared names (variables, parameters, loop variables,
+///   functions, types and fields) that start with `RESERVED_PREFIX`
+/// - unused-variable: a `val` or `var` that nothing reads; names starting
+///   with `_` are exempt
+/// - unused-parameter: a function parameter its body never reads, with the
+///   same exemption
+/// - deprecated: `for x in iterate(items)`, from before for-in looped over
+///   arrays directly
+fn check_warnings(statements: &[Statement]) -> Vec<Warning> {
+    let mut names = Vec::new();
+    declared_names(statements, &mut names);
+    let mut warnings: Vec<Warning> = names
+        .into_iter()
+        .filter(|name| name.starts_with(RESERVED_PREFIX))
+        .map(|name| {
+            Warning::new(
+                "reserved-name",
+                format!(
+                    "'{}' starts with {}, which is reserved for compiler temporaries",
+                    name, RESERVED_PREFIX
+                ),
+            )
+        })
+        .collect();
+
+    // Top-level code may be read by functions too, so its uses include theirs
+    let mut used = HashSet::new();
+    for_each_statement(statements, true, &mut |statement| {
+        statement_reads(statement, &mut used)
+    });
+    unused_variables(statements, &used, &mut warnings);
+    for_each_statement(statements, true, &mut |statement| {
+        if let Statement::Function {
+            name, params, body, ..
+        } = statement
+        {
+            let mut used = HashSet::new();
+            for_each_statement(body, false, &mut |statement| {
+                statement_reads(statement, &mut used)
+            });
+            unused_variables(body, &used, &mut warnings);
+            for param in params {
+                if !param.name.starts_with('_') && !used.contains(param.name.as_str()) {
+                    warnings.push(Warning::new(
+                        "unused-parameter",
+                        format!("Parameter '{}' of '{}' is never used", param.name, name),
+                    ));
+                }
+            }
+        }
+    });
+
+    for_each_statement(statements, true, &mut |statement| {
+        if let Statement::ForIn {
+            variable,
+            iterable: Expression::FunctionCall { name, args },
+            ..
+        } = statement
+        {
+            if name == "iterate" && args.len() == 1 {
+                let items = match &args[0] {
+                    Expression::Identifier(items) => items.as_str(),
+                    _ => "items",
+                };
+                warnings.push(Warning::new(
+                    "deprecated",
+                    format!(
+                        "iterate() is deprecated; loop over the array directly: `for {} in {}`",
+                        variable, items
+                    ),
+                ));
+            }
+        }
+    });
+    warnings
+}
+
+// The vals and vars of a block and the blocks nested in it (not functions)
+// that are never read
+fn unused_variables(statements: &[Statement], used: &HashSet<&str>, warnings: &mut Vec<Warning>) {
+    let mut reported = HashSet::new();
+    for_each_statement(statements, false, &mut |statement| {
+        if let Statement::ValDecl { name, .. } | Statement::VarDecl { name, .. } = statement {
+            if !name.starts_with('_') && !used.contains(name.as_str()) && reported.insert(name) {
+                warnings.push(Warning::new(
+                    "unused-variable",
+                    format!("'{}' is declared but never used", name),
+                ));
+            }
+        }
+    });
+}
+
+// Every statement in a block and the blocks nested in it, including the
+// init and update of a C-style for; function bodies only if `into_functions`
+fn for_each_statement<'a>(
+    statements: &'a [Statement],
+    into_functions: bool,
+    visit: &mut dyn FnMut(&'a Statement),
+) {
+    for statement in statements {
+        visit(statement);
+        match statement {
+            Statement::Function { body, .. } if into_functions => {
+                for_each_statement(body, into_functions, visit)
+            }
+            Statement::IfPresent {
+                then_body,
+                else_body,
+                ..
+            } => {
+                for_each_statement(then_body, into_functions, visit);
+                for_each_statement(
+                    else_body.as_deref().unwrap_or_default(),
+                    into_functions,
+                    visit,
+                );
+            }
+            Statement::ForLoop { init, update, .. } => {
+                for step in [init, update].into_iter().flatten() {
+                    for_each_statement(std::slice::from_ref(step), into_functions, visit);
+                }
+            }
+            Statement::Unsafe { body } => for_each_statement(body, into_functions, visit),
+            _ => {}
+        }
+        for body in nested_bodies(statement) {
+            for_each_statement(body, into_functions, visit);
+        }
+    }
+}
+
+// Names a statement's own expressions read; assigning to a variable isn't
+// reading it
+fn statement_reads<'a>(statement: &'a Statement, used: &mut HashSet<&'a str>) {
+    match statement {
+        Statement::VarDecl { value, .. }
+        | Statement::ValDecl { value, .. }
+        | Statement::ConstDecl { value, .. }
+        | Statement::Assignment { value, .. }
+        | Statement::IfPresent { value, .. }
+        | Statement::Expression(value)
+        | Statement::Return(Some(value))
+        | Statement::Panic { message: value, .. }
+        | Statement::If {
+            condition: value, ..
+        }
+        | Statement::ForCondition {
+            condition: value, ..
+        }
+        | Statement::DoWhile {
+            condition: value, ..
+        }
+        | Statement::ForLoop {
+            condition: Some(value),
+            ..
+        }
+        | Statement::ForIn {
+            iterable: value, ..
+        } => expression_reads(value, used),
+        Statement::Assert {
+            condition, message, ..
+        } => {
+            expression_reads(condition, used);
+            expression_reads(message, used);
+        }
+        Statement::Asm {
+            outputs, inputs, ..
+        } => used.extend(
+            outputs
+                .iter()
+                .chain(inputs)
+                .map(|operand| operand.variable.as_str()),
+        ),
+        _ => {}
+    }
+}
+
+fn expression_reads<'a>(expr: &'a Expression, used: &mut HashSet<&'a str>) {
+    Reads { used }.visit_expression(expr);
+}
+
+struct Reads<'a, 'u> {
+    used: &'u mut HashSet<&'a str>,
+}
+
+impl<'a> Visitor<'a> for Reads<'a, '_> {
+    fn visit_expression(&mut self, expr: &'a Expression) {
+        match expr {
+            // The callee may be a local holding a function
+            Expression::Identifier(name) | Expression::FunctionCall { name, .. } => {
+                self.used.insert(name);
+            }
+            _ => {}
+        }
+        walk_expression(self, expr);
+    }
+}
+
+// Lambdas are lifted into plain C functions, so they may only use their own
+// parameters, not the locals of the code around them (or of an enclosing
+// lambda). Functions nested in `body` are checked on their own.
+fn check_captures<'a>(
+    params: impl IntoIterator<Item = &'a Parameter>,
+    body: &'a [Statement],
+) -> Result<(), String> {
+    let mut captures = Captures {
+        locals: params
+            .into_iter()
+            .map(|param| param.name.as_str())
+            .collect(),
+        lambdas: Vec::new(),
+        captured: None,
+    };
+    walk_statements(&mut captures, body);
+    match captures.captured {
+        Some(name) => Err(format!(
+            "Lambda can't capture local variable '{}'; pass it as a parameter instead",
+            name
+        )),
+        None => Ok(()),
+    }
+}
+
+// A default is filled in at each call site that leaves it out, so it can't
+// use the function's parameters or any local: those names would resolve in
+// the caller. Constants and top-level functions mean the same thing there.
+fn default_names(default: &Expression) -> Vec<&str> {
+    let mut names = DefaultNames { names: Vec::new() };
+    names.visit_expression(default);
+    names.names
+}
+
+struct DefaultNames<'a> {
+    names: Vec<&'a str>,
+}
+
+impl<'a> Visitor<'a> for DefaultNames<'a> {
+    fn visit_expression(&mut self, expr: &'a Expression) {
+        match expr {
+            // A lambda may only use its own parameters anyway
+            Expression::Lambda { .. } => return,
+            Expression::Identifier(name) | Expression::FunctionCall { name, .. } => {
+                self.names.push(name);
+            }
+            _ => {}
+        }
+        walk_expression(self, expr);
+    }
+}
+
+struct Captures<'a> {
+    locals: HashSet<&'a str>,
+    lambdas: Vec<Vec<&'a str>>, // Parameters of the lambdas being walked, innermost last
+    captured: Option<&'a str>,
+}
+
+impl<'a> Visitor<'a> for Captures<'a> {
+    fn visit_statement(&mut self, statement: &'a Statement) {
+        match statement {
+            Statement::Function { .. } => return,
+            Statement::VarDecl { name, .. }
+            | Statement::ValDecl { name, .. }
+            | Statement::IfPresent { name, .. } => {
+                self.locals.insert(name);
+            }
+            Statement::ForIn {
+                index, variable, ..
+            } => {
+                self.locals.insert(variable);
+                self.locals.extend(index.as_deref());
+            }
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expr: &'a Expression) {
+        match expr {
+            Expression::Lambda { params, body, .. } => {
+                self.lambdas
+                    .push(params.iter().map(|param| param.name.as_str()).collect());
+                self.visit_expression(body);
+                self.lambdas.pop();
+                return;
+            }
+            // The callee may be a local holding a function
+            Expression::Identifier(name) | Expression::FunctionCall { name, .. } => {
+                let name = name.as_str();
+                if let Some(own) = self.lambdas.last() {
+                    let outer = self.locals.contains(name)
+                        || self.lambdas.iter().any(|params| params.contains(&name));
+                    if outer && !own.contains(&name) && self.captured.is_none() {
+                        self.captured = Some(name);
+                    }
+                }
+            }
+            _ => {}
+        }
+        walk_expression(self, expr);
+    }
+}
+
+fn declared_names<'a>(statements: &'a [Statement], names: &mut Vec<&'a str>) {
+    for statement in statements {
+        match statement {
+            Statement::VarDecl { name, .. }
+            | Statement::ValDecl { name, .. }
+            | Statement::ConstDecl { name, .. }
+            | Statement::Interface { name, .. } => names.push(name),
+            Statement::TypeDef { name, fields, .. } => {
+                names.push(name);
+                names.extend(fields.iter().map(|field| field.name.as_str()));
+            }
+            Statement::If {
+                then_body,
+                else_body,
+                ..
+            } => {
+                declared_names(then_body, names);
+                declared_names(else_body.as_deref().unwrap_or_default(), names);
+            }
+            Statement::IfPresent {
+                name,
+                then_body,
+                else_body,
+                ..
+            } => {
+                names.push(name);
+                declared_names(then_body, names);
+                declared_names(else_body.as_deref().unwrap_or_default(), names);
+            }
+            Statement::ForIn {
+                index,
+                variable,
+                body,
+                ..
+            } => {
+                names.extend(index.as_deref());
+                names.push(variable);
+                declared_names(body, names);
+            }
+            Statement::ForLoop { init, body, .. } => {
+                declared_names(
+                    init.as_deref()
+                        .map(std::slice::from_ref)
+                        .unwrap_or_default(),
+                    names,
+                );
+                declared_names(body, names);
+            }
+            Statement::ForCondition { body, .. }
+            | Statement::DoWhile { body, .. }
+            | Statement::Defer { body }
+            | Statement::Unsafe { body } => declared_names(body, names),
+            Statement::Function {
+                name,
+                receiver,
+                params,
+                body,
+                ..
+            } => {
+                // Methods are named Type_method; only the method part is the user's
+                let method = match receiver {
+                    Some(_) => name.split_once('_').map_or(name.as_str(), |(_, m)| m),
+                    None => name.as_str(),
+                };
+                names.push(method);
+                names.extend(receiver.iter().chain(params).map(|p| p.name.as_str()));
+                declared_names(body, names);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Views borrow memory they don't own, so they may only live in locals,
+/// parameters and return values. Storing one in a struct (or inside a
+/// generic container, which is a struct too) could outlive what it points
+/// into, and we don't track lifetimes, so that is rejected outright.
+///
+/// Integer literals given a fixed-width type (by annotation, parameter or
+/// return type) must fit in it.
+///
+/// Optionals (`T?`) may be none, so they have to be unwrapped with
+/// `if (val x := opt)` (or tested against `none`) before their value is used,
+/// and `none` only goes where an optional is expected.
+///
+/// A type that `implements` an interface must have a method matching each of
+/// its signatures, and only such types may be passed where the interface is
+/// expected.
+///
+/// Type and function names that look like a generated instance of a generic
+/// (`Array_Integer`, `first_String`) are rejected, since their C definitions
+/// would collide.
+///
+/// Checking goes on past a failing statement, so the error lists every
+/// statement's first problem, one per line. On success, returns the
+/// program's lint warnings (see `Warning`).
+pub fn check_program(program: &Program) -> Result<Vec<Warning>, String> {
+    check_statements(&program.statements)
+}
+
+/// `check_program` once imports are resolved, with the constants the program
+/// imports (see `ModuleSystem::imported_constants`)
+pub fn check_program_with_constants(
+    program: &Program,
+    imported: &[Constant],
+) -> Result<Vec<Warning>, String> {
+    check_statements_with_constants(&program.statements, imported)
+}
+
+pub fn check_statements(statements: &[Statement]) -> Result<Vec<Warning>, String> {
+    check_statements_with_constants(statements, &[])
+}
+
+fn check_statements_with_constants(
+    statements: &[Statement],
+    imported: &[Constant],
+) -> Result<Vec<Warning>, String> {
+    let mut checker = Checker {
+        parameters: HashMap::new(),
+        return_types: HashMap::new(),
+        interfaces: HashMap::new(),
+        implementations: HashMap::new(),
+        fields: HashMap::new(),
+        scopes: vec![HashMap::new()],
+        constants: HashSet::new(),
+        warnings: Vec::new(),
+        errors: Vec::new(),
+        unsafe_depth: 0,
+        generics: BUILTIN_GENERICS
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        variadic_externs: HashMap::new(),
+        externs: HashSet::new(),
+    };
+    // Constants are visible everywhere in the module, like functions
+    let own = evaluate_constants_with(statements, imported)?;
+    if let Some(constant) = own
+        .iter()
+        .find(|constant| imported.iter().any(|i| i.name == constant.name))
+    {
+        return Err(format!(
+            "Constant '{}' is already imported; give this one another name",
+            constant.name
+        ));
+    }
+    for constant in imported.iter().cloned().chain(own) {
+        checker.declare(&constant.name, constant.const_type);
+        checker.constants.insert(constant.name);
+    }
+    for statement in statements {
+        match statement {
+            Statement::Function {
+                name,
+                type_params,
+                params,
+                return_type,
+                ..
+            } => {
+                if !type_params.is_empty() {
+                    checker.generics.insert(name.clone());
+                }
+                checker.parameters.insert(name.clone(), params.clone());
+                if let Some(t) = return_type {
+                    checker.return_types.insert(name.clone(), t.clone());
+                }
+            }
+            Statement::Interface { name, methods } => {
+                checker.interfaces.insert(name.clone(), methods.clone());
+            }
+            Statement::ExternBlock { functions, .. } => {
+                checker
+                    .externs
+                    .extend(functions.iter().map(|function| function.name.clone()));
+                for function in functions.iter().filter(|function| function.variadic) {
+                    checker
+                        .variadic_externs
+                        .insert(function.name.clone(), function.params.clone());
+                }
+            }
+            Statement::TypeDef {
+                name,
+                type_params,
+                fields,
+                implements,
+                ..
+            } => {
+                if type_params.is_empty() {
+                    checker.fields.insert(name.clone(), fields.clone());
+                } else {
+                    checker.generics.insert(name.clone());
+                }
+                checker
+                    .implementations
+                    .insert(name.clone(), implements.clone());
+            }
+            _ => {}
+        }
+    }
+    if let Err(message) = check_captures([], statements) {
+        checker.errors.push(message);
+    }
+    checker.check_block(statements, None);
+    if !checker.errors.is_empty() {
+        return Err(checker.errors.join("\n"));
+    }
+    let mut warnings = checker.warnings;
+    warnings.extend(check_warnings(statements));
+    Ok(warnings)
+}
+
+/// The arguments of a call to a function taking `params`, in parameter
+/// order: named arguments (`greet(name: "Ann")`) move to their parameter's
+/// position, and parameters the call leaves out get their default value.
+/// Positional arguments come first; any beyond the parameters are kept.
+pub fn call_arguments(
+    callee: &str,
+    params: &[Parameter],
+    args: Vec<Expression>,
+) -> Result<Vec<Expression>, String> {
+    let named = |arg: &Expression| matches!(arg, Expression::NamedArgument { .. });
+    if args.len() >= params.len() && !args.iter().any(named) {
+        return Ok(args);
+    }
+
+    let mut slots: Vec<Option<Expression>> = vec![None; params.len()];
+    let mut extra = Vec::new();
+    let mut seen_named = false;
+    for (index, arg) in args.into_iter().enumerate() {
+        match arg {
+            Expression::NamedArgument { name, value } => {
+                seen_named = true;
+                let Some(position) = params.iter().position(|param| param.name == name) else {
+                    return Err(format!("'{}' has no parameter named '{}'", callee, name));
+                };
+                if slots[position].is_some() {
+                    return Err(format!(
+                        "Argument '{}' of '{}' is given more than once",
+                        name, callee
+                    ));
+                }
+                slots[position] = Some(*value);
+            }
+            _ if seen_named => {
+                return Err(format!(
+                    "Positional arguments must come before named arguments in the call to '{}'",
+                    callee
+                ))
+            }
+            arg => match slots.get_mut(index) {
+                Some(slot) => *slot = Some(arg),
+                None => extra.push(arg),
+            },
+        }
+    }
+    params
+        .iter()
+        .zip(slots)
+        .map(|(param, slot)| {
+            slot.or_else(|| param.default.clone()).ok_or_else(|| {
+                format!(
+                    "Missing argument '{}' in the call to '{}'",
+                    param.name, callee
+                )
+            })
+        })
+        .chain(extra.into_iter().map(Ok))
+        .collect()
+}
+
+/// The `T` of an optional type (`T?` / `Optional[T]`)
+pub fn optional_inner(t: &Type) -> Option<&Type> {
+    match t {
+        Type::Generic { name, type_params } if name == "Optional" && type_params.len() == 1 => {
+            type_params.first()
+        }
+        _ => None,
+    }
+}
+
+/// The key and value types of a `Map[K, V]`
+pub fn map_types(t: &Type) -> Option<(&Type, &Type)> {
+    match t {
+        Type::Generic { name, type_params } if name == "Map" => match type_params.as_slice() {
+            [key, value] => Some((key, value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether `value as to` is allowed for a value of type `from`: between
+/// numeric types (Integer, Float, sized integers), from Bool to an integer
+/// type, and between pointer types
+pub fn castable(from: &Type, to: &Type) -> bool {
+    let numeric = |t: &Type| matches!(t, Type::Integer | Type::Float | Type::SizedInt(_));
+    let integer = |t: &Type| matches!(t, Type::Integer | Type::SizedInt(_));
+    from == to
+        || (numeric(from) && numeric(to))
+        || (*from == Type::Bool && integer(to))
+        || matches!((from, to), (Type::Pointer(_), Type::Pointer(_)))
+}
+
+struct Checker {
+    parameters: HashMap<String, Vec<Parameter>>, // Top-level function name -> parameters
+    return_types: HashMap<String, Type>,         // Top-level function name -> return type
+    interfaces: HashMap<String, Vec<InterfaceMethod>>, // Interface name -> required methods
+    implementations: HashMap<String, Vec<String>>, // Struct name -> interfaces it implements
+    fields: HashMap<String, Vec<Field>>,         // Non-generic struct name -> its fields
+    scopes: Vec<HashMap<String, Type>>, // Known variable types per open block, innermost last
+    constants: HashSet<String>,         // Module-level `const` names
+    warnings: Vec<Warning>,             // Lints that need the types
+    errors: Vec<String>,                // One per statement that failed, in order
+    unsafe_depth: usize,                // Open `unsafe` blocks around the statement
+    generics: HashSet<String>,          // Generic type and function names
+    variadic_externs: HashMap<String, Vec<Parameter>>, // extern "C" functions taking `...` -> their other parameters
+    externs: HashSet<String>, // Every extern "C" function the program declares
+}
+
+impl Checker {
+    // A statement's error doesn't stop the block: later statements are
+    // still checked, so one run reports every independent error
+    fn check_block(&mut self, statements: &[Statement], return_type: Option<&Type>) {
+        for statement in statements {
+            if let Err(message) = self.check_statement(statement, return_type) {
+                self.errors.push(message);
+            }
+        }
+    }
+
+    // A nested block, with its own variables and optionally one binding
+    fn check_scoped_block(
+        &mut self,
+        statements: &[Statement],
+        return_type: Option<&Type>,
+        binding: Option<(&str, Type)>,
+    ) {
+        let mut scope = HashMap::new();
+        if let Some((name, t)) = binding {
+            scope.insert(name.to_string(), t);
+        }
+        self.scopes.push(scope);
+        self.check_block(statements, return_type);
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &str, t: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), t);
+        }
+    }
+
+    // `if count { }` is `count != 0` in C; the implicit-int-condition lint
+    // asks for the comparison to be written out
+    fn lint_condition(&mut self, condition: &Expression) {
+        if let Some(t @ (Type::Integer | Type::SizedInt(_))) = self.expression_type(condition) {
+            self.warnings.push(Warning::new(
+                "implicit-int-condition",
+                format!(
+                    "This condition is an {}, not a Bool; compare it explicitly, e.g. `!= 0`",
+                    t
+                ),
+            ));
+        }
+    }
+
+    // The message of a panic or assert, which is printed as is
+    fn check_message(&self, builtin: &str, message: &Expression) -> Result<(), String> {
+        match self.expression_type(message) {
+            Some(Type::String) | None => self.check_expression(message),
+            Some(t) => Err(format!(
+                "{}'s message must be a String, not {}; use format() or toString()",
+                builtin, t
+            )),
+        }
+    }
+
+    // Uses of a constant are replaced by its value, so nothing may reuse the name
+    fn check_not_constant(&self, name: &str) -> Result<(), String> {
+        if self.constants.contains(name) {
+            return Err(format!(
+                "'{}' is a constant; it can't be assigned or declared again",
+                name
+            ));
+        }
+        Ok(())
+    }
+
+    // Map keys are hashed and compared by value. A generic function's type
+    // parameter is checked once it's bound, in the instance's own map type.
+    fn is_hashable(t: &Type) -> bool {
+        matches!(
+            t,
+            Type::Integer | Type::String | Type::Bool | Type::SizedInt(_) | Type::TypeParameter(_)
+        )
+    }
+
+    // See `default_names`: a parameter or local in a default would be looked
+    // up in the caller's scope instead of the callee's
+    fn check_default_names<'a>(
+        &self,
+        default: &Expression,
+        param: &str,
+        params: impl Iterator<Item = &'a Parameter> + Clone,
+    ) -> Result<(), String> {
+        for name in default_names(default) {
+            let local = params.clone().any(|p| p.name == name)
+                || (self.lookup(name).is_some() && !self.constants.contains(name));
+            if local {
+                return Err(format!(
+                    "Default value of '{}' can't use '{}': defaults are evaluated where the function is called, so they may only use literals, constants and function calls",
+                    param, name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    // Static type of an expression, where it is known without full inference
+    fn expression_type(&self, expr: &Expression) -> Option<Type> {
+        match expr {
+            Expression::IntegerLiteral(_) => Some(Type::Integer),
+            Expression::FloatLiteral(_) => Some(Type::Float),
+            Expression::StringLiteral(_) | Expression::StringInterpolation(_) => Some(Type::String),
+            Expression::BoolLiteral(_) => Some(Type::Bool),
+            Expression::Identifier(name) => self.lookup(name).cloned(),
+            Expression::FunctionCall { name, args }
+                if name == "clone" && args.len() == 1 && !self.return_types.contains_key(name) =>
+            {
+                self.expression_type(&args[0])
+            }
+            Expression::FunctionCall { name, .. } => self.return_types.get(name).cloned(),
+            Expression::StructLiteral {
+                type_name,
+                type_args: None,
+                ..
+            } => Some(Type::Custom(type_name.clone())),
+            Expression::MapLiteral {
+                type_args: Some(type_args),
+                ..
+            } => Some(Type::Generic {
+                name: "Map".to_string(),
+                type_params: type_args.clone(),
+            }),
+            Expression::MapLiteral { entries, .. } => {
+                let (key, value) = entries.first()?;
+                Some(Type::Generic {
+                    name: "Map".to_string(),
+                    type_params: vec![self.expression_type(key)?, self.expression_type(value)?],
+                })
+            }
+            Expression::ArrayLiteral(elements) => Some(Type::Array(Box::new(
+                self.expression_type(elements.first()?)?,
+            ))),
+            Expression::TupleLiteral(elements) => Some(Type::Tuple(
+                elements
+                    .iter()
+                    .map(|element| self.expression_type(element))
+                    .collect::<Option<_>>()?,
+            )),
+            Expression::FieldAccess { object, field } => match self.expression_type(object)? {
+                Type::Tuple(elements) => elements.get(field.parse::<usize>().ok()?).cloned(),
+                _ => None,
+            },
+            Expression::Grouping(inner) => self.expression_type(inner),
+            Expression::If { then_value, .. } => self.expression_type(then_value),
+            Expression::Cast { target, .. } => Some(target.clone()),
+            Expression::Allocate { element, .. } => Some(Type::pointer(element.clone())),
+            Expression::AddressOf { operand } => {
+                Some(Type::pointer(self.expression_type(operand)?))
+            }
+            Expression::Dereference { operand } => match self.expression_type(operand)? {
+                Type::Pointer(target) => Some(*target),
+                _ => None,
+            },
+            Expression::BinaryOp {
+                left,
+                operator: operator @ (BinaryOperator::Add | BinaryOperator::Subtract),
+                right,
+            } => match (self.expression_type(left)?, self.expression_type(right)) {
+                (Type::Pointer(_), Some(Type::Pointer(_)))
+                    if *operator == BinaryOperator::Subtract =>
+                {
+                    Some(Type::Integer)
+                }
+                (pointer @ Type::Pointer(_), _) => Some(pointer),
+                _ => None,
+            },
+            // The fallback has the result's type, optional only if it is
+            Expression::Coalesce { fallback, .. } => self.expression_type(fallback),
+            _ => None,
+        }
+    }
+
+    // Strings compare by content (strcmp in C), so only with other strings
+    fn check_comparable(
+        &self,
+        left: &Expression,
+        operator: &BinaryOperator,
+        right: &Expression,
+    ) -> Result<(), String> {
+        let Some(symbol) = comparison_symbol(operator) else {
+            return Ok(());
+        };
+        let scalar = |t: &Type| {
+            matches!(
+                t,
+                Type::Integer | Type::Float | Type::Bool | Type::SizedInt(_)
+            )
+        };
+        match (self.expression_type(left), self.expression_type(right)) {
+            (Some(Type::String), Some(other)) | (Some(other), Some(Type::String))
+                if scalar(&other) =>
+            {
+                Err(format!(
+                    "Can't compare a String with {} using {}",
+                    other, symbol
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // A pointer moves by a whole number of its targets (`p + n`, `p - n`)
+    // and two pointers to the same type subtract to the distance between
+    // them; nothing else does arithmetic on a pointer, so an address never
+    // turns into a number or back without a cast
+    fn check_pointer_arithmetic(
+        &self,
+        left: &Expression,
+        operator: &BinaryOperator,
+        right: &Expression,
+    ) -> Result<(), String> {
+        let (left_type, right_type) = (self.expression_type(left), self.expression_type(right));
+        let is_pointer = |t: &Option<Type>| matches!(t, Some(Type::Pointer(_)));
+        if !is_pointer(&left_type) && !is_pointer(&right_type) {
+            return Ok(());
+        }
+        let symbol = match operator {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            _ => return Ok(()), // Comparisons, and && / || on null tests
+        };
+        let describe = |t: &Option<Type>| t.as_ref().map_or("a value".to_string(), Type::to_string);
+        match (operator, &left_type, &right_type) {
+            (
+                BinaryOperator::Add | BinaryOperator::Subtract,
+                Some(Type::Pointer(_)),
+                None | Some(Type::Integer | Type::SizedInt(_)),
+            ) => Ok(()),
+            (BinaryOperator::Subtract, Some(from), Some(to)) if from == to => Ok(()),
+            (BinaryOperator::Subtract, Some(Type::Pointer(_)), Some(Type::Pointer(_))) => {
+                Err(format!(
+                    "Can't subtract a {} from a {}; only pointers to the same type can be subtracted",
+                    describe(&right_type),
+                    describe(&left_type)
+                ))
+            }
+            (BinaryOperator::Add, _, Some(Type::Pointer(_))) if !is_pointer(&left_type) => {
+                Err(format!(
+                    "Pointer arithmetic puts the pointer first: write `pointer + offset`, not `{} + {}`",
+                    describe(&left_type),
+                    describe(&right_type)
+                ))
+            }
+            _ => Err(format!(
+                "Can't use {} on {} and {}; a pointer only supports `+ n` and `- n` with an integer n, and `-` with another pointer to the same type",
+                symbol,
+                describe(&left_type),
+                describe(&right_type)
+            )),
+        }
+    }
+
+    // A scalar annotation decides the variable's C type, so the value must
+    // have that type; an Integer is widened to a Float
+    fn check_declared_type(
+        &self,
+        name: &str,
+        value: &Expression,
+        declared: &Type,
+    ) -> Result<(), String> {
+        let scalar =
+            |t: &Type| matches!(t, Type::Integer | Type::Float | Type::Bool | Type::String);
+        match self.expression_type(value) {
+            Some(found)
+                if scalar(declared)
+                    && scalar(&found)
+                    && found != *declared
+                    && !(found == Type::Integer && *declared == Type::Float) =>
+            {
+                Err(format!(
+                    "'{}' is declared as {} but its value is {}",
+                    name, declared, found
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // `.N` on a tuple names one of its elements, so N must be below its arity
+    fn check_tuple_index(&self, object: &Expression, field: &str) -> Result<(), String> {
+        let (Some(Type::Tuple(elements)), Ok(index)) =
+            (self.expression_type(object), field.parse::<usize>())
+        else {
+            return Ok(());
+        };
+        if index < elements.len() {
+            return Ok(());
+        }
+        Err(format!(
+            "Tuple {} has no element .{}; it has {} elements, from .0",
+            Type::Tuple(elements.clone()),
+            index,
+            elements.len()
+        ))
+    }
+
+    // An optional used as if it were its value, e.g. `x + 1` or `x.name`
+    fn check_not_optional(&self, expr: &Expression) -> Result<(), String> {
+        if let Expression::NoneLiteral = expr {
+            return Err("'none' can only be compared with == or !=".to_string());
+        }
+        match self.expression_type(expr) {
+            Some(t) if optional_inner(&t).is_some() => Err(match expr {
+                Expression::Identifier(name) => format!(
+                    "'{}' is optional ({}) and may be none; unwrap it with `if (val value := {})` first",
+                    name, t, name
+                ),
+                _ => format!(
+                    "This value is optional ({}) and may be none; unwrap it with `if (val value := ...)` first",
+                    t
+                ),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // The generic whose instances codegen would also call `name`: the
+    // generic's name, `_`, then type arguments, which start upper case
+    fn generated_name_base(&self, name: &str) -> Option<&str> {
+        self.generics
+            .iter()
+            .find(|generic| {
+                name.strip_prefix(generic.as_str())
+                    .and_then(|rest| rest.strip_prefix('_'))
+                    .is_some_and(|args| args.starts_with(|c: char| c.is_ascii_uppercase()))
+            })
+            .map(String::as_str)
+    }
+
+    // A value stored in (or passed as, or returned as) the expected type
+    // Each method an interface requires, with the same parameter and return
+    // types, among the methods declared on `type_name`
+    fn check_implements(&self, type_name: &str, interface: &str) -> Result<(), String> {
+        let Some(required) = self.interfaces.get(interface) else {
+            return Err(format!(
+                "'{}' implements '{}', which is not an interface",
+                type_name, interface
+            ));
+        };
+        for method in required {
+            let function = method_function_name(type_name, &method.name);
+            let wanted = signature(
+                &method.name,
+                method.params.iter().map(|param| &param.param_type),
+                method.return_type.as_ref(),
+            );
+            let Some(params) = self.parameters.get(&function) else {
+                return Err(format!(
+                    "{} doesn't implement {}: missing method `{}`",
+                    type_name, interface, wanted
+                ));
+            };
+            let found = signature(
+                &method.name,
+                params.iter().map(|param| &param.param_type),
+                self.return_types.get(&function),
+            );
+            if found != wanted {
+                return Err(format!(
+                    "{} doesn't implement {}: `{}` should be `{}`",
+                    type_name, interface, found, wanted
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Methods are functions declared on a struct of the program (`fun
+    // (p: Point) name()`), an interface's, or bolt's push/pop/insert/get on
+    // an Array[T]; nothing else has methods, in either backend
+    fn check_method_exists(&self, t: &Type, method: &str) -> Result<(), String> {
+        let hint = match t {
+            Type::Custom(name) if self.interfaces.contains_key(name) => return Ok(()),
+            Type::Custom(name) if self.implementations.contains_key(name) => {
+                if self
+                    .parameters
+                    .contains_key(&method_function_name(name, method))
+                {
+                    return Ok(());
+                }
+                ""
+            }
+            // Declared in another module, or bound by an instance
+            Type::Custom(_) | Type::TypeParameter(_) => return Ok(()),
+            Type::Generic { name, .. } if name == "Array" => {
+                if ARRAY_METHODS.contains(&method) {
+                    return Ok(());
+                }
+                ""
+            }
+            Type::Generic { name, .. } if name == "Map" => {
+                "; maps are used through bolt:map's functions, like set(map, key, value)"
+            }
+            Type::Array(_) if ARRAY_METHODS.contains(&method) => {
+                "; an array literal has a fixed size, use an Array[T] to grow it"
+            }
+            _ => "",
+        };
+        let hint = match method {
+            "clone" => "; call clone(value) instead",
+            _ => hint,
+        };
+        Err(format!("{} has no method '{}'{}", t, method, hint))
+    }
+
+    // A value passed where an interface is expected must be of a type that
+    // implements it
+    fn check_conforms(
+        &self,
+        value: &Expression,
+        expected: &Type,
+        callee: &str,
+    ) -> Result<(), String> {
+        let Type::Custom(interface) = expected else {
+            return Ok(());
+        };
+        if !self.interfaces.contains_key(interface) {
+            return Ok(());
+        }
+        match self.expression_type(value) {
+            Some(Type::Custom(name))
+                if name == *interface
+                    || self
+                        .implementations
+                        .get(&name)
+                        .is_some_and(|implemented| implemented.contains(interface)) =>
+            {
+                Ok(())
+            }
+            Some(t) => Err(format!(
+                "{} doesn't implement {}, which '{}' expects",
+                t, interface, callee
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn check_assignable(&self, value: &Expression, expected: &Type) -> Result<(), String> {
+        check_literal_fits(value, expected)?;
+        // `none` is also the null pointer, which ends a linked list
+        if optional_inner(expected).is_some() || matches!(expected, Type::Pointer(_)) {
+            return Ok(());
+        }
+        if let Expression::NoneLiteral = value {
+            return Err(format!(
+                "'none' can only be used as an optional value or a pointer, not {}",
+                expected
+            ));
+        }
+        self.check_not_optional(value)
+    }
+
+    // A value where `expected` is expected. An integer literal takes that
+    // type, so only a literal that isn't a sized integer or a Float (also in
+    // an array literal) is held to Integer's range
+    fn check_typed_value(&self, value: &Expression, expected: &Type) -> Result<(), String> {
+        self.check_assignable(value, expected)?;
+        match (value, expected) {
+            (_, Type::SizedInt(_) | Type::Float) if integer_literal(value).is_some() => Ok(()),
+            (Expression::ArrayLiteral(elements), Type::Generic { name, type_params })
+                if name == "Array" && type_params.len() == 1 =>
+            {
+                elements
+                    .iter()
+                    .try_for_each(|element| self.check_typed_value(element, &type_params[0]))
+            }
+            (Expression::ArrayLiteral(elements), Type::Array(element_type)) => elements
+                .iter()
+                .try_for_each(|element| self.check_typed_value(element, element_type)),
+            _ => self.check_expression(value),
+        }
+    }
+
+    // An operand of a binary operator: a literal takes the sized integer or
+    // Float type of the other operand
+    fn check_operand(&self, operand: &Expression, other: &Expression) -> Result<(), String> {
+        match self.expression_type(other) {
+            Some(t @ (Type::SizedInt(_) | Type::Float)) if integer_literal(operand).is_some() => {
+                check_literal_fits(operand, &t)
+            }
+            _ => self.check_expression(operand),
+        }
+    }
+
+    fn check_statement(
+        &mut self,
+        statement: &Statement,
+        return_type: Option<&Type>,
+    ) -> Result<(), String> {
+        if let Statement::TypeDef {
+            name,
+            fields,
+            implements,
+            ..
+        } = statement
+        {
+            if let Some(generic) = self.generated_name_base(name) {
+                return Err(format!(
+                    "Type name '{}' collides with the structs generated for {}[...]; rename it",
+                    name, generic
+                ));
+            }
+            for interface in implements {
+                self.check_implements(name, interface)?;
+            }
+            for field in fields {
+                if contains_view(&field.field_type) {
+                    return Err(format!(
+                        "Field '{}' of type '{}' can't hold a view ({}); store an owned copy instead",
+                        field.name, name, field.field_type
+                    ));
+                }
+            }
+        }
+        for t in declared_types(statement) {
+            let (name, elements) = match t {
+                Type::Generic { name, type_params } => (name.as_str(), type_params),
+                Type::Tuple(elements) => ("Tuple", elements),
+                _ => continue,
+            };
+            if elements.iter().any(contains_view) {
+                return Err(format!(
+                    "'{}' can't hold a view ({}); store owned copies instead",
+                    name, t
+                ));
+            }
+            if let Some((key, _)) = map_types(t) {
+                if !Self::is_hashable(key) {
+                    return Err(format!(
+                        "{} can't be a map key in {}; use Integer, String or Bool keys",
+                        key, t
+                    ));
+                }
+            }
+        }
+
+        match statement {
+            Statement::ValDecl {
+                name,
+                type_annotation,
+                value,
+            }
+            | Statement::VarDecl {
+                name,
+                type_annotation,
+                value,
+            } => {
+                self.check_not_constant(name)?;
+                match type_annotation {
+                    Some(t) => self.check_typed_value(value, t)?,
+                    None if matches!(value, Expression::NoneLiteral) => {
+                        return Err(format!(
+                            "Can't infer the type of '{}' from 'none'; declare it like `{}: Integer? = none`",
+                            name, name
+                        ))
+                    }
+                    None if matches!(
+                        value,
+                        Expression::MapLiteral { type_args: None, entries } if entries.is_empty()
+                    ) =>
+                    {
+                        return Err(format!(
+                            "Can't infer the type of the empty map '{}'; declare it like `{}: Map[String, Integer] = {{}}`",
+                            name, name
+                        ))
+                    }
+                    None => self.check_expression(value)?,
+                }
+                if let Some(t) = type_annotation {
+                    self.check_declared_type(name, value, t)?;
+                }
+                if let Some(t) = type_annotation
+                    .clone()
+                    .or_else(|| self.expression_type(value))
+                {
+                    self.declare(name, t);
+                }
+            }
+            Statement::Function {
+                name,
+                type_params,
+                receiver,
+                params,
+                return_type,
+                body,
+                ..
+            } => {
+                if let (None, Some(generic)) = (receiver, self.generated_name_base(name)) {
+                    return Err(format!(
+                        "Function name '{}' collides with the instances generated for '{}'; rename it",
+                        name, generic
+                    ));
+                }
+                if let (true, None, Some(t)) = (name == "main", receiver, return_type) {
+                    if *t != Type::Integer {
+                        return Err(format!(
+                            "main returns an Integer (the exit status) or nothing, not {}",
+                            t
+                        ));
+                    }
+                }
+                // The C main wrapper can pass the command line and nothing else
+                if name == "main" && receiver.is_none() {
+                    match params.as_slice() {
+                        [] => {}
+                        [param] if is_string_array(&param.param_type) => {}
+                        _ => {
+                            return Err(
+                                "main takes no parameters or a single `args: Array[String]`"
+                                    .to_string(),
+                            )
+                        }
+                    }
+                }
+                // Defaults are evaluated at each call site that leaves them out
+                for param in receiver.iter().chain(params) {
+                    self.check_not_constant(&param.name)?;
+                    if let Some(default) = &param.default {
+                        self.check_default_names(
+                            default,
+                            &param.name,
+                            receiver.iter().chain(params),
+                        )?;
+                        self.check_typed_value(default, &param.param_type)?;
+                    }
+                }
+                // Type arguments are only ever inferred from the arguments
+                if let Some(unused) = type_params.iter().find(|type_param| {
+                    !params
+                        .iter()
+                        .any(|param| mentions_type(&param.param_type, type_param))
+                }) {
+                    return Err(format!(
+                        "Type parameter '{}' of '{}' isn't used by any parameter, so it can't be inferred",
+                        unused, name
+                    ));
+                }
+                check_captures(receiver.iter().chain(params), body)?;
+                self.scopes.push(
+                    receiver
+                        .iter()
+                        .chain(params)
+                        .map(|param| (param.name.clone(), param.param_type.clone()))
+                        .collect(),
+                );
+                self.check_block(body, return_type.as_ref());
+                self.scopes.pop();
+                return Ok(());
+            }
+            Statement::Return(Some(value)) => match return_type {
+                Some(t) => self.check_typed_value(value, t)?,
+                None => self.check_expression(value)?,
+            },
+            Statement::Assignment { variable, value } => {
+                self.check_not_constant(variable)?;
+                match self.lookup(variable) {
+                    Some(t) => self.check_typed_value(value, t)?,
+                    None => self.check_expression(value)?,
+                }
+            }
+            Statement::IfPresent {
+                name,
+                value,
+                then_body,
+                else_body,
+            } => {
+                self.check_not_constant(name)?;
+                let value_type = self.expression_type(value);
+                let binding = match &value_type {
+                    Some(t) => match optional_inner(t) {
+                        Some(inner) => Some((name.as_str(), inner.clone())),
+                        None => {
+                            return Err(format!(
+                                "`if (val {} := ...)` needs an optional value, got {}",
+                                name, t
+                            ))
+                        }
+                    },
+                    None => None,
+                };
+                self.check_expression(value)?;
+                self.check_scoped_block(then_body, return_type, binding);
+                if let Some(body) = else_body {
+                    self.check_scoped_block(body, return_type, None);
+                }
+            }
+            Statement::Expression(expr) => self.check_effect(expr)?,
+            Statement::Panic { message, .. } => self.check_message("panic", message)?,
+            Statement::Assert {
+                condition, message, ..
+            } => {
+                match self.expression_type(condition) {
+                    Some(Type::Bool) | None => {}
+                    Some(t) => {
+                        return Err(format!(
+                        "assert needs a Bool condition, not {}; compare it explicitly, e.g. `!= 0`",
+                        t
+                    ))
+                    }
+                }
+                self.check_expression(condition)?;
+                self.check_message("assert", message)?
+            }
+            Statement::If { condition, .. }
+            | Statement::ForCondition { condition, .. }
+            | Statement::DoWhile { condition, .. }
+            | Statement::ForLoop {
+                condition: Some(condition),
+                ..
+            } => {
+                self.lint_condition(condition);
+                self.check_expression(condition)?
+            }
+            Statement::ForIn {
+                index,
+                variable,
+                iterable,
+                ..
+            } => {
+                self.check_not_constant(variable)?;
+                if let Some(index) = index {
+                    self.check_not_constant(index)?;
+                    if index == variable {
+                        return Err(format!(
+                            "'{}' can't name both the index and the element of a for-in loop",
+                            index
+                        ));
+                    }
+                    if let Expression::Range { .. } = iterable {
+                        return Err(format!(
+                            "A range has no separate index; write `for {} in ...`",
+                            variable
+                        ));
+                    }
+                }
+                match iterable {
+                    // The one place a range is a value
+                    Expression::Range { start, end, .. } => {
+                        for bound in [start, end] {
+                            self.check_not_optional(bound)?;
+                            self.check_expression(bound)?;
+                        }
+                    }
+                    _ => self.check_expression(iterable)?,
+                }
+            }
+            Statement::Unsafe { body } => {
+                self.unsafe_depth += 1;
+                self.check_scoped_block(body, return_type, None);
+                self.unsafe_depth -= 1;
+            }
+            Statement::Asm { outputs, .. } => {
+                if self.unsafe_depth == 0 {
+                    return Err("asm is only allowed inside an unsafe block".to_string());
+                }
+                for output in outputs {
+                    self.check_not_constant(&output.variable)?;
+                }
+            }
+            // Evaluated up front by evaluate_constants
+            Statement::ConstDecl {
+                name,
+                type_annotation,
+                value,
+                ..
+            } => {
+                if self.scopes.len() > 1 {
+                    return Err(format!(
+                        "Constant '{}' must be declared at module level, not inside a block",
+                        name
+                    ));
+                }
+                if let Some(t) = type_annotation {
+                    self.check_assignable(value, t)?;
+                }
+            }
+            _ => {}
+        }
+
+        for body in nested_bodies(statement) {
+            self.check_scoped_block(body, return_type, None);
+        }
+        Ok(())
+    }
+
+    // Arguments to typed parameters (literal ranges, optionals), and
+    // optionals used before they're unwrapped, anywhere in an expression
+    // An expression used as a value, so not a call to a function that
+    // returns Unit (has no return type)
+    fn check_expression(&self, expr: &Expression) -> Result<(), String> {
+        if let Expression::FunctionCall { name, .. } = expr {
+            if self.parameters.contains_key(name) && !self.return_types.contains_key(name) {
+                return Err(format!(
+                    "'{}' returns Unit (it has no return type), so its call can't be used as a value",
+                    name
+                ));
+            }
+        }
+        self.check_effect(expr)
+    }
+
+    // An expression evaluated for its effect, as a statement
+    fn check_effect(&self, expr: &Expression) -> Result<(), String> {
+        match expr {
+            // A literal without a sized type to take is an Integer
+            _ if integer_literal(expr).is_some() => check_literal_fits(expr, &Type::Integer),
+            Expression::FunctionCall { name, args } => {
+                // The builtins, unless the program declares its own, like
+                // an extern printf taking a C format
+                if FORMAT_FUNCTIONS.contains(&name.as_str())
+                    && !self.parameters.contains_key(name)
+                    && !self.externs.contains(name)
+                {
+                    self.check_format_call(name, args)?;
+                }
+                // The builtin, unless the program declares its own free
+                if name == "free" && !self.parameters.contains_key(name) {
+                    match (
+                        args.as_slice(),
+                        args.first().and_then(|a| self.expression_type(a)),
+                    ) {
+                        ([_], Some(Type::Pointer(_)) | None) => {}
+                        ([_], Some(t)) => {
+                            return Err(format!(
+                                "free takes a pointer from alloc or realloc, not a {}",
+                                t
+                            ))
+                        }
+                        _ => return Err("free takes exactly one pointer".to_string()),
+                    }
+                }
+                if name == "exit" && !self.parameters.contains_key(name) {
+                    match (
+                        args.as_slice(),
+                        args.first().and_then(|a| self.expression_type(a)),
+                    ) {
+                        ([_], Some(Type::Integer) | None) => {}
+                        _ => return Err("exit takes one Integer, the exit status".to_string()),
+                    }
+                }
+                if let Some(fixed) = self.variadic_externs.get(name) {
+                    self.check_c_format_call(name, fixed, args)?;
+                }
+                if let Some(params) = self.parameters.get(name) {
+                    let args = call_arguments(name, params, args.clone())?;
+                    if args.len() > params.len() {
+                        return Err(format!(
+                            "Too many arguments in the call to '{}': it takes {}, not {}",
+                            name,
+                            params.len(),
+                            args.len()
+                        ));
+                    }
+                    for (arg, param) in args.iter().zip(params) {
+                        self.check_typed_value(arg, &param.param_type)?;
+                        self.check_conforms(arg, &param.param_type, name)?;
+                    }
+                    return Ok(());
+                }
+                args.iter().try_for_each(|arg| self.check_expression(arg))
+            }
+            Expression::NamedArgument { value, .. } => self.check_expression(value),
+            Expression::MethodCall {
+                object,
+                method,
+                args,
+            } => {
+                // Only what the interface declares can be called through it
+                if let Some(Type::Custom(interface)) = self.expression_type(object) {
+                    if let Some(methods) = self.interfaces.get(&interface) {
+                        if !methods.iter().any(|m| m.name == *method) {
+                            return Err(format!("{} has no method '{}'", interface, method));
+                        }
+                    }
+                }
+                if let Some(t) = self.expression_type(object) {
+                    self.check_method_exists(&t, method)?;
+                }
+                // push and insert take a value of the array's element type
+                if let Some(Type::Generic { name, type_params }) = self.expression_type(object) {
+                    if let ("Array", "push" | "insert", [element], Some(value)) = (
+                        name.as_str(),
+                        method.as_str(),
+                        type_params.as_slice(),
+                        args.last(),
+                    ) {
+                        self.check_assignable(value, element)?;
+                        let is_number_literal = matches!(
+                            value,
+                            Expression::IntegerLiteral(_) | Expression::FloatLiteral(_)
+                        );
+                        match self.expression_type(value) {
+                            Some(found) if found != *element && !is_number_literal => {
+                                return Err(format!(
+                                    "{} expects {}, got {}",
+                                    method, element, found
+                                ));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                self.check_expression(object)?;
+                args.iter().try_for_each(|arg| self.check_expression(arg))
+            }
+            Expression::NamespacedFunctionCall {
+                namespace,
+                function,
+                args,
+            } if namespace == "stdio" && FORMAT_FUNCTIONS.contains(&function.as_str()) => {
+                self.check_format_call(function, args)?;
+                args.iter().try_for_each(|arg| self.check_expression(arg))
+            }
+            Expression::NamespacedFunctionCall { args, .. }
+            | Expression::ArrayLiteral(args)
+            | Expression::TupleLiteral(args) => {
+                args.iter().try_for_each(|arg| self.check_expression(arg))
+            }
+            Expression::BinaryOp {
+                left,
+                operator,
+                right,
+            } => {
+                check_not_chained(left, operator, right)?;
+                // `x == none` / `x != none` is how an optional is tested
+                let none_test =
+                    matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual)
+                        && (matches!(**left, Expression::NoneLiteral)
+                            || matches!(**right, Expression::NoneLiteral));
+                if !none_test {
+                    self.check_not_optional(left)?;
+                    self.check_not_optional(right)?;
+                    self.check_comparable(left, operator, right)?;
+                    self.check_pointer_arithmetic(left, operator, right)?;
+                }
+                self.check_operand(left, right)?;
+                self.check_operand(right, left)
+            }
+            Expression::UnaryOp {
+                operator: UnaryOperator::Negate,
+                operand,
+            } if matches!(
+                self.expression_type(operand),
+                Some(Type::String | Type::Bool)
+            ) =>
+            {
+                Err(format!(
+                    "Only numbers can be negated, not a {}",
+                    self.expression_type(operand).unwrap()
+                ))
+            }
+            Expression::UnaryOp { operand, .. } => {
+                self.check_not_optional(operand)?;
+                self.check_expression(operand)
+            }
+            Expression::FieldAccess { object, field } => {
+                self.check_not_optional(object)?;
+                self.check_tuple_index(object, field)?;
+                self.check_expression(object)
+            }
+            Expression::OptionalFieldAccess { object, field } => {
+                match self.expression_type(object) {
+                    Some(t) if optional_inner(&t).is_none() => Err(format!(
+                        "`?.{}` is for optional values, but this is a {}; use `.{}`",
+                        field, t, field
+                    )),
+                    _ => self.check_expression(object),
+                }
+            }
+            Expression::Coalesce { value, fallback } => {
+                if let Expression::NoneLiteral = **value {
+                    return Err("`none ?? ...` is always its fallback".to_string());
+                }
+                match self.expression_type(value) {
+                    Some(t) if optional_inner(&t).is_none() && !matches!(t, Type::Pointer(_)) => {
+                        Err(format!(
+                            "`??` needs an optional or a pointer on its left, not a {}",
+                            t
+                        ))
+                    }
+                    _ => {
+                        self.check_expression(value)?;
+                        self.check_expression(fallback)
+                    }
+                }
+            }
+            Expression::AddressOf { operand }
+            | Expression::Dereference { operand }
+            | Expression::Grouping(operand) => self.check_expression(operand),
+            Expression::Cast { value, target } => {
+                self.check_not_optional(value)?;
+                match self.expression_type(value) {
+                    Some(from) if !castable(&from, target) => {
+                        Err(format!("Can't cast {} to {}", from, target))
+                    }
+                    // `5000000000 as Int64` is how a wide literal is written
+                    // where its type isn't known otherwise
+                    _ if integer_literal(value).is_some() => match target {
+                        Type::SizedInt(_) | Type::Float => check_literal_fits(value, target),
+                        _ => self.check_expression(value),
+                    },
+                    _ => self.check_expression(value),
+                }
+            }
+            Expression::Allocate {
+                element,
+                block,
+                count,
+            } => {
+                match self.expression_type(count) {
+                    Some(Type::Integer | Type::SizedInt(_)) | None => {}
+                    Some(t) => {
+                        return Err(format!(
+                            "An allocation's count must be an integer, not {}",
+                            t
+                        ))
+                    }
+                }
+                if let Some(block) = block {
+                    match self.expression_type(block) {
+                        Some(Type::Pointer(target)) if *target == *element => {}
+                        None => {}
+                        Some(t) => {
+                            return Err(format!(
+                                "realloc[{}] resizes a {}, not a {}",
+                                element,
+                                Type::pointer(element.clone()),
+                                t
+                            ))
+                        }
+                    }
+                    self.check_expression(block)?;
+                }
+                self.check_expression(count)
+            }
+            Expression::ArrayAccess { array, index } => {
+                self.check_not_optional(array)?;
+                self.check_expression(array)?;
+                self.check_expression(index)
+            }
+            Expression::Slice { object, start, end } => {
+                self.check_not_optional(object)?;
+                if let Some(t) = self.expression_type(object) {
+                    let sliceable = !matches!(
+                        t,
+                        Type::Integer
+                            | Type::Float
+                            | Type::Bool
+                            | Type::SizedInt(_)
+                            | Type::Tuple(_)
+                    ) && map_types(&t).is_none();
+                    if !sliceable {
+                        return Err(format!("Only arrays and strings can be sliced, not {}", t));
+                    }
+                }
+                [object, start, end]
+                    .into_iter()
+                    .try_for_each(|part| self.check_expression(part))
+            }
+            Expression::StructLiteral {
+                type_name,
+                type_args: None,
+                fields,
+            } if self.fields.contains_key(type_name) => {
+                for field in fields {
+                    match self.fields[type_name].iter().find(|f| f.name == field.name) {
+                        Some(declared) => {
+                            self.check_typed_value(&field.value, &declared.field_type)?
+                        }
+                        None => self.check_expression(&field.value)?,
+                    }
+                }
+                Ok(())
+            }
+            Expression::StructLiteral { fields, .. } => fields
+                .iter()
+                .try_for_each(|field| self.check_expression(&field.value)),
+            Expression::MapLiteral { type_args, entries } => {
+                if let Some(args) = type_args {
+                    if args.len() != 2 {
+                        return Err(
+                            "Map takes a key and a value type, like Map[String, Integer]"
+                                .to_string(),
+                        );
+                    }
+                    if !Self::is_hashable(&args[0]) {
+                        return Err(format!(
+                            "{} can't be a map key; use Integer, String or Bool keys",
+                            args[0]
+                        ));
+                    }
+                }
+                // Every entry has the key and value types of the first (or
+                // of the constructor's type arguments)
+                let mut expected = type_args
+                    .as_ref()
+                    .map(|args| (args.first().cloned(), args.get(1).cloned()));
+                for (key, value) in entries {
+                    self.check_not_optional(key)?;
+                    self.check_expression(key)?;
+                    self.check_expression(value)?;
+                    let (key_type, value_type) =
+                        (self.expression_type(key), self.expression_type(value));
+                    if let Some(t) = &key_type {
+                        if !Self::is_hashable(t) {
+                            return Err(format!(
+                                "{} can't be a map key; use Integer, String or Bool keys",
+                                t
+                            ));
+                        }
+                    }
+                    match &expected {
+                        None => expected = Some((key_type, value_type)),
+                        Some((expected_key, expected_value)) => {
+                            for (found, wanted, what) in [
+                                (&key_type, expected_key, "key"),
+                                (&value_type, expected_value, "value"),
+                            ] {
+                                if let (Some(found), Some(wanted)) = (found, wanted) {
+                                    if found != wanted {
+                                        return Err(format!(
+                                            "Map {} {} doesn't match the other {}s ({})",
+                                            what, found, what, wanted
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Expression::Range { .. } => {
+                Err("A range like `0..n` can only be iterated with `for i in 0..n`".to_string())
+            }
+            Expression::If {
+                condition,
+                then_value,
+                else_value,
+            } => {
+                self.check_not_optional(condition)?;
+                self.check_expression(condition)?;
+                self.check_expression(then_value)?;
+                self.check_expression(else_value)?;
+                match (
+                    self.expression_type(then_value),
+                    self.expression_type(else_value),
+                ) {
+                    (Some(then_type), Some(else_type))
+                        if then_type != else_type
+                            && !matches!(
+                                (&then_type, &else_type),
+                                (Type::Integer, Type::Float) | (Type::Float, Type::Integer)
+                            ) =>
+                    {
+                        Err(format!(
+                            "The branches of this `if` have different types ({} and {})",
+                            then_type, else_type
+                        ))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Expression::Lambda { params, body, .. } => {
+                for param in params {
+                    self.check_not_constant(&param.name)?;
+                }
+                self.check_expression(body)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // format("x = {}", x) is turned into a printf format at compile time, so
+    // the format must be a literal with one value per placeholder, and each
+    // value something print can show
+    fn check_format_call(&self, name: &str, args: &[Expression]) -> Result<(), String> {
+        let Some((Expression::StringLiteral(format), values)) = args.split_first() else {
+            return Err(format!(
+                "{} needs a string literal as its format, like {}(\"x = {{}}\", x)",
+                name, name
+            ));
+        };
+        let placeholders = format_pieces(format).len() - 1;
+        if placeholders != values.len() {
+            return Err(format!(
+                "{}(\"{}\", ...) has {} placeholder(s) but {} value(s)",
+                name,
+                format.escape_debug(),
+                placeholders,
+                values.len()
+            ));
+        }
+        for (index, value) in values.iter().enumerate() {
+            match self.expression_type(value) {
+                Some(t @ Type::Function { .. }) => {
+                    return Err(format!(
+                        "{}(\"{}\", ...) can't show placeholder {}'s value, a function ({})",
+                        name,
+                        format.escape_debug(),
+                        index + 1,
+                        t
+                    ))
+                }
+                Some(t) if map_types(&t).is_some() => {
+                    return Err(format!(
+                        "{}(\"{}\", ...) can't show placeholder {}'s value, a {}",
+                        name,
+                        format.escape_debug(),
+                        index + 1,
+                        t
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // A call to a variadic extern whose last fixed parameter is a String is
+    // taken to be printf-style: with a literal format, its conversions are
+    // checked against the values after it, since C can't tell at run time
+    fn check_c_format_call(
+        &self,
+        name: &str,
+        fixed: &[Parameter],
+        args: &[Expression],
+    ) -> Result<(), String> {
+        if args.len() < fixed.len() {
+            return Err(format!(
+                "{} takes at least {} argument(s), not {}",
+                name,
+                fixed.len(),
+                args.len()
+            ));
+        }
+        if fixed.last().map(|param| &param.param_type) != Some(&Type::String) {
+            return Ok(());
+        }
+        let Expression::StringLiteral(format) = &args[fixed.len() - 1] else {
+            return Ok(());
+        };
+        let conversions = c_format_conversions(format).map_err(|problem| {
+            format!("{}(\"{}\", ...): {}", name, format.escape_debug(), problem)
+        })?;
+        let values = &args[fixed.len()..];
+        if conversions.len() != values.len() {
+            return Err(format!(
+                "{}(\"{}\", ...) has {} conversion(s) but {} value(s)",
+                name,
+                format.escape_debug(),
+                conversions.len(),
+                values.len()
+            ));
+        }
+        for ((spec, wanted), value) in conversions.iter().zip(values) {
+            match self.expression_type(value) {
+                Some(t) if !wanted.accepts(&t) => {
+                    return Err(format!(
+                        "{}(\"{}\", ...): {} takes {}, not {}",
+                        name,
+                        format.escape_debug(),
+                        spec,
+                        wanted.describe(),
+                        t
+                    ))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// What a C printf conversion reads from its argument
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CFormatArgument {
+    Int,  // %d, %x, %c, ... and a `*` width or precision
+    Long, // %ld, %lld, %zu, ...
+    Double,
+    String,
+    Pointer,
+}
+
+impl CFormatArgument {
+    fn accepts(self, t: &Type) -> bool {
+        match (self, t) {
+            (CFormatArgument::Int, Type::Integer | Type::Bool) => true,
+            (CFormatArgument::Int, Type::SizedInt(kind)) => kind.bits() <= 32,
+            (CFormatArgument::Long, Type::SizedInt(kind)) => kind.bits() == 64,
+            (CFormatArgument::Double, Type::Float) => true,
+            (CFormatArgument::String, Type::String) => true,
+            (CFormatArgument::Pointer, Type::Pointer(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            CFormatArgument::Int => "an Integer",
+            CFormatArgument::Long => "an Int64 or UInt64",
+            CFormatArgument::Double => "a Float",
+            CFormatArgument::String => "a String",
+            CFormatArgument::Pointer => "a pointer",
+        }
+    }
+}
+
+// The arguments a C printf format reads, in order, each with the conversion
+// (or `*`) that reads it
+fn c_format_conversions(format: &str) -> Result<Vec<(String, CFormatArgument)>, String> {
+    let mut conversions = Vec::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            continue;
+        }
+        let mut spec = String::from("%");
+        let mut stars = 0;
+        let mut length = String::new();
+        let conversion = loop {
+            let Some(c) = chars.next() else {
+                return Err(format!("the conversion {} is unfinished", spec));
+            };
+            spec.push(c);
+            match c {
+                '-' | '+' | ' ' | '#' | '\'' | '.' | '0'..='9' => {}
+                '*' => stars += 1,
+                'h' | 'l' | 'j' | 'z' | 't' | 'L' => length.push(c),
+                _ => break c,
+            }
+        };
+        for _ in 0..stars {
+            conversions.push(("*".to_string(), CFormatArgument::Int));
+        }
+        let argument = match (conversion, length.as_str()) {
+            ('d' | 'i' | 'o' | 'u' | 'x' | 'X' | 'c', "" | "h" | "hh") => CFormatArgument::Int,
+            ('d' | 'i' | 'o' | 'u' | 'x' | 'X', "l" | "ll" | "j" | "z" | "t") => {
+                CFormatArgument::Long
+            }
+            ('f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'a' | 'A', "" | "l") => CFormatArgument::Double,
+            ('s', "") => CFormatArgument::String,
+            ('p', "") => CFormatArgument::Pointer,
+            ('n', _) => return Err("%n isn't supported".to_string()),
+            _ => return Err(format!("{} isn't a conversion Bolt can check", spec)),
+        };
+        conversions.push((spec, argument));
+    }
+    Ok(conversions)
+}
+
+// An integer literal must fit the integer type it's used as: a sized type
+// where one is expected, and Integer, a C int, otherwise
+fn check_literal_fits(value: &Expression, expected: &Type) -> Result<(), String> {
+    let Some(n) = integer_literal(value) else {
+        return Ok(());
+    };
+    let (name, (min, max)) = match expected {
+        Type::SizedInt(kind) => (kind.name(), kind.range()),
+        Type::Integer => ("Integer", (i32::MIN as i128, i32::MAX as i128)),
+        _ => return Ok(()),
+    };
+    if !(min..=max).contains(&n) {
+        return Err(format!(
+            "Integer literal {} is out of range for {} ({}..{})",
+            n, name, min, max
+        ));
+    }
+    Ok(())
+}
+
+// The value of an integer literal, also a negated one the constant folder
+// left alone because it doesn't fit an Integer
+fn integer_literal(expr: &Expression) -> Option<i128> {
+    match expr {
+        Expression::IntegerLiteral(n) => Some(*n),
+        Expression::UnaryOp {
+            operator: UnaryOperator::Negate,
+            operand,
+        } => match **operand {
+            Expression::IntegerLiteral(n) => Some(-n),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// `a < b < c` would compare the Bool result of `a < b` with c; a comparison
+// as the left operand must be parenthesized to be meant that way
+fn check_not_chained(
+    left: &Expression,
+    operator: &BinaryOperator,
+    right: &Expression,
+) -> Result<(), String> {
+    let Expression::BinaryOp {
+        left: first,
+        operator: first_operator,
+        right: middle,
+    } = left
+    else {
+        return Ok(());
+    };
+    let (Some(first_symbol), Some(symbol)) = (
+        comparison_symbol(first_operator),
+        comparison_symbol(operator),
+    ) else {
+        return Ok(());
+    };
+    let (a, b, c) = match (
+        operand_text(first),
+        operand_text(middle),
+        operand_text(right),
+    ) {
+        (Some(a), Some(b), Some(c)) => (a, b, c),
+        _ => ("a".to_string(), "b".to_string(), "c".to_string()),
+    };
+    // Only orderings read as a range; `a == b == c` is more likely meant to
+    // compare the first result, so that gets the parentheses instead
+    let ordering = |operator: &BinaryOperator| {
+        !matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual)
+    };
+    let rewrite = if ordering(first_operator) && ordering(operator) {
+        format!("{a} {first_symbol} {b} && {b} {symbol} {c}")
+    } else {
+        format!("({a} {first_symbol} {b}) {symbol} {c}")
+    };
+    let suggestion = format!(
+        "`{a} {first_symbol} {b} {symbol} {c}` compares the Bool result of `{a} {first_symbol} {b}` with {c}; write `{rewrite}`"
+    );
+    Err(format!("Comparisons can't be chained: {}", suggestion))
+}
+
+fn comparison_symbol(operator: &BinaryOperator) -> Option<&'static str> {
+    match operator {
+        BinaryOperator::Equal => Some("=="),
+        BinaryOperator::NotEqual => Some("!="),
+        BinaryOperator::Less => Some("<"),
+        BinaryOperator::LessEqual => Some("<="),
+        BinaryOperator::Greater => Some(">"),
+        BinaryOperator::GreaterEqual => Some(">="),
+        _ => None,
+    }
+}
+
+// Source text of a short operand, for quoting it back in a diagnostic
+fn operand_text(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Identifier(name) => Some(name.clone()),
+        Expression::IntegerLiteral(n) => Some(n.to_string()),
+        Expression::FloatLiteral(f) => Some(f.to_string()),
+        Expression::BoolLiteral(b) => Some(b.to_string()),
+        Expression::FieldAccess { object, field } => {
+            Some(format!("{}.{}", operand_text(object)?, field))
+        }
+        _ => None,
+    }
+}
+
+// Declared types of a statement's variables, parameters and return value
+fn declared_types(statement: &Statement) -> Vec<&Type> {
+    match statement {
+        Statement::ValDecl {
+            type_annotation: Some(t),
+            ..
+        }
+        | Statement::VarDecl {
+            type_annotation: Some(t),
+            ..
+        } => vec![t],
+        Statement::Function {
+            params,
+            return_type,
+            ..
+        } => params
+            .iter()
+            .map(|param| &param.param_type)
+            .chain(return_type.iter())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Array[String], the type of main's command line
+fn is_string_array(t: &Type) -> bool {
+    matches!(t, Type::Generic { name, type_params }
+        if name == "Array" && matches!(type_params.as_slice(), [Type::String]))
+}
+
+// Whether a type refers to the type (or type parameter) named `name`
+fn mentions_type(t: &Type, name: &str) -> bool {
+    match t {
+        Type::Custom(n) | Type::TypeParameter(n) => n == name,
+        Type::Array(inner) | Type::Pointer(inner) => mentions_type(inner, name),
+        Type::Generic { type_params, .. } | Type::Tuple(type_params) => {
+            type_params.iter().any(|t| mentions_type(t, name))
+        }
+        Type::Function {
+            params,
+            return_type,
+        } => params.iter().any(|t| mentions_type(t, name)) || mentions_type(return_type, name),
+        _ => false,
+    }
+}
+
+// Blocks nested in a statement; function bodies are checked with their return type
+fn nested_bodies(statement: &Statement) -> Vec<&[Statement]> {
+    match statement {
+        Statement::ForIn { body, .. }
+        | Statement::ForCondition { body, .. }
+        | Statement::DoWhile { body, .. }
+        | Statement::ForLoop { body, .. }
+        | Statement::Defer { body } => vec![body],
+        Statement::If {
+            then_body,
+            else_body,
+            ..
+        } => std::iter::once(then_body.as_slice())
+            .chain(else_body.as_deref())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// `name(Integer, String): Bool`, how method signatures appear in errors
+fn signature<'a>(
+    name: &str,
+    params: impl IntoIterator<Item = &'a Type>,
+    return_type: Option<&Type>,
+) -> String {
+    let params: Vec<String> = params.into_iter().map(|t| t.to_string()).collect();
+    match return_type {
+        Some(t) => format!("{}({}): {}", name, params.join(", "), t),
+        None => format!("{}({})", name, params.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{
+        AsmOperand, BinaryOperator, Expression, ExternFunction, Field, IntKind, Parameter,
+        StructField,
+    };
+
+    fn type_def(field_type: Type) -> Statement {
+        Statement::TypeDef {
+            name: "Token".to_string(),
+            type_params: vec![],
+            fields: vec![Field {
+                name: "text".to_string(),
+                field_type,
+            }],
+            implements: vec![],
+        }
+    }
+
+    #[test]
+    fn test_views_rejected_in_struct_fields() {
+        let str_view = Type::Custom("StrView".to_string());
+        let slice = Type::Generic {
+            name: "Slice".to_string(),
+            type_params: vec![Type::Integer],
+        };
+
+        let error = check_statements(&[type_def(str_view.clone())]).unwrap_err();
+        assert!(error.contains("Field 'text' of type 'Token'"));
+        assert!(check_statements(&[type_def(slice.clone())]).is_err());
+        assert!(check_statements(&[type_def(Type::String)]).is_ok());
+
+        // Containers are structs too
+        let array_of_views = Type::Generic {
+            name: "Array".to_string(),
+            type_params: vec![str_view],
+        };
+        assert!(check_statements(&[type_def(array_of_views)]).is_err());
+    }
+
+    #[test]
+    fn test_views_allowed_in_locals_and_signatures() {
+        let str_view = Type::Custom("StrView".to_string());
+        let function = |body: Vec<Statement>| Statement::Function {
+            name: "firstWord".to_string(),
+            type_params: vec![],
+            receiver: None,
+            params: vec![Parameter {
+                name: "text".to_string(),
+                param_type: str_view.clone(),
+                default: None,
+            }],
+            return_type: Some(str_view.clone()),
+            body,
+            exported: false,
+        };
+        let local = |type_annotation: Type| Statement::ValDecl {
+            name: "word".to_string(),
+            type_annotation: Some(type_annotation),
+            value: Expression::Identifier("text".to_string()),
+        };
+
+        assert!(check_statements(&[function(vec![local(str_view.clone())])]).is_ok());
+
+        // Nested bodies are checked too
+        let views = Type::Generic {
+            name: "Array".to_string(),
+            type_params: vec![str_view.clone()],
+        };
+        let error = check_statements(&[function(vec![local(views)])]).unwrap_err();
+        assert!(error.contains("'Array' can't hold a view"));
+    }
+
+    #[test]
+    fn test_integer_literals_must_fit_sized_types() {
+        let declare = |kind: IntKind, n: i128| Statement::ValDecl {
+            name: "x".to_string(),
+            type_annotation: Some(Type::SizedInt(kind)),
+            value: Expression::IntegerLiteral(n),
+        };
+        assert!(check_statements(&[declare(IntKind::Int8, 127)]).is_ok());
+        assert!(check_statements(&[declare(IntKind::UInt8, 255)]).is_ok());
+        let error = check_statements(&[declare(IntKind::Int8, 128)]).unwrap_err();
+        assert_eq!(
+            error,
+            "Integer literal 128 is out of range for Int8 (-128..127)"
+        );
+        assert!(check_statements(&[declare(IntKind::UInt32, -1)]).is_err());
+        // Literals up to UInt64's largest value can be written
+        assert!(check_statements(&[declare(IntKind::UInt64, u64::MAX.into())]).is_ok());
+        assert!(check_statements(&[declare(IntKind::Int64, u64::MAX.into())]).is_err());
+
+        // Arguments to fixed-width parameters are checked at the call
+        let function = Statement::Function {
+            name: "send".to_string(),
+            type_params: vec![],
+            receiver: None,
+            params: vec![Parameter {
+                name: "byte".to_string(),
+                param_type: Type::SizedInt(IntKind::UInt8),
+                default: None,
+            }],
+            return_type: None,
+            body: vec![],
+            exported: false,
+        };
+        let call = Statement::Expression(Expression::FunctionCall {
+            name: "send".to_string(),
+            args: vec![Expression::IntegerLiteral(256)],
+        });
+        assert!(check_statements(&[function, call]).is_err());
+    }
+
+    #[test]
+    fn test_integer_literals_must_fit_integer_unless_typed_wider() {
+        let declare = |type_annotation: Option<Type>, value: Expression| Statement::ValDecl {
+            name: "x".to_string(),
+            type_annotation,
+            value,
+        };
+        let literal = Expression::IntegerLiteral;
+        let negated = |n: i128| Expression::UnaryOp {
+            operator: UnaryOperator::Negate,
+            operand: Box::new(literal(n)),
+        };
+        let out_of_range = |n: i64| {
+            format!(
+                "Integer literal {} is out of range for Integer (-2147483648..2147483647)",
+                n
+            )
+        };
+
+        assert_eq!(
+            check_statements(&[declare(Some(Type::Integer), literal(5000000000))]).unwrap_err(),
+            out_of_range(5000000000)
+        );
+        assert_eq!(
+            check_statements(&[declare(None, literal(3000000000))]).unwrap_err(),
+            out_of_range(3000000000)
+        );
+        let print = Statement::Expression(Expression::FunctionCall {
+            name: "print".to_string(),
+            args: vec![Expression::BinaryOp {
+                left: Box::new(literal(9223372036854775807)),
+                operator: BinaryOperator::Add,
+                right: Box::new(literal(1)),
+            }],
+        });
+        assert_eq!(
+            check_statements(&[print]).unwrap_err(),
+            out_of_range(9223372036854775807)
+        );
+        assert!(check_statements(&[declare(None, negated(2147483648))]).is_ok());
+        assert!(check_statements(&[declare(None, negated(2147483649))]).is_err());
+
+        // A literal typed wider by its context only has to fit that type
+        let int64 = Type::SizedInt(IntKind::Int64);
+        assert!(check_statements(&[declare(Some(int64.clone()), negated(5000000000))]).is_ok());
+        assert!(check_statements(&[declare(Some(Type::Float), literal(3000000000))]).is_ok());
+        let cast = Expression::Cast {
+            value: Box::new(literal(5000000000)),
+            target: int64.clone(),
+        };
+        assert!(check_statements(&[declare(None, cast)]).is_ok());
+        let wide = Statement::TypeDef {
+            name: "Wide".to_string(),
+            type_params: Vec::new(),
+            fields: vec![Field::new("v", int64)],
+            implements: Vec::new(),
+        };
+        let literal_of = |n: i128| Expression::StructLiteral {
+            type_name: "Wide".to_string(),
+            type_args: None,
+            fields: vec![StructField::new("v", literal(n))],
+        };
+        assert!(check_statements(&[wide, declare(None, literal_of(6000000000))]).is_ok());
+    }
+
+    #[test]
+    fn test_optionals_must_be_unwrapped_before_use() {
+        let optional = Type::Generic {
+            name: "Optional".to_string(),
+            type_params: vec![Type::Integer],
+        };
+        let maybe = Statement::ValDecl {
+            name: "maybe".to_string(),
+            type_annotation: Some(optional),
+            value: Expression::NoneLiteral,
+        };
+        let add_one = |operand: &str| Expression::BinaryOp {
+            left: Box::new(Expression::Identifier(operand.to_string())),
+            operator: BinaryOperator::Add,
+            right: Box::new(Expression::IntegerLiteral(1)),
+        };
+
+        let error = check_statements(&[maybe.clone(), Statement::Expression(add_one("maybe"))])
+            .unwrap_err();
+        assert!(error.contains("'maybe' is optional"));
+
+        // Comparing against none is how it's tested
+        let test = Statement::Expression(Expression::BinaryOp {
+            left: Box::new(Expression::Identifier("maybe".to_string())),
+            operator: BinaryOperator::NotEqual,
+            right: Box::new(Expression::NoneLiteral),
+        });
+        assert!(check_statements(&[maybe.clone(), test]).is_ok());
+
+        // Inside `if (val n := maybe)`, n is the plain value
+        let unwrap = |body: Expression| Statement::IfPresent {
+            name: "n".to_string(),
+            value: Expression::Identifier("maybe".to_string()),
+            then_body: vec![Statement::Expression(body)],
+            else_body: None,
+        };
+        assert!(check_statements(&[maybe.clone(), unwrap(add_one("n"))]).is_ok());
+        assert!(check_statements(&[maybe, unwrap(add_one("maybe"))]).is_err());
+    }
+
+    #[test]
+    fn test_if_expression_branches_share_a_type() {
+        let choose = |then_value: Expression, else_value: Expression| Statement::ValDecl {
+            name: "x".to_string(),
+            type_annotation: None,
+            value: Expression::If {
+                condition: Box::new(Expression::BoolLiteral(true)),
+                then_value: Box::new(then_value),
+                else_value: Box::new(else_value),
+            },
+        };
+        let error = check_statements(&[choose(
+            Expression::IntegerLiteral(1),
+            Expression::StringLiteral("one".to_string()),
+        )])
+        .unwrap_err();
+        assert!(error.contains("different types (Integer and String)"));
+        assert!(check_statements(&[choose(
+            Expression::IntegerLiteral(1),
+            Expression::FloatLiteral(1.5)
+        )])
+        .is_ok());
+    }
+
+    #[test]
+    fn test_ranges_are_only_iterated() {
+        let range = Expression::Range {
+            start: Box::new(Expression::IntegerLiteral(0)),
+            end: Box::new(Expression::IntegerLiteral(3)),
+            inclusive: false,
+        };
+        let for_in = Statement::ForIn {
+            index: None,
+            variable: "i".to_string(),
+            iterable: range.clone(),
+            body: vec![],
+        };
+        assert!(check_statements(&[for_in]).is_ok());
+        let stored = Statement::ValDecl {
+            name: "r".to_string(),
+            type_annotation: None,
+            value: range,
+        };
+        assert!(check_statements(&[stored])
+            .unwrap_err()
+            .contains("can only be iterated"));
+    }
+
+    #[test]
+    fn test_chained_comparisons_are_rejected() {
+        let compare =
+            |left: Expression, operator: BinaryOperator, right: &str| Expression::BinaryOp {
+                left: Box::new(left),
+                operator,
+                right: Box::new(Expression::Identifier(right.to_string())),
+            };
+        let a = || Expression::Identifier("a".to_string());
+        let chained = compare(
+            compare(a(), BinaryOperator::Less, "b"),
+            BinaryOperator::LessEqual,
+            "c",
+        );
+        let error = check_statements(&[Statement::Expression(chained)]).unwrap_err();
+        assert!(error.contains("write `a < b && b <= c`"), "{}", error);
+
+        // Equality chains get the parentheses, not a range
+        let equality = |first_operator| {
+            compare(
+                compare(a(), first_operator, "b"),
+                BinaryOperator::Equal,
+                "flag",
+            )
+        };
+        let error = check_statements(&[Statement::Expression(equality(BinaryOperator::Equal))])
+            .unwrap_err();
+        assert!(error.contains("write `(a == b) == flag`"), "{}", error);
+        let error =
+            check_statements(&[Statement::Expression(equality(BinaryOperator::Less))]).unwrap_err();
+        assert!(error.contains("write `(a < b) == flag`"), "{}", error);
+
+        // Parentheses say the Bool comparison is meant
+        let grouped = compare(
+            Expression::Grouping(Box::new(compare(a(), BinaryOperator::Less, "b"))),
+            BinaryOperator::Equal,
+            "flag",
+        );
+        assert!(check_statements(&[Statement::Expression(grouped)]).is_ok());
+    }
+
+    #[test]
+    fn test_strings_only_compare_with_strings() {
+        let compare = |right: Expression| {
+            Statement::Expression(Expression::BinaryOp {
+                left: Box::new(Expression::StringLiteral("a".to_string())),
+                operator: BinaryOperator::Equal,
+                right: Box::new(right),
+            })
+        };
+        assert!(check_statements(&[compare(Expression::StringLiteral("b".to_string()))]).is_ok());
+        assert_eq!(
+            check_statements(&[compare(Expression::IntegerLiteral(1))]).unwrap_err(),
+            "Can't compare a String with Integer using =="
+        );
+    }
+
+    #[test]
+    fn test_scalar_annotations_must_match_the_value() {
+        let declare = |declared: Type, value: Expression| Statement::ValDecl {
+            name: "x".to_string(),
+            type_annotation: Some(declared),
+            value,
+        };
+        assert!(check_statements(&[declare(Type::Float, Expression::IntegerLiteral(2))]).is_ok());
+        assert_eq!(
+            check_statements(&[declare(Type::Bool, Expression::IntegerLiteral(5))]).unwrap_err(),
+            "'x' is declared as Bool but its value is Integer"
+        );
+        assert_eq!(
+            check_statements(&[declare(Type::Integer, Expression::FloatLiteral(2.5))]).unwrap_err(),
+            "'x' is declared as Integer but its value is Float"
+        );
+    }
+
+    #[test]
+    fn test_optional_operators_need_an_optional() {
+        let declare = |name: &str, value: Expression| Statement::ValDecl {
+            name: name.to_string(),
+            type_annotation: None,
+            value,
+        };
+        let identifier = |name: &str| Box::new(Expression::Identifier(name.to_string()));
+        let count = declare("count", Expression::IntegerLiteral(3));
+        assert_eq!(
+            check_statements(&[
+                count.clone(),
+                declare(
+                    "n",
+                    Expression::Coalesce {
+                        value: identifier("count"),
+                        fallback: Box::new(Expression::IntegerLiteral(0)),
+                    }
+                ),
+            ])
+            .unwrap_err(),
+            "`??` needs an optional or a pointer on its left, not a Integer"
+        );
+        assert_eq!(
+            check_statements(&[
+                count,
+                declare(
+                    "n",
+                    Expression::OptionalFieldAccess {
+                        object: identifier("count"),
+                        field: "size".to_string(),
+                    }
+                ),
+            ])
+            .unwrap_err(),
+            "`?.size` is for optional values, but this is a Integer; use `.size`"
+        );
+
+        let maybe = Statement::ValDecl {
+            name: "maybe".to_string(),
+            type_annotation: Some(Type::Generic {
+                name: "Optional".to_string(),
+                type_params: vec![Type::Integer],
+            }),
+            value: Expression::NoneLiteral,
+        };
+        let fallback = declare(
+            "n",
+            Expression::Coalesce {
+                value: identifier("maybe"),
+                fallback: Box::new(Expression::IntegerLiteral(0)),
+            },
+        );
+        assert!(check_statements(&[maybe, fallback]).is_ok());
+    }
+
+    #[test]
+    fn test_format_needs_a_literal_with_one_value_per_placeholder() {
+        let format = |args: Vec<Expression>| {
+            Statement::Expression(Expression::FunctionCall {
+                name: "format".to_string(),
+                args,
+            })
+        };
+        let template = |text: &str| Expression::StringLiteral(text.to_string());
+        assert!(check_statements(&[format(vec![
+            template("{} and {{}}"),
+            Expression::IntegerLiteral(1)
+        ])])
+        .is_ok());
+        assert_eq!(
+            check_statements(&[format(vec![
+                template("{} {}"),
+                Expression::IntegerLiteral(1)
+            ])])
+            .unwrap_err(),
+            "format(\"{} {}\", ...) has 2 placeholder(s) but 1 value(s)"
+        );
+        assert_eq!(
+            check_statements(&[format(vec![Expression::Identifier("f".to_string())])]).unwrap_err(),
+            "format needs a string literal as its format, like format(\"x = {}\", x)"
+        );
+    }
+
+    #[test]
+    fn test_format_rejects_values_it_cannot_show() {
+        let function_type = Type::Function {
+            params: vec![Type::Integer],
+            return_type: Box::new(Type::Integer),
+        };
+        let show = Statement::Function {
+            name: "show".to_string(),
+            type_params: Vec::new(),
+            receiver: None,
+            params: vec![Parameter::new("f", function_type)],
+            return_type: Some(Type::String),
+            body: vec![Statement::Return(Some(Expression::FunctionCall {
+                name: "format".to_string(),
+                args: vec![
+                    Expression::StringLiteral("f = {}".to_string()),
+                    Expression::Identifier("f".to_string()),
+                ],
+            }))],
+            exported: false,
+        };
+        assert_eq!(
+            check_statements(&[show]).unwrap_err(),
+            "format(\"f = {}\", ...) can't show placeholder 1's value, a function ((Integer) -> Integer)"
+        );
+    }
+
+    #[test]
+    fn test_printf_style_extern_calls_are_checked_against_their_format() {
+        let mut dprintf = ExternFunction::new(
+            "dprintf",
+            vec![
+                Parameter::new("fd", Type::Integer),
+                Parameter::new("format", Type::String),
+            ],
+            Some(Type::Integer),
+        );
+        dprintf.variadic = true;
+        let externs = Statement::ExternBlock {
+            language: "C".to_string(),
+            functions: vec![dprintf],
+        };
+        let check = |format: &str, values: Vec<Expression>| {
+            let mut args = vec![
+                Expression::IntegerLiteral(1),
+                Expression::StringLiteral(format.to_string()),
+            ];
+            args.extend(values);
+            check_statements(&[
+                externs.clone(),
+                Statement::Expression(Expression::FunctionCall {
+                    name: "dprintf".to_string(),
+                    args,
+                }),
+            ])
+        };
+        let text = |s: &str| Expression::StringLiteral(s.to_string());
+
+        assert!(check(
+            "%s: %5.2f%% of %-*d\n",
+            vec![
+                text("done"),
+                Expression::FloatLiteral(99.5),
+                Expression::IntegerLiteral(4),
+                Expression::IntegerLiteral(10)
+            ]
+        )
+        .is_ok());
+        assert_eq!(
+            check("%d\n", vec![text("ten")]).unwrap_err(),
+            "dprintf(\"%d\\n\", ...): %d takes an Integer, not String"
+        );
+        assert_eq!(
+            check("%ld", vec![Expression::IntegerLiteral(1)]).unwrap_err(),
+            "dprintf(\"%ld\", ...): %ld takes an Int64 or UInt64, not Integer"
+        );
+        assert_eq!(
+            check("%s %s", vec![text("one")]).unwrap_err(),
+            "dprintf(\"%s %s\", ...) has 2 conversion(s) but 1 value(s)"
+        );
+        assert_eq!(
+            check("%n", vec![]).unwrap_err(),
+            "dprintf(\"%n\", ...): %n isn't supported"
+        );
+        assert_eq!(
+            check("100%", vec![]).unwrap_err(),
+            "dprintf(\"100%\", ...): the conversion % is unfinished"
+        );
+    }
+
+    #[test]
+    fn test_an_extern_printf_replaces_the_builtin() {
+        let printf = |value: i128| {
+            Statement::Expression(Expression::FunctionCall {
+                name: "printf".to_string(),
+                args: vec![
+                    Expression::StringLiteral("%d%%\n".to_string()),
+                    Expression::IntegerLiteral(value),
+                ],
+            })
+        };
+        assert_eq!(
+            check_statements(&[printf(5)]).unwrap_err(),
+            "printf(\"%d%%\\n\", ...) has 0 placeholder(s) but 1 value(s)"
+        );
+
+        let mut extern_printf = ExternFunction::new(
+            "printf",
+            vec![Parameter::new("format", Type::String)],
+            Some(Type::Integer),
+        );
+        extern_printf.variadic = true;
+        let externs = Statement::ExternBlock {
+            language: "C".to_string(),
+            functions: vec![extern_printf],
+        };
+        assert!(check_statements(&[externs, printf(5)]).is_ok());
+    }
+
+    #[test]
+    fn test_lambdas_only_use_their_parameters() {
+        let times = |param: &str, factor: &str| Expression::Lambda {
+            params: vec![Parameter::new(param, Type::Integer)],
+            return_type: None,
+            body: Box::new(Expression::BinaryOp {
+                left: Box::new(Expression::Identifier(param.to_string())),
+                operator: BinaryOperator::Multiply,
+                right: Box::new(Expression::Identifier(factor.to_string())),
+            }),
+        };
+        let declare = |name: &str, value: Expression| Statement::ValDecl {
+            name: name.to_string(),
+            type_annotation: None,
+            value,
+        };
+        let capture_error =
+            "Lambda can't capture local variable 'k'; pass it as a parameter instead";
+
+        assert_eq!(
+            check_statements(&[
+                declare("k", Expression::IntegerLiteral(3)),
+                declare("f", times("x", "k")),
+            ])
+            .unwrap_err(),
+            capture_error
+        );
+        // A function's parameters are its locals; a lambda parameter
+        // shadows them
+        let scale = |lambda: Expression| Statement::Function {
+            name: "scale".to_string(),
+            type_params: Vec::new(),
+            receiver: None,
+            params: vec![Parameter::new("k", Type::Integer)],
+            return_type: None,
+            body: vec![declare("f", lambda)],
+            exported: false,
+        };
+        assert_eq!(
+            check_statements(&[scale(times("x", "k"))]).unwrap_err(),
+            capture_error
+        );
+        assert!(check_statements(&[scale(times("k", "k"))]).is_ok());
+        // An inner lambda can't use the outer one's parameters either
+        let nested = Expression::Lambda {
+            params: vec![Parameter::new("k", Type::Integer)],
+            return_type: None,
+            body: Box::new(times("x", "k")),
+        };
+        assert_eq!(
+            check_statements(&[declare("f", nested)]).unwrap_err(),
+            capture_error
+        );
+    }
+
+    #[test]
+    fn test_only_declared_methods_can_be_called() {
+        let declare = |name: &str, value: Expression| Statement::ValDecl {
+            name: name.to_string(),
+            type_annotation: None,
+            value,
+        };
+        let call = |object: &str, method: &str| {
+            Statement::Expression(Expression::MethodCall {
+                object: Box::new(Expression::Identifier(object.to_string())),
+                method: method.to_string(),
+                args: vec![Expression::IntegerLiteral(9)],
+            })
+        };
+        let token = declare(
+            "token",
+            Expression::StructLiteral {
+                type_name: "Token".to_string(),
+                type_args: None,
+                fields: vec![StructField::new("text", Expression::IntegerLiteral(1))],
+            },
+        );
+        let shout = Statement::Function {
+            name: method_function_name("Token", "shout"),
+            type_params: Vec::new(),
+            receiver: Some(Parameter::new("t", Type::Custom("Token".to_string()))),
+            params: vec![Parameter::new("times", Type::Integer)],
+            return_type: None,
+            body: Vec::new(),
+            exported: false,
+        };
+
+        let numbers = declare(
+            "numbers",
+            Expression::ArrayLiteral(vec![Expression::IntegerLiteral(3)]),
+        );
+        assert_eq!(
+            check_statements(&[numbers, call("numbers", "push")]).unwrap_err(),
+            "[]Integer has no method 'push'; an array literal has a fixed size, use an Array[T] to grow it"
+        );
+        assert_eq!(
+            check_statements(&[
+                type_def(Type::Integer),
+                token.clone(),
+                call("token", "clone")
+            ])
+            .unwrap_err(),
+            "Token has no method 'clone'; call clone(value) instead"
+        );
+        assert!(check_statements(&[
+            type_def(Type::Integer),
+            shout,
+            token,
+            call("token", "shout")
+        ])
+        .is_ok());
+        let counts = Statement::ValDecl {
+            name: "counts".to_string(),
+            type_annotation: Some(Type::Generic {
+                name: "Map".to_string(),
+                type_params: vec![Type::String, Type::Integer],
+            }),
+            value: Expression::MapLiteral {
+                type_args: None,
+                entries: Vec::new(),
+            },
+        };
+        assert_eq!(
+            check_statements(&[counts, call("counts", "set")]).unwrap_err(),
+            "Map[String, Integer] has no method 'set'; maps are used through bolt:map's functions, like set(map, key, value)"
+        );
+    }
+
+    #[test]
+    fn test_only_numbers_are_negated() {
+        let negate = |operand: Expression| {
+            Statement::Expression(Expression::UnaryOp {
+                operator: UnaryOperator::Negate,
+                operand: Box::new(operand),
+            })
+        };
+        assert!(check_statements(&[negate(Expression::IntegerLiteral(1))]).is_ok());
+        let error =
+            check_statements(&[negate(Expression::StringLiteral("a".to_string()))]).unwrap_err();
+        assert_eq!(error, "Only numbers can be negated, not a String");
+    }
+
+    #[test]
+    fn test_none_needs_an_optional_type() {
+        let declare = |type_annotation: Option<Type>| Statement::ValDecl {
+            name: "x".to_string(),
+            type_annotation,
+            value: Expression::NoneLiteral,
+        };
+        assert!(check_statements(&[declare(None)]).is_err());
+        assert!(check_statements(&[declare(Some(Type::Integer))]).is_err());
+
+        let unwrap_plain = Statement::IfPresent {
+            name: "n".to_string(),
+            value: Expression::IntegerLiteral(3),
+            then_body: vec![],
+            else_body: None,
+        };
+        let error = check_statements(&[unwrap_plain]).unwrap_err();
+        assert!(error.contains("needs an optional value"));
+    }
+
+    #[test]
+    fn test_implementations_must_match_interface() {
+        let interface = Statement::Interface {
+            name: "Printable".to_string(),
+            methods: vec![InterfaceMethod {
+                name: "toString".to_string(),
+                params: vec![],
+                return_type: Some(Type::String),
+            }],
+        };
+        let person = |implements: &[&str]| Statement::TypeDef {
+            name: "Person".to_string(),
+            type_params: vec![],
+            fields: vec![],
+            implements: implements.iter().map(|s| s.to_string()).collect(),
+        };
+        let method = |params: Vec<Parameter>| Statement::Function {
+            name: "Person_toString".to_string(),
+            type_params: vec![],
+            receiver: Some(Parameter {
+                name: "p".to_string(),
+                param_type: Type::Custom("Person".to_string()),
+                default: None,
+            }),
+            params,
+            return_type: Some(Type::String),
+            body: vec![],
+            exported: false,
+        };
+
+        let conforming = [interface.clone(), person(&["Printable"]), method(vec![])];
+        assert!(check_statements(&conforming).is_ok());
+
+        let error = check_statements(&[interface.clone(), person(&["Printable"])]).unwrap_err();
+        assert_eq!(
+            error,
+            "Person doesn't implement Printable: missing method `toString(): String`"
+        );
+
+        let indent = Parameter {
+            name: "indent".to_string(),
+            param_type: Type::Integer,
+            default: None,
+        };
+        let error = check_statements(&[
+            interface.clone(),
+            person(&["Printable"]),
+            method(vec![indent]),
+        ])
+        .unwrap_err();
+        assert!(error.contains("`toString(Integer): String` should be `toString(): String`"));
+
+        let error = check_statements(&[person(&["Printable"]), method(vec![])]).unwrap_err();
+        assert!(error.contains("which is not an interface"));
+    }
+
+    #[test]
+    fn test_interface_arguments_must_implement_it() {
+        let interface = Statement::Interface {
+            name: "Printable".to_string(),
+            methods: vec![],
+        };
+        let show = Statement::Function {
+            name: "show".to_string(),
+            type_params: vec![],
+            receiver: None,
+            params: vec![Parameter {
+                name: "item".to_string(),
+                param_type: Type::Custom("Printable".to_string()),
+                default: None,
+            }],
+            return_type: None,
+            body: vec![],
+            exported: false,
+        };
+        let type_def = |name: &str, implements: Vec<String>| Statement::TypeDef {
+            name: name.to_string(),
+            type_params: vec![],
+            fields: vec![],
+            implements,
+        };
+        let call = |type_name: &str| {
+            Statement::Expression(Expression::FunctionCall {
+                name: "show".to_string(),
+                args: vec![Expression::StructLiteral {
+                    type_name: type_name.to_string(),
+                    type_args: None,
+                    fields: vec![],
+                }],
+            })
+        };
+        let statements = [
+            interface,
+            show,
+            type_def("Person", vec!["Printable".to_string()]),
+            type_def("Robot", vec![]),
+        ];
+
+        let ok = [statements.as_slice(), &[call("Person")]].concat();
+        assert!(check_statements(&ok).is_ok());
+        let error =
+            check_statements(&[statements.as_slice(), &[call("Robot")]].concat()).unwrap_err();
+        assert_eq!(
+            error,
+            "Robot doesn't implement Printable, which 'show' expects"
+        );
+    }
+
+    #[test]
+    fn test_type_parameters_must_be_inferable() {
+        let generic = |param_type: Type| Statement::Function {
+            name: "make".to_string(),
+            type_params: vec!["T".to_string()],
+            receiver: None,
+            params: vec![Parameter {
+                name: "value".to_string(),
+                param_type,
+                default: None,
+            }],
+            return_type: Some(Type::Custom("T".to_string())),
+            body: vec![],
+            exported: false,
+        };
+        let array_of_t = Type::Generic {
+            name: "Array".to_string(),
+            type_params: vec![Type::Custom("T".to_string())],
+        };
+        assert!(check_statements(&[generic(array_of_t)]).is_ok());
+        let error = check_statements(&[generic(Type::Integer)]).unwrap_err();
+        assert_eq!(
+            error,
+            "Type parameter 'T' of 'make' isn't used by any parameter, so it can't be inferred"
+        );
+    }
+
+    #[test]
+    fn test_call_arguments_fill_defaults_and_reorder_named() {
+        let param = |name: &str, default: Option<Expression>| Parameter {
+            name: name.to_string(),
+            param_type: Type::Integer,
+            default,
+        };
+        let params = vec![
+            param("x", None),
+            param("y", Some(Expression::IntegerLiteral(2))),
+        ];
+        let named = |name: &str, value: i128| Expression::NamedArgument {
+            name: name.to_string(),
+            value: Box::new(Expression::IntegerLiteral(value)),
+        };
+
+        let args = call_arguments("f", &params, vec![Expression::IntegerLiteral(1)]).unwrap();
+        assert!(matches!(
+            args.as_slice(),
+            [Expression::IntegerLiteral(1), Expression::IntegerLiteral(2)]
+        ));
+        let args = call_arguments("f", &params, vec![named("y", 5), named("x", 4)]).unwrap();
+        assert!(matches!(
+            args.as_slice(),
+            [Expression::IntegerLiteral(4), Expression::IntegerLiteral(5)]
+        ));
+
+        let error = |args| call_arguments("f", &params, args).unwrap_err();
+        assert_eq!(error(vec![named("z", 1)]), "'f' has no parameter named 'z'");
+        assert_eq!(
+            error(vec![Expression::IntegerLiteral(1), named("x", 1)]),
+            "Argument 'x' of 'f' is given more than once"
+        );
+        assert_eq!(
+            error(vec![named("x", 1), Expression::IntegerLiteral(2)]),
+            "Positional arguments must come before named arguments in the call to 'f'"
+        );
+        assert_eq!(
+            error(vec![named("y", 1)]),
+            "Missing argument 'x' in the call to 'f'"
+        );
+
+        // Defaults don't make room for more arguments than parameters
+        let g = Statement::Function {
+            name: "g".to_string(),
+            type_params: Vec::new(),
+            receiver: None,
+            params: params.clone(),
+            return_type: None,
+            body: Vec::new(),
+            exported: false,
+        };
+        let call = |count: i128| {
+            Statement::Expression(Expression::FunctionCall {
+                name: "g".to_string(),
+                args: (1..=count).map(Expression::IntegerLiteral).collect(),
+            })
+        };
+        assert!(check_statements(&[g.clone(), call(2)]).is_ok());
+        assert_eq!(
+            check_statements(&[g, call(3)]).unwrap_err(),
+            "Too many arguments in the call to 'g': it takes 2, not 3"
+        );
+    }
+
+    #[test]
+    fn test_defaults_cannot_use_parameters_or_locals() {
+        let span = |default: Expression| Statement::Function {
+            name: "span".to_string(),
+            type_params: Vec::new(),
+            receiver: None,
+            params: vec![
+                Parameter::new("start", Type::Integer),
+                Parameter {
+                    default: Some(default),
+                    ..Parameter::new("end", Type::Integer)
+                },
+            ],
+            return_type: Some(Type::Integer),
+            body: Vec::new(),
+            exported: false,
+        };
+        let identifier = |name: &str| Expression::Identifier(name.to_string());
+        assert_eq!(
+            check_statements(&[span(identifier("start"))]).unwrap_err(),
+            "Default value of 'end' can't use 'start': defaults are evaluated where the function is called, so they may only use literals, constants and function calls"
+        );
+
+        let local = Statement::ValDecl {
+            name: "limit".to_string(),
+            type_annotation: None,
+            value: Expression::IntegerLiteral(3),
+        };
+        assert!(check_statements(&[local, span(identifier("limit"))])
+            .unwrap_err()
+            .contains("can't use 'limit'"));
+
+        let constant = Statement::ConstDecl {
+            name: "LIMIT".to_string(),
+            type_annotation: None,
+            value: Expression::IntegerLiteral(3),
+            exported: false,
+        };
+        assert!(check_statements(&[constant, span(identifier("LIMIT"))]).is_ok());
+    }
+
+    #[test]
+    fn test_constants_cannot_be_reassigned_or_shadowed() {
+        let constant = Statement::ConstDecl {
+            name: "LIMIT".to_string(),
+            type_annotation: None,
+            value: Expression::IntegerLiteral(3),
+            exported: false,
+        };
+        let assignment = Statement::Assignment {
+            variable: "LIMIT".to_string(),
+            value: Expression::IntegerLiteral(4),
+        };
+        let error = check_statements(&[constant.clone(), assignment]).unwrap_err();
+        assert_eq!(
+            error,
+            "'LIMIT' is a constant; it can't be assigned or declared again"
+        );
+
+        let nested = Statement::If {
+            condition: Expression::BoolLiteral(true),
+            then_body: vec![constant.clone()],
+            else_body: None,
+        };
+        let error = check_statements(&[nested]).unwrap_err();
+        assert_eq!(
+            error,
+            "Constant 'LIMIT' must be declared at module level, not inside a block"
+        );
+        assert!(check_statements(&[constant]).is_ok());
+    }
+
+    #[test]
+    fn test_map_keys_and_entries_must_agree() {
+        let declare = |value: Expression| Statement::ValDecl {
+            name: "ages".to_string(),
+            type_annotation: None,
+            value,
+        };
+        let entry = |key: Expression, value: i128| (key, Expression::IntegerLiteral(value));
+        let ages = Expression::MapLiteral {
+            type_args: None,
+            entries: vec![
+                entry(Expression::StringLiteral("ann".to_string()), 31),
+                entry(Expression::StringLiteral("bob".to_string()), 27),
+            ],
+        };
+        assert!(check_statements(&[declare(ages)]).is_ok());
+
+        let mixed = Expression::MapLiteral {
+            type_args: None,
+            entries: vec![
+                entry(Expression::StringLiteral("ann".to_string()), 31),
+                entry(Expression::IntegerLiteral(2), 27),
+            ],
+        };
+        assert_eq!(
+            check_statements(&[declare(mixed)]).unwrap_err(),
+            "Map key Integer doesn't match the other keys (String)"
+        );
+
+        let float_keys = Expression::MapLiteral {
+            type_args: Some(vec![Type::Float, Type::Integer]),
+            entries: Vec::new(),
+        };
+        assert!(check_statements(&[declare(float_keys)])
+            .unwrap_err()
+            .contains("can't be a map key"));
+
+        let empty = Expression::MapLiteral {
+            type_args: None,
+            entries: Vec::new(),
+        };
+        assert!(check_statements(&[declare(empty)])
+            .unwrap_err()
+            .starts_with("Can't infer the type of the empty map 'ages'"));
+    }
+
+    #[test]
+    fn test_array_push_takes_the_element_type() {
+        let push = |value: Expression| Statement::Function {
+            name: "record".to_string(),
+            type_params: vec![],
+            receiver: None,
+            params: vec![Parameter {
+                name: "scores".to_string(),
+                param_type: Type::Generic {
+                    name: "Array".to_string(),
+                    type_params: vec![Type::Integer],
+                },
+                default: None,
+            }],
+            return_type: None,
+            body: vec![Statement::Expression(Expression::MethodCall {
+                object: Box::new(Expression::Identifier("scores".to_string())),
+                method: "push".to_string(),
+                args: vec![value],
+            })],
+            exported: false,
+        };
+        assert!(check_statements(&[push(Expression::IntegerLiteral(7))]).is_ok());
+        assert_eq!(
+            check_statements(&[push(Expression::StringLiteral("seven".to_string()))]).unwrap_err(),
+            "push expects Integer, got String"
+        );
+    }
+
+    #[test]
+    fn test_reserved_prefix_names_are_warned_about() {
+        let statements = vec![
+            Statement::ValDecl {
+                name: "count".to_string(),
+                type_annotation: None,
+                value: Expression::IntegerLiteral(1),
+            },
+            Statement::ForIn {
+                index: None,
+                variable: "_bolt_i_0".to_string(),
+                iterable: Expression::ArrayLiteral(vec![Expression::IntegerLiteral(1)]),
+                body: vec![Statement::VarDecl {
+                    name: "_bolt_total".to_string(),
+                    type_annotation: None,
+                    value: Expression::IntegerLiteral(0),
+                }],
+            },
+        ];
+        let reserved: Vec<String> = check_statements(&statements)
+            .unwrap()
+            .into_iter()
+            .filter(|warning| warning.lint == "reserved-name")
+            .map(|warning| warning.message)
+            .collect();
+        assert_eq!(
+            reserved,
+            vec![
+                "'_bolt_i_0' starts with _bolt_, which is reserved for compiler temporaries",
+                "'_bolt_total' starts with _bolt_, which is reserved for compiler temporaries",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lints_for_unused_variables_int_conditions_and_iterate() {
+        let val = |name: &str, value: Expression| Statement::ValDecl {
+            name: name.to_string(),
+            type_annotation: None,
+            value,
+        };
+        let ident = |name: &str| Expression::Identifier(name.to_string());
+        let statements = vec![
+            val("count", Expression::IntegerLiteral(3)),
+            val("items", Expression::ArrayLiteral(vec![ident("count")])),
+            val("_ignored", Expression::IntegerLiteral(0)),
+            Statement::If {
+                condition: ident("count"),
+                then_body: vec![val("unused", Expression::IntegerLiteral(1))],
+                else_body: None,
+            },
+            Statement::ForIn {
+                index: None,
+                variable: "item".to_string(),
+                iterable: Expression::FunctionCall {
+                    name: "iterate".to_string(),
+                    args: vec![ident("items")],
+                },
+                body: vec![Statement::Assignment {
+                    variable: "written".to_string(),
+                    value: ident("item"),
+                }],
+            },
+            Statement::VarDecl {
+                name: "written".to_string(),
+                type_annotation: None,
+                value: Expression::IntegerLiteral(0),
+            },
+        ];
+        let warnings = check_statements(&statements).unwrap();
+        let found: Vec<(&str, &str)> = warnings
+            .iter()
+            .map(|warning| (warning.lint, warning.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "implicit-int-condition",
+                    "This condition is an Integer, not a Bool; compare it explicitly, e.g. `!= 0`"
+                ),
+                ("unused-variable", "'unused' is declared but never used"),
+                ("unused-variable", "'written' is declared but never used"),
+                (
+                    "deprecated",
+                    "iterate() is deprecated; loop over the array directly: `for item in items`"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_parameters_are_linted() {
+        let param = |name: &str| Parameter {
+            name: name.to_string(),
+            param_type: Type::Integer,
+            default: None,
+        };
+        let function = Statement::Function {
+            name: "pick".to_string(),
+            type_params: vec![],
+            receiver: None,
+            params: vec![param("used"), param("ignored"), param("_skipped")],
+            return_type: Some(Type::Integer),
+            body: vec![Statement::Return(Some(Expression::identifier("used")))],
+            exported: false,
+        };
+        let warnings = check_statements(&[function]).unwrap();
+        let found: Vec<(&str, &str)> = warnings
+            .iter()
+            .map(|warning| (warning.lint, warning.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(
+                "unused-parameter",
+                "Parameter 'ignored' of 'pick' is never used"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_errors_in_separate_statements_are_all_reported() {
+        let int8 = |name: &str, value: i128| Statement::ValDecl {
+            name: name.to_string(),
+            type_annotation: Some(Type::SizedInt(IntKind::Int8)),
+            value: Expression::IntegerLiteral(value),
+        };
+        let statements = vec![
+            int8("a", 300),
+            int8("b", 1),
+            Statement::Function {
+                name: "f".to_string(),
+                type_params: vec![],
+                receiver: None,
+                params: vec![],
+                return_type: None,
+                body: vec![int8("c", -200)],
+                exported: false,
+            },
+        ];
+        assert_eq!(
+            check_statements(&statements).unwrap_err(),
+            "Integer literal 300 is out of range for Int8 (-128..127)\n\
+             Integer literal -200 is out of range for Int8 (-128..127)"
+        );
+    }
+
+    #[test]
+    fn test_asm_only_inside_unsafe() {
+        let asm = || Statement::Asm {
+            template: "nop".to_string(),
+            outputs: vec![AsmOperand {
+                constraint: "+r".to_string(),
+                variable: "total".to_string(),
+            }],
+            inputs: vec![],
+            clobbers: vec![],
+        };
+        assert_eq!(
+            check_statements(&[asm()]).unwrap_err(),
+            "asm is only allowed inside an unsafe block"
+        );
+        assert!(check_statements(&[Statement::Unsafe { body: vec![asm()] }]).is_ok());
+    }
+
+    #[test]
+    fn test_names_of_generated_instances_are_rejected() {
+        let type_def = |name: &str, type_params: Vec<String>| Statement::TypeDef {
+            name: name.to_string(),
+            type_params,
+            fields: vec![],
+            implements: vec![],
+        };
+        let function = |name: &str, type_params: Vec<String>| Statement::Function {
+            name: name.to_string(),
+            type_params: type_params.clone(),
+            receiver: None,
+            params: type_params
+                .iter()
+                .map(|t| Parameter {
+                    name: "value".to_string(),
+                    param_type: Type::TypeParameter(t.clone()),
+                    default: None,
+                })
+                .collect(),
+            return_type: None,
+            body: vec![],
+            exported: false,
+        };
+        assert_eq!(
+            check_statements(&[type_def("Optional_Integer", vec![])]).unwrap_err(),
+            "Type name 'Optional_Integer' collides with the structs generated for Optional[...]; rename it"
+        );
+        assert_eq!(
+            check_statements(&[
+                type_def("Box", vec!["T".to_string()]),
+                type_def("Box_String", vec![]),
+            ])
+            .unwrap_err(),
+            "Type name 'Box_String' collides with the structs generated for Box[...]; rename it"
+        );
+        assert_eq!(
+            check_statements(&[
+                function("first", vec!["T".to_string()]),
+                function("first_Integer", vec![]),
+            ])
+            .unwrap_err(),
+            "Function name 'first_Integer' collides with the instances generated for 'first'; rename it"
+        );
+        // Only a type argument (upper case) after the underscore collides
+        assert!(check_statements(&[
+            function("first", vec!["T".to_string()]),
+            function("first_item", vec![]),
+            type_def("Map_entry", vec![]),
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn test_for_in_index_must_be_its_own_name() {
+        let for_in = |index: &str, iterable: Expression| Statement::ForIn {
+            index: Some(index.to_string()),
+            variable: "item".to_string(),
+            iterable,
+            body: vec![],
+        };
+        let range = Expression::Range {
+            start: Box::new(Expression::IntegerLiteral(0)),
+            end: Box::new(Expression::IntegerLiteral(3)),
+            inclusive: false,
+        };
+        let items = Expression::ArrayLiteral(vec![Expression::IntegerLiteral(1)]);
+        assert!(check_statements(&[for_in("i", items.clone())]).is_ok());
+        assert_eq!(
+            check_statements(&[for_in("item", items)]).unwrap_err(),
+            "'item' can't name both the index and the element of a for-in loop"
+        );
+        assert_eq!(
+            check_statements(&[for_in("i", range)]).unwrap_err(),
+            "A range has no separate index; write `for item in ...`"
+        );
+    }
+
+    #[test]
+    fn test_unit_calls_are_not_values() {
+        let log = Statement::Function {
+            name: "log".to_string(),
+            type_params: vec![],
+            receiver: None,
+            params: vec![],
+            return_type: None,
+            body: vec![],
+            exported: false,
+        };
+        let call = || Expression::FunctionCall {
+            name: "log".to_string(),
+            args: vec![],
+        };
+        assert!(check_statements(&[log.clone(), Statement::Expression(call())]).is_ok());
+        assert_eq!(
+            check_statements(&[
+                log,
+                Statement::ValDecl {
+                    name: "result".to_string(),
+                    type_annotation: None,
+                    value: call(),
+                },
+            ])
+            .unwrap_err(),
+            "'log' returns Unit (it has no return type), so its call can't be used as a value"
+        );
+    }
+
+    #[test]
+    fn test_none_is_the_null_pointer() {
+        let declare = |type_annotation: Type| Statement::VarDecl {
+            name: "next".to_string(),
+            type_annotation: Some(type_annotation),
+            value: Expression::NoneLiteral,
+        };
+        assert!(check_statements(&[declare(Type::Pointer(Box::new(Type::Integer)))]).is_ok());
+        assert_eq!(
+            check_statements(&[declare(Type::Integer)]).unwrap_err(),
+            "'none' can only be used as an optional value or a pointer, not Integer"
+        );
+    }
+
+    #[test]
+    fn test_only_arrays_and_strings_can_be_sliced() {
+        let slice_of = |value: Expression| {
+            vec![
+                Statement::ValDecl {
+                    name: "value".to_string(),
+                    type_annotation: None,
+                    value,
+                },
+                Statement::ValDecl {
+                    name: "part".to_string(),
+                    type_annotation: None,
+                    value: Expression::Slice {
+                        object: Box::new(Expression::Identifier("value".to_string())),
+                        start: Box::new(Expression::IntegerLiteral(0)),
+                        end: Box::new(Expression::IntegerLiteral(1)),
+                    },
+                },
+            ]
+        };
+        assert!(check_statements(&slice_of(Expression::StringLiteral("hi".to_string()))).is_ok());
+        assert_eq!(
+            check_statements(&slice_of(Expression::IntegerLiteral(5))).unwrap_err(),
+            "Only arrays and strings can be sliced, not Integer"
+        );
+    }
+
+    #[test]
+    fn test_casts_between_numbers_and_pointers_only() {
+        let cast_of = |value: Expression, target: Type| {
+            vec![Statement::ValDecl {
+                name: "converted".to_string(),
+                type_annotation: None,
+                value: Expression::Cast {
+                    value: Box::new(value),
+                    target,
+                },
+            }]
+        };
+        assert!(check_statements(&cast_of(Expression::IntegerLiteral(1), Type::Float)).is_ok());
+        assert!(check_statements(&cast_of(
+            Expression::BoolLiteral(true),
+            Type::SizedInt(IntKind::UInt8)
+        ))
+        .is_ok());
+        assert_eq!(
+            check_statements(&cast_of(
+                Expression::StringLiteral("7".to_string()),
+                Type::Integer
+            ))
+            .unwrap_err(),
+            "Can't cast String to Integer"
+        );
+        assert_eq!(
+            check_statements(&cast_of(
+                Expression::IntegerLiteral(0),
+                Type::Pointer(Box::new(Type::Integer))
+            ))
+            .unwrap_err(),
+            "Can't cast Integer to ^Integer"
+        );
+    }
+
+    #[test]
+    fn test_heap_blocks_are_typed_pointers() {
+        let alloc = |count: Expression| Expression::Allocate {
+            element: Type::Integer,
+            block: None,
+            count: Box::new(count),
+        };
+        let program = |statement: Statement| {
+            vec![
+                Statement::val("block", alloc(Expression::IntegerLiteral(4))),
+                statement,
+            ]
+        };
+        let free = |arg: Expression| {
+            Statement::Expression(Expression::FunctionCall {
+                name: "free".to_string(),
+                args: vec![arg],
+            })
+        };
+        assert!(check_statements(&program(free(Expression::identifier("block")))).is_ok());
+        assert_eq!(
+            check_statements(&program(free(Expression::IntegerLiteral(4)))).unwrap_err(),
+            "free takes a pointer from alloc or realloc, not a Integer"
+        );
+        assert_eq!(
+            check_statements(&program(Statement::val(
+                "more",
+                alloc(Expression::StringLiteral("4".to_string()))
+            )))
+            .unwrap_err(),
+            "An allocation's count must be an integer, not String"
+        );
+        let resize = Expression::Allocate {
+            element: Type::Float,
+            block: Some(Box::new(Expression::identifier("block"))),
+            count: Box::new(Expression::IntegerLiteral(8)),
+        };
+        assert_eq!(
+            check_statements(&program(Statement::val("more", resize))).unwrap_err(),
+            "realloc[Float] resizes a ^Float, not a ^Integer"
+        );
+    }
+
+    #[test]
+    fn test_main_returns_an_exit_status() {
+        let main = |return_type: Option<Type>, body: Vec<Statement>| Statement::Function {
+            name: "main".to_string(),
+            type_params: vec![],
+            receiver: None,
+            params: vec![],
+            return_type,
+            body,
+            exported: false,
+        };
+        let exit = |arg: Expression| {
+            Statement::Expression(Expression::FunctionCall {
+                name: "exit".to_string(),
+                args: vec![arg],
+            })
+        };
+        assert!(check_statements(&[main(
+            Some(Type::Integer),
+            vec![Statement::Return(Some(Expression::IntegerLiteral(3)))]
+        )])
+        .is_ok());
+        assert_eq!(
+            check_statements(&[main(Some(Type::String), vec![])]).unwrap_err(),
+            "main returns an Integer (the exit status) or nothing, not String"
+        );
+        let with_params = |params: Vec<Parameter>| Statement::Function {
+            name: "main".to_string(),
+            type_params: vec![],
+            receiver: None,
+            params,
+            return_type: None,
+            body: vec![],
+            exported: false,
+        };
+        let args = |param_type: Type| Parameter {
+            name: "args".to_string(),
+            param_type,
+            default: None,
+        };
+        assert!(check_statements(&[with_params(vec![args(Type::Generic {
+            name: "Array".to_string(),
+            type_params: vec![Type::String],
+        })])])
+        .is_ok());
+        assert_eq!(
+            check_statements(&[with_params(vec![args(Type::Integer)])]).unwrap_err(),
+            "main takes no parameters or a single `args: Array[String]`"
+        );
+        assert!(check_statements(&[exit(Expression::IntegerLiteral(2))]).is_ok());
+        assert_eq!(
+            check_statements(&[exit(Expression::StringLiteral("2".to_string()))]).unwrap_err(),
+            "exit takes one Integer, the exit status"
+        );
+    }
+
+    #[test]
+    fn test_pointer_arithmetic_moves_by_integers_only() {
+        let with_pointers = |value: Expression| {
+            vec![
+                Statement::VarDecl {
+                    name: "bytes".to_string(),
+                    type_annotation: Some(Type::pointer(Type::SizedInt(IntKind::UInt8))),
+                    value: Expression::NoneLiteral,
+                },
+                Statement::VarDecl {
+                    name: "numbers".to_string(),
+                    type_annotation: Some(Type::pointer(Type::Integer)),
+                    value: Expression::NoneLiteral,
+                },
+                Statement::val("result", value),
+            ]
+        };
+        let bytes = || Expression::identifier("bytes");
+        let numbers = || Expression::identifier("numbers");
+        for ok in [
+            Expression::binary(bytes(), BinaryOperator::Add, Expression::IntegerLiteral(4)),
+            Expression::binary(
+                numbers(),
+                BinaryOperator::Subtract,
+                Expression::IntegerLiteral(1),
+            ),
+            Expression::binary(bytes(), BinaryOperator::Subtract, bytes()),
+            Expression::binary(numbers(), BinaryOperator::Less, numbers()),
+        ] {
+            assert!(check_statements(&with_pointers(ok)).is_ok());
+        }
+        assert_eq!(
+            check_statements(&with_pointers(Expression::binary(
+                bytes(),
+                BinaryOperator::Subtract,
+                numbers()
+            )))
+            .unwrap_err(),
+            "Can't subtract a ^Integer from a ^UInt8; only pointers to the same type can be subtracted"
+        );
+        assert_eq!(
+            check_statements(&with_pointers(Expression::binary(
+                Expression::IntegerLiteral(1),
+                BinaryOperator::Add,
+                bytes()
+            )))
+            .unwrap_err(),
+            "Pointer arithmetic puts the pointer first: write `pointer + offset`, not `Integer + ^UInt8`"
+        );
+        assert_eq!(
+            check_statements(&with_pointers(Expression::binary(
+                numbers(),
+                BinaryOperator::Multiply,
+                Expression::IntegerLiteral(2)
+            )))
+            .unwrap_err(),
+            "Can't use * on ^Integer and Integer; a pointer only supports `+ n` and `- n` with an integer n, and `-` with another pointer to the same type"
+        );
+        assert!(check_statements(&with_pointers(Expression::binary(
+            numbers(),
+            BinaryOperator::Add,
+            Expression::FloatLiteral(0.5)
+        )))
+        .is_err());
+    }
+
+    #[test]
+    fn test_tuple_indexes_stay_below_the_arity() {
+        let with_pair = |value: Expression| {
+            vec![
+                Statement::val(
+                    "pair",
+                    Expression::TupleLiteral(vec![
+                        Expression::IntegerLiteral(1),
+                        Expression::StringLiteral("one".to_string()),
+                    ]),
+                ),
+                Statement::val("element", value),
+            ]
+        };
+        let element = |index: &str| Expression::FieldAccess {
+            object: Box::new(Expression::identifier("pair")),
+            field: index.to_string(),
+        };
+        assert!(check_statements(&with_pair(element("0"))).is_ok());
+        assert!(check_statements(&with_pair(element("1"))).is_ok());
+        assert_eq!(
+            check_statements(&with_pair(element("5"))).unwrap_err(),
+            "Tuple (Integer, String) has no element .5; it has 2 elements, from .0"
+        );
+    }
+}
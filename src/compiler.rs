@@ -0,0 +1,152 @@
+//! The compiler's front end as a library: lexing, parsing, resolving
+//! imports and type checking, with every error and warning reported to a
+//! `DiagnosticSink` (see `diagnostics`) rather than printed.
+
+use crate::ast::Program;
+use crate::checker::{check_program, check_program_with_constants};
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::ice::quietly;
+use crate::lexer::Lexer;
+use crate::module::ModuleSystem;
+use crate::parser::Parser;
+use crate::symbol_table::SymbolTable;
+
+/// A program that parsed and type-checked, with its resolved imports
+pub struct Checked {
+    pub program: Program,
+    pub symbol_table: SymbolTable,
+    pub modules: ModuleSystem,
+}
+
+/// The front end of the compiler (lexing, parsing, resolving imports and
+/// type checking), reporting what it finds to a sink
+pub struct Compiler<'a> {
+    sink: &'a mut dyn DiagnosticSink,
+}
+
+impl<'a> Compiler<'a> {
+    pub fn new(sink: &'a mut dyn DiagnosticSink) -> Self {
+        Compiler { sink }
+    }
+
+    /// Lex and parse `source`, whose panics and assertions report
+    /// `source_name` as their file. Parse errors don't end the run: the
+    /// statements that did parse are type-checked too, and every error of
+    /// both phases is reported before None is returned.
+    pub fn parse(&mut self, source: &str, source_name: &str) -> Option<(Program, SymbolTable)> {
+        let tokens = match Lexer::new(source.to_string()).tokenize() {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                self.sink
+                    .report(Diagnostic::error(format!("Lexer error: {}", e)));
+                return None;
+            }
+        };
+
+        // The parser panics on most syntax errors, which it recovers from,
+        // so the default hook's message would only duplicate the error
+        let mut parser = Parser::new(tokens).with_source_name(source_name);
+        let program = quietly(|| parser.parse_recovering())
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload));
+
+        if parser.diagnostics().is_empty() {
+            return Some((program, parser.into_symbol_table()));
+        }
+        for e in parser.diagnostics() {
+            self.sink
+                .report(Diagnostic::error(format!("Parser error: {}", e)));
+        }
+        if let Err(message) = check_program(&program) {
+            self.report_errors(&message);
+        }
+        None
+    }
+
+    /// Resolve the program's imports and type-check it with the constants
+    /// it imports; warnings are reported too, and don't fail the check
+    pub fn resolve_and_check(&mut self, program: &Program) -> Option<ModuleSystem> {
+        let mut modules = ModuleSystem::new();
+        if let Err(e) = modules.resolve_imports(program) {
+            self.sink
+                .report(Diagnostic::error(format!("Module resolution error: {}", e)));
+            return None;
+        }
+        match modules
+            .imported_constants(&program.statements)
+            .and_then(|imported| check_program_with_constants(program, &imported))
+        {
+            Ok(warnings) => {
+                for warning in warnings {
+                    self.sink.report(Diagnostic::warning(warning));
+                }
+                Some(modules)
+            }
+            Err(message) => {
+                self.report_errors(&message);
+                None
+            }
+        }
+    }
+
+    /// `parse`, then `resolve_and_check`
+    pub fn check(&mut self, source: &str, source_name: &str) -> Option<Checked> {
+        let (program, symbol_table) = self.parse(source, source_name)?;
+        let modules = self.resolve_and_check(&program)?;
+        Some(Checked {
+            program,
+            symbol_table,
+            modules,
+        })
+    }
+
+    // The checker reports one error per line
+    fn report_errors(&mut self, message: &str) {
+        for line in message.lines() {
+            self.sink.report(Diagnostic::error(line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{Severity, SeverityCounts};
+
+    #[test]
+    fn test_parse_and_type_errors_are_reported_together() {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let source = "val x: String = 5\nval := 3\n";
+        assert!(Compiler::new(&mut diagnostics)
+            .check(source, "app.bolt")
+            .is_none());
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics[0].message.starts_with("Parser error: "));
+        assert_eq!(
+            diagnostics[1].message,
+            "'x' is declared as String but its value is Integer"
+        );
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_warnings_are_counted_and_do_not_fail() {
+        let mut counts = SeverityCounts::default();
+        let checked = Compiler::new(&mut counts).check(
+            "val unused := 1\nfun f() {\n    val y := 2\n}\n",
+            "app.bolt",
+        );
+        assert!(checked.is_some());
+        assert_eq!(counts.errors, 0);
+        assert!(counts.warnings > 0);
+    }
+
+    #[test]
+    fn test_closures_forward_diagnostics() {
+        let mut lines = Vec::new();
+        let mut forward = |diagnostic: Diagnostic| lines.push(diagnostic.to_string());
+        Compiler::new(&mut forward).check("val x: String = 5", "app.bolt");
+        assert!(lines.iter().any(|line| line.starts_with("Error: ")));
+    }
+}
@@ -0,0 +1,836 @@
+use crate::ast::{BinaryOperator, Expression, InterpolationPart, Statement, Type, UnaryOperator};
+use std::collections::{HashMap, HashSet};
+
+/// A value known at compile time
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+}
+
+impl ConstValue {
+    pub fn from_expression(expr: &Expression) -> Option<Self> {
+        match expr {
+            // Only literals a 64-bit constant holds are folded
+            Expression::IntegerLiteral(n) => i64::try_from(*n).ok().map(ConstValue::Integer),
+            Expression::FloatLiteral(f) => Some(ConstValue::Float(*f)),
+            Expression::StringLiteral(s) => Some(ConstValue::String(s.clone())),
+            Expression::BoolLiteral(b) => Some(ConstValue::Bool(*b)),
+            Expression::Grouping(inner) => Self::from_expression(inner),
+            _ => None,
+        }
+    }
+
+    pub fn into_expression(self) -> Expression {
+        match self {
+            ConstValue::Integer(n) => Expression::IntegerLiteral(n.into()),
+            ConstValue::Float(f) => Expression::FloatLiteral(f),
+            ConstValue::String(s) => Expression::StringLiteral(s),
+            ConstValue::Bool(b) => Expression::BoolLiteral(b),
+        }
+    }
+
+    /// The type a constant of this value has when it isn't annotated
+    pub fn value_type(&self) -> Type {
+        match self {
+            ConstValue::Integer(_) => Type::Integer,
+            ConstValue::Float(_) => Type::Float,
+            ConstValue::String(_) => Type::String,
+            ConstValue::Bool(_) => Type::Bool,
+        }
+    }
+
+    // The value as `${...}` would render it, where that doesn't depend on
+    // runtime formatting
+    fn interpolated(&self) -> Option<String> {
+        match self {
+            ConstValue::Integer(n) => Some(n.to_string()),
+            ConstValue::String(s) => Some(s.clone()),
+            ConstValue::Bool(b) => Some(b.to_string()),
+            ConstValue::Float(_) => None,
+        }
+    }
+}
+
+type ConstFunction = fn(&[ConstValue]) -> Option<ConstValue>;
+
+/// Builtins without side effects, so a call with constant arguments can run
+/// at compile time. Each entry must agree with the C the call would run.
+const PURE_FUNCTIONS: &[(&str, ConstFunction)] = &[
+    ("length", const_length),
+    ("concat", const_concat),
+    ("contains", const_contains),
+    ("indexOf", const_index_of),
+    ("trim", const_trim),
+    ("compare", const_compare),
+    ("toString", const_to_string),
+];
+
+// C strings end at the first NUL
+fn c_str(s: &str) -> &str {
+    s.split('\0').next().unwrap_or_default()
+}
+
+fn strings(args: &[ConstValue]) -> Option<Vec<&str>> {
+    args.iter()
+        .map(|arg| match arg {
+            ConstValue::String(s) => Some(c_str(s)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn const_length(args: &[ConstValue]) -> Option<ConstValue> {
+    match strings(args)?.as_slice() {
+        [s] => Some(ConstValue::Integer(s.len() as i64)),
+        _ => None,
+    }
+}
+
+fn const_concat(args: &[ConstValue]) -> Option<ConstValue> {
+    match strings(args)?.as_slice() {
+        [a, b] => Some(ConstValue::String(format!("{}{}", a, b))),
+        _ => None,
+    }
+}
+
+fn const_contains(args: &[ConstValue]) -> Option<ConstValue> {
+    match strings(args)?.as_slice() {
+        [s, substr] => Some(ConstValue::Bool(s.contains(substr))),
+        _ => None,
+    }
+}
+
+fn const_index_of(args: &[ConstValue]) -> Option<ConstValue> {
+    match strings(args)?.as_slice() {
+        [s, substr] => Some(ConstValue::Integer(
+            s.find(substr).map_or(-1, |index| index as i64),
+        )),
+        _ => None,
+    }
+}
+
+fn const_trim(args: &[ConstValue]) -> Option<ConstValue> {
+    match strings(args)?.as_slice() {
+        [s] => Some(ConstValue::String(
+            s.trim_matches([' ', '\t', '\n', '\r']).to_string(),
+        )),
+        _ => None,
+    }
+}
+
+fn const_compare(args: &[ConstValue]) -> Option<ConstValue> {
+    match strings(args)?.as_slice() {
+        [a, b] => Some(ConstValue::Integer(a.cmp(b) as i64)),
+        _ => None,
+    }
+}
+
+fn const_to_string(args: &[ConstValue]) -> Option<ConstValue> {
+    match args {
+        [ConstValue::Integer(n)] if fits_c_int(*n) => Some(ConstValue::String(n.to_string())),
+        _ => None,
+    }
+}
+
+/// Evaluate a call to a pure builtin, if `name` is one and the arguments suit it
+pub fn eval_call(name: &str, args: &[ConstValue]) -> Option<ConstValue> {
+    let (_, function) = PURE_FUNCTIONS.iter().find(|(pure, _)| *pure == name)?;
+    function(args)
+}
+
+// Integer is a C int, so only results that fit one are folded; anything
+// else keeps whatever the C compiler makes of it
+fn fits_c_int(n: i64) -> bool {
+    i32::try_from(n).is_ok()
+}
+
+pub fn eval_binary(
+    operator: &BinaryOperator,
+    left: &ConstValue,
+    right: &ConstValue,
+) -> Option<ConstValue> {
+    use ConstValue::*;
+    match (left, right) {
+        (Integer(a), Integer(b)) => {
+            let (a, b) = (*a, *b);
+            let value = match operator {
+                BinaryOperator::Add => Integer(a.checked_add(b)?),
+                BinaryOperator::Subtract => Integer(a.checked_sub(b)?),
+                BinaryOperator::Multiply => Integer(a.checked_mul(b)?),
+                BinaryOperator::Divide => Integer(a.checked_div(b)?),
+                BinaryOperator::Modulo => Integer(a.checked_rem(b)?),
+                _ => return compare(operator, a.cmp(&b)),
+            };
+            match value {
+                Integer(n) if fits_c_int(n) => Some(value),
+                _ => None,
+            }
+        }
+        (Integer(_) | Float(_), Integer(_) | Float(_)) => {
+            let as_float = |value: &ConstValue| match value {
+                Integer(n) => *n as f64,
+                Float(f) => *f,
+                _ => unreachable!(),
+            };
+            let (a, b) = (as_float(left), as_float(right));
+            let value = match operator {
+                BinaryOperator::Add => a + b,
+                BinaryOperator::Subtract => a - b,
+                BinaryOperator::Multiply => a * b,
+                BinaryOperator::Divide => a / b,
+                BinaryOperator::Modulo => return None,
+                _ => return compare(operator, a.partial_cmp(&b)?),
+            };
+            value.is_finite().then_some(Float(value))
+        }
+        (String(a), String(b)) => match operator {
+            BinaryOperator::Add => Some(String(format!("{}{}", c_str(a), c_str(b)))),
+            _ => compare(operator, c_str(a).cmp(c_str(b))),
+        },
+        (Bool(a), Bool(b)) => match operator {
+            BinaryOperator::And => Some(Bool(*a && *b)),
+            BinaryOperator::Or => Some(Bool(*a || *b)),
+            BinaryOperator::Equal => Some(Bool(a == b)),
+            BinaryOperator::NotEqual => Some(Bool(a != b)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_comparison(operator: &BinaryOperator) -> bool {
+    matches!(
+        operator,
+        BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Less
+            | BinaryOperator::LessEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterEqual
+    )
+}
+
+fn compare(operator: &BinaryOperator, order: std::cmp::Ordering) -> Option<ConstValue> {
+    Some(ConstValue::Bool(match operator {
+        BinaryOperator::Equal => order.is_eq(),
+        BinaryOperator::NotEqual => order.is_ne(),
+        BinaryOperator::Less => order.is_lt(),
+        BinaryOperator::LessEqual => order.is_le(),
+        BinaryOperator::Greater => order.is_gt(),
+        BinaryOperator::GreaterEqual => order.is_ge(),
+        _ => return None,
+    }))
+}
+
+pub fn eval_unary(operator: &UnaryOperator, operand: &ConstValue) -> Option<ConstValue> {
+    match (operator, operand) {
+        (UnaryOperator::Not, ConstValue::Bool(b)) => Some(ConstValue::Bool(!b)),
+        (UnaryOperator::Negate, ConstValue::Integer(n)) => n
+            .checked_neg()
+            .filter(|n| fits_c_int(*n))
+            .map(ConstValue::Integer),
+        (UnaryOperator::Negate, ConstValue::Float(f)) => Some(ConstValue::Float(-f)),
+        _ => None,
+    }
+}
+
+/// `value as target` between Integer and Float; casts that C may wrap or
+/// truncate differently (sized integers, out-of-range floats) are left to C
+pub fn eval_cast(value: ConstValue, target: &Type) -> Option<ConstValue> {
+    match (value, target) {
+        (ConstValue::Integer(n), Type::Float) => Some(ConstValue::Float(n as f64)),
+        (ConstValue::Float(f), Type::Integer) => Some(f.trunc())
+            .filter(|t| t.is_finite() && fits_c_int(*t as i64))
+            .map(|t| ConstValue::Integer(t as i64)),
+        (ConstValue::Bool(b), Type::Integer) => Some(ConstValue::Integer(b as i64)),
+        (value @ ConstValue::Integer(_), Type::Integer)
+        | (value @ ConstValue::Float(_), Type::Float) => Some(value),
+        _ => None,
+    }
+}
+
+/// A module-level `const` with its value worked out
+#[derive(Debug, Clone)]
+pub struct Constant {
+    pub name: String,
+    pub const_type: Type,
+    pub value: ConstValue,
+}
+
+/// Evaluate the `const` declarations among `statements`, in order, so each
+/// may use the ones before it. A value that doesn't fold to a literal, or
+/// doesn't match its annotation, is an error.
+pub fn evaluate_constants(statements: &[Statement]) -> Result<Vec<Constant>, String> {
+    evaluate_constants_with(statements, &[])
+}
+
+/// `evaluate_constants` for a module that imports `imported` from other
+/// modules, so its own constants may be defined in terms of them. Only the
+/// module's own constants are returned.
+pub fn evaluate_constants_with(
+    statements: &[Statement],
+    imported: &[Constant],
+) -> Result<Vec<Constant>, String> {
+    let user_functions = statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Function { name, .. } => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut folder = ConstFolder::new(user_functions).with_constants(imported);
+    let mut constants = Vec::new();
+    for statement in statements {
+        let Statement::ConstDecl {
+            name,
+            type_annotation,
+            value,
+            ..
+        } = statement
+        else {
+            continue;
+        };
+        let mut value = value.clone();
+        folder.fold_expression(&mut value);
+        let Some(value) = ConstValue::from_expression(&value) else {
+            // What a checked build would stop on at run time
+            if let Some(problem) = unfoldable_arithmetic(&value) {
+                return Err(format!("Constant '{}' {}", name, problem));
+            }
+            return Err(format!(
+                "The value of constant '{}' isn't known at compile time; use literals, operators, other constants and pure builtins like length()",
+                name
+            ));
+        };
+        let (const_type, value) = match (type_annotation, value) {
+            (None, value) => (value.value_type(), value),
+            (Some(Type::Float), ConstValue::Integer(n)) => {
+                (Type::Float, ConstValue::Float(n as f64))
+            }
+            (Some(t @ Type::SizedInt(_)), value @ ConstValue::Integer(_)) => (t.clone(), value),
+            (Some(t), value) if *t == value.value_type() => (t.clone(), value),
+            (Some(t), value) => {
+                return Err(format!(
+                    "Constant '{}' is declared {} but its value is {}",
+                    name,
+                    t,
+                    value.value_type()
+                ))
+            }
+        };
+        folder.constants.insert(name.clone(), value.clone());
+        constants.push(Constant {
+            name: name.clone(),
+            const_type,
+            value,
+        });
+    }
+    Ok(constants)
+}
+
+// An Integer operation on literals that the folder left as written, because
+// its result doesn't fit an Integer or it divides by zero
+fn unfoldable_arithmetic(expr: &Expression) -> Option<&'static str> {
+    match expr {
+        Expression::BinaryOp {
+            left,
+            operator,
+            right,
+        } => match (&**left, operator, &**right) {
+            (
+                Expression::IntegerLiteral(_),
+                BinaryOperator::Divide | BinaryOperator::Modulo,
+                Expression::IntegerLiteral(0),
+            ) => Some("divides by zero"),
+            (
+                Expression::IntegerLiteral(_),
+                BinaryOperator::Add
+                | BinaryOperator::Subtract
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Modulo,
+                Expression::IntegerLiteral(_),
+            ) => Some("overflows an Integer"),
+            _ => unfoldable_arithmetic(left).or_else(|| unfoldable_arithmetic(right)),
+        },
+        Expression::UnaryOp { operand, .. } | Expression::Grouping(operand) => {
+            unfoldable_arithmetic(operand)
+        }
+        _ => None,
+    }
+}
+
+/// Constant folding: operators on literals, `"...".length` and calls to the
+/// pure builtins above are replaced by their values before code generation.
+/// Functions the program defines itself are never folded, even if they share
+/// a builtin's name.
+pub struct ConstFolder {
+    user_functions: HashSet<String>,
+    constants: HashMap<String, ConstValue>, // Uses of these names become their values
+}
+
+impl ConstFolder {
+    pub fn new(user_functions: HashSet<String>) -> Self {
+        Self {
+            user_functions,
+            constants: HashMap::new(),
+        }
+    }
+
+    pub fn with_constants(mut self, constants: &[Constant]) -> Self {
+        self.constants.extend(
+            constants
+                .iter()
+                .map(|constant| (constant.name.clone(), constant.value.clone())),
+        );
+        self
+    }
+
+    pub fn fold_statements(&self, statements: &mut [Statement]) {
+        for statement in statements {
+            self.fold_statement(statement);
+        }
+    }
+
+    fn fold_statement(&self, statement: &mut Statement) {
+        match statement {
+            Statement::VarDecl { value, .. }
+            | Statement::ValDecl { value, .. }
+            | Statement::Assignment { value, .. }
+            | Statement::Return(Some(value))
+            | Statement::Panic { message: value, .. } => self.fold_expression(value),
+            Statement::Assert {
+                condition, message, ..
+            } => {
+                self.fold_expression(condition);
+                self.fold_expression(message);
+            }
+            // A bare literal statement would print itself, so only the
+            // operands of an expression statement are folded
+            Statement::Expression(expr) => self.fold_operands(expr),
+            Statement::If {
+                condition,
+                then_body,
+                else_body,
+            } => {
+                self.fold_expression(condition);
+                self.fold_statements(then_body);
+                if let Some(body) = else_body {
+                    self.fold_statements(body);
+                }
+            }
+            Statement::IfPresent {
+                value,
+                then_body,
+                else_body,
+                ..
+            } => {
+                self.fold_expression(value);
+                self.fold_statements(then_body);
+                if let Some(body) = else_body {
+                    self.fold_statements(body);
+                }
+            }
+            Statement::ForIn { iterable, body, .. } => {
+                self.fold_expression(iterable);
+                self.fold_statements(body);
+            }
+            Statement::ForCondition { condition, body } => {
+                self.fold_expression(condition);
+                self.fold_statements(body);
+            }
+            Statement::DoWhile { body, condition } => {
+                self.fold_statements(body);
+                self.fold_expression(condition);
+            }
+            Statement::ForLoop {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                if let Some(init) = init {
+                    self.fold_statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.fold_expression(condition);
+                }
+                if let Some(update) = update {
+                    self.fold_statement(update);
+                }
+                self.fold_statements(body);
+            }
+            Statement::Function { body, .. }
+            | Statement::Defer { body }
+            | Statement::Unsafe { body } => self.fold_statements(body),
+            _ => {}
+        }
+    }
+
+    pub fn fold_expression(&self, expr: &mut Expression) {
+        self.fold_operands(expr);
+        if let Some(value) = self.eval(expr) {
+            *expr = value.into_expression();
+        }
+    }
+
+    fn fold_operands(&self, expr: &mut Expression) {
+        match expr {
+            // Leave `a < b < c` as written for the checker to report
+            Expression::BinaryOp { left, operator, .. }
+                if is_comparison(operator)
+                    && matches!(&**left, Expression::BinaryOp { operator, .. } if is_comparison(operator)) =>
+                {}
+            Expression::ArrayLiteral(items)
+            | Expression::TupleLiteral(items)
+            | Expression::FunctionCall { args: items, .. }
+            | Expression::NamespacedFunctionCall { args: items, .. } => {
+                for item in items {
+                    self.fold_expression(item);
+                }
+            }
+            // The receiver is passed by address, so like &x it stays a place
+            Expression::MethodCall { args, .. } => {
+                for arg in args {
+                    self.fold_expression(arg);
+                }
+            }
+            Expression::BinaryOp { left, right, .. }
+            | Expression::Range {
+                start: left,
+                end: right,
+                ..
+            }
+            | Expression::ArrayAccess {
+                array: left,
+                index: right,
+            }
+            | Expression::Coalesce {
+                value: left,
+                fallback: right,
+            } => {
+                self.fold_expression(left);
+                self.fold_expression(right);
+            }
+            Expression::Slice { object, start, end } => {
+                self.fold_expression(object);
+                self.fold_expression(start);
+                self.fold_expression(end);
+            }
+            Expression::If {
+                condition,
+                then_value,
+                else_value,
+            } => {
+                self.fold_expression(condition);
+                self.fold_expression(then_value);
+                self.fold_expression(else_value);
+            }
+            Expression::UnaryOp { operand, .. }
+            | Expression::FieldAccess {
+                object: operand, ..
+            }
+            | Expression::OptionalFieldAccess {
+                object: operand, ..
+            }
+            | Expression::Grouping(operand)
+            | Expression::NamedArgument { value: operand, .. }
+            | Expression::Cast { value: operand, .. }
+            | Expression::Dereference { operand } => self.fold_expression(operand),
+            Expression::Allocate { block, count, .. } => {
+                if let Some(block) = block {
+                    self.fold_expression(block);
+                }
+                self.fold_expression(count);
+            }
+            Expression::StructLiteral { fields, .. } => {
+                for field in fields {
+                    self.fold_expression(&mut field.value);
+                }
+            }
+            Expression::MapLiteral { entries, .. } => {
+                for (key, value) in entries {
+                    self.fold_expression(key);
+                    self.fold_expression(value);
+                }
+            }
+            Expression::StringInterpolation(parts) => {
+                for part in parts {
+                    if let InterpolationPart::Expression(expr) = part {
+                        self.fold_expression(expr);
+                    }
+                }
+            }
+            Expression::Lambda { body, .. } => self.fold_expression(body),
+            // &x needs x to stay a place
+            Expression::AddressOf { .. }
+            | Expression::StringLiteral(_)
+            | Expression::IntegerLiteral(_)
+            | Expression::FloatLiteral(_)
+            | Expression::BoolLiteral(_)
+            | Expression::NoneLiteral
+            | Expression::Identifier(_) => {}
+        }
+    }
+
+    // Value of an expression whose operands are already folded
+    fn eval(&self, expr: &Expression) -> Option<ConstValue> {
+        match expr {
+            Expression::BinaryOp {
+                left,
+                operator,
+                right,
+            } => eval_binary(
+                operator,
+                &ConstValue::from_expression(left)?,
+                &ConstValue::from_expression(right)?,
+            ),
+            Expression::UnaryOp { operator, operand } => {
+                eval_unary(operator, &ConstValue::from_expression(operand)?)
+            }
+            Expression::FunctionCall { name, args } if !self.user_functions.contains(name) => {
+                let args: Option<Vec<ConstValue>> =
+                    args.iter().map(ConstValue::from_expression).collect();
+                eval_call(name, &args?)
+            }
+            Expression::FieldAccess { object, field } if field == "length" => {
+                eval_call("length", &[ConstValue::from_expression(object)?])
+            }
+            Expression::Grouping(inner) => ConstValue::from_expression(inner),
+            Expression::Cast { value, target } => {
+                eval_cast(ConstValue::from_expression(value)?, target)
+            }
+            Expression::Identifier(name) => self.constants.get(name).cloned(),
+            Expression::If {
+                condition,
+                then_value,
+                else_value,
+            } => match ConstValue::from_expression(condition)? {
+                ConstValue::Bool(true) => ConstValue::from_expression(then_value),
+                ConstValue::Bool(false) => ConstValue::from_expression(else_value),
+                _ => None,
+            },
+            Expression::StringInterpolation(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        InterpolationPart::Literal(literal) => text.push_str(literal),
+                        InterpolationPart::Expression(expr) => {
+                            text.push_str(&ConstValue::from_expression(expr)?.interpolated()?)
+                        }
+                    }
+                }
+                Some(ConstValue::String(text))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::IntKind;
+
+    fn string(s: &str) -> Expression {
+        Expression::StringLiteral(s.to_string())
+    }
+
+    fn call(name: &str, args: Vec<Expression>) -> Expression {
+        Expression::FunctionCall {
+            name: name.to_string(),
+            args,
+        }
+    }
+
+    fn folded(mut expr: Expression) -> Expression {
+        ConstFolder::new(HashSet::new()).fold_expression(&mut expr);
+        expr
+    }
+
+    #[test]
+    fn test_pure_builtins_fold_with_literal_arguments() {
+        assert!(matches!(
+            folded(call("length", vec![string("hello")])),
+            Expression::IntegerLiteral(5)
+        ));
+        assert!(matches!(
+            folded(call("concat", vec![string("a"), string("b")])),
+            Expression::StringLiteral(s) if s == "ab"
+        ));
+        assert!(matches!(
+            folded(call("contains", vec![string("bolt"), string("ol")])),
+            Expression::BoolLiteral(true)
+        ));
+        assert!(matches!(
+            folded(call("indexOf", vec![string("bolt"), string("x")])),
+            Expression::IntegerLiteral(-1)
+        ));
+        // Nested calls fold inside out
+        assert!(matches!(
+            folded(call("length", vec![call("trim", vec![string("  hi \n")])])),
+            Expression::IntegerLiteral(2)
+        ));
+        // Anything not constant stays a call
+        assert!(matches!(
+            folded(call(
+                "length",
+                vec![Expression::Identifier("s".to_string())]
+            )),
+            Expression::FunctionCall { .. }
+        ));
+    }
+
+    #[test]
+    fn test_user_functions_shadow_builtins() {
+        let mut expr = call("length", vec![string("hello")]);
+        ConstFolder::new(HashSet::from(["length".to_string()])).fold_expression(&mut expr);
+        assert!(matches!(expr, Expression::FunctionCall { .. }));
+    }
+
+    #[test]
+    fn test_operators_fold_within_c_int_range() {
+        let binary = |left: i128, operator: BinaryOperator, right: i128| Expression::BinaryOp {
+            left: Box::new(Expression::IntegerLiteral(left)),
+            operator,
+            right: Box::new(Expression::IntegerLiteral(right)),
+        };
+        assert!(matches!(
+            folded(binary(6, BinaryOperator::Multiply, 7)),
+            Expression::IntegerLiteral(42)
+        ));
+        assert!(matches!(
+            folded(binary(1, BinaryOperator::Less, 2)),
+            Expression::BoolLiteral(true)
+        ));
+        // Division by zero and int overflow are left to run as written
+        assert!(matches!(
+            folded(binary(1, BinaryOperator::Divide, 0)),
+            Expression::BinaryOp { .. }
+        ));
+        assert!(matches!(
+            folded(binary(2000000000, BinaryOperator::Add, 2000000000)),
+            Expression::BinaryOp { .. }
+        ));
+        assert!(matches!(
+            folded(Expression::FieldAccess {
+                object: Box::new(string("four")),
+                field: "length".to_string(),
+            }),
+            Expression::IntegerLiteral(4)
+        ));
+        let negate = |operand: Expression| Expression::UnaryOp {
+            operator: UnaryOperator::Negate,
+            operand: Box::new(operand),
+        };
+        assert!(matches!(
+            folded(negate(Expression::IntegerLiteral(5))),
+            Expression::IntegerLiteral(-5)
+        ));
+        assert!(matches!(
+            folded(negate(Expression::FloatLiteral(2.5))),
+            Expression::FloatLiteral(f) if f == -2.5
+        ));
+        // A chained comparison stays as written so the checker can report it
+        let chained = Expression::BinaryOp {
+            left: Box::new(binary(1, BinaryOperator::Less, 2)),
+            operator: BinaryOperator::Less,
+            right: Box::new(Expression::IntegerLiteral(3)),
+        };
+        match folded(chained) {
+            Expression::BinaryOp { left, .. } => {
+                assert!(matches!(*left, Expression::BinaryOp { .. }))
+            }
+            other => panic!("Expected the comparison unchanged, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_casts_fold_between_integer_and_float() {
+        let cast = |value: Expression, target: Type| Expression::Cast {
+            value: Box::new(value),
+            target,
+        };
+        assert!(matches!(
+            folded(cast(Expression::IntegerLiteral(3), Type::Float)),
+            Expression::FloatLiteral(f) if f == 3.0
+        ));
+        assert!(matches!(
+            folded(cast(Expression::FloatLiteral(-2.7), Type::Integer)),
+            Expression::IntegerLiteral(-2)
+        ));
+        // Sized integers wrap in C, so their casts are left to it
+        assert!(matches!(
+            folded(cast(
+                Expression::IntegerLiteral(300),
+                Type::SizedInt(IntKind::UInt8)
+            )),
+            Expression::Cast { .. }
+        ));
+    }
+
+    #[test]
+    fn test_constants_evaluate_in_order() {
+        let constant =
+            |name: &str, type_annotation: Option<Type>, value: Expression| Statement::ConstDecl {
+                name: name.to_string(),
+                type_annotation,
+                value,
+                exported: false,
+            };
+        let statements = vec![
+            constant("WIDTH", Some(Type::Float), Expression::IntegerLiteral(3)),
+            constant(
+                "AREA",
+                None,
+                Expression::BinaryOp {
+                    left: Box::new(Expression::Identifier("WIDTH".to_string())),
+                    operator: BinaryOperator::Multiply,
+                    right: Box::new(Expression::FloatLiteral(2.0)),
+                },
+            ),
+        ];
+        let constants = evaluate_constants(&statements).unwrap();
+        assert_eq!(constants[0].value, ConstValue::Float(3.0));
+        assert_eq!(constants[1].const_type, Type::Float);
+        assert_eq!(constants[1].value, ConstValue::Float(6.0));
+
+        let runtime = vec![constant("LINE", None, call("readLine", vec![]))];
+        assert!(evaluate_constants(&runtime)
+            .unwrap_err()
+            .contains("isn't known at compile time"));
+        let mismatched = vec![constant(
+            "NAME",
+            Some(Type::String),
+            Expression::IntegerLiteral(1),
+        )];
+        assert_eq!(
+            evaluate_constants(&mismatched).unwrap_err(),
+            "Constant 'NAME' is declared String but its value is Integer"
+        );
+
+        // What a checked build stops on is an error in a constant
+        let binary = |left: i128, operator: BinaryOperator, right: i128| Expression::BinaryOp {
+            left: Box::new(Expression::IntegerLiteral(left)),
+            operator,
+            right: Box::new(Expression::IntegerLiteral(right)),
+        };
+        let overflow = vec![constant(
+            "LOW",
+            None,
+            binary(-2147483648, BinaryOperator::Divide, -1),
+        )];
+        assert_eq!(
+            evaluate_constants(&overflow).unwrap_err(),
+            "Constant 'LOW' overflows an Integer"
+        );
+        let by_zero = vec![constant("REST", None, binary(7, BinaryOperator::Modulo, 0))];
+        assert_eq!(
+            evaluate_constants(&by_zero).unwrap_err(),
+            "Constant 'REST' divides by zero"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Where the compiler's errors and warnings go.
+//!
+//! `compiler::Compiler` reports each diagnostic to a `DiagnosticSink` the host picks
+//! instead of printing it, so the `bolt` command, a playground or a test
+//! harness can each present them their own way:
+//!
+//! - `Vec<Diagnostic>` collects them
+//! - `StderrSink` prints them like the `bolt` command does
+//! - `SeverityCounts` only counts them
+//! - any `FnMut(Diagnostic)` closure forwards them, e.g. to an editor
+//!
+//! ```
+//! use bolt::compiler::Compiler;
+//! use bolt::diagnostics::{Diagnostic, Severity};
+//!
+//! let mut diagnostics: Vec<Diagnostic> = Vec::new();
+//! let checked = Compiler::new(&mut diagnostics).check("val x: String = 5", "app.bolt");
+//! assert!(checked.is_none());
+//! assert_eq!(diagnostics[0].severity, Severity::Error);
+//! ```
+
+use crate::checker::Warning;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub lint: Option<&'static str>, // The lint a warning comes from, see lint::LINTS
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            lint: None,
+        }
+    }
+
+    pub fn warning(warning: Warning) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message: warning.message,
+            lint: Some(warning.lint),
+        }
+    }
+}
+
+/// `Error: message`, or `Warning: message [lint]`
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.severity, self.lint) {
+            (Severity::Error, _) => write!(f, "Error: {}", self.message),
+            (Severity::Warning, Some(lint)) => write!(f, "Warning: {} [{}]", self.message, lint),
+            (Severity::Warning, None) => write!(f, "Warning: {}", self.message),
+        }
+    }
+}
+
+pub trait DiagnosticSink {
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+impl DiagnosticSink for Vec<Diagnostic> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.push(diagnostic);
+    }
+}
+
+impl<F: FnMut(Diagnostic)> DiagnosticSink for F {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self(diagnostic)
+    }
+}
+
+/// Prints each diagnostic on its own line of stderr
+#[derive(Debug, Default)]
+pub struct StderrSink;
+
+impl DiagnosticSink for StderrSink {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        eprintln!("{}", diagnostic);
+    }
+}
+
+/// How many diagnostics of each severity were reported
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SeverityCounts {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl DiagnosticSink for SeverityCounts {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        match diagnostic.severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+    }
+}
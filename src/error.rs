@@ -12,6 +12,15 @@ impl fmt::Display for SourceLocation {
     }
 }
 
+/// A suggested source edit attached to a diagnostic: replace `length`
+/// characters starting at `location` with `replacement`.
+#[derive(Debug, Clone)]
+pub struct FixIt {
+    pub location: SourceLocation,
+    pub length: usize,
+    pub replacement: String,
+}
+
 // Parser Errors
 #[derive(Debug)]
 pub enum ParseError {
@@ -32,6 +41,11 @@ pub enum ParseError {
         feature: String,
         location: Option<SourceLocation>,
     },
+    // A recoverable mistake with a known fix (e.g. `val x = 5` instead of `val x := 5`)
+    SuggestedFix {
+        message: String,
+        fix: FixIt,
+    },
 }
 
 impl fmt::Display for ParseError {
@@ -69,6 +83,13 @@ impl fmt::Display for ParseError {
                     write!(f, "{} not yet implemented", feature)
                 }
             }
+            ParseError::SuggestedFix { message, fix } => {
+                write!(
+                    f,
+                    "{}: {} (help: replace with '{}')",
+                    fix.location, message, fix.replacement
+                )
+            }
         }
     }
 }
@@ -124,7 +145,31 @@ pub enum CompileError {
     ParseError(ParseError),
     LexError(LexError),
     CodegenError(String),
+    RuntimeError(String), // A program run by the interpreter failed
     IoError(std::io::Error),
+    // Every error one run found (parse errors, then type errors), in order
+    Multiple(Vec<CompileError>),
+}
+
+impl CompileError {
+    /// What to print for this error: each error of a `Multiple` on its own
+    /// line, at most `max` of them, then a count of the ones left out
+    pub fn report(&self, max: usize) -> Vec<String> {
+        let CompileError::Multiple(errors) = self else {
+            return vec![format!("Error: {}", self)];
+        };
+        let mut lines: Vec<String> = errors
+            .iter()
+            .take(max)
+            .map(|e| format!("Error: {}", e))
+            .collect();
+        match errors.len().saturating_sub(max) {
+            0 => {}
+            1 => lines.push("and 1 more error".to_string()),
+            more => lines.push(format!("and {} more errors", more)),
+        }
+        lines
+    }
 }
 
 impl fmt::Display for CompileError {
@@ -133,7 +178,12 @@ impl fmt::Display for CompileError {
             CompileError::ParseError(e) => write!(f, "Parse error: {}", e),
             CompileError::LexError(e) => write!(f, "Lexical error: {}", e),
             CompileError::CodegenError(msg) => write!(f, "Code generation error: {}", msg),
+            CompileError::RuntimeError(msg) => write!(f, "Runtime error: {}", msg),
             CompileError::IoError(e) => write!(f, "I/O error: {}", e),
+            CompileError::Multiple(errors) => {
+                let lines: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
         }
     }
 }
@@ -216,6 +266,22 @@ mod tests {
         assert!(display.contains("Unexpected character"));
     }
 
+    #[test]
+    fn test_suggested_fix_display() {
+        let error = ParseError::SuggestedFix {
+            message: "Use ':=' to declare a variable".to_string(),
+            fix: FixIt {
+                location: SourceLocation { line: 3, column: 7 },
+                length: 1,
+                replacement: ":=".to_string(),
+            },
+        };
+        let display = format!("{}", error);
+        assert!(display.contains("3:7"));
+        assert!(display.contains("Use ':='"));
+        assert!(display.contains("replace with ':='"));
+    }
+
     #[test]
     fn test_compile_error_from_parse_error() {
         let parse_error = ParseError::InvalidSyntax {
@@ -257,4 +323,27 @@ mod tests {
         // Test that it implements std::error::Error
         let _: &dyn std::error::Error = &parse_error;
     }
+
+    #[test]
+    fn test_report_caps_multiple_errors() {
+        let errors = CompileError::Multiple(
+            (1..=4)
+                .map(|n| CompileError::CodegenError(format!("problem {}", n)))
+                .collect(),
+        );
+        assert_eq!(
+            errors.report(2),
+            vec![
+                "Error: Code generation error: problem 1",
+                "Error: Code generation error: problem 2",
+                "and 2 more errors",
+            ]
+        );
+        assert_eq!(errors.report(3).last().unwrap(), "and 1 more error");
+        assert_eq!(errors.report(10).len(), 4);
+        assert_eq!(
+            CompileError::RuntimeError("boom".to_string()).report(1),
+            vec!["Error: Runtime error: boom"]
+        );
+    }
 }
@@ -0,0 +1,175 @@
+//! Language extensions an embedder can switch off at run time.
+//!
+//! Everything is enabled by default, so `bolt` itself accepts every program.
+//! A host compiling untrusted code (a playground, a plugin system) turns off
+//! what it can't allow and hands the `Extensions` to `CCodeGen`, or checks a
+//! program itself with `check_extensions`. Only code the user wrote is
+//! checked; the standard library may use any extension.
+
+use crate::ast::Statement;
+use crate::visit::{walk_statement, walk_statements, Visitor};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Extension {
+    /// `asm "..." out(x) in(y)` inside `unsafe` blocks (experimental; only
+    /// the C backend can run it)
+    InlineAsm,
+    /// `native "C" { ... }` and `extern "C" { ... }` blocks, which run
+    /// arbitrary C
+    NativeCode,
+}
+
+impl Extension {
+    pub const ALL: [Extension; 2] = [Extension::InlineAsm, Extension::NativeCode];
+
+    /// Short name for messages and for hosts' own settings, e.g. "asm"
+    pub fn name(self) -> &'static str {
+        match self {
+            Extension::InlineAsm => "asm",
+            Extension::NativeCode => "native",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Extension> {
+        Extension::ALL
+            .into_iter()
+            .find(|extension| extension.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extensions {
+    enabled: HashSet<Extension>,
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Extensions::all()
+    }
+}
+
+impl Extensions {
+    pub fn all() -> Extensions {
+        Extensions {
+            enabled: Extension::ALL.into_iter().collect(),
+        }
+    }
+
+    pub fn none() -> Extensions {
+        Extensions {
+            enabled: HashSet::new(),
+        }
+    }
+
+    /// The same set with `extension` turned on or off
+    pub fn with(mut self, extension: Extension, enabled: bool) -> Extensions {
+        if enabled {
+            self.enabled.insert(extension);
+        } else {
+            self.enabled.remove(&extension);
+        }
+        self
+    }
+
+    pub fn is_enabled(&self, extension: Extension) -> bool {
+        self.enabled.contains(&extension)
+    }
+}
+
+/// The first use of a disabled extension in `statements`, as an error
+pub fn check_extensions(statements: &[Statement], extensions: &Extensions) -> Result<(), String> {
+    let mut uses = Uses {
+        extensions,
+        disabled: None,
+    };
+    walk_statements(&mut uses, statements);
+    match uses.disabled {
+        Some(extension) => Err(format!(
+            "{} needs the `{}` language extension, which is turned off",
+            match extension {
+                Extension::InlineAsm => "Inline asm",
+                Extension::NativeCode => "A native or extern block",
+            },
+            extension.name()
+        )),
+        None => Ok(()),
+    }
+}
+
+struct Uses<'e> {
+    extensions: &'e Extensions,
+    disabled: Option<Extension>,
+}
+
+impl<'ast> Visitor<'ast> for Uses<'_> {
+    fn visit_statement(&mut self, statement: &'ast Statement) {
+        let extension = match statement {
+            Statement::Asm { .. } => Some(Extension::InlineAsm),
+            Statement::NativeBlock { .. } | Statement::ExternBlock { .. } => {
+                Some(Extension::NativeCode)
+            }
+            _ => None,
+        };
+        match extension {
+            Some(extension) if !self.extensions.is_enabled(extension) => {
+                self.disabled.get_or_insert(extension);
+            }
+            _ => walk_statement(self, statement),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{AsmOperand, Expression, ExternFunction, Type};
+
+    fn asm() -> Statement {
+        Statement::Unsafe {
+            body: vec![Statement::Asm {
+                template: "nop".to_string(),
+                outputs: vec![AsmOperand::new("+r", "x")],
+                inputs: vec![],
+                clobbers: vec![],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_disabled_extensions_are_rejected_where_used() {
+        let program = vec![Statement::val("x", Expression::IntegerLiteral(1)), asm()];
+        assert!(check_extensions(&program, &Extensions::default()).is_ok());
+        assert!(check_extensions(
+            &program,
+            &Extensions::none().with(Extension::InlineAsm, true)
+        )
+        .is_ok());
+        assert_eq!(
+            check_extensions(
+                &program,
+                &Extensions::all().with(Extension::InlineAsm, false)
+            )
+            .unwrap_err(),
+            "Inline asm needs the `asm` language extension, which is turned off"
+        );
+
+        let native = Statement::ExternBlock {
+            language: "C".to_string(),
+            functions: vec![ExternFunction::new("abs", vec![], Some(Type::Integer))],
+        };
+        assert_eq!(
+            check_extensions(&[native], &Extensions::none()).unwrap_err(),
+            "A native or extern block needs the `native` language extension, which is turned off"
+        );
+    }
+
+    #[test]
+    fn test_extension_names_round_trip() {
+        for extension in Extension::ALL {
+            assert_eq!(Extension::from_name(extension.name()), Some(extension));
+        }
+        assert_eq!(Extension::from_name("gotos"), None);
+    }
+}
@@ -0,0 +1,295 @@
+//! Internal compiler errors: panics inside the compiler, reported as bugs.
+//!
+//! Code generation still panics on some problems the checker doesn't catch
+//! yet; those mistakes in the program are raised with `user_error`, and any
+//! other panic is a bug of the compiler's own. `catch` turns a panic in one
+//! phase into a `Stopped` instead of a Rust backtrace: the user's error, or
+//! an `Ice` whose `report` says what to put in a bug report. `minimize`
+//! shrinks the input to the top-level items that still make the compiler
+//! panic, for the file the report points to.
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+/// Where the minimized input of the last crash is written
+pub const REPRO_FILE: &str = "ice-repro.bolt";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ice {
+    pub phase: &'static str, // e.g. "type checking"
+    pub message: String,
+    pub compiler_location: Option<String>, // The panic's file:line in the compiler
+    pub function: Option<String>,          // The Bolt function being compiled, if known
+}
+
+/// How a phase run by `catch` stopped before finishing
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stopped {
+    /// A mistake in the program, raised with `user_error`
+    UserError(String),
+    /// A bug in the compiler
+    Ice(Ice),
+}
+
+thread_local! {
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+    static USER_ERROR: Cell<bool> = const { Cell::new(false) };
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+static QUIET_HOOK: Once = Once::new();
+
+/// Stop the phase over a mistake in the program, which `catch` reports as
+/// an error of the program rather than a crash of the compiler
+#[track_caller]
+pub fn user_error(message: String) -> ! {
+    USER_ERROR.with(|flag| flag.set(true));
+    panic!("{}", message)
+}
+
+/// Run one phase of the compiler, turning a panic into `Stopped`. The
+/// default panic message is kept off stderr while it runs.
+pub fn catch<T>(phase: &'static str, run: impl FnOnce() -> T) -> Result<T, Stopped> {
+    USER_ERROR.with(|flag| flag.set(false));
+    quietly(run).map_err(|payload| {
+        let message = panic_message(payload.as_ref());
+        let location = PANIC_LOCATION.with(|cell| cell.borrow_mut().take());
+        if USER_ERROR.with(|flag| flag.replace(false)) {
+            return Stopped::UserError(message);
+        }
+        Stopped::Ice(Ice {
+            phase,
+            message,
+            compiler_location: location,
+            function: None,
+        })
+    })
+}
+
+/// `catch_unwind` with the panic's message kept off stderr and its location
+/// recorded for `catch`. The hook doing that is installed once and only
+/// quiets the threads inside `quietly`, so threads compiling at the same
+/// time (the language server's workers, parallel tests) never swap it under
+/// each other; a panic anywhere else still reaches the previous hook.
+pub fn quietly<T>(run: impl FnOnce() -> T) -> std::thread::Result<T> {
+    QUIET_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !QUIET.with(Cell::get) {
+                return previous(info);
+            }
+            let location = info
+                .location()
+                .map(|location| format!("{}:{}", location.file(), location.line()));
+            PANIC_LOCATION.with(|cell| *cell.borrow_mut() = location);
+        }));
+    });
+    let outer = QUIET.with(|quiet| quiet.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(run));
+    QUIET.with(|quiet| quiet.set(outer));
+    result
+}
+
+/// The message a panic was raised with
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "unknown error".to_string())
+}
+
+impl Ice {
+    /// Whether another crash is the same one, for `minimize`
+    pub fn same_as(&self, other: &Ice) -> bool {
+        self.phase == other.phase && self.message == other.message
+    }
+
+    /// The lines to print: the message, the compiler version, the phase and
+    /// where in `source` (read from `source_name`) it happened, and where the
+    /// reproduction was written
+    pub fn report(&self, source_name: &str, source: &str, repro: Option<&str>) -> Vec<String> {
+        let mut lines = vec![
+            format!("internal compiler error: {}", self.message),
+            format!(
+                "  bolt {} (commit {}) crashed during {}",
+                env!("CARGO_PKG_VERSION"),
+                env!("BOLT_COMMIT"),
+                self.phase
+            ),
+        ];
+        match &self.function {
+            Some(function) => match function_line(source, function) {
+                Some(line) => lines.push(format!(
+                    "  while compiling `{}` at {}:{}",
+                    function, source_name, line
+                )),
+                None => lines.push(format!("  while compiling `{}`", function)),
+            },
+            None => lines.push(format!("  in {}", source_name)),
+        }
+        if let Some(location) = &self.compiler_location {
+            lines.push(format!("  panicked at {}", location));
+        }
+        match repro {
+            Some(path) => lines.push(format!(
+                "This is a bug in bolt. Please report it and attach {}",
+                path
+            )),
+            None => lines.push("This is a bug in bolt. Please report it".to_string()),
+        }
+        lines
+    }
+
+    /// The reproduction file's text: a comment recording the crash, then
+    /// the (minimized) program
+    pub fn repro(&self, source: &str) -> String {
+        let mut text = format!(
+            "// bolt {} (commit {}) crashed during {}:\n",
+            env!("CARGO_PKG_VERSION"),
+            env!("BOLT_COMMIT"),
+            self.phase
+        );
+        for line in self.message.lines() {
+            text.push_str(&format!("//   {}\n", line));
+        }
+        text.push('\n');
+        text.push_str(source);
+        text
+    }
+}
+
+// The 1-based line declaring `fun name`; a generated name such as
+// Point_length (a method) or first_Integer (an instance) is looked up by
+// either half too
+fn function_line(source: &str, name: &str) -> Option<usize> {
+    let names = [
+        name,
+        name.split('_').next().unwrap_or(name),
+        name.rsplit('_').next().unwrap_or(name),
+    ];
+    source
+        .lines()
+        .position(|line| {
+            let line = line.trim_start();
+            let declaration = line.strip_prefix("export ").unwrap_or(line);
+            declaration.starts_with("fun ")
+                && names.iter().any(|name| {
+                    declaration.contains(&format!(" {}(", name))
+                        || declaration.contains(&format!(" {}[", name))
+                })
+        })
+        .map(|index| index + 1)
+}
+
+/// Drop every top-level item of `source` (a line starting in the first
+/// column, with the indented and closing lines after it) that `crashes`
+/// doesn't need to still return true, trying each once, last first
+pub fn minimize(source: &str, crashes: impl Fn(&str) -> bool) -> String {
+    let mut items: Vec<String> = Vec::new();
+    for line in source.lines() {
+        let continues = line.is_empty()
+            || line.starts_with(char::is_whitespace)
+            || line.starts_with('}')
+            || line.starts_with(')')
+            || line.starts_with(']');
+        match items.last_mut() {
+            Some(item) if continues => {
+                item.push_str(line);
+                item.push('\n');
+            }
+            _ => items.push(format!("{}\n", line)),
+        }
+    }
+
+    let mut index = items.len();
+    while index > 0 {
+        index -= 1;
+        let removed = items.remove(index);
+        if !crashes(&items.concat()) {
+            items.insert(index, removed);
+        }
+    }
+    items.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catch_reports_the_panic_and_where() {
+        assert_eq!(catch("parsing", || 1), Ok(1));
+        let Err(Stopped::Ice(ice)) = catch("code generation", || -> () {
+            panic!("no C type for {}", "T")
+        }) else {
+            panic!("expected an internal compiler error");
+        };
+        assert_eq!(ice.phase, "code generation");
+        assert_eq!(ice.message, "no C type for T");
+        assert!(ice.compiler_location.unwrap().starts_with("src/ice.rs:"));
+    }
+
+    #[test]
+    fn test_user_errors_are_not_crashes() {
+        assert_eq!(
+            catch("code generation", || -> () {
+                user_error("Lambda can't capture local variable 'k'".to_string())
+            }),
+            Err(Stopped::UserError(
+                "Lambda can't capture local variable 'k'".to_string()
+            ))
+        );
+        // Any other panic still is one
+        assert!(matches!(
+            catch("code generation", || -> () { panic!("bug") }),
+            Err(Stopped::Ice(_))
+        ));
+    }
+
+    #[test]
+    fn test_threads_catch_their_own_panics() {
+        let threads: Vec<_> = (0..8)
+            .map(|n| {
+                std::thread::spawn(move || {
+                    (0..50)
+                        .map(|_| catch("code generation", || -> () { panic!("thread {}", n) }))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for (n, thread) in threads.into_iter().enumerate() {
+            for stopped in thread.join().unwrap() {
+                let Err(Stopped::Ice(ice)) = stopped else {
+                    panic!("expected an internal compiler error");
+                };
+                assert_eq!(ice.message, format!("thread {}", n));
+                assert!(ice.compiler_location.is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_minimize_keeps_what_the_crash_needs() {
+        let source = "import { print } from \"bolt:stdio\"\n\nfun fine() {\n    print(1)\n}\n\nfun broken() {\n    crash()\n}\n\nprint(2)\n";
+        let minimized = minimize(source, |candidate| candidate.contains("crash()"));
+        assert_eq!(minimized, "fun broken() {\n    crash()\n}\n\n");
+    }
+
+    #[test]
+    fn test_report_points_at_the_function() {
+        let ice = Ice {
+            phase: "code generation",
+            message: "boom".to_string(),
+            compiler_location: None,
+            function: Some("Point_length".to_string()),
+        };
+        let source = "type Point = { x: Integer }\n\nfun (p: Point) length(): Integer {\n    return p.x\n}\n";
+        let report = ice.report("app.bolt", source, Some(REPRO_FILE));
+        assert_eq!(report[0], "internal compiler error: boom");
+        assert_eq!(report[2], "  while compiling `Point_length` at app.bolt:3");
+        assert!(report[3].ends_with(REPRO_FILE));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::SourceLocation;
+pub use crate::stdlib::content_hash;
+use crate::symbol_table::SymbolTable;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// File name of the persistent symbol index, written next to where `bolt` runs
+pub const INDEX_FILE: &str = ".bolt-index";
+
+/// Bumped whenever the on-disk layout changes; older indexes are ignored
+pub const INDEX_VERSION: u32 = 1;
+
+/// One source file in the index: its symbols (as `SymbolTable::to_json`),
+/// exported names and a hash of the source it was built from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub path: String,
+    pub hash: String,
+    pub exports: Vec<String>,
+    pub symbols: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    pub version: u32,
+    pub files: Vec<IndexedFile>,
+}
+
+/// Where a symbol was declared, as found in the index
+#[derive(Debug, Clone)]
+pub struct IndexedDefinition {
+    pub path: String,
+    pub kind: String,
+    pub location: SourceLocation,
+}
+
+impl Default for SymbolIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn add_file(
+        &mut self,
+        path: &str,
+        source: &str,
+        symbol_table: &SymbolTable,
+        exports: Vec<String>,
+    ) {
+        self.files.retain(|file| file.path != path);
+        self.files.push(IndexedFile {
+            path: path.to_string(),
+            hash: content_hash(source),
+            exports,
+            symbols: symbol_table.to_json(),
+        });
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// Read an index from disk. A missing, unreadable or outdated index is
+    /// simply not there; callers fall back to parsing.
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let index: SymbolIndex = serde_json::from_str(&content).ok()?;
+        (index.version == INDEX_VERSION).then_some(index)
+    }
+
+    pub fn file(&self, path: &str) -> Option<&IndexedFile> {
+        self.files.iter().find(|file| file.path == path)
+    }
+
+    /// Whether the indexed copy of `path` was built from exactly `source`
+    pub fn is_fresh(&self, path: &str, source: &str) -> bool {
+        self.file(path)
+            .is_some_and(|file| file.hash == content_hash(source))
+    }
+
+    /// Find a top-level declaration by name across all indexed files
+    pub fn find_definition(&self, name: &str) -> Option<IndexedDefinition> {
+        self.files.iter().find_map(|file| {
+            let global_symbols = file.symbols["scopes"]
+                .as_array()?
+                .iter()
+                .find(|scope| scope["parent"].is_null())?["symbols"]
+                .as_array()?;
+            let symbol = global_symbols
+                .iter()
+                .find(|symbol| symbol["name"] == name && symbol["line"].is_u64())?;
+
+            Some(IndexedDefinition {
+                path: file.path.clone(),
+                kind: symbol["kind"].as_str().unwrap_or_default().to_string(),
+                location: SourceLocation {
+                    line: symbol["line"].as_u64()? as usize,
+                    column: symbol["column"].as_u64()? as usize,
+                },
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Type;
+
+    fn index_with_function() -> SymbolIndex {
+        let mut symbol_table = SymbolTable::new();
+        symbol_table
+            .declare_function(
+                "helper".to_string(),
+                vec![Type::Integer],
+                Some(Type::Integer),
+                Some(SourceLocation {
+                    line: 3,
+                    column: 12,
+                }),
+            )
+            .unwrap();
+
+        let mut index = SymbolIndex::new();
+        index.add_file(
+            "/work/lib.bolt",
+            "export fun helper(x: Integer): Integer",
+            &symbol_table,
+            vec!["helper".to_string()],
+        );
+        index
+    }
+
+    #[test]
+    fn test_find_definition_across_files() {
+        let index = index_with_function();
+
+        let definition = index.find_definition("helper").unwrap();
+        assert_eq!(definition.path, "/work/lib.bolt");
+        assert_eq!(definition.kind, "function");
+        assert_eq!(definition.location.line, 3);
+        assert_eq!(definition.location.column, 12);
+        assert!(index.find_definition("missing").is_none());
+    }
+
+    #[test]
+    fn test_index_round_trip_and_freshness() {
+        let index = index_with_function();
+        let json = serde_json::to_string(&index).unwrap();
+        let loaded: SymbolIndex = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.files.len(), 1);
+        assert_eq!(loaded.files[0].exports, vec!["helper"]);
+        assert!(loaded.is_fresh("/work/lib.bolt", "export fun helper(x: Integer): Integer"));
+        assert!(!loaded.is_fresh("/work/lib.bolt", "export fun helper(): Integer"));
+        assert!(!loaded.is_fresh("/work/other.bolt", ""));
+    }
+}
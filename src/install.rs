@@ -0,0 +1,135 @@
+//! `bolt install`: copy the compiler that is running, the bolt-lsp built
+//! next to it, and the embedded stdlib under a prefix (~/.bolt by default).
+//! Running it again from a newer build replaces the installed files, which
+//! is how an installation is updated.
+
+use crate::stdlib;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The executables an installation holds, found in the build directory
+pub const BINARIES: [&str; 2] = ["bolt", "bolt-lsp"];
+
+/// What `install` put where
+#[derive(Debug)]
+pub struct Installation {
+    pub bin_dir: PathBuf,
+    pub std_dir: PathBuf,
+    pub binaries: Vec<PathBuf>,
+    pub missing: Vec<String>, // Binaries not found in the build directory
+}
+
+/// ~/.bolt, or None when HOME isn't set
+pub fn default_prefix() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".bolt"))
+}
+
+/// Copy the binaries from `build_dir` to `prefix`/bin and write the stdlib
+/// to `prefix`/std
+pub fn install(prefix: &Path, build_dir: &Path) -> io::Result<Installation> {
+    let bin_dir = prefix.join("bin");
+    let std_dir = prefix.join("std");
+    fs::create_dir_all(&bin_dir)?;
+    fs::create_dir_all(&std_dir)?;
+
+    let mut binaries = Vec::new();
+    let mut missing = Vec::new();
+    for name in BINARIES {
+        let file_name = format!("{}{}", name, env::consts::EXE_SUFFIX);
+        let source = build_dir.join(&file_name);
+        let target = bin_dir.join(&file_name);
+        if !source.is_file() {
+            missing.push(name.to_string());
+            continue;
+        }
+        // Installing from the installation itself would truncate the binary
+        if !same_file(&source, &target) {
+            fs::copy(&source, &target)?;
+        }
+        binaries.push(target);
+    }
+
+    for (name, source) in stdlib::SOURCES {
+        fs::write(std_dir.join(format!("{}.bolt", name)), source)?;
+    }
+
+    Ok(Installation {
+        bin_dir,
+        std_dir,
+        binaries,
+        missing,
+    })
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// How to put `bin_dir` on the PATH, unless `path` (the PATH variable)
+/// already has it
+pub fn path_guidance(bin_dir: &Path, path: Option<&OsStr>) -> Option<String> {
+    let on_path = path.is_some_and(|path| env::split_paths(path).any(|dir| dir == bin_dir));
+    (!on_path).then(|| {
+        format!(
+            "{} is not on your PATH. Add it in your shell profile (e.g. ~/.bashrc):\n    export PATH=\"{}:$PATH\"",
+            bin_dir.display(),
+            bin_dir.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            env::temp_dir().join(format!("bolt_install_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_install_copies_binaries_and_stdlib() {
+        let build_dir = temp_dir("build");
+        let bolt = format!("bolt{}", env::consts::EXE_SUFFIX);
+        fs::write(build_dir.join(&bolt), "compiler").unwrap();
+        let prefix = temp_dir("prefix");
+
+        let installation = install(&prefix, &build_dir).unwrap();
+        assert_eq!(installation.binaries, vec![prefix.join("bin").join(&bolt)]);
+        assert_eq!(installation.missing, vec!["bolt-lsp".to_string()]);
+        assert_eq!(
+            fs::read_to_string(prefix.join("bin").join(&bolt)).unwrap(),
+            "compiler"
+        );
+        assert_eq!(
+            fs::read_to_string(prefix.join("std/math.bolt")).unwrap(),
+            stdlib::source("math").unwrap()
+        );
+
+        // Installing again from the installed copy leaves it intact
+        install(&prefix, &prefix.join("bin")).unwrap();
+        assert_eq!(
+            fs::read_to_string(prefix.join("bin").join(&bolt)).unwrap(),
+            "compiler"
+        );
+    }
+
+    #[test]
+    fn test_path_guidance_only_when_bin_dir_is_missing() {
+        let bin_dir = Path::new("/home/me/.bolt/bin");
+        let path = env::join_paths(["/usr/bin", "/home/me/.bolt/bin"]).unwrap();
+        assert_eq!(path_guidance(bin_dir, Some(&path)), None);
+        let guidance = path_guidance(bin_dir, Some(OsStr::new("/usr/bin"))).unwrap();
+        assert!(guidance.contains("export PATH=\"/home/me/.bolt/bin:$PATH\""));
+        assert!(path_guidance(bin_dir, None).is_some());
+    }
+}
@@ -0,0 +1,1946 @@
+//! Tree-walking interpreter over the typed IR, for running programs where no
+//! C compiler is available and for quick feedback while developing.
+//!
+//! It follows the C backend wherever the difference would show: `Integer` is
+//! 32 bits wide and wraps, fixed-width integers wrap at their width, floats
+//! print with six decimals, structs and tuples are copied by value, and
+//! arrays share their storage (so `sort(numbers)` sorts the array literal
+//! behind `numbers.data` too), as do maps. The parts of the `bolt:` modules
+//! implemented in C (string, io, and the array and map operations) are
+//! implemented in Rust here; modules written in Bolt run like the entry
+//! program.
+
+use crate::ast::{BinaryOperator, Program as AstProgram, Type, UnaryOperator};
+use crate::ir::{lower_program, Expr, ExprKind, Function, Stmt};
+use crate::module::ModuleSystem;
+use crate::symbol_table::{
+    ends_line, format_pieces, method_function_name, FORMAT_FUNCTIONS, NANOID_ALPHABET,
+    PRINT_FUNCTIONS,
+};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Unit,
+    Integer(i128), // Wide enough for every integer type, wrapped to its width
+    Float(f64),
+    Bool(bool),
+    String(Rc<str>), // Also StrView, which prints and compares like its text
+    Array(ArrayValue),
+    Tuple(Vec<Value>),
+    Map(Rc<RefCell<Vec<(Value, Value)>>>), // Entries in insertion order, shared like C's table
+    Struct {
+        name: String,
+        fields: Vec<(String, Value)>,
+    },
+    Optional(Option<Box<Value>>),
+    Function(String),
+    Lambda(Rc<Lambda>),
+    Pointer(Rc<RefCell<Value>>), // The variable (or temporary) pointed to
+}
+
+#[derive(Debug)]
+pub struct Lambda {
+    params: Vec<(String, Type)>,
+    body: Expr,
+}
+
+/// A window onto shared element storage. Copies of an array, its `data`,
+/// and slices of it all see the same elements.
+#[derive(Debug, Clone)]
+pub struct ArrayValue {
+    items: Rc<RefCell<Vec<Value>>>,
+    start: usize,
+    length: usize,
+    capacity: usize,
+}
+
+impl ArrayValue {
+    fn new(items: Vec<Value>) -> Self {
+        let length = items.len();
+        Self {
+            items: Rc::new(RefCell::new(items)),
+            start: 0,
+            length,
+            capacity: length,
+        }
+    }
+
+    fn to_vec(&self) -> Vec<Value> {
+        let items = self.items.borrow();
+        items[self.start..self.start + self.length].to_vec()
+    }
+
+    fn get(&self, index: i128) -> Result<Value, String> {
+        self.check_index(index)?;
+        Ok(self.items.borrow()[self.start + index as usize].clone())
+    }
+
+    // Replace the elements in place, for the operations that sort or reverse
+    fn set_all(&self, values: Vec<Value>) {
+        let mut items = self.items.borrow_mut();
+        for (i, value) in values.into_iter().enumerate() {
+            items[self.start + i] = value;
+        }
+    }
+
+    // Inserts before index (at most length), moving to storage twice the
+    // size when full, like the generated C
+    fn insert(&mut self, index: usize, value: Value) {
+        if self.length == self.capacity {
+            self.items = Rc::new(RefCell::new(self.to_vec()));
+            self.start = 0;
+            self.capacity = if self.capacity > 0 {
+                self.capacity * 2
+            } else {
+                4
+            };
+        }
+        let mut items = self.items.borrow_mut();
+        let end = self.start + self.length;
+        if end == items.len() {
+            items.push(Value::Unit);
+        }
+        items[self.start + index..=end].rotate_right(1);
+        items[self.start + index] = value;
+        drop(items);
+        self.length += 1;
+    }
+
+    // realloc: new storage for count values starting with this view's,
+    // including any written past its length through another view
+    fn resized(&self, count: usize) -> Self {
+        let items = self.items.borrow();
+        let mut kept: Vec<Value> = items[self.start..].iter().take(count).cloned().collect();
+        kept.resize(count, Value::Unit);
+        ArrayValue::new(kept)
+    }
+
+    fn check_index(&self, index: i128) -> Result<(), String> {
+        if index < 0 || index >= self.length as i128 {
+            return Err(format!(
+                "Index {} is out of bounds for an array of length {}",
+                index, self.length
+            ));
+        }
+        Ok(())
+    }
+
+    // The same elements from `offset` further along, the way pointer
+    // arithmetic moves a pointer into the array. Like a C pointer it may
+    // stop just past the last element, but not outside the array
+    fn offset(&self, offset: i128) -> Result<Self, String> {
+        let end = self.start + self.length;
+        let start = self.start as i128 + offset;
+        if start < 0 || start > end as i128 {
+            return Err(format!(
+                "Pointer moved by {} leaves its array of length {}",
+                offset, end
+            ));
+        }
+        let length = end - start as usize;
+        Ok(Self {
+            items: Rc::clone(&self.items),
+            start: start as usize,
+            length,
+            capacity: length,
+        })
+    }
+
+    // How many elements `other` starts before this view, if both share storage
+    fn distance(&self, other: &Self) -> Option<i128> {
+        Rc::ptr_eq(&self.items, &other.items).then(|| self.start as i128 - other.start as i128)
+    }
+
+    // The elements from start up to end, clamped to the array, without copying
+    fn slice(&self, start: i128, end: i128) -> Self {
+        let start = start.clamp(0, self.length as i128) as usize;
+        let end = (end.clamp(0, self.length as i128) as usize).max(start);
+        Self {
+            items: Rc::clone(&self.items),
+            start: self.start + start,
+            length: end - start,
+            capacity: end - start,
+        }
+    }
+}
+
+// How print and toString show a value
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{:.6}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+            // C strings end at the first NUL
+            Value::String(s) => write!(f, "{}", s.split('\0').next().unwrap_or_default()),
+            Value::Array(array) => {
+                write!(f, "[")?;
+                for (i, item) in array.to_vec().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Tuple(elements) => {
+                write!(f, "(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, ")")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Struct { name, fields } if fields.is_empty() => write!(f, "{} {{}}", name),
+            Value::Struct { name, fields } => {
+                write!(f, "{} {{ ", name)?;
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", field, value)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Optional(None) => write!(f, "none"),
+            Value::Optional(Some(value)) => write!(f, "{}", value),
+            Value::Function(name) => write!(f, "{}", name),
+            Value::Lambda(_) => write!(f, "<lambda>"),
+            Value::Pointer(cell) => write!(f, "{:p}", Rc::as_ptr(cell)),
+        }
+    }
+}
+
+/// Run a checked entry program whose imports `modules` has resolved.
+/// `args` is what `getArgs()` returns, the program's path first. Output from
+/// print goes to `out`; runtime errors (an index out of bounds, division by
+/// zero, Integer overflow, unwrapping `none`) stop the program and are returned. Otherwise the
+/// result is the exit status, from `exit(status)` or an Integer-returning
+/// main, and 0 if there was neither.
+pub fn interpret(
+    program: &AstProgram,
+    modules: &ModuleSystem,
+    args: Vec<String>,
+    out: &mut dyn Write,
+) -> Result<i32, String> {
+    let mut interpreter = Interpreter::new(args, out);
+    let body = interpreter.load(program, modules)?;
+    interpreter.run(&body)
+}
+
+/// Like [`interpret`], but watches the files of the imported modules while
+/// the program runs. When one changes, it is parsed again and its functions
+/// replace the old ones: calls already running finish with the old body and
+/// the next call runs the new one, so a long-running loop (a game loop, a
+/// script polling for work) picks up edits without restarting or losing its
+/// state. Reloads are reported on stderr; one that fails keeps the old code.
+pub fn interpret_with_hot_reload(
+    program: &AstProgram,
+    modules: ModuleSystem,
+    args: Vec<String>,
+    out: &mut dyn Write,
+) -> Result<i32, String> {
+    let mut interpreter = Interpreter::new(args, out);
+    let body = interpreter.load(program, &modules)?;
+    interpreter.hot_reload = Some(HotReload::new(modules));
+    interpreter.run(&body)
+}
+
+// How often a running program checks its modules' files for changes
+const RELOAD_INTERVAL: Duration = Duration::from_millis(250);
+
+// The modules of a hot-reloading program and when each file last changed
+struct HotReload {
+    modules: ModuleSystem,
+    modified: HashMap<String, Option<SystemTime>>, // Module path -> file modification time
+    last_check: Instant,
+}
+
+impl HotReload {
+    fn new(modules: ModuleSystem) -> Self {
+        let modified = modules
+            .module_paths()
+            .map(|path| (path.clone(), Self::modified_time(&modules, path)))
+            .collect();
+        Self {
+            modules,
+            modified,
+            last_check: Instant::now(),
+        }
+    }
+
+    fn modified_time(modules: &ModuleSystem, module_path: &str) -> Option<SystemTime> {
+        let source = modules.get_source(module_path)?;
+        fs::metadata(&source.file_path).ok()?.modified().ok()
+    }
+
+    // The functions of every module whose file changed since it was loaded,
+    // lowered again
+    fn changed_functions(&mut self) -> Vec<Function> {
+        let mut module_paths: Vec<String> = self.modified.keys().cloned().collect();
+        module_paths.sort();
+        let mut functions = Vec::new();
+        for module_path in module_paths {
+            let modified = Self::modified_time(&self.modules, &module_path);
+            if modified == self.modified[&module_path] {
+                continue;
+            }
+            self.modified.insert(module_path.clone(), modified);
+            let reloaded = self.modules.reload_module(&module_path).and_then(|()| {
+                let module = self.modules.get_module(&module_path).unwrap();
+                lower_program(module, &self.modules)
+            });
+            match reloaded {
+                Ok(lowered) => {
+                    eprintln!("Reloaded {}", module_path);
+                    functions.extend(lowered.functions);
+                }
+                Err(e) => eprintln!("Reloading {} failed: {}", module_path, e),
+            }
+        }
+        functions
+    }
+}
+
+// bolt:random's xoshiro128** generator, step for step the one in the C
+// runtime so a seeded program draws the same numbers either way
+struct Random {
+    state: [u32; 4],
+}
+
+impl Random {
+    fn seeded(mut seed: u64) -> Self {
+        let mut splitmix = || {
+            seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            z ^ (z >> 31)
+        };
+        let (low, high) = (splitmix(), splitmix());
+        Self {
+            state: [
+                low as u32,
+                (low >> 32) as u32,
+                high as u32,
+                (high >> 32) as u32,
+            ],
+        }
+    }
+
+    fn from_clock() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        Self::seeded(nanos as u64)
+    }
+
+    fn next(&mut self) -> u32 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 9;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(11);
+        result
+    }
+
+    // Uniform in 0..bound-1 for bound <= 2^32
+    fn below(&mut self, bound: u64) -> u64 {
+        (self.next() as u64 * bound) >> 32
+    }
+
+    // Uniform in [0, 1) from 53 bits
+    fn float(&mut self) -> f64 {
+        let high = (self.next() >> 5) as u64;
+        let low = (self.next() >> 6) as u64;
+        ((high << 26) | low) as f64 / 9007199254740992.0
+    }
+}
+
+// What a statement leaves the enclosing block to do
+enum Flow {
+    Next,
+    Break,
+    Return(Value),
+}
+
+type Variable = Rc<RefCell<Value>>;
+
+struct Interpreter<'a> {
+    functions: HashMap<String, Rc<Function>>, // Replaced as modules are hot reloaded
+    globals: HashMap<String, Variable>,
+    scopes: Vec<HashMap<String, Variable>>, // Of the running function, innermost last
+    args: Vec<String>,
+    out: &'a mut dyn Write,
+    hot_reload: Option<HotReload>,
+    random: Option<Random>, // Seeded from the clock on first use, unless seedRandom was called
+    temp_paths: Vec<String>, // Made by tempFile/tempDir and not removed yet; dropped with the program
+    exit_status: Option<i32>, // Set by exit(), whose Err unwinds the program without being an error
+}
+
+// Like the C runtime's exit hook, a program's leftover temporary paths go
+// when it ends, whether it finished or failed
+impl Drop for Interpreter<'_> {
+    fn drop(&mut self) {
+        for path in self.temp_paths.drain(..) {
+            remove_tree(&path);
+        }
+    }
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(args: Vec<String>, out: &'a mut dyn Write) -> Self {
+        Self {
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            scopes: Vec::new(),
+            args,
+            out,
+            hot_reload: None,
+            random: None,
+            temp_paths: Vec::new(),
+            exit_status: None,
+        }
+    }
+
+    // Define the functions of the program and its modules and initialize the
+    // modules' globals; returns the entry program's top-level code
+    fn load(&mut self, program: &AstProgram, modules: &ModuleSystem) -> Result<Vec<Stmt>, String> {
+        let entry = lower_program(program, modules)?;
+        let mut module_paths: Vec<&String> = modules.module_paths().collect();
+        module_paths.sort();
+        let mut module_bodies = Vec::new();
+        for module_path in module_paths {
+            if let Some(module) = modules.get_module(module_path) {
+                let lowered = lower_program(module, modules)
+                    .map_err(|e| format!("In module {}: {}", module_path, e))?;
+                self.define(lowered.functions);
+                module_bodies.push(lowered.body);
+            }
+        }
+        self.define(entry.functions);
+
+        // Module-level variables are globals, visible to the module's functions
+        for body in &module_bodies {
+            for statement in body {
+                if let Stmt::Let { .. } = statement {
+                    self.exec(statement)?;
+                }
+            }
+        }
+        Ok(entry.body)
+    }
+
+    // Run the top-level code, then main if the program defines one; the
+    // exit status is what main or exit() returned, else 0
+    fn run(&mut self, body: &[Stmt]) -> Result<i32, String> {
+        match (self.run_to_end(body), self.exit_status) {
+            (_, Some(status)) => Ok(status),
+            (result, None) => result,
+        }
+    }
+
+    fn run_to_end(&mut self, body: &[Stmt]) -> Result<i32, String> {
+        self.exec_block(body)?;
+        if let Some(main) = self.functions.get("main").cloned() {
+            let args = match main.params.first() {
+                Some(_) => vec![self.arguments()],
+                None => Vec::new(),
+            };
+            if let Value::Integer(status) = self.call_function(&main, args)? {
+                return Ok(status as i32);
+            }
+        }
+        Ok(0)
+    }
+
+    // Swap in the functions of changed modules; loops call this every
+    // iteration, so it only looks at the files every RELOAD_INTERVAL
+    fn reload_changed_modules(&mut self) {
+        let Some(hot_reload) = &mut self.hot_reload else {
+            return;
+        };
+        if hot_reload.last_check.elapsed() < RELOAD_INTERVAL {
+            return;
+        }
+        hot_reload.last_check = Instant::now();
+        let functions = hot_reload.changed_functions();
+        self.define(functions);
+    }
+
+    fn define(&mut self, functions: Vec<Function>) {
+        for function in functions {
+            self.functions
+                .insert(function.name.clone(), Rc::new(function));
+        }
+    }
+
+    fn declare(&mut self, name: &str, value: Value) {
+        let variable = Rc::new(RefCell::new(value));
+        match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.to_string(), variable),
+            None => self.globals.insert(name.to_string(), variable),
+        };
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+    }
+
+    fn arguments(&self) -> Value {
+        Value::Array(ArrayValue::new(
+            self.args
+                .iter()
+                .map(|arg| Value::String(arg.as_str().into()))
+                .collect(),
+        ))
+    }
+
+    fn exec_block(&mut self, statements: &[Stmt]) -> Result<Flow, String> {
+        self.scopes.push(HashMap::new());
+        let flow = self.exec_statements(statements);
+        self.scopes.pop();
+        flow
+    }
+
+    fn exec_statements(&mut self, statements: &[Stmt]) -> Result<Flow, String> {
+        let mut deferred = Vec::new();
+        let mut flow = Ok(Flow::Next);
+        for statement in statements {
+            if let Stmt::Defer(body) = statement {
+                deferred.push(body);
+                continue;
+            }
+            flow = self.exec(statement);
+            if !matches!(flow, Ok(Flow::Next)) {
+                break;
+            }
+        }
+        // Deferred code runs however the block exits, innermost first
+        for body in deferred.into_iter().rev() {
+            self.exec_block(body)?;
+        }
+        flow
+    }
+
+    fn exec(&mut self, statement: &Stmt) -> Result<Flow, String> {
+        match statement {
+            Stmt::Let {
+                name, ty, value, ..
+            } => {
+                let value = convert(self.eval(value)?, ty);
+                self.declare(name, value);
+            }
+            Stmt::Assign { name, value } => {
+                let value = self.eval(value)?;
+                let variable = self
+                    .lookup(name)
+                    .ok_or_else(|| format!("Assignment to unknown variable '{}'", name))?;
+                // A Float variable stays a Float
+                let value = match (&*variable.borrow(), value) {
+                    (Value::Float(_), Value::Integer(n)) => Value::Float(n as f64),
+                    (_, value) => value,
+                };
+                *variable.borrow_mut() = value;
+            }
+            Stmt::Expr(expr) => {
+                self.eval(expr)?;
+            }
+            Stmt::If {
+                condition,
+                then_body,
+                else_body,
+            } => {
+                let branch = if self.eval_bool(condition)? {
+                    then_body
+                } else {
+                    else_body
+                };
+                return self.exec_block(branch);
+            }
+            Stmt::Loop(body) => loop {
+                self.reload_changed_modules();
+                match self.exec_block(body)? {
+                    Flow::Next => {}
+                    Flow::Break => break,
+                    Flow::Return(value) => return Ok(Flow::Return(value)),
+                }
+            },
+            Stmt::Break => return Ok(Flow::Break),
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => Value::Unit,
+                };
+                return Ok(Flow::Return(value));
+            }
+            // Collected by exec_statements
+            Stmt::Defer(_) => {}
+            Stmt::Block(body) => return self.exec_block(body),
+        }
+        Ok(Flow::Next)
+    }
+
+    fn eval_bool(&mut self, expr: &Expr) -> Result<bool, String> {
+        match self.eval(expr)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(format!("Expected a Bool, got {}", other)),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value, String> {
+        Ok(match &expr.kind {
+            ExprKind::Integer(n) => convert(Value::Integer(*n), &expr.ty),
+            ExprKind::Float(x) => Value::Float(*x),
+            ExprKind::Bool(b) => Value::Bool(*b),
+            ExprKind::String(s) => Value::String(s.as_str().into()),
+            ExprKind::None => Value::Optional(None),
+            ExprKind::Local(name) => match self.lookup(name) {
+                Some(variable) => variable.borrow().clone(),
+                None => return Err(format!("Unknown variable '{}'", name)),
+            },
+            ExprKind::Function(name) => Value::Function(name.clone()),
+            ExprKind::Call { function, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(function, args)?
+            }
+            ExprKind::Binary {
+                operator,
+                left,
+                right,
+            } => self.binary(operator, left, right, &expr.ty)?,
+            ExprKind::Unary { operator, operand } => match (operator, self.eval(operand)?) {
+                (UnaryOperator::Not, Value::Bool(b)) => Value::Bool(!b),
+                (UnaryOperator::Negate, Value::Integer(n)) => checked(-n, &expr.ty)?,
+                (UnaryOperator::Negate, Value::Float(x)) => Value::Float(-x),
+                (_, value) => return Err(format!("Can't apply {:?} to {}", operator, value)),
+            },
+            ExprKind::Array(elements) => Value::Array(ArrayValue::new(
+                elements
+                    .iter()
+                    .map(|element| self.eval(element))
+                    .collect::<Result<Vec<_>, _>>()?,
+            )),
+            ExprKind::Tuple(elements) => Value::Tuple(
+                elements
+                    .iter()
+                    .map(|element| self.eval(element))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            ExprKind::Map(entries) => {
+                let map = Rc::new(RefCell::new(Vec::new()));
+                for (key, value) in entries {
+                    let (key, value) = (self.eval(key)?, self.eval(value)?);
+                    map_set(&map, key, value);
+                }
+                Value::Map(map)
+            }
+            ExprKind::Struct(fields) => {
+                let mut values = Vec::new();
+                for (name, value) in fields {
+                    values.push((name.clone(), self.eval(value)?));
+                }
+                match &expr.ty {
+                    // Array[T] { data, length, capacity } views its data
+                    Type::Array(_) => Value::Array(array_from_fields(values)?),
+                    ty => Value::Struct {
+                        name: struct_name(ty),
+                        fields: values,
+                    },
+                }
+            }
+            ExprKind::Field { object, field } => {
+                let object = self.eval(object)?;
+                field_of(object, field)?
+            }
+            ExprKind::Index { array, index } => {
+                let array = self.eval(array)?;
+                let index = match self.eval(index)? {
+                    Value::Integer(n) => n,
+                    other => return Err(format!("Can't index with {}", other)),
+                };
+                index_of(array, index)?
+            }
+            ExprKind::AddressOf(operand) => match &operand.kind {
+                ExprKind::Local(name) => match self.lookup(name) {
+                    Some(variable) => Value::Pointer(Rc::clone(variable)),
+                    None => return Err(format!("Unknown variable '{}'", name)),
+                },
+                // Points into the array, so arithmetic can move along it
+                ExprKind::Index { array, index } => match (self.eval(array)?, self.eval(index)?) {
+                    (Value::Array(array), Value::Integer(index)) => {
+                        Value::Array(array.offset(index)?)
+                    }
+                    (array, index) => Value::Pointer(Rc::new(RefCell::new(index_of(
+                        array,
+                        match index {
+                            Value::Integer(n) => n,
+                            other => return Err(format!("Can't index with {}", other)),
+                        },
+                    )?))),
+                },
+                _ => Value::Pointer(Rc::new(RefCell::new(self.eval(operand)?))),
+            },
+            ExprKind::Deref(operand) => match self.eval(operand)? {
+                Value::Pointer(variable) => variable.borrow().clone(),
+                Value::Array(array) => array.get(0)?,
+                other => return Err(format!("Can't dereference {}", other)),
+            },
+            ExprKind::Cast(value) => cast(self.eval(value)?, &expr.ty),
+            ExprKind::Select {
+                condition,
+                then_value,
+                else_value,
+            } => {
+                let value = if self.eval_bool(condition)? {
+                    self.eval(then_value)?
+                } else {
+                    self.eval(else_value)?
+                };
+                convert(value, &expr.ty)
+            }
+            ExprKind::Wrap(value) => Value::Optional(Some(Box::new(self.eval(value)?))),
+            ExprKind::IsPresent(optional) => match self.eval(optional)? {
+                Value::Optional(value) => Value::Bool(value.is_some()),
+                _ => Value::Bool(true),
+            },
+            ExprKind::Unwrap(optional) => match self.eval(optional)? {
+                Value::Optional(Some(value)) => *value,
+                Value::Optional(None) => return Err("Unwrapped an absent optional".to_string()),
+                value => value,
+            },
+            ExprKind::Coalesce { value, fallback } => match self.eval(value)? {
+                Value::Optional(None) => self.eval(fallback)?,
+                Value::Optional(Some(value)) if !is_optional(&expr.ty) => *value,
+                present => present,
+            },
+            ExprKind::OptionalField { object, field } => match self.eval(object)? {
+                Value::Optional(Some(value)) => match field_of(*value, field)? {
+                    optional @ Value::Optional(_) => optional,
+                    value => Value::Optional(Some(Box::new(value))),
+                },
+                _ => Value::Optional(None),
+            },
+            ExprKind::Lambda { params, body } => Value::Lambda(Rc::new(Lambda {
+                params: params
+                    .iter()
+                    .map(|param| (param.name.clone(), param.param_type.clone()))
+                    .collect(),
+                body: (**body).clone(),
+            })),
+        })
+    }
+
+    fn binary(
+        &mut self,
+        operator: &BinaryOperator,
+        left: &Expr,
+        right: &Expr,
+        ty: &Type,
+    ) -> Result<Value, String> {
+        // && and || only evaluate the right side when they need it
+        match operator {
+            BinaryOperator::And => {
+                return Ok(Value::Bool(self.eval_bool(left)? && self.eval_bool(right)?))
+            }
+            BinaryOperator::Or => {
+                return Ok(Value::Bool(self.eval_bool(left)? || self.eval_bool(right)?))
+            }
+            _ => {}
+        }
+        let pointer = matches!(left.ty, Type::Pointer(_));
+        let left = self.eval(left)?;
+        let right = self.eval(right)?;
+        if pointer {
+            if let Some(result) = pointer_binary(operator, &left, &right) {
+                return result;
+            }
+        }
+        let comparison = |test: fn(Ordering) -> bool| match compare(&left, &right) {
+            Some(order) => Ok(Value::Bool(test(order))),
+            None => Err(format!("Can't compare {} and {}", left, right)),
+        };
+        match operator {
+            BinaryOperator::Equal => return Ok(Value::Bool(equal(&left, &right))),
+            BinaryOperator::NotEqual => return Ok(Value::Bool(!equal(&left, &right))),
+            BinaryOperator::Less => return comparison(Ordering::is_lt),
+            BinaryOperator::LessEqual => return comparison(Ordering::is_le),
+            BinaryOperator::Greater => return comparison(Ordering::is_gt),
+            BinaryOperator::GreaterEqual => return comparison(Ordering::is_ge),
+            _ => {}
+        }
+        match (&left, &right) {
+            (Value::String(a), Value::String(b)) if *operator == BinaryOperator::Add => {
+                Ok(Value::String(format!("{}{}", a, b).into()))
+            }
+            (Value::Integer(a), Value::Integer(b)) => {
+                let result = match operator {
+                    BinaryOperator::Add => a + b,
+                    BinaryOperator::Subtract => a - b,
+                    BinaryOperator::Multiply => a * b,
+                    BinaryOperator::Divide | BinaryOperator::Modulo if *b == 0 => {
+                        return Err("Division by zero".to_string())
+                    }
+                    BinaryOperator::Divide => a / b,
+                    BinaryOperator::Modulo => a % b,
+                    _ => unreachable!("comparisons and logic are handled above"),
+                };
+                checked(result, ty)
+            }
+            _ => match (number(&left), number(&right)) {
+                (Some(a), Some(b)) => Ok(Value::Float(match operator {
+                    BinaryOperator::Add => a + b,
+                    BinaryOperator::Subtract => a - b,
+                    BinaryOperator::Multiply => a * b,
+                    BinaryOperator::Divide => a / b,
+                    BinaryOperator::Modulo => a % b,
+                    _ => unreachable!("comparisons and logic are handled above"),
+                })),
+                _ => Err(format!(
+                    "Can't apply {:?} to {} and {}",
+                    operator, left, right
+                )),
+            },
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, String> {
+        // A local holding a function, like a callback parameter
+        if let Some(variable) = self.lookup(name) {
+            let callee = variable.borrow().clone();
+            return self.call_value(callee, args);
+        }
+        if PRINT_FUNCTIONS.contains(&name) {
+            // print("x =", x) separates its arguments with a space
+            for (position, arg) in args.iter().enumerate() {
+                let separator = if position == 0 { "" } else { " " };
+                write!(self.out, "{}{}", separator, arg).map_err(|e| e.to_string())?;
+            }
+            if ends_line(name) {
+                writeln!(self.out).map_err(|e| e.to_string())?;
+            }
+            return Ok(Value::Unit);
+        }
+        if FORMAT_FUNCTIONS.contains(&name) {
+            // The checker makes the format a literal with one value per `{}`
+            let Some((Value::String(format), values)) = args.split_first() else {
+                return Err(format!("{} needs a format string", name));
+            };
+            let mut text = String::new();
+            for (position, piece) in format_pieces(format).iter().enumerate() {
+                if position > 0 {
+                    text.push_str(&values[position - 1].to_string());
+                }
+                text.push_str(piece);
+            }
+            if name == "format" {
+                return Ok(Value::String(text.into()));
+            }
+            write!(self.out, "{}", text).map_err(|e| e.to_string())?;
+            return Ok(Value::Unit);
+        }
+        if let Some(function) = self.functions.get(name).cloned() {
+            return self.call_function(&function, args);
+        }
+        if let Some(result) = self.call_native(name, &args)? {
+            return Ok(result);
+        }
+        // Interfaces are dispatched on the receiver: Printable_toString(person)
+        // runs Person_toString
+        if let Some(Value::Struct {
+            name: type_name, ..
+        }) = args.first()
+        {
+            for (underscore, _) in name.match_indices('_') {
+                let method = method_function_name(type_name, &name[underscore + 1..]);
+                if let Some(function) = self.functions.get(&method).cloned() {
+                    return self.call_function(&function, args);
+                }
+            }
+        }
+        Err(format!(
+            "Function '{}' is not available in the interpreter",
+            name
+        ))
+    }
+
+    fn call_value(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, String> {
+        match callee {
+            Value::Function(name) => self.call(&name, args),
+            Value::Lambda(lambda) => {
+                // Lambdas can't capture locals, so only their parameters are in scope
+                let frame = lambda
+                    .params
+                    .iter()
+                    .zip(args)
+                    .map(|((name, ty), value)| {
+                        (name.clone(), Rc::new(RefCell::new(convert(value, ty))))
+                    })
+                    .collect();
+                let scopes = std::mem::replace(&mut self.scopes, vec![frame]);
+                let result = self.eval(&lambda.body);
+                self.scopes = scopes;
+                result
+            }
+            other => Err(format!("{} is not a function", other)),
+        }
+    }
+
+    fn call_function(&mut self, function: &Function, args: Vec<Value>) -> Result<Value, String> {
+        let frame = function
+            .params
+            .iter()
+            .zip(args)
+            .map(|(param, value)| {
+                let value = convert(value, &param.param_type);
+                (param.name.clone(), Rc::new(RefCell::new(value)))
+            })
+            .collect();
+        let scopes = std::mem::replace(&mut self.scopes, vec![frame]);
+        let flow = self.exec_statements(&function.body);
+        self.scopes = scopes;
+        match flow? {
+            Flow::Return(value) => Ok(convert(value, &function.return_type)),
+            _ => Ok(Value::Unit),
+        }
+    }
+
+    // Bytes from the operating system for bolt:uuid, like the C runtime's
+    // bolt_random_bytes: the generator fills in what can't be read
+    fn random_bytes(&mut self, count: usize) -> Vec<u8> {
+        let mut bytes = vec![0; count];
+        let filled = fs::File::open("/dev/urandom")
+            .and_then(|mut source| source.read_exact(&mut bytes))
+            .map_or(0, |()| count);
+        let random = self.random.get_or_insert_with(Random::from_clock);
+        for byte in &mut bytes[filled..] {
+            *byte = random.next() as u8;
+        }
+        bytes
+    }
+
+    // A new empty file (or directory) named `prefix` and six random
+    // characters in the temp directory, as mkstemp and mkdtemp make them;
+    // "" if it can't be created
+    fn create_temp(&mut self, prefix: &str, directory: bool) -> String {
+        let root = std::env::var("TMPDIR")
+            .ok()
+            .filter(|root| !root.is_empty())
+            .unwrap_or_else(|| "/tmp".to_string());
+        for _ in 0..100 {
+            let suffix: String = self
+                .random_bytes(6)
+                .iter()
+                .map(|byte| NANOID_ALPHABET.as_bytes()[(byte & 63) as usize] as char)
+                .collect();
+            let path = format!("{}/{}{}", root, prefix, suffix);
+            let created = if directory {
+                fs::create_dir(&path)
+            } else {
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .map(drop)
+            };
+            match created {
+                Ok(()) => {
+                    self.temp_paths.push(path.clone());
+                    return path;
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(_) => break,
+            }
+        }
+        String::new()
+    }
+
+    // The C-implemented parts of the standard library and the builtins;
+    // None when `name` isn't one of them
+    fn call_native(&mut self, name: &str, args: &[Value]) -> Result<Option<Value>, String> {
+        let string = |index: usize| match args.get(index) {
+            Some(Value::String(s)) => Ok(s.to_string()),
+            Some(other) => Err(format!("{} expects a String, got {}", name, other)),
+            None => Err(format!("{} is missing an argument", name)),
+        };
+        let integer = |index: usize| match args.get(index) {
+            Some(Value::Integer(n)) => Ok(*n),
+            Some(other) => Err(format!("{} expects an Integer, got {}", name, other)),
+            None => Err(format!("{} is missing an argument", name)),
+        };
+        let float = |index: usize| match args.get(index).and_then(number) {
+            Some(x) => Ok(x),
+            None => Err(format!("{} expects a Float", name)),
+        };
+        let array = |index: usize| match args.get(index) {
+            Some(Value::Array(array)) => Ok(array.clone()),
+            Some(other) => Err(format!("{} expects an array, got {}", name, other)),
+            None => Err(format!("{} is missing an argument", name)),
+        };
+        let text = |s: String| Value::String(s.into());
+        let int = |n: usize| Value::Integer(n as i128);
+        let paths = |paths: Vec<String>| {
+            Value::Array(ArrayValue::new(paths.into_iter().map(text).collect()))
+        };
+        let position = |found: Option<usize>| Value::Integer(found.map_or(-1, |i| i as i128));
+
+        Ok(Some(match name {
+            "toString" => text(args.first().map(Value::to_string).unwrap_or_default()),
+            "clone" => deep_copy(args.first().cloned().unwrap_or(Value::Unit)),
+            "length" => match args.first() {
+                Some(Value::Array(array)) => int(array.length),
+                _ => int(string(0)?.len()),
+            },
+            "concat" => text(string(0)? + &string(1)?),
+            "indexOf" => position(string(0)?.find(&string(1)?)),
+            "contains" => Value::Bool(string(0)?.contains(&string(1)?)),
+            "trim" => text(
+                string(0)?
+                    .trim_matches(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+                    .to_string(),
+            ),
+            "compare" => Value::Integer(match string(0)?.cmp(&string(1)?) {
+                Ordering::Less => -1,
+                Ordering::Equal => 0,
+                Ordering::Greater => 1,
+            }),
+            "naturalCompare" => Value::Integer(natural_compare(
+                string(0)?.as_bytes(),
+                string(1)?.as_bytes(),
+            )),
+            "view" => {
+                let s = string(0)?;
+                let end = if args.len() > 2 {
+                    integer(2)?
+                } else {
+                    s.len() as i128
+                };
+                let end = end.clamp(0, s.len() as i128) as usize;
+                let start = (integer(1)?.clamp(0, end as i128)) as usize;
+                text(String::from_utf8_lossy(&s.as_bytes()[start..end]).into_owned())
+            }
+            "readFile" => text(fs::read_to_string(string(0)?).unwrap_or_default()),
+            "writeFile" => Value::Bool(fs::write(string(0)?, string(1)?).is_ok()),
+            "appendFile" => {
+                let content = string(1)?;
+                let appended = fs::OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(string(0)?)
+                    .and_then(|mut file| file.write_all(content.as_bytes()));
+                Value::Bool(appended.is_ok())
+            }
+            "fileExists" => Value::Bool(fs::File::open(string(0)?).is_ok()),
+            "deleteFile" => Value::Bool(fs::remove_file(string(0)?).is_ok()),
+            "tempFile" => text(self.create_temp(&string(0)?, false)),
+            "tempDir" => text(self.create_temp("bolt-", true)),
+            "removeTemp" => {
+                let path = string(0)?;
+                match self.temp_paths.iter().position(|made| *made == path) {
+                    Some(position) => {
+                        self.temp_paths.swap_remove(position);
+                        Value::Bool(remove_tree(&path))
+                    }
+                    None => Value::Bool(false),
+                }
+            }
+            // Blocks are arrays of Unit until written; the views into them
+            // are what ^T values are here, so free has nothing to release
+            "alloc" => Value::Array(ArrayValue::new(vec![
+                Value::Unit;
+                integer(0)?.max(0) as usize
+            ])),
+            "realloc" => Value::Array(array(0)?.resized(integer(1)?.max(0) as usize)),
+            "free" => Value::Unit,
+            "exit" => {
+                self.exit_status = Some(integer(0)? as i32);
+                return Err(String::new());
+            }
+            "listDir" => paths(list_dir(&string(0)?)),
+            "isDir" => Value::Bool(is_dir(&string(0)?, true)),
+            "glob" => paths(glob(&string(0)?)),
+            "walk" => {
+                let mut files = Vec::new();
+                walk_into(&mut files, &string(0)?);
+                paths(files)
+            }
+            // panic and assert, lowered with their location
+            "bolt_panic" => {
+                return Err(format!("{}: {}: {}", string(0)?, string(1)?, string(2)?));
+            }
+            "getenv" => text(std::env::var(string(0)?).unwrap_or_default()),
+            "getArgs" => self.arguments(),
+            "seedRandom" => {
+                self.random = Some(Random::seeded(integer(0)? as i64 as u64));
+                Value::Unit
+            }
+            "randomInt" => {
+                let (min, max) = (integer(0)?, integer(1)?);
+                if max < min {
+                    Value::Integer(min)
+                } else {
+                    let random = self.random.get_or_insert_with(Random::from_clock);
+                    Value::Integer(min + random.below((max - min + 1) as u64) as i128)
+                }
+            }
+            "randomFloat" => {
+                Value::Float(self.random.get_or_insert_with(Random::from_clock).float())
+            }
+            "randomNormal" => {
+                let (mean, stddev) = (float(0)?, float(1)?);
+                let random = self.random.get_or_insert_with(Random::from_clock);
+                let u1 = 1.0 - random.float();
+                let u2 = random.float();
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                Value::Float(mean + stddev * z)
+            }
+            "uuid4" => {
+                let mut bytes = self.random_bytes(16);
+                bytes[6] = (bytes[6] & 0x0f) | 0x40;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+                let mut id = String::new();
+                for (i, byte) in bytes.iter().enumerate() {
+                    if matches!(i, 4 | 6 | 8 | 10) {
+                        id.push('-');
+                    }
+                    id.push_str(&format!("{:02x}", byte));
+                }
+                text(id)
+            }
+            "nanoid" => {
+                let length = integer(0)?.max(0) as usize;
+                let symbols = NANOID_ALPHABET.as_bytes();
+                text(
+                    self.random_bytes(length)
+                        .into_iter()
+                        .map(|byte| symbols[(byte & 63) as usize] as char)
+                        .collect(),
+                )
+            }
+            "shuffle" => {
+                // Fisher-Yates, in the C helper's order
+                let array = array(0)?;
+                let mut items = array.to_vec();
+                let random = self.random.get_or_insert_with(Random::from_clock);
+                for i in (1..items.len()).rev() {
+                    let j = random.below(i as u64 + 1) as usize;
+                    items.swap(i, j);
+                }
+                array.set_all(items);
+                Value::Array(array)
+            }
+            "choice" => {
+                let items = array(0)?.to_vec();
+                if items.is_empty() {
+                    Value::Optional(None)
+                } else {
+                    let random = self.random.get_or_insert_with(Random::from_clock);
+                    let index = random.below(items.len() as u64) as usize;
+                    Value::Optional(Some(Box::new(items[index].clone())))
+                }
+            }
+            "sort" => {
+                let array = array(0)?;
+                let mut items = array.to_vec();
+                items.sort_by(|a, b| compare(a, b).unwrap_or(Ordering::Equal));
+                array.set_all(items);
+                Value::Array(array)
+            }
+            "sortBy" => {
+                // Stable, so equal keys keep their order
+                let array = array(0)?;
+                let mut keyed = Vec::new();
+                for item in array.to_vec() {
+                    let key = self.call_value(args[1].clone(), vec![item.clone()])?;
+                    keyed.push((key, item));
+                }
+                keyed.sort_by(|(a, _), (b, _)| compare(a, b).unwrap_or(Ordering::Equal));
+                array.set_all(keyed.into_iter().map(|(_, item)| item).collect());
+                Value::Array(array)
+            }
+            "reverse" => {
+                let array = array(0)?;
+                let mut items = array.to_vec();
+                items.reverse();
+                array.set_all(items);
+                Value::Array(array)
+            }
+            "binarySearch" => {
+                let items = array(0)?.to_vec();
+                let (mut low, mut high) = (0i128, items.len() as i128 - 1);
+                let mut found = None;
+                while low <= high {
+                    let mid = low + (high - low) / 2;
+                    match compare(&items[mid as usize], &args[1]) {
+                        Some(Ordering::Less) => low = mid + 1,
+                        Some(Ordering::Greater) => high = mid - 1,
+                        _ => {
+                            found = Some(mid as usize);
+                            break;
+                        }
+                    }
+                }
+                position(found)
+            }
+            "indexOfValue" => position(
+                array(0)?
+                    .to_vec()
+                    .iter()
+                    .position(|item| equal(item, &args[1])),
+            ),
+            "filter" => {
+                let mut kept = Vec::new();
+                for item in array(0)?.to_vec() {
+                    if self.test(&args[1], &item)? {
+                        kept.push(item);
+                    }
+                }
+                Value::Array(ArrayValue::new(kept))
+            }
+            "map" | "mapValues" => {
+                let mut mapped = Vec::new();
+                for item in array(0)?.to_vec() {
+                    mapped.push(self.call_value(args[1].clone(), vec![item])?);
+                }
+                Value::Array(ArrayValue::new(mapped))
+            }
+            "reduce" => {
+                let mut accumulator = args[1].clone();
+                for item in array(0)?.to_vec() {
+                    accumulator = self.call_value(args[2].clone(), vec![accumulator, item])?;
+                }
+                accumulator
+            }
+            "any" | "all" => {
+                let want = name == "any";
+                let mut result = !want;
+                for item in array(0)?.to_vec() {
+                    if self.test(&args[1], &item)? == want {
+                        result = want;
+                        break;
+                    }
+                }
+                Value::Bool(result)
+            }
+            "slice" => Value::Array(array(0)?.slice(integer(1)?, integer(2)?)),
+            // items[start..end] and s[start..end] check their bounds, like a debug build
+            "checkedSlice" | "string_slice" => {
+                let (start, end) = (integer(1)?, integer(2)?);
+                let length = match name {
+                    "checkedSlice" => array(0)?.length,
+                    _ => string(0)?.len(),
+                } as i128;
+                if start < 0 || end > length || start > end {
+                    return Err(format!(
+                        "Slice [{}..{}] is out of bounds for length {}",
+                        start, end, length
+                    ));
+                }
+                match name {
+                    "checkedSlice" => Value::Array(array(0)?.slice(start, end)),
+                    _ => {
+                        let bytes = &string(0)?.into_bytes()[start as usize..end as usize];
+                        text(String::from_utf8_lossy(bytes).into_owned())
+                    }
+                }
+            }
+            "Array_push" | "Array_insert" | "Array_pop" | "Array_get" => {
+                let Some(Value::Pointer(variable)) = args.first() else {
+                    return Err(format!("{} expects an array variable", name));
+                };
+                let Value::Array(mut array) = variable.borrow().clone() else {
+                    return Err(format!("{} expects an array variable", name));
+                };
+                let result = match name {
+                    "Array_push" => {
+                        array.insert(array.length, args[1].clone());
+                        Value::Unit
+                    }
+                    "Array_insert" => {
+                        let index = integer(1)?.clamp(0, array.length as i128) as usize;
+                        array.insert(index, args[2].clone());
+                        Value::Unit
+                    }
+                    "Array_pop" if array.length > 0 => {
+                        let last = array.get(array.length as i128 - 1)?;
+                        array.length -= 1;
+                        Value::Optional(Some(Box::new(last)))
+                    }
+                    "Array_pop" => Value::Optional(None),
+                    _ => Value::Optional(array.get(integer(1)?).ok().map(Box::new)),
+                };
+                *variable.borrow_mut() = Value::Array(array);
+                result
+            }
+            "get" | "set" | "has" | "delete" => {
+                let Some(Value::Map(map)) = args.first() else {
+                    return Ok(None);
+                };
+                let found = map
+                    .borrow()
+                    .iter()
+                    .position(|(key, _)| equal(key, &args[1]));
+                match (name, found) {
+                    ("get", found) => {
+                        Value::Optional(found.map(|index| Box::new(map.borrow()[index].1.clone())))
+                    }
+                    ("set", _) => {
+                        map_set(map, args[1].clone(), args[2].clone());
+                        Value::Unit
+                    }
+                    ("has", found) => Value::Bool(found.is_some()),
+                    (_, found) => {
+                        if let Some(index) = found {
+                            map.borrow_mut().remove(index);
+                        }
+                        Value::Unit
+                    }
+                }
+            }
+            _ => return Ok(None),
+        }))
+    }
+
+    // Call a predicate on one element
+    fn test(&mut self, predicate: &Value, item: &Value) -> Result<bool, String> {
+        match self.call_value(predicate.clone(), vec![item.clone()])? {
+            Value::Bool(b) => Ok(b),
+            other => Err(format!("A predicate returned {}, not a Bool", other)),
+        }
+    }
+}
+
+// A value as a variable or parameter of type `ty` holds it: integers wrap
+// to the type's width and become floats where a Float is expected
+fn convert(value: Value, ty: &Type) -> Value {
+    match (value, ty) {
+        (Value::Integer(n), Type::Integer) => Value::Integer(n as i32 as i128),
+        (Value::Integer(n), Type::SizedInt(kind)) => {
+            let bits = kind.bits();
+            let mask = (1i128 << bits) - 1;
+            let wrapped = n & mask;
+            if kind.is_signed() && wrapped >> (bits - 1) == 1 {
+                Value::Integer(wrapped - (1i128 << bits))
+            } else {
+                Value::Integer(wrapped)
+            }
+        }
+        (Value::Integer(n), Type::Float) => Value::Float(n as f64),
+        (value, _) => value,
+    }
+}
+
+// The result of Integer arithmetic, which stops the program when it doesn't
+// fit like a checked C build does (see bolt_add_int). Sized integers wrap.
+fn checked(result: i128, ty: &Type) -> Result<Value, String> {
+    if *ty == Type::Integer && i32::try_from(result).is_err() {
+        return Err("Integer overflow".to_string());
+    }
+    Ok(convert(Value::Integer(result), ty))
+}
+
+fn is_optional(ty: &Type) -> bool {
+    matches!(ty, Type::Generic { name, .. } if name == "Optional")
+}
+
+// `value as ty`, which converts like C: floats truncate toward zero into
+// integer types and a Bool becomes 0 or 1. Pointers keep pointing at the
+// same variable.
+fn cast(value: Value, ty: &Type) -> Value {
+    let value = match (value, ty) {
+        (Value::Float(x), Type::Integer | Type::SizedInt(_)) => Value::Integer(x as i128),
+        (Value::Bool(b), Type::Integer | Type::SizedInt(_)) => Value::Integer(b as i128),
+        (value, _) => value,
+    };
+    convert(value, ty)
+}
+
+// Arithmetic and comparisons on pointers into arrays, which are views of
+// the elements like `data`: moving one moves the view. A pointer to a
+// single variable can't move; None leaves the rest (equality with another
+// pointer or none) to the usual rules
+fn pointer_binary(
+    operator: &BinaryOperator,
+    left: &Value,
+    right: &Value,
+) -> Option<Result<Value, String>> {
+    let distance = match (operator, left, right) {
+        (BinaryOperator::Add, Value::Array(array), Value::Integer(n)) => {
+            return Some(array.offset(*n).map(Value::Array))
+        }
+        (BinaryOperator::Subtract, Value::Array(array), Value::Integer(n)) => {
+            return Some(array.offset(-n).map(Value::Array))
+        }
+        (BinaryOperator::Add | BinaryOperator::Subtract, Value::Pointer(_), Value::Integer(0)) => {
+            return Some(Ok(left.clone()))
+        }
+        (BinaryOperator::Add | BinaryOperator::Subtract, Value::Pointer(_), Value::Integer(_)) => {
+            return Some(Err("Only a pointer into an array can be moved".to_string()))
+        }
+        (_, Value::Array(a), Value::Array(b)) => a.distance(b),
+        _ => return None,
+    };
+    let ordered = |test: fn(Ordering) -> bool| match distance {
+        Some(distance) => Ok(Value::Bool(test(distance.cmp(&0)))),
+        None => Err("Can't compare pointers into different arrays".to_string()),
+    };
+    Some(match operator {
+        BinaryOperator::Subtract => distance
+            .map(Value::Integer)
+            .ok_or_else(|| "Can't subtract pointers into different arrays".to_string()),
+        BinaryOperator::Equal => Ok(Value::Bool(distance == Some(0))),
+        BinaryOperator::NotEqual => Ok(Value::Bool(distance != Some(0))),
+        BinaryOperator::Less => ordered(Ordering::is_lt),
+        BinaryOperator::LessEqual => ordered(Ordering::is_le),
+        BinaryOperator::Greater => ordered(Ordering::is_gt),
+        BinaryOperator::GreaterEqual => ordered(Ordering::is_ge),
+        _ => return None,
+    })
+}
+
+// A file, or a directory and everything in it
+fn remove_tree(path: &str) -> bool {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path).is_ok(),
+        Ok(_) => fs::remove_file(path).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// bolt:fs: the names in a directory, sorted like the generated C's qsort
+fn list_dir(path: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(if path.is_empty() { "." } else { path })
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+// dir/name; just name below the current directory ("")
+fn join_path(dir: &str, name: &str) -> String {
+    if dir.is_empty() || dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+// A directory itself, or (following symlinks) one it leads to
+fn is_dir(path: &str, follow: bool) -> bool {
+    let path = if path.is_empty() { "." } else { path };
+    let metadata = if follow {
+        fs::metadata(path)
+    } else {
+        fs::symlink_metadata(path)
+    };
+    metadata.is_ok_and(|metadata| metadata.is_dir())
+}
+
+fn walk_into(files: &mut Vec<String>, dir: &str) {
+    for name in list_dir(dir) {
+        let path = join_path(dir, &name);
+        if is_dir(&path, false) {
+            walk_into(files, &path);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+// Matches segments[at..] of a glob pattern against the paths below dir
+fn glob_into(matches: &mut Vec<String>, dir: &str, segments: &[&str], at: usize) {
+    let Some(segment) = segments.get(at) else {
+        if !dir.is_empty() {
+            matches.push(dir.to_string());
+        }
+        return;
+    };
+    let last = at == segments.len() - 1;
+    if *segment == "**" {
+        glob_into(matches, dir, segments, at + 1);
+        for name in list_dir(dir) {
+            let path = join_path(dir, &name);
+            if !name.starts_with('.') && is_dir(&path, false) {
+                glob_into(matches, &path, segments, at);
+            }
+        }
+    } else if !segment.contains(['*', '?', '[']) {
+        let path = join_path(dir, segment);
+        let found = if last {
+            fs::symlink_metadata(&path).is_ok()
+        } else {
+            is_dir(&path, true)
+        };
+        if found {
+            glob_into(matches, &path, segments, at + 1);
+        }
+    } else {
+        for name in list_dir(dir) {
+            let path = join_path(dir, &name);
+            if wildcard_match(segment.as_bytes(), name.as_bytes()) && (last || is_dir(&path, true))
+            {
+                glob_into(matches, &path, segments, at + 1);
+            }
+        }
+    }
+}
+
+fn glob(pattern: &str) -> Vec<String> {
+    let segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let mut matches = Vec::new();
+    glob_into(
+        &mut matches,
+        if pattern.starts_with('/') { "/" } else { "" },
+        &segments,
+        0,
+    );
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+// fnmatch(pattern, name, FNM_PERIOD): a leading "." only matches a literal one
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    if name.first() == Some(&b'.') && pattern.first() != Some(&b'.') {
+        return false;
+    }
+    matches_from(pattern, name)
+}
+
+fn matches_from(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some(b'*') => (0..=name.len()).any(|skip| matches_from(&pattern[1..], &name[skip..])),
+        Some(b'?') => !name.is_empty() && matches_from(&pattern[1..], &name[1..]),
+        Some(b'[') => match bracket_match(&pattern[1..], name.first()) {
+            Some((matched, rest)) => matched && matches_from(rest, &name[1..]),
+            // An unclosed '[' is an ordinary character
+            None => name.first() == Some(&b'[') && matches_from(&pattern[1..], &name[1..]),
+        },
+        Some(b'\\') if pattern.len() > 1 => {
+            name.first() == Some(&pattern[1]) && matches_from(&pattern[2..], &name[1..])
+        }
+        Some(c) => name.first() == Some(c) && matches_from(&pattern[1..], &name[1..]),
+    }
+}
+
+// `[abc]`, `[a-z]` or `[!a]` (pattern starts after the '['): whether c is in
+// the set and the pattern after the ']', or None when there's no ']'
+fn bracket_match<'p>(pattern: &'p [u8], c: Option<&u8>) -> Option<(bool, &'p [u8])> {
+    let negated = matches!(pattern.first(), Some(b'!' | b'^'));
+    let start = usize::from(negated);
+    let mut found = false;
+    let mut i = start;
+    while i < pattern.len() {
+        // A ']' right at the start is a member, not the end
+        if pattern[i] == b']' && i > start {
+            let matched = c.is_some() && found != negated;
+            return Some((matched, &pattern[i + 1..]));
+        }
+        let low = pattern[i];
+        let (high, width) = match pattern.get(i + 1..i + 3) {
+            Some([b'-', high]) if *high != b']' => (*high, 3),
+            _ => (low, 1),
+        };
+        found |= c.is_some_and(|&c| low <= c && c <= high);
+        i += width;
+    }
+    None
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(n) => Some(*n as f64),
+        Value::Float(x) => Some(*x),
+        _ => None,
+    }
+}
+
+fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Integer(a), Value::Integer(b)) => a == b,
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::String(a), Value::String(b)) => a == b,
+        (Value::Unit, Value::Unit) => true,
+        (Value::Optional(None), Value::Optional(None)) => true,
+        (Value::Optional(None), _) | (_, Value::Optional(None)) => false,
+        (Value::Optional(Some(a)), b) | (b, Value::Optional(Some(a))) => equal(a, b),
+        (Value::Tuple(a), Value::Tuple(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (
+            Value::Struct {
+                name: a_name,
+                fields: a,
+            },
+            Value::Struct {
+                name: b_name,
+                fields: b,
+            },
+        ) => {
+            a_name == b_name
+                && a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|((a_field, a), (b_field, b))| a_field == b_field && equal(a, b))
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.length == b.length
+                && a.to_vec()
+                    .iter()
+                    .zip(b.to_vec().iter())
+                    .all(|(a, b)| equal(a, b))
+        }
+        (Value::Pointer(a), Value::Pointer(b)) => Rc::ptr_eq(a, b),
+        (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+        (Value::Function(a), Value::Function(b)) => a == b,
+        _ => match (number(left), number(right)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        },
+    }
+}
+
+// clone(value): arrays, also inside structs, tuples and optionals, get
+// storage of their own; pointers and maps stay shared, as in the C helpers
+fn deep_copy(value: Value) -> Value {
+    match value {
+        Value::Array(array) => Value::Array(ArrayValue::new(
+            array.to_vec().into_iter().map(deep_copy).collect(),
+        )),
+        Value::Tuple(items) => Value::Tuple(items.into_iter().map(deep_copy).collect()),
+        Value::Struct { name, fields } => Value::Struct {
+            name,
+            fields: fields
+                .into_iter()
+                .map(|(field, value)| (field, deep_copy(value)))
+                .collect(),
+        },
+        Value::Optional(Some(value)) => Value::Optional(Some(Box::new(deep_copy(*value)))),
+        other => other,
+    }
+}
+
+// Add an entry, or replace the value of the entry with an equal key
+fn map_set(map: &RefCell<Vec<(Value, Value)>>, key: Value, value: Value) {
+    let mut entries = map.borrow_mut();
+    match entries
+        .iter_mut()
+        .find(|(existing, _)| equal(existing, &key))
+    {
+        Some(entry) => entry.1 = value,
+        None => entries.push((key, value)),
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => number(left)?.partial_cmp(&number(right)?),
+    }
+}
+
+// Runs of digits compare by numeric value: "file2" < "file10"
+fn natural_compare(a: &[u8], b: &[u8]) -> i128 {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            while i < a.len() && a[i] == b'0' {
+                i += 1;
+            }
+            while j < b.len() && b[j] == b'0' {
+                j += 1;
+            }
+            let a_end = i + a[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            let b_end = j + b[j..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if a_end - i != b_end - j {
+                return if a_end - i < b_end - j { -1 } else { 1 };
+            }
+            match a[i..a_end].cmp(&b[j..b_end]) {
+                Ordering::Less => return -1,
+                Ordering::Greater => return 1,
+                Ordering::Equal => {}
+            }
+            i = a_end;
+            j = b_end;
+        } else {
+            if a[i] != b[j] {
+                return if a[i] < b[j] { -1 } else { 1 };
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    match (i < a.len(), j < b.len()) {
+        (true, _) => 1,
+        (_, true) => -1,
+        _ => 0,
+    }
+}
+
+// The name print shows for a struct: Person, or Box_Integer for a generic one
+fn struct_name(ty: &Type) -> String {
+    match ty {
+        Type::Custom(name) => name.clone(),
+        Type::Generic { name, type_params } => std::iter::once(name.clone())
+            .chain(type_params.iter().map(struct_name))
+            .collect::<Vec<_>>()
+            .join("_"),
+        other => other.to_string(),
+    }
+}
+
+// `Array[T] { data: raw, length: n, capacity: c }`: the first n elements of
+// what data points to
+fn array_from_fields(fields: Vec<(String, Value)>) -> Result<ArrayValue, String> {
+    let mut data = None;
+    let mut length = None;
+    let mut capacity = None;
+    for (name, value) in fields {
+        match (name.as_str(), value) {
+            ("data", value) => data = Some(value),
+            ("length", Value::Integer(n)) => length = Some(n.max(0) as usize),
+            ("capacity", Value::Integer(n)) => capacity = Some(n.max(0) as usize),
+            _ => {}
+        }
+    }
+    let mut array = match data {
+        Some(Value::Array(array)) => array,
+        Some(Value::Pointer(variable)) => ArrayValue::new(vec![variable.borrow().clone()]),
+        _ => ArrayValue::new(Vec::new()),
+    };
+    let length = length.unwrap_or(array.length).min(array.length);
+    array.length = length;
+    array.capacity = capacity.unwrap_or(length);
+    Ok(array)
+}
+
+fn field_of(object: Value, field: &str) -> Result<Value, String> {
+    match (&object, field) {
+        (Value::Struct { fields, .. }, _) => {
+            if let Some((_, value)) = fields.iter().find(|(name, _)| name == field) {
+                return Ok(value.clone());
+            }
+        }
+        (Value::Tuple(elements), _) => {
+            if let Some(value) = field.parse::<usize>().ok().and_then(|i| elements.get(i)) {
+                return Ok(value.clone());
+            }
+        }
+        (Value::Array(array), "length") => return Ok(Value::Integer(array.length as i128)),
+        (Value::Array(array), "capacity") => return Ok(Value::Integer(array.capacity as i128)),
+        (Value::Array(_), "data") => return Ok(object),
+        // Through a pointer to an element, e.g. `(&people[1]).name`
+        (Value::Array(array), _) => return field_of(array.get(0)?, field),
+        (Value::String(s), "length") => return Ok(Value::Integer(s.len() as i128)),
+        (Value::Map(entries), "length") => {
+            return Ok(Value::Integer(entries.borrow().len() as i128))
+        }
+        (Value::Pointer(variable), _) => return field_of(variable.borrow().clone(), field),
+        _ => {}
+    }
+    Err(format!("{} has no field '{}'", object, field))
+}
+
+fn index_of(array: Value, index: i128) -> Result<Value, String> {
+    match array {
+        Value::Array(array) => array.get(index),
+        Value::Pointer(variable) => match variable.borrow().clone() {
+            Value::Array(array) => array.get(index),
+            value if index == 0 => Ok(value),
+            _ => Err(format!("Index {} is out of bounds for a pointer", index)),
+        },
+        other => Err(format!("Can't index {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn run(source: &str) -> Result<String, String> {
+        let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        let mut modules = ModuleSystem::new();
+        modules.resolve_imports(&program)?;
+        let mut out = Vec::new();
+        interpret(&program, &modules, vec!["test".to_string()], &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_runs_functions_loops_and_defer() {
+        let output = run("fun fib(n: Integer): Integer {\n    if n < 2 {\n        return n\n    }\n    return fib(n - 1) + fib(n - 2)\n}\nfun countdown() {\n    defer print(\"liftoff\")\n    for i in 0..3 {\n        if i == 2 {\n            return\n        }\n        print(3 - i)\n    }\n}\nprint(fib(10))\ncountdown()\nprint(\"x = ${1.5 * 2}\")\n").unwrap();
+        assert_eq!(output, "55\n3\n2\nliftoff\nx = 3.000000\n");
+    }
+
+    #[test]
+    fn test_integers_overflow_like_c() {
+        // Sized integers wrap; Integer arithmetic stops like a checked build
+        let output =
+            run("val small: UInt8 = 250\nval sum: UInt8 = small + 10\nprint(sum)\n").unwrap();
+        assert_eq!(output, "4\n");
+        for overflow in ["big + 1", "big * 2", "-big - 2", "-(-big - 1)"] {
+            let source = format!("val big := 2147483647\nprint({})\n", overflow);
+            assert_eq!(
+                run(&source).unwrap_err(),
+                "Integer overflow",
+                "{}",
+                overflow
+            );
+        }
+    }
+
+    #[test]
+    fn test_structs_copy_and_arrays_share() {
+        let output = run("import { sort } from \"bolt:array\"\ntype Array[T] = { data: ^T, length: Integer, capacity: Integer }\ntype Point = { x: Integer, y: Integer }\nfun (p: Point) sum(): Integer {\n    return p.x + p.y\n}\nval raw := [3, 1, 2]\nval numbers: Array[Integer] = Array[Integer] { data: raw, length: 3, capacity: 3 }\nsort(numbers)\nprint(raw)\nval p := Point { x: 1, y: 2 }\nprint(p)\nprint(p.sum())\n").unwrap();
+        assert_eq!(output, "[1, 2, 3]\nPoint { x: 1, y: 2 }\n3\n");
+    }
+
+    #[test]
+    fn test_print_functions_match_the_compiled_output() {
+        let output = run("import stdio from \"bolt:stdio\"\nwrite(\"a\")\nstdio.write(1)\nprintln(true)\nstdio.print(1.5)\n").unwrap();
+        assert_eq!(output, "a1true\n1.500000\n");
+    }
+
+    #[test]
+    fn test_temporary_paths_are_removed_when_the_program_ends() {
+        let source = "import { tempFile, tempDir, writeFile } from \"bolt:io\"\nval file := tempFile(\"left-\")\nval dir := tempDir()\nwriteFile(dir + \"/inside.txt\", \"x\")\nprint(file)\nprint(dir)\n";
+        let output = run(source).unwrap();
+        let paths: Vec<&str> = output.lines().collect();
+        assert_eq!(paths.len(), 2);
+        for path in paths {
+            assert!(
+                !std::path::Path::new(path).exists(),
+                "{} was left behind",
+                path
+            );
+        }
+
+        // Also when it stops with an error
+        let failing = "import { tempFile } from \"bolt:io\"\nval file := tempFile(\"failed-\")\nprint(file)\nval items := [1]\nprint(items[5])\n";
+        let tokens = Lexer::new(failing.to_string()).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        let mut modules = ModuleSystem::new();
+        modules.resolve_imports(&program).unwrap();
+        let mut out = Vec::new();
+        assert!(interpret(&program, &modules, vec![], &mut out).is_err());
+        let path = String::from_utf8(out).unwrap();
+        assert!(path.contains("/failed-"));
+        assert!(!std::path::Path::new(path.trim_end()).exists());
+    }
+
+    #[test]
+    fn test_glob_names_match_like_fnmatch() {
+        let matches =
+            |pattern: &str, name: &str| wildcard_match(pattern.as_bytes(), name.as_bytes());
+        assert!(matches("*.bolt", "main.bolt"));
+        assert!(!matches("*.bolt", "main.bolt.txt"));
+        assert!(matches("n?tes.txt", "notes.txt"));
+        assert!(matches("[a-c]*", "build"));
+        assert!(!matches("[!a-c]*", "build"));
+        assert!(matches("[]x]", "]"));
+        assert!(matches("a[b", "a[b"));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "x"));
+        // Only a pattern spelling out the '.' matches a hidden name
+        assert!(!matches("*", ".git"));
+        assert!(matches(".*", ".git"));
+    }
+
+    #[test]
+    fn test_runtime_errors_are_reported() {
+        let error = run("val xs := [1, 2]\nval i := 5\nprint(xs[i])\n").unwrap_err();
+        assert_eq!(error, "Index 5 is out of bounds for an array of length 2");
+        let error = run("val zero := 0\nprint(1 / zero)\n").unwrap_err();
+        assert_eq!(error, "Division by zero");
+    }
+
+    #[test]
+    fn test_panic_and_failed_asserts_report_their_line() {
+        let error = run("val n := 3\nassert(n > 0, \"positive\")\nassert(n < 0, format(\"{} is positive\", n))\n").unwrap_err();
+        assert_eq!(error, "<input>:3: assertion failed: 3 is positive");
+        let error =
+            run("fun fail() {\n    panic(\"stop\")\n}\nprint(1)\nfail()\nprint(2)\n").unwrap_err();
+        assert_eq!(error, "<input>:2: panic: stop");
+    }
+
+    #[test]
+    fn test_hot_reload_replaces_module_functions() {
+        let dir = std::env::temp_dir().join(format!("bolt_hot_reload_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let module = dir.join("game.bolt");
+        let module_path = module.to_string_lossy().into_owned();
+        fs::write(&module, "export fun speed(): Integer {\n    return 1\n}\n").unwrap();
+        let source = format!("import {{ speed }} from \"{}\"\n", module_path);
+        let program = Parser::new(Lexer::new(source).tokenize().unwrap())
+            .parse()
+            .unwrap();
+        let mut modules = ModuleSystem::new();
+        modules.resolve_imports(&program).unwrap();
+
+        let mut out = Vec::new();
+        let mut interpreter = Interpreter::new(Vec::new(), &mut out);
+        interpreter.load(&program, &modules).unwrap();
+        interpreter.hot_reload = Some(HotReload::new(modules));
+        let speed = |interpreter: &mut Interpreter| interpreter.call("speed", Vec::new());
+        assert!(matches!(speed(&mut interpreter), Ok(Value::Integer(1))));
+
+        fs::write(&module, "export fun speed(): Integer {\n    return 2\n}\n").unwrap();
+        let hot_reload = interpreter.hot_reload.as_mut().unwrap();
+        // The rewrite may land within the file system's timestamp resolution
+        hot_reload.modified.insert(module_path.clone(), None);
+        hot_reload.last_check -= RELOAD_INTERVAL;
+        interpreter.reload_changed_modules();
+        assert!(matches!(speed(&mut interpreter), Ok(Value::Integer(2))));
+
+        // A broken edit keeps the last good version running
+        fs::write(&module, "export fun speed(: Integer {\n").unwrap();
+        let hot_reload = interpreter.hot_reload.as_mut().unwrap();
+        hot_reload.modified.insert(module_path, None);
+        hot_reload.last_check -= RELOAD_INTERVAL;
+        interpreter.reload_changed_modules();
+        assert!(matches!(speed(&mut interpreter), Ok(Value::Integer(2))));
+    }
+}
@@ -0,0 +1,1841 @@
+//! Typed intermediate representation between the checked AST and a backend.
+//!
+//! Lowering resolves the type of every expression from declarations,
+//! signatures and struct definitions (never from variable or field names),
+//! turns every loop into a plain `Loop` that exits through `Break`, and
+//! spells out the temporaries that iteration and optional binding need, so a
+//! backend can emit code without re-deriving what the source meant.
+
+use crate::ast::{
+    BinaryOperator, Expression, Field, InterpolationPart, Parameter, Program as AstProgram,
+    Statement, Type, UnaryOperator,
+};
+use crate::checker::{call_arguments, map_types};
+use crate::const_eval::{evaluate_constants_with, Constant};
+use crate::module::ModuleSystem;
+use crate::symbol_table::{method_function_name, ARRAY_METHODS, FORMAT_FUNCTIONS, PRINT_FUNCTIONS};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Prefix of every local the lowering introduces
+pub const TEMP_PREFIX: &str = "_bolt_tmp";
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub structs: Vec<Struct>,
+    pub functions: Vec<Function>,
+    pub body: Vec<Stmt>, // Top-level code of the entry module, in order
+}
+
+#[derive(Debug, Clone)]
+pub struct Struct {
+    pub name: String,
+    pub type_params: Vec<String>,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Parameter>,
+    pub return_type: Type, // unit() when the function returns nothing
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Let {
+        name: String,
+        ty: Type,
+        value: Expr,
+        mutable: bool,
+    },
+    Assign {
+        name: String,
+        value: Expr,
+    },
+    Expr(Expr),
+    If {
+        condition: Expr,
+        then_body: Vec<Stmt>,
+        else_body: Vec<Stmt>,
+    },
+    Loop(Vec<Stmt>), // Runs until a Break or Return
+    Break,
+    Return(Option<Expr>),
+    Defer(Vec<Stmt>),
+    Block(Vec<Stmt>), // A scope for the temporaries of a lowered construct
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Integer(i128),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    None,
+    Local(String),
+    Function(String), // A named function used as a value
+    Call {
+        function: String,
+        args: Vec<Expr>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expr>,
+    },
+    Array(Vec<Expr>),
+    Tuple(Vec<Expr>),
+    Map(Vec<(Expr, Expr)>), // Key and value of each entry, in source order
+    Struct(Vec<(String, Expr)>), // The struct type is the expression's type
+    Field {
+        object: Box<Expr>,
+        field: String,
+    },
+    Index {
+        array: Box<Expr>,
+        index: Box<Expr>,
+    },
+    AddressOf(Box<Expr>),
+    Deref(Box<Expr>),
+    Cast(Box<Expr>), // To the expression's type
+    Select {
+        condition: Box<Expr>,
+        then_value: Box<Expr>,
+        else_value: Box<Expr>,
+    },
+    Wrap(Box<Expr>),      // An optional holding the value, where one is expected
+    IsPresent(Box<Expr>), // Whether an optional holds a value
+    Unwrap(Box<Expr>),    // The value of an optional known to be present
+    Coalesce {
+        value: Box<Expr>, // Evaluated once; `fallback` only when it is absent
+        fallback: Box<Expr>,
+    },
+    OptionalField {
+        object: Box<Expr>, // An optional struct; absent gives an absent field
+        field: String,
+    },
+    Lambda {
+        params: Vec<Parameter>,
+        body: Box<Expr>,
+    },
+}
+
+/// The type of expressions that produce no value, such as calls to
+/// functions without a return type
+pub fn unit() -> Type {
+    Type::Tuple(Vec::new())
+}
+
+impl Expr {
+    fn new(kind: ExprKind, ty: Type) -> Self {
+        Self { kind, ty }
+    }
+
+    fn local(name: &str, ty: Type) -> Self {
+        Self::new(ExprKind::Local(name.to_string()), ty)
+    }
+
+    fn binary(operator: BinaryOperator, left: Expr, right: Expr, ty: Type) -> Self {
+        Self::new(
+            ExprKind::Binary {
+                operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            ty,
+        )
+    }
+
+    fn not(self) -> Self {
+        Self::new(
+            ExprKind::Unary {
+                operator: UnaryOperator::Not,
+                operand: Box::new(self),
+            },
+            Type::Bool,
+        )
+    }
+}
+
+/// Lower a checked entry program. Signatures and struct definitions of the
+/// modules it imports come from `modules`, which must have resolved them.
+pub fn lower_program(program: &AstProgram, modules: &ModuleSystem) -> Result<Program, String> {
+    let mut lowerer = Lowerer {
+        signatures: HashMap::new(),
+        parameters: HashMap::new(),
+        constants: HashMap::new(),
+        structs: HashMap::new(),
+        scopes: vec![HashMap::new()],
+        return_type: None,
+        temp_count: 0,
+    };
+    let mut module_paths: Vec<&String> = modules.module_paths().collect();
+    module_paths.sort();
+    for module_path in module_paths {
+        if let Some(module) = modules.get_module(module_path) {
+            lowerer.declare(&module.statements, modules.module_constants(module_path)?)?;
+        }
+    }
+    let imported = modules.imported_constants(&program.statements)?;
+    let own = evaluate_constants_with(&program.statements, &imported)?;
+    lowerer.declare(
+        &program.statements,
+        imported.into_iter().chain(own).collect(),
+    )?;
+
+    let mut lowered = Program {
+        structs: Vec::new(),
+        functions: Vec::new(),
+        body: Vec::new(),
+    };
+    for statement in &program.statements {
+        match statement {
+            Statement::TypeDef { name, .. } => {
+                lowered.structs.push(lowerer.structs[name].clone());
+            }
+            Statement::Function {
+                name,
+                receiver,
+                params,
+                return_type,
+                body,
+                ..
+            } => lowered.functions.push(lowerer.lower_function(
+                name,
+                &with_receiver(receiver, params),
+                return_type,
+                body,
+            )?),
+            Statement::Interface { .. }
+            | Statement::ConstDecl { .. }
+            | Statement::Import { .. }
+            | Statement::Export { .. }
+            | Statement::NativeBlock { .. }
+            | Statement::ExternBlock { .. } => {}
+            _ => lowered.body.extend(lowerer.lower_statement(statement)?),
+        }
+    }
+    Ok(lowered)
+}
+
+struct Lowerer {
+    signatures: HashMap<String, (Vec<Type>, Type)>, // Parameter and return types per function
+    parameters: HashMap<String, Vec<Parameter>>,    // For resolving named arguments and defaults
+    constants: HashMap<String, Constant>,           // Module-level `const`s, used by value
+    structs: HashMap<String, Struct>,
+    scopes: Vec<HashMap<String, Type>>,
+    return_type: Option<Type>, // Of the function being lowered
+    temp_count: usize,
+}
+
+impl Lowerer {
+    fn declare(
+        &mut self,
+        statements: &[Statement],
+        constants: Vec<Constant>,
+    ) -> Result<(), String> {
+        let mut signature = |name: &str, params: &[Parameter], return_type: &Option<Type>| {
+            let params = params.iter().map(|p| normalize(&p.param_type)).collect();
+            let return_type = return_type.as_ref().map_or_else(unit, normalize);
+            self.signatures
+                .insert(name.to_string(), (params, return_type));
+        };
+        for statement in statements {
+            if let Statement::Function {
+                name,
+                receiver,
+                params,
+                ..
+            } = statement
+            {
+                self.parameters
+                    .insert(name.clone(), with_receiver(receiver, params));
+            }
+        }
+        for statement in statements {
+            match statement {
+                Statement::Function {
+                    name,
+                    receiver,
+                    params,
+                    return_type,
+                    ..
+                } => signature(name, &with_receiver(receiver, params), return_type),
+                Statement::NativeBlock { functions, .. } => {
+                    for function in functions {
+                        signature(&function.name, &function.params, &function.return_type);
+                    }
+                }
+                Statement::ExternBlock { functions, .. } => {
+                    for function in functions {
+                        signature(&function.name, &function.params, &function.return_type);
+                    }
+                }
+                // Interface values are dispatched statically, so a method
+                // call on one is typed by the interface's signature
+                Statement::Interface { name, methods } => {
+                    for method in methods {
+                        let receiver = Some(Parameter {
+                            name: "self".to_string(),
+                            param_type: Type::Custom(name.clone()),
+                            default: None,
+                        });
+                        signature(
+                            &method_function_name(name, &method.name),
+                            &with_receiver(&receiver, &method.params),
+                            &method.return_type,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        for statement in statements {
+            if let Statement::TypeDef {
+                name,
+                type_params,
+                fields,
+                ..
+            } = statement
+            {
+                let fields = fields
+                    .iter()
+                    .map(|field| Field {
+                        name: field.name.clone(),
+                        field_type: normalize(&field.field_type),
+                    })
+                    .collect();
+                self.structs.insert(
+                    name.clone(),
+                    Struct {
+                        name: name.clone(),
+                        type_params: type_params.clone(),
+                        fields,
+                    },
+                );
+            }
+        }
+        for constant in constants {
+            self.constants.insert(constant.name.clone(), constant);
+        }
+        Ok(())
+    }
+
+    fn lower_function(
+        &mut self,
+        name: &str,
+        params: &[Parameter],
+        return_type: &Option<Type>,
+        body: &[Statement],
+    ) -> Result<Function, String> {
+        let return_type = return_type.as_ref().map_or_else(unit, normalize);
+        let scopes = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+        let enclosing_return = self.return_type.replace(return_type.clone());
+        for param in params {
+            self.bind(&param.name, normalize(&param.param_type));
+        }
+        let body = self.lower_block(body);
+        self.scopes = scopes;
+        self.return_type = enclosing_return;
+        Ok(Function {
+            name: name.to_string(),
+            params: params.to_vec(),
+            return_type,
+            body: body?,
+        })
+    }
+
+    fn bind(&mut self, name: &str, ty: Type) {
+        self.scopes
+            .last_mut()
+            .expect("there is always a scope")
+            .insert(name.to_string(), ty);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn temp(&mut self) -> String {
+        self.temp_count += 1;
+        format!("{}{}", TEMP_PREFIX, self.temp_count)
+    }
+
+    fn lower_block(&mut self, statements: &[Statement]) -> Result<Vec<Stmt>, String> {
+        self.scopes.push(HashMap::new());
+        let mut lowered = Vec::new();
+        let mut result = Ok(());
+        for statement in statements {
+            match self.lower_statement(statement) {
+                Ok(stmts) => lowered.extend(stmts),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.scopes.pop();
+        result.map(|_| lowered)
+    }
+
+    // Most statements lower to one Stmt; declarations lower to none
+    fn lower_statement(&mut self, statement: &Statement) -> Result<Vec<Stmt>, String> {
+        let stmt = match statement {
+            Statement::VarDecl {
+                name,
+                type_annotation,
+                value,
+            }
+            | Statement::ValDecl {
+                name,
+                type_annotation,
+                value,
+            } => {
+                let annotation = type_annotation.as_ref().map(normalize);
+                let value = self.lower_expression(value, annotation.as_ref())?;
+                let ty = annotation.unwrap_or_else(|| value.ty.clone());
+                self.bind(name, ty.clone());
+                Stmt::Let {
+                    name: name.clone(),
+                    ty,
+                    value,
+                    mutable: matches!(statement, Statement::VarDecl { .. }),
+                }
+            }
+            Statement::Assignment { variable, value } => {
+                let target = self
+                    .lookup(variable)
+                    .cloned()
+                    .ok_or_else(|| format!("Assignment to unknown variable '{}'", variable))?;
+                Stmt::Assign {
+                    name: variable.clone(),
+                    value: self.lower_expression(value, Some(&target))?,
+                }
+            }
+            Statement::Expression(expr) => Stmt::Expr(self.lower_expression(expr, None)?),
+            Statement::Panic { message, location } => {
+                self.lower_panic(location, "panic", message)?
+            }
+            // Interpreted programs always check, like debug builds
+            Statement::Assert {
+                condition,
+                message,
+                location,
+            } => Stmt::If {
+                condition: self.lower_expression(condition, Some(&Type::Bool))?,
+                then_body: Vec::new(),
+                else_body: vec![self.lower_panic(location, "assertion failed", message)?],
+            },
+            Statement::If {
+                condition,
+                then_body,
+                else_body,
+            } => Stmt::If {
+                condition: self.lower_expression(condition, Some(&Type::Bool))?,
+                then_body: self.lower_block(then_body)?,
+                else_body: self.lower_block(else_body.as_deref().unwrap_or_default())?,
+            },
+            Statement::IfPresent {
+                name,
+                value,
+                then_body,
+                else_body,
+            } => self.lower_if_present(name, value, then_body, else_body)?,
+            Statement::ForIn {
+                index,
+                variable,
+                iterable,
+                body,
+            } => match iterable {
+                Expression::Range {
+                    start,
+                    end,
+                    inclusive,
+                } => self.lower_range_loop(variable, start, end, *inclusive, body)?,
+                _ => self.lower_for_in(index.as_deref(), variable, iterable, body)?,
+            },
+            Statement::ForCondition { condition, body } => {
+                let condition = self.lower_expression(condition, Some(&Type::Bool))?;
+                let mut lowered = vec![exit_unless(condition)];
+                lowered.extend(self.lower_block(body)?);
+                Stmt::Loop(lowered)
+            }
+            Statement::DoWhile { body, condition } => {
+                let mut lowered = self.lower_block(body)?;
+                let condition = self.lower_expression(condition, Some(&Type::Bool))?;
+                lowered.push(exit_unless(condition));
+                Stmt::Loop(lowered)
+            }
+            Statement::ForLoop {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                self.scopes.push(HashMap::new());
+                let lowered = self.lower_for_loop(init, condition, update, body);
+                self.scopes.pop();
+                lowered?
+            }
+            Statement::Return(value) => {
+                let expected = self.return_type.clone();
+                Stmt::Return(
+                    value
+                        .as_ref()
+                        .map(|v| self.lower_expression(v, expected.as_ref()))
+                        .transpose()?,
+                )
+            }
+            Statement::Defer { body } => Stmt::Defer(self.lower_block(body)?),
+            Statement::Unsafe { body } => Stmt::Block(self.lower_block(body)?),
+            // Only gcc can assemble it, so programs using asm need the C backend
+            Statement::Asm { .. } => return Err(
+                "Inline asm needs the C backend; compile the program instead of interpreting it"
+                    .to_string(),
+            ),
+            Statement::Function { name, .. } => {
+                return Err(format!("Function '{}' must be declared at top level", name))
+            }
+            Statement::ConstDecl { name, .. } => {
+                return Err(format!("Constant '{}' must be declared at top level", name))
+            }
+            Statement::TypeDef { .. }
+            | Statement::Interface { .. }
+            | Statement::Import { .. }
+            | Statement::Export { .. }
+            | Statement::NativeBlock { .. }
+            | Statement::ExternBlock { .. } => return Ok(Vec::new()),
+        };
+        Ok(vec![stmt])
+    }
+
+    // The bolt_panic native, which stops the program like the C runtime's
+    // bolt_panic: `tests/app.bolt:3: assertion failed: message`
+    fn lower_panic(
+        &mut self,
+        location: &str,
+        kind: &str,
+        message: &Expression,
+    ) -> Result<Stmt, String> {
+        let text = |s: &str| Expr::new(ExprKind::String(s.to_string()), Type::String);
+        let message = self.lower_expression(message, Some(&Type::String))?;
+        Ok(Stmt::Expr(Expr::new(
+            ExprKind::Call {
+                function: "bolt_panic".to_string(),
+                args: vec![text(location), text(kind), message],
+            },
+            unit(),
+        )))
+    }
+
+    // if (val name := value) { then } else { else }
+    //   => { val tmp = value; if present(tmp) { val name = unwrap(tmp); then } else { else } }
+    fn lower_if_present(
+        &mut self,
+        name: &str,
+        value: &Expression,
+        then_body: &[Statement],
+        else_body: &Option<Vec<Statement>>,
+    ) -> Result<Stmt, String> {
+        let value = self.lower_expression(value, None)?;
+        let inner = optional_element(&value.ty).ok_or_else(|| {
+            format!(
+                "`if (val {} := ...)` needs an optional, not {}",
+                name, value.ty
+            )
+        })?;
+        let optional = self.temp();
+        let holder = Expr::local(&optional, value.ty.clone());
+
+        self.scopes.push(HashMap::new());
+        self.bind(name, inner.clone());
+        let then_body = self.lower_block(then_body);
+        self.scopes.pop();
+        let mut then_lowered = vec![Stmt::Let {
+            name: name.to_string(),
+            ty: inner.clone(),
+            value: Expr::new(ExprKind::Unwrap(Box::new(holder.clone())), inner),
+            mutable: false,
+        }];
+        then_lowered.extend(then_body?);
+
+        Ok(Stmt::Block(vec![
+            Stmt::Let {
+                name: optional,
+                ty: value.ty.clone(),
+                value,
+                mutable: false,
+            },
+            Stmt::If {
+                condition: Expr::new(ExprKind::IsPresent(Box::new(holder)), Type::Bool),
+                then_body: then_lowered,
+                else_body: self.lower_block(else_body.as_deref().unwrap_or_default())?,
+            },
+        ]))
+    }
+
+    // for i in start..end { body }
+    //   => { var i = start; val tmp = end; loop { if !(i < tmp) break; body; i = i + 1 } }
+    fn lower_range_loop(
+        &mut self,
+        variable: &str,
+        start: &Expression,
+        end: &Expression,
+        inclusive: bool,
+        body: &[Statement],
+    ) -> Result<Stmt, String> {
+        let start = self.lower_expression(start, None)?;
+        let end = self.lower_expression(end, Some(&start.ty))?;
+        let counter_type = match (&start.ty, &end.ty) {
+            (sized @ Type::SizedInt(_), _) | (_, sized @ Type::SizedInt(_)) => sized.clone(),
+            _ => Type::Integer,
+        };
+        let limit = self.temp();
+        let counter = Expr::local(variable, counter_type.clone());
+        let operator = if inclusive {
+            BinaryOperator::LessEqual
+        } else {
+            BinaryOperator::Less
+        };
+
+        self.scopes.push(HashMap::new());
+        self.bind(variable, counter_type.clone());
+        let body = self.lower_block(body);
+        self.scopes.pop();
+        let mut iteration = vec![exit_unless(Expr::binary(
+            operator,
+            counter.clone(),
+            Expr::local(&limit, counter_type.clone()),
+            Type::Bool,
+        ))];
+        iteration.extend(body?);
+        iteration.push(increment(variable, counter_type.clone()));
+
+        Ok(Stmt::Block(vec![
+            Stmt::Let {
+                name: variable.to_string(),
+                ty: counter_type.clone(),
+                value: start,
+                mutable: true,
+            },
+            Stmt::Let {
+                name: limit,
+                ty: counter_type,
+                value: end,
+                mutable: false,
+            },
+            Stmt::Loop(iteration),
+        ]))
+    }
+
+    // for item in items { body }
+    //   => { val tmp1 = items; var tmp2 = 0;
+    //        loop { if !(tmp2 < tmp1.length) break; val item = tmp1[tmp2]; body; tmp2 = tmp2 + 1 } }
+    fn lower_for_in(
+        &mut self,
+        index_variable: Option<&str>,
+        variable: &str,
+        iterable: &Expression,
+        body: &[Statement],
+    ) -> Result<Stmt, String> {
+        let iterable = self.lower_expression(iterable, None)?;
+        let element = element_type(&iterable.ty)
+            .ok_or_else(|| format!("Can't iterate over a value of type {}", iterable.ty))?;
+        let items = self.temp();
+        let index = self.temp();
+        let items_expr = Expr::local(&items, iterable.ty.clone());
+        let index_expr = Expr::local(&index, Type::Integer);
+
+        self.scopes.push(HashMap::new());
+        if let Some(index_variable) = index_variable {
+            self.bind(index_variable, Type::Integer);
+        }
+        self.bind(variable, element.clone());
+        let body = self.lower_block(body);
+        self.scopes.pop();
+        let length = Expr::new(
+            ExprKind::Field {
+                object: Box::new(items_expr.clone()),
+                field: "length".to_string(),
+            },
+            Type::Integer,
+        );
+        let mut iteration = vec![
+            exit_unless(Expr::binary(
+                BinaryOperator::Less,
+                index_expr.clone(),
+                length,
+                Type::Bool,
+            )),
+            Stmt::Let {
+                name: variable.to_string(),
+                ty: element.clone(),
+                value: Expr::new(
+                    ExprKind::Index {
+                        array: Box::new(items_expr),
+                        index: Box::new(index_expr.clone()),
+                    },
+                    element,
+                ),
+                mutable: false,
+            },
+        ];
+        // `for i, item in items`: i is a copy of the counter
+        if let Some(index_variable) = index_variable {
+            iteration.push(Stmt::Let {
+                name: index_variable.to_string(),
+                ty: Type::Integer,
+                value: index_expr,
+                mutable: false,
+            });
+        }
+        iteration.extend(body?);
+        iteration.push(increment(&index, Type::Integer));
+
+        Ok(Stmt::Block(vec![
+            Stmt::Let {
+                name: items,
+                ty: iterable.ty.clone(),
+                value: iterable,
+                mutable: false,
+            },
+            Stmt::Let {
+                name: index,
+                ty: Type::Integer,
+                value: Expr::new(ExprKind::Integer(0), Type::Integer),
+                mutable: true,
+            },
+            Stmt::Loop(iteration),
+        ]))
+    }
+
+    // for (init; condition; update) { body } => { init; loop { if !condition break; body; update } }
+    fn lower_for_loop(
+        &mut self,
+        init: &Option<Box<Statement>>,
+        condition: &Option<Expression>,
+        update: &Option<Box<Statement>>,
+        body: &[Statement],
+    ) -> Result<Stmt, String> {
+        let mut lowered = Vec::new();
+        if let Some(init) = init {
+            lowered.extend(self.lower_statement(init)?);
+        }
+        let mut iteration = Vec::new();
+        if let Some(condition) = condition {
+            iteration.push(exit_unless(
+                self.lower_expression(condition, Some(&Type::Bool))?,
+            ));
+        }
+        iteration.extend(self.lower_block(body)?);
+        if let Some(update) = update {
+            iteration.extend(self.lower_statement(update)?);
+        }
+        lowered.push(Stmt::Loop(iteration));
+        Ok(Stmt::Block(lowered))
+    }
+
+    // `expected` is the type the context wants, used for `none`, empty
+    // arrays and integer literals given a sized type
+    fn lower_expression(
+        &mut self,
+        expr: &Expression,
+        expected: Option<&Type>,
+    ) -> Result<Expr, String> {
+        // -2147483648 is an Integer even though 2147483648 isn't
+        if let Expression::UnaryOp {
+            operator: UnaryOperator::Negate,
+            operand,
+        } = expr
+        {
+            if let Expression::IntegerLiteral(n) = **operand {
+                return self.lower_expression(&Expression::IntegerLiteral(-n), expected);
+            }
+        }
+        let lowered = match expr {
+            Expression::IntegerLiteral(n) => match expected {
+                Some(sized @ Type::SizedInt(_)) => Expr::new(ExprKind::Integer(*n), sized.clone()),
+                Some(Type::Float) => Expr::new(ExprKind::Float(*n as f64), Type::Float),
+                _ => Expr::new(ExprKind::Integer(*n), Type::Integer),
+            },
+            Expression::FloatLiteral(x) => Expr::new(ExprKind::Float(*x), Type::Float),
+            Expression::BoolLiteral(b) => Expr::new(ExprKind::Bool(*b), Type::Bool),
+            Expression::StringLiteral(s) => Expr::new(ExprKind::String(s.clone()), Type::String),
+            Expression::NoneLiteral => match expected {
+                Some(t) if optional_element(t).is_some() => Expr::new(ExprKind::None, t.clone()),
+                // The null pointer
+                Some(pointer @ Type::Pointer(_)) => Expr::new(ExprKind::None, pointer.clone()),
+                _ => return Err("`none` needs an optional type from its context".to_string()),
+            },
+            Expression::ArrayLiteral(elements) => {
+                let expected_element = expected.and_then(element_type);
+                let elements = elements
+                    .iter()
+                    .map(|e| self.lower_expression(e, expected_element.as_ref()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let element = match (elements.first(), expected_element) {
+                    (_, Some(t)) => t,
+                    (Some(first), None) => first.ty.clone(),
+                    // As in C codegen, an unannotated empty array holds integers
+                    (None, None) => Type::Integer,
+                };
+                Expr::new(ExprKind::Array(elements), Type::Array(Box::new(element)))
+            }
+            Expression::TupleLiteral(elements) => {
+                let expected_elements = match expected {
+                    Some(Type::Tuple(types)) if types.len() == elements.len() => Some(types),
+                    _ => None,
+                };
+                let elements = elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| self.lower_expression(e, expected_elements.map(|t| &t[i])))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let ty = Type::Tuple(elements.iter().map(|e| e.ty.clone()).collect());
+                Expr::new(ExprKind::Tuple(elements), ty)
+            }
+            Expression::MapLiteral { type_args, entries } => {
+                let declared = match (type_args, expected.and_then(map_types)) {
+                    (Some(args), _) => {
+                        let args: Vec<Type> = args.iter().map(normalize).collect();
+                        Some((args[0].clone(), args[1].clone()))
+                    }
+                    (None, Some((key, value))) => Some((key.clone(), value.clone())),
+                    (None, None) => None,
+                };
+                let mut lowered = Vec::new();
+                for (key, value) in entries {
+                    let key = self.lower_expression(key, declared.as_ref().map(|(k, _)| k))?;
+                    let value = self.lower_expression(value, declared.as_ref().map(|(_, v)| v))?;
+                    lowered.push((key, value));
+                }
+                let (key, value) = match (declared, lowered.first()) {
+                    (Some(types), _) => types,
+                    (None, Some((key, value))) => (key.ty.clone(), value.ty.clone()),
+                    (None, None) => {
+                        return Err(
+                            "An empty map needs a Map[K, V] type from its context".to_string()
+                        )
+                    }
+                };
+                Expr::new(
+                    ExprKind::Map(lowered),
+                    Type::Generic {
+                        name: "Map".to_string(),
+                        type_params: vec![key, value],
+                    },
+                )
+            }
+            Expression::Identifier(name) => match self.lookup(name) {
+                Some(ty) => Expr::local(name, ty.clone()),
+                None if self.constants.contains_key(name) => {
+                    let constant = &self.constants[name];
+                    let (value, ty) = (constant.value.clone(), constant.const_type.clone());
+                    self.lower_expression(&value.into_expression(), Some(&ty))?
+                }
+                None => match self.signatures.get(name) {
+                    Some((params, return_type)) => Expr::new(
+                        ExprKind::Function(name.clone()),
+                        Type::Function {
+                            params: params.clone(),
+                            return_type: Box::new(return_type.clone()),
+                        },
+                    ),
+                    None => return Err(format!("Unknown name '{}'", name)),
+                },
+            },
+            Expression::FunctionCall { name, args }
+            | Expression::NamespacedFunctionCall {
+                function: name,
+                args,
+                ..
+            } => self.lower_call(name, args)?,
+            // person.greet(x) => Person_greet(person, x)
+            Expression::MethodCall {
+                object,
+                method,
+                args,
+            } => {
+                let receiver = self.lower_expression(object, None)?;
+                if matches!(receiver.ty, Type::Array(_)) && ARRAY_METHODS.contains(&method.as_str())
+                {
+                    return self.lower_array_method(receiver, method, args);
+                }
+                let Type::Custom(type_name) = &receiver.ty else {
+                    return Err(format!("{} has no method '{}'", receiver.ty, method));
+                };
+                let function = method_function_name(type_name, method);
+                let all_args: Vec<Expression> = std::iter::once((**object).clone())
+                    .chain(args.iter().cloned())
+                    .collect();
+                self.lower_call(&function, &all_args)?
+            }
+            Expression::BinaryOp {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.lower_expression(left, None)?;
+                let right = self.lower_expression(right, Some(&left.ty))?;
+                // "count: " + n converts n the way interpolation does
+                if *operator == BinaryOperator::Add
+                    && (left.ty == Type::String || right.ty == Type::String)
+                {
+                    return Ok(Expr::binary(
+                        BinaryOperator::Add,
+                        to_string(left),
+                        to_string(right),
+                        Type::String,
+                    ));
+                }
+                let ty = binary_type(operator, &left.ty, &right.ty);
+                Expr::binary(operator.clone(), left, right, ty)
+            }
+            Expression::UnaryOp { operator, operand } => {
+                let operand = self.lower_expression(operand, expected)?;
+                let ty = match operator {
+                    UnaryOperator::Not => Type::Bool,
+                    UnaryOperator::Negate => operand.ty.clone(),
+                };
+                Expr::new(
+                    ExprKind::Unary {
+                        operator: operator.clone(),
+                        operand: Box::new(operand),
+                    },
+                    ty,
+                )
+            }
+            Expression::StructLiteral {
+                type_name,
+                type_args,
+                fields,
+            } => {
+                let ty = match type_args {
+                    Some(args) => normalize(&Type::Generic {
+                        name: type_name.clone(),
+                        type_params: args.clone(),
+                    }),
+                    None => Type::Custom(type_name.clone()),
+                };
+                let declared = self.struct_fields(&ty).unwrap_or_default();
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        let expected = declared
+                            .iter()
+                            .find(|f| f.name == field.name)
+                            .map(|f| f.field_type.clone());
+                        Ok((
+                            field.name.clone(),
+                            self.lower_expression(&field.value, expected.as_ref())?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Expr::new(ExprKind::Struct(fields), ty)
+            }
+            Expression::FieldAccess { object, field } => {
+                let object = self.lower_expression(object, None)?;
+                let ty = self.field_type(&object.ty, field).ok_or_else(|| {
+                    format!("A value of type {} has no field '{}'", object.ty, field)
+                })?;
+                Expr::new(
+                    ExprKind::Field {
+                        object: Box::new(object),
+                        field: field.clone(),
+                    },
+                    ty,
+                )
+            }
+            Expression::OptionalFieldAccess { object, field } => {
+                let object = self.lower_expression(object, None)?;
+                let field_type = optional_element(&object.ty)
+                    .and_then(|inner| self.field_type(&inner, field))
+                    .ok_or_else(|| {
+                        format!("`?.{}` needs an optional struct, not {}", field, object.ty)
+                    })?;
+                let ty = if optional_element(&field_type).is_some() {
+                    field_type
+                } else {
+                    Type::Generic {
+                        name: "Optional".to_string(),
+                        type_params: vec![field_type],
+                    }
+                };
+                Expr::new(
+                    ExprKind::OptionalField {
+                        object: Box::new(object),
+                        field: field.clone(),
+                    },
+                    ty,
+                )
+            }
+            Expression::Coalesce { value, fallback } => {
+                let value = self.lower_expression(value, None)?;
+                let inner = match &value.ty {
+                    Type::Pointer(_) => value.ty.clone(),
+                    ty => optional_element(ty).ok_or_else(|| {
+                        format!("`??` needs an optional or a pointer, not {}", ty)
+                    })?,
+                };
+                // `a ?? b ?? 0` tries b while the result can still be absent
+                let fallback = match self.lower_expression(fallback, Some(&value.ty))? {
+                    fallback if fallback.ty == value.ty => fallback,
+                    _ => self.lower_expression(fallback, Some(&inner))?,
+                };
+                let ty = fallback.ty.clone();
+                Expr::new(
+                    ExprKind::Coalesce {
+                        value: Box::new(value),
+                        fallback: Box::new(fallback),
+                    },
+                    ty,
+                )
+            }
+            // items[1..3] => checkedSlice(items, 1, 3), s[1..3] => string_slice(s, 1, 3)
+            Expression::Slice { object, start, end } => {
+                let object = self.lower_expression(object, None)?;
+                let (function, ty) = match element_type(&object.ty) {
+                    Some(element) => (
+                        "checkedSlice",
+                        Type::Generic {
+                            name: "Slice".to_string(),
+                            type_params: vec![element],
+                        },
+                    ),
+                    None if object.ty == Type::String => ("string_slice", Type::String),
+                    None => {
+                        return Err(format!(
+                            "Only arrays and strings can be sliced, not {}",
+                            object.ty
+                        ))
+                    }
+                };
+                let start = self.lower_expression(start, Some(&Type::Integer))?;
+                let end = self.lower_expression(end, Some(&Type::Integer))?;
+                Expr::new(
+                    ExprKind::Call {
+                        function: function.to_string(),
+                        args: vec![object, start, end],
+                    },
+                    ty,
+                )
+            }
+            Expression::ArrayAccess { array, index } => {
+                let array = self.lower_expression(array, None)?;
+                let pointee = match &array.ty {
+                    Type::Pointer(inner) => Some((**inner).clone()),
+                    _ => None,
+                };
+                let ty = element_type(&array.ty)
+                    .or(pointee)
+                    .ok_or_else(|| format!("Can't index a value of type {}", array.ty))?;
+                let index = self.lower_expression(index, Some(&Type::Integer))?;
+                Expr::new(
+                    ExprKind::Index {
+                        array: Box::new(array),
+                        index: Box::new(index),
+                    },
+                    ty,
+                )
+            }
+            Expression::AddressOf { operand } => {
+                let operand = self.lower_expression(operand, None)?;
+                let ty = Type::Pointer(Box::new(operand.ty.clone()));
+                Expr::new(ExprKind::AddressOf(Box::new(operand)), ty)
+            }
+            Expression::Dereference { operand } => {
+                let operand = self.lower_expression(operand, None)?;
+                let ty = match &operand.ty {
+                    Type::Pointer(inner) => (**inner).clone(),
+                    other => return Err(format!("Can't dereference a value of type {}", other)),
+                };
+                Expr::new(ExprKind::Deref(Box::new(operand)), ty)
+            }
+            Expression::Cast { value, target } => {
+                // `5000000000 as Int64` is an Int64 literal
+                let literal = matches!(**value, Expression::IntegerLiteral(_));
+                let value = self.lower_expression(value, literal.then_some(target))?;
+                Expr::new(ExprKind::Cast(Box::new(value)), target.clone())
+            }
+            // Natives that only see the count (and the old block), so the
+            // interpreter's blocks hold no values until they are written
+            Expression::Allocate {
+                element,
+                block,
+                count,
+            } => {
+                let ty = Type::pointer(normalize(element));
+                let count = self.lower_expression(count, None)?;
+                let (function, args) = match block {
+                    Some(block) => ("realloc", vec![self.lower_expression(block, None)?, count]),
+                    None => ("alloc", vec![count]),
+                };
+                Expr::new(
+                    ExprKind::Call {
+                        function: function.to_string(),
+                        args,
+                    },
+                    ty,
+                )
+            }
+            Expression::Grouping(inner) => self.lower_expression(inner, expected)?,
+            Expression::NamedArgument { name, .. } => {
+                return Err(format!(
+                    "Named argument '{}' is only allowed in a call",
+                    name
+                ))
+            }
+            Expression::StringInterpolation(parts) => self.lower_interpolation(parts)?,
+            Expression::Lambda {
+                params,
+                return_type,
+                body,
+            } => {
+                // Lambdas can't capture locals, so only their parameters are in scope
+                let scopes = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+                for param in params {
+                    self.bind(&param.name, normalize(&param.param_type));
+                }
+                let annotation = return_type.as_ref().map(normalize);
+                let body = self.lower_expression(body, annotation.as_ref());
+                self.scopes = scopes;
+                let body = body?;
+                let ty = Type::Function {
+                    params: params.iter().map(|p| normalize(&p.param_type)).collect(),
+                    return_type: Box::new(annotation.unwrap_or_else(|| body.ty.clone())),
+                };
+                Expr::new(
+                    ExprKind::Lambda {
+                        params: params.clone(),
+                        body: Box::new(body),
+                    },
+                    ty,
+                )
+            }
+            Expression::Range { .. } => {
+                return Err("A range can only be iterated with `for ... in`".to_string())
+            }
+            Expression::If {
+                condition,
+                then_value,
+                else_value,
+            } => {
+                let condition = self.lower_expression(condition, Some(&Type::Bool))?;
+                let then_value = self.lower_expression(then_value, expected)?;
+                let else_value = self.lower_expression(else_value, Some(&then_value.ty))?;
+                // Mixed numbers widen, as they do in arithmetic
+                let ty = match (&then_value.ty, &else_value.ty) {
+                    (Type::Integer, Type::Float) => Type::Float,
+                    (then_type, _) => then_type.clone(),
+                };
+                Expr::new(
+                    ExprKind::Select {
+                        condition: Box::new(condition),
+                        then_value: Box::new(then_value),
+                        else_value: Box::new(else_value),
+                    },
+                    ty,
+                )
+            }
+        };
+        // A plain value where an optional is expected is wrapped in one
+        match expected {
+            Some(optional) if optional_element(optional).is_some() => {
+                if optional_element(&lowered.ty).is_none() {
+                    return Ok(Expr::new(
+                        ExprKind::Wrap(Box::new(lowered)),
+                        optional.clone(),
+                    ));
+                }
+                Ok(lowered)
+            }
+            _ => Ok(lowered),
+        }
+    }
+
+    // Calls resolve their return type from the callee's signature, binding
+    // the type variables of generic natives (`map(arr: Array[T], f: (T) -> U): Array[U]`)
+    // from the argument types
+    // numbers.push(4) => Array_push(&numbers, 4); the native updates the
+    // variable, so push, pop and insert are seen by later uses
+    fn lower_array_method(
+        &mut self,
+        receiver: Expr,
+        method: &str,
+        args: &[Expression],
+    ) -> Result<Expr, String> {
+        let element = element_type(&receiver.ty).unwrap_or(Type::Integer);
+        let params = match method {
+            "push" => vec![element.clone()],
+            "insert" => vec![Type::Integer, element.clone()],
+            "get" => vec![Type::Integer],
+            _ => Vec::new(),
+        };
+        if args.len() != params.len() {
+            return Err(format!(
+                "{} expects {} argument(s), got {}",
+                method,
+                params.len(),
+                args.len()
+            ));
+        }
+        let pointer = Type::Pointer(Box::new(receiver.ty.clone()));
+        let mut lowered = vec![Expr::new(ExprKind::AddressOf(Box::new(receiver)), pointer)];
+        for (arg, param) in args.iter().zip(&params) {
+            lowered.push(self.lower_expression(arg, Some(param))?);
+        }
+        let ty = match method {
+            "pop" | "get" => Type::Generic {
+                name: "Optional".to_string(),
+                type_params: vec![element],
+            },
+            _ => unit(),
+        };
+        Ok(Expr::new(
+            ExprKind::Call {
+                function: method_function_name("Array", method),
+                args: lowered,
+            },
+            ty,
+        ))
+    }
+
+    fn lower_call(&mut self, name: &str, args: &[Expression]) -> Result<Expr, String> {
+        let args = match self.parameters.get(name) {
+            Some(params) if self.lookup(name).is_none() => {
+                call_arguments(name, params, args.to_vec())?
+            }
+            _ if args
+                .iter()
+                .any(|arg| matches!(arg, Expression::NamedArgument { .. })) =>
+            {
+                return Err(format!(
+                    "Named arguments can only be passed to Bolt functions, not '{}'",
+                    name
+                ))
+            }
+            _ => args.to_vec(),
+        };
+        let args = args.as_slice();
+        let signature = match self.lookup(name) {
+            Some(Type::Function {
+                params,
+                return_type,
+            }) => Some((params.clone(), (**return_type).clone())),
+            _ => self.signatures.get(name).cloned(),
+        };
+        let Some((params, return_type)) = signature else {
+            let args = args
+                .iter()
+                .map(|arg| self.lower_expression(arg, None))
+                .collect::<Result<Vec<_>, _>>()?;
+            // clone(x) copies x, so it has x's type
+            let ty = match args.as_slice() {
+                [value] if name == "clone" => value.ty.clone(),
+                _ => builtin_return_type(name)
+                    .ok_or_else(|| format!("Unknown function '{}'", name))?,
+            };
+            return Ok(Expr::new(
+                ExprKind::Call {
+                    function: name.to_string(),
+                    args,
+                },
+                ty,
+            ));
+        };
+
+        let mut bindings = HashMap::new();
+        let mut lowered = Vec::new();
+        for (i, arg) in args.iter().enumerate() {
+            let param = params.get(i).map(|p| self.substitute(p, &bindings));
+            let expected = param.as_ref().filter(|p| !self.is_open(p));
+            let arg = self.lower_expression(arg, expected)?;
+            if let Some(param) = &param {
+                self.unify(param, &arg.ty, &mut bindings);
+            }
+            lowered.push(arg);
+        }
+        Ok(Expr::new(
+            ExprKind::Call {
+                function: name.to_string(),
+                args: lowered,
+            },
+            self.substitute(&return_type, &bindings),
+        ))
+    }
+
+    // "a ${b} c" => "a " + toString(b) + " c"
+    fn lower_interpolation(&mut self, parts: &[InterpolationPart]) -> Result<Expr, String> {
+        let mut result: Option<Expr> = None;
+        for part in parts {
+            let piece = match part {
+                InterpolationPart::Literal(text) => {
+                    Expr::new(ExprKind::String(text.clone()), Type::String)
+                }
+                InterpolationPart::Expression(expr) => {
+                    to_string(self.lower_expression(expr, None)?)
+                }
+            };
+            result = Some(match result {
+                Some(left) => Expr::binary(BinaryOperator::Add, left, piece, Type::String),
+                None => piece,
+            });
+        }
+        Ok(result.unwrap_or_else(|| Expr::new(ExprKind::String(String::new()), Type::String)))
+    }
+
+    // A name in a native signature that isn't a declared type, like the T
+    // of `sort(arr: Array[T])`
+    fn is_type_variable(&self, name: &str) -> bool {
+        !self.structs.contains_key(name) && name != "StrView"
+    }
+
+    fn is_open(&self, t: &Type) -> bool {
+        match t {
+            Type::TypeParameter(_) => true,
+            Type::Custom(name) => self.is_type_variable(name),
+            Type::Array(inner) | Type::Pointer(inner) => self.is_open(inner),
+            Type::Generic { type_params, .. } | Type::Tuple(type_params) => {
+                type_params.iter().any(|t| self.is_open(t))
+            }
+            Type::Function {
+                params,
+                return_type,
+            } => params.iter().any(|t| self.is_open(t)) || self.is_open(return_type),
+            _ => false,
+        }
+    }
+
+    fn unify(&self, param: &Type, arg: &Type, bindings: &mut HashMap<String, Type>) {
+        match (param, arg) {
+            (Type::TypeParameter(name), _) => {
+                bindings.entry(name.clone()).or_insert_with(|| arg.clone());
+            }
+            (Type::Custom(name), _) if self.is_type_variable(name) => {
+                bindings.entry(name.clone()).or_insert_with(|| arg.clone());
+            }
+            (Type::Array(p), Type::Array(a)) | (Type::Pointer(p), Type::Pointer(a)) => {
+                self.unify(p, a, bindings)
+            }
+            (
+                Type::Generic {
+                    name: p_name,
+                    type_params: p,
+                },
+                Type::Generic {
+                    name: a_name,
+                    type_params: a,
+                },
+            ) if p_name == a_name => {
+                for (p, a) in p.iter().zip(a) {
+                    self.unify(p, a, bindings);
+                }
+            }
+            (Type::Tuple(p), Type::Tuple(a)) => {
+                for (p, a) in p.iter().zip(a) {
+                    self.unify(p, a, bindings);
+                }
+            }
+            (
+                Type::Function {
+                    params: p_params,
+                    return_type: p_return,
+                },
+                Type::Function {
+                    params: a_params,
+                    return_type: a_return,
+                },
+            ) => {
+                for (p, a) in p_params.iter().zip(a_params) {
+                    self.unify(p, a, bindings);
+                }
+                self.unify(p_return, a_return, bindings);
+            }
+            _ => {}
+        }
+    }
+
+    fn substitute(&self, t: &Type, bindings: &HashMap<String, Type>) -> Type {
+        match t {
+            Type::TypeParameter(name) | Type::Custom(name) if bindings.contains_key(name) => {
+                bindings[name].clone()
+            }
+            Type::Array(inner) => Type::Array(Box::new(self.substitute(inner, bindings))),
+            Type::Pointer(inner) => Type::Pointer(Box::new(self.substitute(inner, bindings))),
+            Type::Generic { name, type_params } => Type::Generic {
+                name: name.clone(),
+                type_params: type_params
+                    .iter()
+                    .map(|t| self.substitute(t, bindings))
+                    .collect(),
+            },
+            Type::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter()
+                    .map(|t| self.substitute(t, bindings))
+                    .collect(),
+            ),
+            Type::Function {
+                params,
+                return_type,
+            } => Type::Function {
+                params: params
+                    .iter()
+                    .map(|t| self.substitute(t, bindings))
+                    .collect(),
+                return_type: Box::new(self.substitute(return_type, bindings)),
+            },
+            other => other.clone(),
+        }
+    }
+
+    // Fields of a struct type, with a generic struct's parameters substituted
+    fn struct_fields(&self, t: &Type) -> Option<Vec<Field>> {
+        let (name, args) = match t {
+            Type::Custom(name) => (name, &[][..]),
+            Type::Generic { name, type_params } => (name, &type_params[..]),
+            _ => return None,
+        };
+        let definition = self.structs.get(name)?;
+        let bindings: HashMap<String, Type> = definition
+            .type_params
+            .iter()
+            .cloned()
+            .zip(args.iter().cloned())
+            .collect();
+        Some(
+            definition
+                .fields
+                .iter()
+                .map(|field| Field {
+                    name: field.name.clone(),
+                    field_type: self.substitute(&field.field_type, &bindings),
+                })
+                .collect(),
+        )
+    }
+
+    fn field_type(&self, t: &Type, field: &str) -> Option<Type> {
+        if let (Type::Tuple(elements), Ok(index)) = (t, field.parse::<usize>()) {
+            return elements.get(index).cloned();
+        }
+        if let Some(found) = self
+            .struct_fields(t)
+            .and_then(|fields| fields.into_iter().find(|f| f.name == field))
+        {
+            return Some(found.field_type);
+        }
+        // Arrays are { data, length, capacity } structs
+        match (element_type(t), field) {
+            (Some(element), "data") => Some(Type::Pointer(Box::new(element))),
+            (Some(_), "length" | "capacity") => Some(Type::Integer),
+            (None, "length") if is_string_like(t) => Some(Type::Integer),
+            (None, "length") if map_types(t).is_some() => Some(Type::Integer),
+            _ => None,
+        }
+    }
+}
+
+// A method's receiver is its first parameter in the IR
+fn with_receiver(receiver: &Option<Parameter>, params: &[Parameter]) -> Vec<Parameter> {
+    receiver.iter().chain(params).cloned().collect()
+}
+
+// `if !condition { break }`, the exit test at the top of a lowered loop
+fn exit_unless(condition: Expr) -> Stmt {
+    Stmt::If {
+        condition: condition.not(),
+        then_body: vec![Stmt::Break],
+        else_body: Vec::new(),
+    }
+}
+
+// `name = name + 1`
+fn increment(name: &str, ty: Type) -> Stmt {
+    let one = Expr::new(ExprKind::Integer(1), ty.clone());
+    Stmt::Assign {
+        name: name.to_string(),
+        value: Expr::binary(BinaryOperator::Add, Expr::local(name, ty.clone()), one, ty),
+    }
+}
+
+// `Array[T]` and `[]T` are the same type; the IR only uses the latter
+fn normalize(t: &Type) -> Type {
+    match t {
+        Type::Generic { name, type_params } if name == "Array" && type_params.len() == 1 => {
+            Type::Array(Box::new(normalize(&type_params[0])))
+        }
+        Type::Generic { name, type_params } => Type::Generic {
+            name: name.clone(),
+            type_params: type_params.iter().map(normalize).collect(),
+        },
+        Type::Array(inner) => Type::Array(Box::new(normalize(inner))),
+        Type::Pointer(inner) => Type::Pointer(Box::new(normalize(inner))),
+        Type::Tuple(elements) => Type::Tuple(elements.iter().map(normalize).collect()),
+        Type::Function {
+            params,
+            return_type,
+        } => Type::Function {
+            params: params.iter().map(normalize).collect(),
+            return_type: Box::new(normalize(return_type)),
+        },
+        other => other.clone(),
+    }
+}
+
+fn optional_element(t: &Type) -> Option<Type> {
+    match t {
+        Type::Generic { name, type_params } if name == "Optional" && type_params.len() == 1 => {
+            Some(type_params[0].clone())
+        }
+        _ => None,
+    }
+}
+
+// Element type of something that indexes and iterates like an array
+fn element_type(t: &Type) -> Option<Type> {
+    match t {
+        Type::Array(element) => Some((**element).clone()),
+        Type::Generic { name, type_params } if name == "Slice" && type_params.len() == 1 => {
+            Some(type_params[0].clone())
+        }
+        _ => None,
+    }
+}
+
+// A value as a String, through toString unless it already is one
+fn to_string(value: Expr) -> Expr {
+    match value.ty {
+        Type::String => value,
+        _ => Expr::new(
+            ExprKind::Call {
+                function: "toString".to_string(),
+                args: vec![value],
+            },
+            Type::String,
+        ),
+    }
+}
+
+fn is_string_like(t: &Type) -> bool {
+    matches!(t, Type::String) || matches!(t, Type::Custom(name) if name == "StrView")
+}
+
+fn binary_type(operator: &BinaryOperator, left: &Type, right: &Type) -> Type {
+    match operator {
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::Less
+        | BinaryOperator::LessEqual
+        | BinaryOperator::Greater
+        | BinaryOperator::GreaterEqual
+        | BinaryOperator::And
+        | BinaryOperator::Or => Type::Bool,
+        BinaryOperator::Add if *left == Type::String || *right == Type::String => Type::String,
+        BinaryOperator::Subtract
+            if matches!((left, right), (Type::Pointer(_), Type::Pointer(_))) =>
+        {
+            Type::Integer
+        }
+        _ => match (left, right) {
+            (Type::Float, _) | (_, Type::Float) => Type::Float,
+            (sized @ Type::SizedInt(_), _) | (_, sized @ Type::SizedInt(_)) => sized.clone(),
+            (pointer @ Type::Pointer(_), _) => pointer.clone(),
+            _ => Type::Integer,
+        },
+    }
+}
+
+// Builtins the C runtime provides without a Bolt declaration
+fn builtin_return_type(name: &str) -> Option<Type> {
+    match name {
+        "toString" | "readFile" | "concat" | "trim" | "getenv" | "format" => Some(Type::String),
+        "writeFile" | "appendFile" | "fileExists" | "deleteFile" | "contains" => Some(Type::Bool),
+        "length" | "indexOf" | "compare" => Some(Type::Integer),
+        "getArgs" => Some(Type::Array(Box::new(Type::String))),
+        "free" | "exit" => Some(unit()),
+        _ if PRINT_FUNCTIONS.contains(&name) || FORMAT_FUNCTIONS.contains(&name) => Some(unit()),
+        _ => None,
+    }
+}
+
+fn operator_symbol(operator: &BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Add => "+",
+        BinaryOperator::Subtract => "-",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::Divide => "/",
+        BinaryOperator::Modulo => "%",
+        BinaryOperator::Equal => "==",
+        BinaryOperator::NotEqual => "!=",
+        BinaryOperator::Less => "<",
+        BinaryOperator::LessEqual => "<=",
+        BinaryOperator::Greater => ">",
+        BinaryOperator::GreaterEqual => ">=",
+        BinaryOperator::And => "&&",
+        BinaryOperator::Or => "||",
+    }
+}
+
+fn parameter_list(params: &[Parameter]) -> String {
+    params
+        .iter()
+        .map(|p| format!("{}: {}", p.name, normalize(&p.param_type)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// The IR prints in a Bolt-like syntax, with every declaration's type spelled out
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for definition in &self.structs {
+            let fields: Vec<String> = definition
+                .fields
+                .iter()
+                .map(|field| format!("{}: {}", field.name, field.field_type))
+                .collect();
+            writeln!(f, "type {} = {{ {} }}", definition.name, fields.join(", "))?;
+        }
+        for function in &self.functions {
+            writeln!(
+                f,
+                "fun {}({}): {} {{",
+                function.name,
+                parameter_list(&function.params),
+                function.return_type
+            )?;
+            write_block(f, &function.body, 1)?;
+            writeln!(f, "}}")?;
+        }
+        write_block(f, &self.body, 0)
+    }
+}
+
+fn write_block(f: &mut fmt::Formatter, stmts: &[Stmt], depth: usize) -> fmt::Result {
+    for stmt in stmts {
+        write_stmt(f, stmt, depth)?;
+    }
+    Ok(())
+}
+
+fn write_stmt(f: &mut fmt::Formatter, stmt: &Stmt, depth: usize) -> fmt::Result {
+    let indent = "    ".repeat(depth);
+    let nested = |f: &mut fmt::Formatter, header: String, body: &[Stmt]| {
+        writeln!(f, "{}{}{{", indent, header)?;
+        write_block(f, body, depth + 1)?;
+        writeln!(f, "{}}}", indent)
+    };
+    match stmt {
+        Stmt::Let {
+            name,
+            ty,
+            value,
+            mutable,
+        } => {
+            let keyword = if *mutable { "var" } else { "val" };
+            writeln!(f, "{}{} {}: {} = {}", indent, keyword, name, ty, value)
+        }
+        Stmt::Assign { name, value } => writeln!(f, "{}{} = {}", indent, name, value),
+        Stmt::Expr(expr) => writeln!(f, "{}{}", indent, expr),
+        Stmt::If {
+            condition,
+            then_body,
+            else_body,
+        } => {
+            writeln!(f, "{}if {} {{", indent, condition)?;
+            write_block(f, then_body, depth + 1)?;
+            if !else_body.is_empty() {
+                writeln!(f, "{}}} else {{", indent)?;
+                write_block(f, else_body, depth + 1)?;
+            }
+            writeln!(f, "{}}}", indent)
+        }
+        Stmt::Loop(body) => nested(f, "loop ".to_string(), body),
+        Stmt::Break => writeln!(f, "{}break", indent),
+        Stmt::Return(Some(value)) => writeln!(f, "{}return {}", indent, value),
+        Stmt::Return(None) => writeln!(f, "{}return", indent),
+        Stmt::Defer(body) => nested(f, "defer ".to_string(), body),
+        Stmt::Block(body) => nested(f, String::new(), body),
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Integer(n) => write!(f, "{}", n),
+            ExprKind::Float(x) => write!(f, "{:?}", x),
+            ExprKind::Bool(b) => write!(f, "{}", b),
+            ExprKind::String(s) => write!(f, "{:?}", s),
+            ExprKind::None => write!(f, "none"),
+            ExprKind::Local(name) | ExprKind::Function(name) => write!(f, "{}", name),
+            ExprKind::Call { function, args } => write!(f, "{}({})", function, list(args)),
+            ExprKind::Binary {
+                operator,
+                left,
+                right,
+            } => write!(f, "({} {} {})", left, operator_symbol(operator), right),
+            ExprKind::Unary {
+                operator: UnaryOperator::Not,
+                operand,
+            } => write!(f, "!{}", operand),
+            ExprKind::Unary {
+                operator: UnaryOperator::Negate,
+                operand,
+            } => write!(f, "-{}", operand),
+            ExprKind::Array(elements) => write!(f, "[{}]", list(elements)),
+            ExprKind::Tuple(elements) => write!(f, "({})", list(elements)),
+            ExprKind::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            ExprKind::Struct(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect();
+                write!(f, "{} {{ {} }}", self.ty, fields.join(", "))
+            }
+            ExprKind::Field { object, field } => write!(f, "{}.{}", object, field),
+            ExprKind::Index { array, index } => write!(f, "{}[{}]", array, index),
+            ExprKind::AddressOf(operand) => write!(f, "&{}", operand),
+            ExprKind::Deref(operand) => write!(f, "^{}", operand),
+            ExprKind::Cast(value) => write!(f, "({} as {})", value, self.ty),
+            ExprKind::Select {
+                condition,
+                then_value,
+                else_value,
+            } => write!(
+                f,
+                "if {} {{ {} }} else {{ {} }}",
+                condition, then_value, else_value
+            ),
+            ExprKind::Wrap(value) => write!(f, "some({})", value),
+            ExprKind::IsPresent(optional) => write!(f, "present({})", optional),
+            ExprKind::Unwrap(optional) => write!(f, "unwrap({})", optional),
+            ExprKind::Coalesce { value, fallback } => write!(f, "({} ?? {})", value, fallback),
+            ExprKind::OptionalField { object, field } => write!(f, "{}?.{}", object, field),
+            ExprKind::Lambda { params, body } => {
+                write!(f, "({}) -> {}", parameter_list(params), body)
+            }
+        }
+    }
+}
+
+fn list(exprs: &[Expr]) -> String {
+    exprs
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn lower(source: &str) -> Program {
+        let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        lower_program(&program, &ModuleSystem::new()).unwrap()
+    }
+
+    // Types of the top-level declarations, in order
+    fn let_types(program: &Program) -> Vec<String> {
+        program
+            .body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Let { ty, .. } => Some(ty.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_every_declaration_gets_a_resolved_type() {
+        let program = lower(
+            "val xs := [1, 2]\nval total := xs.length + 1\nval ratio := total * 1.5\nval label := \"n = ${total}\"\nval maybe: Integer? = total\n",
+        );
+        assert_eq!(
+            let_types(&program),
+            vec![
+                "[]Integer",
+                "Integer",
+                "Float",
+                "String",
+                "Optional[Integer]"
+            ]
+        );
+        let rendered = program.to_string();
+        assert!(rendered.contains("(\"n = \" + toString(total))"));
+        assert!(rendered.contains("some(total)"));
+    }
+
+    #[test]
+    fn test_field_types_come_from_struct_definitions() {
+        // C codegen would guess String from the field name
+        let program = lower(
+            "type User = { name: Integer }\nval user := User { name: 7 }\nval id := user.name\n",
+        );
+        assert_eq!(let_types(&program), vec!["User", "Integer"]);
+    }
+
+    #[test]
+    fn test_generic_native_calls_bind_type_variables() {
+        let program = lower(
+            "native \"C\" {\n    fun map(arr: Array[T], f: (T) -> U): Array[U]\n}\nval labels := map([1, 2], (x: Integer) -> toString(x))\n",
+        );
+        assert_eq!(let_types(&program), vec!["[]String"]);
+    }
+
+    #[test]
+    fn test_loops_lower_to_loop_with_explicit_temporaries() {
+        let program = lower("val xs := [1, 2]\nfor x in xs {\n    print(x)\n}\n");
+        let Some(Stmt::Block(block)) = program.body.last() else {
+            panic!("for-in should lower to a block");
+        };
+        assert!(matches!(
+            &block[..],
+            [Stmt::Let { name: items, .. }, Stmt::Let { name: index, mutable: true, .. }, Stmt::Loop(_)]
+                if items.starts_with(TEMP_PREFIX) && index.starts_with(TEMP_PREFIX)
+        ));
+        let rendered = program.to_string();
+        assert!(rendered.contains("if !(_bolt_tmp2 < _bolt_tmp1.length) {"));
+        assert!(rendered.contains("val x: Integer = _bolt_tmp1[_bolt_tmp2]"));
+
+        let program = lower("var n := 0\nwhile n < 3 {\n    n = n + 1\n}\n");
+        assert!(matches!(
+            program.body.last(),
+            Some(Stmt::Loop(body)) if matches!(body.first(), Some(Stmt::If { then_body, .. }) if matches!(then_body[..], [Stmt::Break]))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_names_are_errors() {
+        let tokens = Lexer::new("val x := missing + 1\n".to_string())
+            .tokenize()
+            .unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        assert_eq!(
+            lower_program(&program, &ModuleSystem::new()).unwrap_err(),
+            "Unknown name 'missing'"
+        );
+    }
+}
@@ -1,7 +1,10 @@
+use std::num::{IntErrorKind, ParseIntError};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     Var,
     Val,
+    Const,
     Fun,
     Type,
     If,
@@ -10,15 +13,28 @@ pub enum TokenType {
     False,
     Return,
     For,
+    While,
+    Do, // `do { ... } while (condition)`
+    Defer,
+    Guard, // `guard condition else { return }`
+    None,  // `none`, the absent Optional value
     In,
     Import,
     Export,
     From,
     Native,
     Extern,
+    Interface,
+    Implements,
+    As,
+    Unsafe,
+    Asm,
     Identifier(String),
     String(String),
-    Integer(i64),
+    InterpolatedString(Vec<StringPart>), // "Hello, ${name}!"
+    Integer(u64),
+    Float(f64),
+    Byte(u8), // b'A'
     Colon,
     ColonEqual,
     Equal,
@@ -35,6 +51,11 @@ pub enum TokenType {
     Star,
     Slash,
     Percent,
+    PlusEqual,    // +=
+    MinusEqual,   // -=
+    StarEqual,    // *=
+    SlashEqual,   // /=
+    PercentEqual, // %=
     EqualEqual,
     NotEqual,
     Less,
@@ -45,12 +66,27 @@ pub enum TokenType {
     OrOr,
     Bang,
     Dot,
-    Caret,     // ^ for pointer types and dereference
-    Ampersand, // & for address-of
+    DotDot,           // .. in ranges (0..10)
+    DotDotEqual,      // ..= in inclusive ranges (0..=10)
+    Ellipsis,         // ... after an extern function's last parameter (C varargs)
+    Caret,            // ^ for pointer types and dereference
+    Ampersand,        // & for address-of
+    Arrow,            // -> in function types
+    Question,         // ? in optional types (Integer?)
+    QuestionQuestion, // ?? between an optional and its fallback
+    QuestionDot,      // ?. for the field of an optional struct
     Newline,
     Eof,
 }
 
+// Piece of an interpolated string: literal text or the raw source of a
+// `${...}` expression, which the parser lexes and parses on its own
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Literal(String),
+    Code(String),
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
@@ -82,13 +118,18 @@ impl Lexer {
     pub fn tokenize(&mut self) -> LexerResult<Vec<Token>> {
         let mut tokens = Vec::new();
 
+        // A `#!/usr/bin/env bolt` line lets a script be executable on Unix
+        if self.input.starts_with(&['#', '!']) {
+            self.skip_line_comment();
+        }
+
         while !self.is_at_end() {
             self.skip_whitespace();
             if self.is_at_end() {
                 break;
             }
 
-            let token = self.next_token();
+            let token = self.next_token()?;
             tokens.push(token);
         }
 
@@ -101,7 +142,7 @@ impl Lexer {
         Ok(tokens)
     }
 
-    fn next_token(&mut self) -> Token {
+    fn next_token(&mut self) -> LexerResult<Token> {
         let line = self.line;
         let column = self.column;
 
@@ -160,15 +201,20 @@ impl Lexer {
             }
             '+' => {
                 self.advance();
-                TokenType::Plus
+                self.with_equal(TokenType::Plus, TokenType::PlusEqual)
             }
             '-' => {
                 self.advance();
-                TokenType::Minus
+                if !self.is_at_end() && self.current_char() == '>' {
+                    self.advance();
+                    TokenType::Arrow
+                } else {
+                    self.with_equal(TokenType::Minus, TokenType::MinusEqual)
+                }
             }
             '*' => {
                 self.advance();
-                TokenType::Star
+                self.with_equal(TokenType::Star, TokenType::StarEqual)
             }
             '/' => {
                 self.advance();
@@ -185,12 +231,12 @@ impl Lexer {
                     // Recursively get the next token after skipping the comment
                     return self.next_token();
                 } else {
-                    TokenType::Slash
+                    self.with_equal(TokenType::Slash, TokenType::SlashEqual)
                 }
             }
             '%' => {
                 self.advance();
-                TokenType::Percent
+                self.with_equal(TokenType::Percent, TokenType::PercentEqual)
             }
             '!' => {
                 self.advance();
@@ -239,49 +285,70 @@ impl Lexer {
             }
             '.' => {
                 self.advance();
-                TokenType::Dot
+                if self.current_char() == '.' {
+                    self.advance();
+                    if !self.is_at_end() && self.current_char() == '.' {
+                        self.advance();
+                        TokenType::Ellipsis
+                    } else {
+                        self.with_equal(TokenType::DotDot, TokenType::DotDotEqual)
+                    }
+                } else {
+                    TokenType::Dot
+                }
             }
             '^' => {
                 self.advance();
                 TokenType::Caret
             }
+            '?' => {
+                self.advance();
+                match self.current_char() {
+                    '?' => {
+                        self.advance();
+                        TokenType::QuestionQuestion
+                    }
+                    '.' => {
+                        self.advance();
+                        TokenType::QuestionDot
+                    }
+                    _ => TokenType::Question,
+                }
+            }
             '\n' => {
                 self.advance();
                 TokenType::Newline
             }
             '"' => self.read_string(),
+            'b' if self.peek_char() == '\'' => self.read_byte(),
             _ if ch.is_alphabetic() || ch == '_' => self.read_identifier(),
-            _ if ch.is_numeric() => self.read_number(),
+            _ if ch.is_numeric() => self.read_number(line, column)?,
             _ => panic!("Unexpected character: {}", ch),
         };
 
-        Token {
+        Ok(Token {
             token_type,
             line,
             column,
-        }
+        })
     }
 
     fn read_string(&mut self) -> TokenType {
+        let (line, column) = (self.line, self.column);
         self.advance(); // Skip opening quote
         let mut value = String::new();
+        let mut parts = Vec::new();
 
         while !self.is_at_end() && self.current_char() != '"' {
-            if self.current_char() == '\\' {
-                self.advance();
+            if self.current_char() == '$' && self.peek_char() == '{' {
+                self.advance(); // consume '$'
+                self.advance(); // consume '{'
+                parts.push(StringPart::Literal(std::mem::take(&mut value)));
+                parts.push(StringPart::Code(self.read_interpolation()));
+            } else if self.current_char() == '\\' {
+                self.advance(); // consume '\\'
                 if !self.is_at_end() {
-                    match self.current_char() {
-                        'n' => value.push('\n'),
-                        't' => value.push('\t'),
-                        'r' => value.push('\r'),
-                        '\\' => value.push('\\'),
-                        '"' => value.push('"'),
-                        _ => {
-                            value.push('\\');
-                            value.push(self.current_char());
-                        }
-                    }
-                    self.advance();
+                    value.push(self.read_escape());
                 }
             } else {
                 value.push(self.current_char());
@@ -289,11 +356,112 @@ impl Lexer {
             }
         }
 
-        if !self.is_at_end() {
-            self.advance(); // Skip closing quote
+        if self.is_at_end() {
+            panic!(
+                "Unterminated string literal starting at line {}, column {}",
+                line, column
+            );
+        }
+        self.advance(); // Skip closing quote
+
+        if parts.is_empty() {
+            return TokenType::String(value);
+        }
+        parts.push(StringPart::Literal(value));
+        parts.retain(|part| part != &StringPart::Literal(String::new()));
+        TokenType::InterpolatedString(parts)
+    }
+
+    // `b'A'`: one ASCII character (or escape) as a Byte
+    fn read_byte(&mut self) -> TokenType {
+        let (line, column) = (self.line, self.column);
+        self.advance(); // consume 'b'
+        self.advance(); // consume opening quote
+        let ch = match self.current_char() {
+            '\\' => {
+                self.advance(); // consume '\\'
+                self.read_escape()
+            }
+            '\'' | '\n' | '\0' => panic!(
+                "Empty or unterminated byte literal at line {}, column {}",
+                line, column
+            ),
+            ch => {
+                self.advance();
+                ch
+            }
+        };
+        if !ch.is_ascii() {
+            panic!(
+                "Byte literal '{}' at line {}, column {} is not ASCII",
+                ch, line, column
+            );
+        }
+        if self.current_char() != '\'' {
+            panic!(
+                "Byte literal at line {}, column {} must hold exactly one character",
+                line, column
+            );
         }
+        self.advance(); // consume closing quote
+        TokenType::Byte(ch as u8)
+    }
 
-        TokenType::String(value)
+    // The character an escape sequence stands for; the '\\' is already consumed
+    fn read_escape(&mut self) -> char {
+        let (line, column) = (self.line, self.column - 1);
+        let escaped = match self.current_char() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            '$' => '$',
+            other => panic!(
+                "Unknown escape sequence '\\{}' at line {}, column {}",
+                other, line, column
+            ),
+        };
+        self.advance();
+        escaped
+    }
+
+    // Raw source of a `${...}` expression, up to its matching '}'
+    fn read_interpolation(&mut self) -> String {
+        let mut code = String::new();
+        let mut depth = 0;
+        let mut in_string = false;
+
+        while !self.is_at_end() {
+            let ch = self.current_char();
+            if in_string {
+                if ch == '\\' {
+                    code.push(ch);
+                    self.advance();
+                } else if ch == '"' {
+                    in_string = false;
+                }
+            } else {
+                match ch {
+                    '"' => in_string = true,
+                    '{' => depth += 1,
+                    '}' if depth == 0 => {
+                        self.advance(); // consume '}'
+                        return code;
+                    }
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            if !self.is_at_end() {
+                code.push(self.current_char());
+                self.advance();
+            }
+        }
+
+        panic!("Unterminated string interpolation: expected '}}'");
     }
 
     fn read_identifier(&mut self) -> TokenType {
@@ -309,6 +477,7 @@ impl Lexer {
         match value.as_str() {
             "var" => TokenType::Var,
             "val" => TokenType::Val,
+            "const" => TokenType::Const,
             "fun" => TokenType::Fun,
             "type" => TokenType::Type,
             "if" => TokenType::If,
@@ -317,25 +486,78 @@ impl Lexer {
             "false" => TokenType::False,
             "return" => TokenType::Return,
             "for" => TokenType::For,
+            "while" => TokenType::While,
+            "do" => TokenType::Do,
+            "defer" => TokenType::Defer,
+            "guard" => TokenType::Guard,
+            "none" => TokenType::None,
             "in" => TokenType::In,
             "import" => TokenType::Import,
             "export" => TokenType::Export,
             "from" => TokenType::From,
             "native" => TokenType::Native,
             "extern" => TokenType::Extern,
+            "interface" => TokenType::Interface,
+            "implements" => TokenType::Implements,
+            "as" => TokenType::As,
+            "unsafe" => TokenType::Unsafe,
+            "asm" => TokenType::Asm,
             _ => TokenType::Identifier(value),
         }
     }
 
-    fn read_number(&mut self) -> TokenType {
+    // Integer literals are read as u64, so that UInt64's largest value can
+    // be written; the checker holds each to the type it's used as. One too
+    // large for any integer type is an error rather than a wrapped value
+    fn read_number(&mut self, line: usize, column: usize) -> LexerResult<TokenType> {
         let mut value = String::new();
+        // `pair.0.1` indexes twice rather than reading a 0.1 (but `0..1.5`
+        // still ends in a float)
+        let tuple_index = self.position > 0
+            && self.input[self.position - 1] == '.'
+            && (self.position < 2 || self.input[self.position - 2] != '.');
 
         while !self.is_at_end() && self.current_char().is_numeric() {
             value.push(self.current_char());
             self.advance();
         }
 
-        TokenType::Integer(value.parse().unwrap())
+        // A '.' followed by a digit continues the literal as a float; anything
+        // else (e.g. `arr.length`) is left for the Dot token.
+        if !tuple_index && self.current_char() == '.' && self.peek_char().is_ascii_digit() {
+            value.push('.');
+            self.advance(); // consume '.'
+            while !self.is_at_end() && self.current_char().is_numeric() {
+                value.push(self.current_char());
+                self.advance();
+            }
+            return value
+                .parse()
+                .map(TokenType::Float)
+                .map_err(|_| Self::invalid_number(&value, line, column));
+        }
+
+        value
+            .parse()
+            .map(TokenType::Integer)
+            .map_err(|error: ParseIntError| match error.kind() {
+                IntErrorKind::PosOverflow => format!(
+                    "Integer literal {} at line {}, column {} is too large; the largest is {}",
+                    value,
+                    line,
+                    column,
+                    u64::MAX
+                ),
+                _ => Self::invalid_number(&value, line, column),
+            })
+    }
+
+    // Digits `parse` doesn't take, such as other scripts' numerals
+    fn invalid_number(value: &str, line: usize, column: usize) -> String {
+        format!(
+            "Invalid number '{}' at line {}, column {}",
+            value, line, column
+        )
     }
 
     fn skip_whitespace(&mut self) {
@@ -368,6 +590,16 @@ impl Lexer {
         // This could be handled as an error in the future
     }
 
+    // An operator, or its compound assignment form when `=` follows (`+=`)
+    fn with_equal(&mut self, operator: TokenType, compound: TokenType) -> TokenType {
+        if !self.is_at_end() && self.current_char() == '=' {
+            self.advance();
+            compound
+        } else {
+            operator
+        }
+    }
+
     fn skip_line_comment(&mut self) {
         // We've already consumed // so skip until end of line or EOF
         while !self.is_at_end() && self.current_char() != '\n' {
@@ -384,6 +616,14 @@ impl Lexer {
         }
     }
 
+    fn peek_char(&self) -> char {
+        if self.position + 1 >= self.input.len() {
+            '\0'
+        } else {
+            self.input[self.position + 1]
+        }
+    }
+
     fn advance(&mut self) {
         if !self.is_at_end() {
             if self.current_char() == '\n' {
@@ -417,8 +657,9 @@ mod tests {
 
     #[test]
     fn test_keywords() {
-        let tokens =
-            tokenize_string("var val fun type if else true false return for in import export from");
+        let tokens = tokenize_string(
+            "var val fun type if else true false return for while do defer guard in import export from interface implements as const unsafe asm",
+        );
         assert_eq!(
             tokens,
             vec![
@@ -432,10 +673,20 @@ mod tests {
                 TokenType::False,
                 TokenType::Return,
                 TokenType::For,
+                TokenType::While,
+                TokenType::Do,
+                TokenType::Defer,
+                TokenType::Guard,
                 TokenType::In,
                 TokenType::Import,
                 TokenType::Export,
                 TokenType::From,
+                TokenType::Interface,
+                TokenType::Implements,
+                TokenType::As,
+                TokenType::Const,
+                TokenType::Unsafe,
+                TokenType::Asm,
                 TokenType::Eof
             ]
         );
@@ -467,6 +718,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_interpolated_string() {
+        let tokens = tokenize_string(r#""Hello, ${name}! ${a + b}" "cost: \${5}""#);
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            tokens[0],
+            TokenType::InterpolatedString(vec![
+                StringPart::Literal("Hello, ".to_string()),
+                StringPart::Code("name".to_string()),
+                StringPart::Literal("! ".to_string()),
+                StringPart::Code("a + b".to_string()),
+            ])
+        );
+        // Escaped '$' stays a plain string
+        assert_eq!(tokens[1], TokenType::String("cost: ${5}".to_string()));
+    }
+
+    #[test]
+    fn test_string_escape_sequences() {
+        let tokens = tokenize_string(r#""a\nb\tc\\d\"e\0\'""#);
+        assert_eq!(tokens[0], TokenType::String("a\nb\tc\\d\"e\0'".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown escape sequence '\\q'")]
+    fn test_unknown_escape_sequence() {
+        tokenize_string(r#""bad \q""#);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unterminated string literal")]
+    fn test_unterminated_string() {
+        tokenize_string("\"no end");
+    }
+
+    #[test]
+    fn test_interpolation_with_nested_string() {
+        let tokens = tokenize_string(r#""${concat("}", x)}""#);
+        assert_eq!(
+            tokens[0],
+            TokenType::InterpolatedString(vec![StringPart::Code(r#"concat("}", x)"#.to_string())])
+        );
+    }
+
+    #[test]
+    fn test_byte_literals() {
+        assert_eq!(
+            tokenize_string(r"b'A' b'\n' b'\'' bytes"),
+            vec![
+                TokenType::Byte(b'A'),
+                TokenType::Byte(b'\n'),
+                TokenType::Byte(b'\''),
+                TokenType::Identifier("bytes".to_string()),
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "must hold exactly one character")]
+    fn test_byte_literal_with_two_characters() {
+        tokenize_string("b'ab'");
+    }
+
     #[test]
     fn test_integer_literals() {
         let tokens = tokenize_string("42 0 999 123456789");
@@ -478,9 +793,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_integer_literals_too_large_are_errors() {
+        // UInt64's largest value is still a literal; the checker decides
+        // whether it fits where it's used
+        assert_eq!(
+            tokenize_string("18446744073709551615"),
+            vec![TokenType::Integer(u64::MAX), TokenType::Eof]
+        );
+        let error = Lexer::new("val x: UInt64 = 18446744073709551616".to_string())
+            .tokenize()
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "Integer literal 18446744073709551616 at line 1, column 17 is too large; the largest is 18446744073709551615"
+        );
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let tokens = tokenize_string("2.5 0.5 10.0");
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Float(2.5),
+                TokenType::Float(0.5),
+                TokenType::Float(10.0),
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_integer_followed_by_dot_is_not_float() {
+        let tokens = tokenize_string("5.length");
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Integer(5),
+                TokenType::Dot,
+                TokenType::Identifier("length".to_string()),
+                TokenType::Eof,
+            ]
+        );
+    }
+
     #[test]
     fn test_operators() {
-        let tokens = tokenize_string(":= : = + - * / % == != < <= > >= && || ! . ^ &");
+        let tokens = tokenize_string(":= : = + - * / % == != < <= > >= && || ! . ^ & ->");
         assert_eq!(
             tokens,
             vec![
@@ -504,6 +864,7 @@ mod tests {
                 TokenType::Dot,
                 TokenType::Caret,
                 TokenType::Ampersand,
+                TokenType::Arrow,
                 TokenType::Eof
             ]
         );
@@ -541,6 +902,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_leading_shebang_is_skipped() {
+        let tokens = tokenize_string("#!/usr/bin/env bolt\nvar x");
+        assert_eq!(
+            tokens,
+            vec![
+                TokenType::Newline,
+                TokenType::Var,
+                TokenType::Identifier("x".to_string()),
+                TokenType::Eof
+            ]
+        );
+    }
+
     #[test]
     fn test_whitespace_handling() {
         let tokens = tokenize_string("  var   x  \n  :=   42  ");
@@ -557,6 +932,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compound_assignment_operators() {
+        assert_eq!(
+            tokenize_string("+= -= *= /= %= + -> /"),
+            vec![
+                TokenType::PlusEqual,
+                TokenType::MinusEqual,
+                TokenType::StarEqual,
+                TokenType::SlashEqual,
+                TokenType::PercentEqual,
+                TokenType::Plus,
+                TokenType::Arrow,
+                TokenType::Slash,
+                TokenType::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn test_range_operators() {
+        assert_eq!(
+            tokenize_string("0..10 1..=n 0..1.5 ..."),
+            vec![
+                TokenType::Integer(0),
+                TokenType::DotDot,
+                TokenType::Integer(10),
+                TokenType::Integer(1),
+                TokenType::DotDotEqual,
+                TokenType::Identifier("n".to_string()),
+                TokenType::Integer(0),
+                TokenType::DotDot,
+                TokenType::Float(1.5),
+                TokenType::Ellipsis,
+                TokenType::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tuple_index_is_not_a_float() {
+        assert_eq!(
+            tokenize_string("pair.0.1"),
+            vec![
+                TokenType::Identifier("pair".to_string()),
+                TokenType::Dot,
+                TokenType::Integer(0),
+                TokenType::Dot,
+                TokenType::Integer(1),
+                TokenType::Eof
+            ]
+        );
+    }
+
     #[test]
     fn test_real_code_example() {
         let code = r#"
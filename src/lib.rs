@@ -1,7 +1,24 @@
 pub mod ast;
 pub mod c_codegen;
+pub mod checker;
+pub mod compiler;
+pub mod const_eval;
+pub mod diagnostics;
 pub mod error;
+pub mod extensions;
+pub mod ice;
+#[cfg(feature = "lsp")]
+pub mod index;
+pub mod install;
+#[cfg(feature = "interpreter")]
+pub mod interpreter;
+pub mod ir;
 pub mod lexer;
+#[cfg(feature = "lsp")]
+pub mod lint;
 pub mod module;
 pub mod parser;
+pub mod position;
+pub mod stdlib;
 pub mod symbol_table;
+pub mod visit;
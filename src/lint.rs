@@ -0,0 +1,188 @@
+//! Lint levels, read from the `[lints]` table of the nearest bolt.toml (or
+//! .boltlint) at or above the program:
+//!
+//! ```toml
+//! [lints]
+//! unused-variable = "deny"
+//! implicit-int-condition = "allow"
+//! ```
+//!
+//! Lints not listed warn. `bolt check`, builds and bolt-lsp all apply the
+//! same levels to the warnings the checker returns.
+
+use crate::checker::Warning;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Every lint, with what it reports
+pub const LINTS: &[(&str, &str)] = &[
+    ("unused-variable", "a val or var that is never read"),
+    (
+        "unused-parameter",
+        "a function parameter the body never reads",
+    ),
+    (
+        "implicit-int-condition",
+        "an if, while or for condition that is an integer rather than a Bool",
+    ),
+    (
+        "deprecated",
+        "syntax on its way out, like iterate(items) in a for-in",
+    ),
+    (
+        "reserved-name",
+        "a name starting with _bolt_, which compiler temporaries use",
+    ),
+];
+
+/// Where lint levels are read from, in order of preference within a directory
+pub const CONFIG_FILES: [&str; 2] = ["bolt.toml", ".boltlint"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Allow, // Not reported
+    Warn,
+    Deny, // Reported as an error, failing the build
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    levels: HashMap<String, Level>,
+}
+
+// Other tables (a future [package], say) are ignored
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    lints: HashMap<String, Level>,
+}
+
+impl LintConfig {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let file: ConfigFile =
+            toml::from_str(text).map_err(|e| e.to_string().trim().to_string())?;
+        for name in file.lints.keys() {
+            if !LINTS.iter().any(|(lint, _)| lint == name) {
+                let known: Vec<&str> = LINTS.iter().map(|(lint, _)| *lint).collect();
+                return Err(format!(
+                    "Unknown lint '{}'; the lints are {}",
+                    name,
+                    known.join(", ")
+                ));
+            }
+        }
+        Ok(LintConfig { levels: file.lints })
+    }
+
+    /// The config file nearest to `dir` (checking `dir` first, then its
+    /// parents) and its levels, or None if there is none
+    pub fn find(dir: &Path) -> Result<Option<(PathBuf, Self)>, String> {
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        for dir in dir.ancestors() {
+            for name in CONFIG_FILES {
+                let path = dir.join(name);
+                if !path.is_file() {
+                    continue;
+                }
+                let config = fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| Self::parse(&text))
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                return Ok(Some((path, config)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// `find`'s levels, or the defaults when there's no config file
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        Ok(Self::find(dir)?
+            .map(|(_, config)| config)
+            .unwrap_or_default())
+    }
+
+    pub fn level(&self, lint: &str) -> Level {
+        self.levels.get(lint).copied().unwrap_or(Level::Warn)
+    }
+
+    /// The warnings to report, each with its level; allowed ones are dropped
+    pub fn apply(&self, warnings: Vec<Warning>) -> Vec<(Level, Warning)> {
+        warnings
+            .into_iter()
+            .map(|warning| (self.level(warning.lint), warning))
+            .filter(|(level, _)| *level != Level::Allow)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn warning(lint: &'static str) -> Warning {
+        Warning {
+            lint,
+            message: format!("{} happened", lint),
+        }
+    }
+
+    #[test]
+    fn test_levels_come_from_the_lints_table() {
+        let config = LintConfig::parse(
+            "[package]\nname = \"demo\"\n\n[lints]\nunused-variable = \"deny\"\ndeprecated = \"allow\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.level("unused-variable"), Level::Deny);
+        assert_eq!(config.level("deprecated"), Level::Allow);
+        assert_eq!(config.level("implicit-int-condition"), Level::Warn);
+
+        let applied = config.apply(vec![
+            warning("deprecated"),
+            warning("unused-variable"),
+            warning("reserved-name"),
+        ]);
+        assert_eq!(
+            applied,
+            vec![
+                (Level::Deny, warning("unused-variable")),
+                (Level::Warn, warning("reserved-name")),
+            ]
+        );
+        assert_eq!(LintConfig::parse("").unwrap(), LintConfig::default());
+    }
+
+    #[test]
+    fn test_unknown_lints_and_levels_are_errors() {
+        assert_eq!(
+            LintConfig::parse("[lints]\nunused-vars = \"deny\"").unwrap_err(),
+            "Unknown lint 'unused-vars'; the lints are unused-variable, unused-parameter, implicit-int-condition, deprecated, reserved-name"
+        );
+        assert!(LintConfig::parse("[lints]\ndeprecated = \"error\"")
+            .unwrap_err()
+            .contains("unknown variant `error`"));
+    }
+
+    #[test]
+    fn test_the_nearest_config_file_is_used() {
+        let root = env::temp_dir().join(format!("bolt_lint_test_{}", std::process::id()));
+        let nested = root.join("src/nested");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(".boltlint"), "[lints]\ndeprecated = \"deny\"").unwrap();
+        assert_eq!(
+            LintConfig::load(&nested).unwrap().level("deprecated"),
+            Level::Deny
+        );
+
+        // bolt.toml wins over .boltlint in the same directory
+        fs::write(root.join("bolt.toml"), "[lints]\ndeprecated = \"allow\"").unwrap();
+        let (path, config) = LintConfig::find(&nested).unwrap().unwrap();
+        assert_eq!(path.file_name().unwrap(), "bolt.toml");
+        assert_eq!(config.level("deprecated"), Level::Allow);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,16 +1,25 @@
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::io::{self, BufRead, BufReader, Read, Write};
-
-mod ast;
-mod error;
-mod lexer;
-mod parser;
-mod symbol_table;
-
-use lexer::Lexer;
-use parser::Parser;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use bolt::ast::Program;
+use bolt::checker::check_program_with_constants;
+use bolt::const_eval::Constant;
+use bolt::error::{ParseError, SourceLocation};
+use bolt::index::{SymbolIndex, INDEX_FILE};
+use bolt::lexer::Lexer;
+use bolt::lint::{Level, LintConfig};
+use bolt::module::{check_entry_program, ModuleSystem};
+use bolt::parser::Parser;
+use bolt::position::{PositionEncoding, PositionMapper};
+use bolt::symbol_table::FunctionSignature;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Message {
@@ -27,106 +36,205 @@ struct Message {
     error: Option<Value>,
 }
 
-struct LspServer {
-    documents: HashMap<String, String>,
+// JSON-RPC error code for a request the client cancelled
+const REQUEST_CANCELLED: i64 = -32800;
+
+// Workers answering requests and checking documents off the message loop
+const WORKER_COUNT: usize = 4;
+
+/// Shared flag a job polls to notice it's no longer wanted
+#[derive(Clone, Default)]
+struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
 }
 
-impl LspServer {
-    fn new() -> Self {
+/// User settings from the client's `bolt` configuration section, applied
+/// live on `workspace/didChangeConfiguration`. Missing keys keep their
+/// defaults.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Settings {
+    enable_lints: bool,
+    disabled_lints: Vec<String>, // Lint names, like "missing-stdio-import"
+    lint_severity: LintSeverity, // How lints at the `warn` level are shown
+    lint_delay_ms: u64,          // Typing pause before lints are published
+    max_diagnostics: usize,      // Per document
+    formatting: FormattingStyle,
+    config_path: Option<String>, // The project's bolt.toml; its directory holds the index
+}
+
+impl Default for Settings {
+    fn default() -> Self {
         Self {
-            documents: HashMap::new(),
+            enable_lints: true,
+            disabled_lints: Vec::new(),
+            lint_severity: LintSeverity::Information,
+            lint_delay_ms: 300,
+            max_diagnostics: 100,
+            formatting: FormattingStyle::default(),
+            config_path: None,
         }
     }
+}
 
-    fn run(&mut self) {
-        let stdin = io::stdin();
-        let mut reader = BufReader::new(stdin.lock());
+impl Settings {
+    fn lint_enabled(&self, name: &str) -> bool {
+        self.enable_lints && !self.disabled_lints.iter().any(|lint| lint == name)
+    }
+}
 
-        loop {
-            // Read LSP headers
-            let mut headers = HashMap::new();
-            loop {
-                let mut line = String::new();
-                if reader.read_line(&mut line).unwrap() == 0 {
-                    return; // EOF
-                }
+/// LSP severities for lints, which are hints about the code rather than
+/// problems; a lint bolt.toml denies is an error
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum LintSeverity {
+    Information,
+    Hint,
+}
 
-                let line = line.trim();
-                if line.is_empty() {
-                    break; // End of headers
-                }
+impl LintSeverity {
+    fn code(self) -> u8 {
+        match self {
+            LintSeverity::Information => 3,
+            LintSeverity::Hint => 4,
+        }
+    }
+}
+
+/// Indentation for `textDocument/formatting`. Unset fields fall back to the
+/// options sent with the request.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct FormattingStyle {
+    indent_width: Option<usize>,
+    use_tabs: Option<bool>,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed set of threads running jobs in the order they're queued
+struct WorkerPool {
+    jobs: Mutex<mpsc::Sender<Job>>,
+}
 
-                if let Some(colon_pos) = line.find(':') {
-                    let key = line[..colon_pos].trim().to_lowercase();
-                    let value = line[colon_pos + 1..].trim();
-                    headers.insert(key, value.to_string());
+impl WorkerPool {
+    fn new(size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..size {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                let job = receiver.lock().unwrap().recv();
+                match job {
+                    Ok(job) => job(),
+                    Err(_) => return, // The pool was dropped
                 }
-            }
+            });
+        }
+        Self {
+            jobs: Mutex::new(sender),
+        }
+    }
 
-            // Read content using the buffered reader directly
-            if let Some(content_length) = headers.get("content-length") {
-                if let Ok(length) = content_length.parse::<usize>() {
-                    let mut content = vec![0u8; length];
-                    match reader.read_exact(&mut content) {
-                        Ok(_) => {
-                            if let Ok(content_str) = String::from_utf8(content) {
-                                eprintln!("LSP: Processing message of length: {}", length);
-                                eprintln!("LSP: Raw content: '{}'", content_str);
-
-                                // The content should already be pure JSON at this point
-                                let trimmed = content_str.trim();
-                                match serde_json::from_str::<Value>(trimmed) {
-                                    Ok(json_value) => {
-                                        // Try to parse as our Message struct
-                                        match serde_json::from_value::<Message>(json_value.clone())
-                                        {
-                                            Ok(msg) => {
-                                                if let Some(method) = &msg.method {
-                                                    eprintln!(
-                                                        "LSP: Successfully parsed method: {}",
-                                                        method
-                                                    );
-                                                }
-                                                self.handle_message(msg);
-                                            }
-                                            Err(e) => {
-                                                eprintln!(
-                                                    "LSP: Failed to convert to Message struct: {}",
-                                                    e
-                                                );
-                                                // Handle as raw JSON
-                                                if let Some(method) = json_value
-                                                    .get("method")
-                                                    .and_then(|m| m.as_str())
-                                                {
-                                                    eprintln!(
-                                                        "LSP: Handling raw JSON method: {}",
-                                                        method
-                                                    );
-                                                    self.handle_raw_message(json_value);
-                                                }
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        eprintln!("LSP: Failed to parse JSON: {}", e);
-                                        eprintln!("LSP: Content: '{}'", trimmed);
+    fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        let _ = self.jobs.lock().unwrap().send(Box::new(job));
+    }
+}
 
-                                        // Check if this looks like it has headers mixed in
-                                        if trimmed.contains("Content-Length:") {
-                                            eprintln!("LSP: ERROR - Headers found in JSON content! Buffer issue detected.");
-                                        }
-                                    }
-                                }
+// Cloning takes a snapshot for a worker: documents and the index are shared,
+// not copied
+#[derive(Clone)]
+struct LspServer {
+    documents: HashMap<String, Arc<String>>,
+    index: Option<Arc<SymbolIndex>>, // Loaded from the workspace's .bolt-index, if any
+    encoding: PositionEncoding,      // Agreed with the client on `initialize`
+    settings: Arc<Settings>,
+    lints: Arc<LintConfig>, // [lints] levels from the workspace's bolt.toml
+    workers: Arc<WorkerPool>,
+    requests: Arc<Mutex<HashMap<String, CancelToken>>>, // In flight, by JSON-RPC id
+    diagnostics: Arc<Mutex<HashMap<String, CancelToken>>>, // Latest check per document URI
+    hints: Arc<Mutex<HashMap<String, Vec<Value>>>>,     // Lints last published per document URI
+    output: Arc<Mutex<Box<dyn Write + Send>>>,          // Stdout or the TCP connection
+}
+
+impl LspServer {
+    fn new(output: Box<dyn Write + Send>) -> Self {
+        Self {
+            documents: HashMap::new(),
+            index: None,
+            encoding: PositionEncoding::Utf16,
+            settings: Arc::new(Settings::default()),
+            lints: Arc::new(LintConfig::default()),
+            workers: Arc::new(WorkerPool::new(WORKER_COUNT)),
+            requests: Arc::new(Mutex::new(HashMap::new())),
+            diagnostics: Arc::new(Mutex::new(HashMap::new())),
+            hints: Arc::new(Mutex::new(HashMap::new())),
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+
+    fn run(&mut self, mut reader: impl BufRead) {
+        loop {
+            let content_str = match read_message(&mut reader) {
+                Ok(Some(content)) => content,
+                Ok(None) => break, // EOF
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    eprintln!("LSP: Skipping message: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("LSP: Failed to read message: {}", e);
+                    break;
+                }
+            };
+            eprintln!("LSP: Processing message of length: {}", content_str.len());
+            eprintln!("LSP: Raw content: '{}'", content_str);
+
+            // The content should already be pure JSON at this point
+            let trimmed = content_str.trim();
+            match serde_json::from_str::<Value>(trimmed) {
+                Ok(json_value) => {
+                    // Try to parse as our Message struct
+                    match serde_json::from_value::<Message>(json_value.clone()) {
+                        Ok(msg) => {
+                            if let Some(method) = &msg.method {
+                                eprintln!("LSP: Successfully parsed method: {}", method);
                             }
+                            self.handle_message(msg);
                         }
                         Err(e) => {
-                            eprintln!("LSP: Failed to read message content: {}", e);
+                            eprintln!("LSP: Failed to convert to Message struct: {}", e);
+                            // Handle as raw JSON
+                            if let Some(method) = json_value.get("method").and_then(|m| m.as_str())
+                            {
+                                eprintln!("LSP: Handling raw JSON method: {}", method);
+                                self.handle_raw_message(json_value);
+                            }
                         }
                     }
                 }
+                Err(e) => {
+                    eprintln!("LSP: Failed to parse JSON: {}", e);
+                    eprintln!("LSP: Content: '{}'", trimmed);
+
+                    // Check if this looks like it has headers mixed in
+                    if trimmed.contains("Content-Length:") {
+                        eprintln!(
+                            "LSP: ERROR - Headers found in JSON content! Buffer issue detected."
+                        );
+                    }
+                }
             }
         }
+        self.wait_for_requests();
     }
 
     fn handle_raw_message(&mut self, json_value: Value) {
@@ -155,9 +263,9 @@ impl LspServer {
                                 {
                                     eprintln!("LSP: Raw didOpen - {} (language: {})", uri, lang_id);
                                 }
-                                self.documents.insert(uri.to_string(), text.to_string());
-                                // Skip diagnostics for now to avoid lexer crashes
-                                // self.publish_diagnostics(uri, text);
+                                self.documents
+                                    .insert(uri.to_string(), Arc::new(text.to_string()));
+                                self.publish_diagnostics(uri, text);
                             }
                         }
                     }
@@ -228,6 +336,7 @@ impl LspServer {
                             json!({"label": "var", "kind": 14, "detail": "Mutable variable", "insertText": "var "}),
                             json!({"label": "fun", "kind": 14, "detail": "Function", "insertText": "fun "}),
                             json!({"label": "type", "kind": 14, "detail": "Type definition", "insertText": "type "}),
+                            json!({"label": "interface", "kind": 14, "detail": "Interface declaration", "insertText": "interface "}),
                             json!({"label": "print", "kind": 3, "detail": "Print function", "insertText": "print("}),
                             // Generic types
                             json!({"label": "Array[Integer]", "kind": 7, "detail": "Integer array", "insertText": "Array[Integer]"}),
@@ -248,8 +357,9 @@ impl LspServer {
                     }
                 }
                 "$/cancelRequest" => {
-                    eprintln!("LSP: Received cancel request - ignoring");
-                    // Just ignore cancel requests
+                    if let Some(id) = params.map(|params| &params["id"]) {
+                        self.cancel_request(id);
+                    }
                 }
                 _ => {
                     eprintln!("LSP: Unknown raw method: {}", method);
@@ -264,6 +374,25 @@ impl LspServer {
             match method.as_str() {
                 "initialize" => {
                     eprintln!("LSP: Initializing with capabilities");
+                    if let Some(params) = &msg.params {
+                        let offered: Vec<&str> = params["capabilities"]["general"]
+                            ["positionEncodings"]
+                            .as_array()
+                            .map(|encodings| encodings.iter().filter_map(Value::as_str).collect())
+                            .unwrap_or_default();
+                        self.encoding = PositionEncoding::negotiate(&offered);
+                        let root = params["rootUri"]
+                            .as_str()
+                            .map(uri_to_path)
+                            .or_else(|| params["rootPath"].as_str().map(str::to_string));
+                        if let Some(root) = root {
+                            self.load_index(Path::new(&root));
+                            self.load_lints(Path::new(&root));
+                        }
+                        if let Some(settings) = params["initializationOptions"].get("settings") {
+                            self.apply_settings(settings);
+                        }
+                    }
                     let response = Message {
                         jsonrpc: "2.0".to_string(),
                         id: msg.id,
@@ -271,6 +400,7 @@ impl LspServer {
                         params: None,
                         result: Some(json!({
                             "capabilities": {
+                                "positionEncoding": self.encoding.name(),
                                 "textDocumentSync": {
                                     "openClose": true,
                                     "change": 1,
@@ -284,7 +414,7 @@ impl LspServer {
                                     "resolveProvider": false
                                 },
                                 "hoverProvider": true,
-                                "definitionProvider": false,
+                                "definitionProvider": true,
                                 "declarationProvider": false,
                                 "implementationProvider": false,
                                 "typeDefinitionProvider": false,
@@ -294,7 +424,7 @@ impl LspServer {
                                 "workspaceSymbolProvider": false,
                                 "codeActionProvider": false,
                                 "codeLensProvider": false,
-                                "documentFormattingProvider": false,
+                                "documentFormattingProvider": true,
                                 "documentRangeFormattingProvider": false,
                                 "documentOnTypeFormattingProvider": false,
                                 "renameProvider": false,
@@ -336,9 +466,9 @@ impl LspServer {
                                 {
                                     eprintln!("LSP: Opened {} (language: {})", uri, lang_id);
                                 }
-                                self.documents.insert(uri.to_string(), text.to_string());
-                                // Skip diagnostics for now to avoid lexer crashes
-                                // self.publish_diagnostics(uri, text);
+                                self.documents
+                                    .insert(uri.to_string(), Arc::new(text.to_string()));
+                                self.publish_diagnostics(uri, text);
                             }
                         }
                     }
@@ -350,9 +480,9 @@ impl LspServer {
                             if let Some(changes) = params["contentChanges"].as_array() {
                                 if let Some(change) = changes.first() {
                                     if let Some(text) = change["text"].as_str() {
-                                        self.documents.insert(uri.to_string(), text.to_string());
-                                        // Skip diagnostics for now to avoid lexer crashes
-                                        // self.publish_diagnostics(uri, text);
+                                        self.documents
+                                            .insert(uri.to_string(), Arc::new(text.to_string()));
+                                        self.publish_diagnostics(uri, text);
                                     }
                                 }
                             }
@@ -362,146 +492,41 @@ impl LspServer {
 
                 "textDocument/completion" => {
                     eprintln!("LSP: Received completion request");
-                    if let Some(params) = &msg.params {
-                        eprintln!(
-                            "LSP: Completion params: {}",
-                            serde_json::to_string_pretty(params).unwrap_or_default()
-                        );
-                    }
-
-                    // Enhanced completion items with generic type support
-                    let items = vec![
-                        // Keywords
-                        json!({"label": "val", "kind": 14, "detail": "Immutable variable", "insertText": "val "}),
-                        json!({"label": "var", "kind": 14, "detail": "Mutable variable", "insertText": "var "}),
-                        json!({"label": "fun", "kind": 14, "detail": "Function", "insertText": "fun "}),
-                        json!({"label": "type", "kind": 14, "detail": "Type definition", "insertText": "type "}),
-                        json!({"label": "if", "kind": 14, "detail": "If statement", "insertText": "if "}),
-                        json!({"label": "for", "kind": 14, "detail": "For loop", "insertText": "for "}),
-                        json!({"label": "import", "kind": 14, "detail": "Import", "insertText": "import "}),
-                        json!({"label": "export", "kind": 14, "detail": "Export declaration", "insertText": "export "}),
-                        json!({"label": "native", "kind": 14, "detail": "Native code block", "insertText": "native "}),
-                        json!({"label": "return", "kind": 14, "detail": "Return statement", "insertText": "return "}),
-                        // Built-in functions
-                        json!({"label": "print", "kind": 3, "detail": "Print function", "insertText": "print("}),
-                        // Standard library modules
-                        json!({"label": "\"bolt:stdio\"", "kind": 9, "detail": "Standard I/O module", "insertText": "\"bolt:stdio\""}),
-                        json!({"label": "\"bolt:math\"", "kind": 9, "detail": "Math utilities module", "insertText": "\"bolt:math\""}),
-                        json!({"label": "\"bolt:io\"", "kind": 9, "detail": "File I/O operations module", "insertText": "\"bolt:io\""}),
-                        json!({"label": "\"bolt:string\"", "kind": 9, "detail": "String utilities module", "insertText": "\"bolt:string\""}),
-                        // File I/O functions (bolt:io)
-                        json!({"label": "readFile", "kind": 3, "detail": "Read file contents: (path: String) -> String", "insertText": "readFile("}),
-                        json!({"label": "writeFile", "kind": 3, "detail": "Write file contents: (path: String, content: String) -> Bool", "insertText": "writeFile("}),
-                        json!({"label": "appendFile", "kind": 3, "detail": "Append to file: (path: String, content: String) -> Bool", "insertText": "appendFile("}),
-                        json!({"label": "deleteFile", "kind": 3, "detail": "Delete file: (path: String) -> Bool", "insertText": "deleteFile("}),
-                        json!({"label": "fileExists", "kind": 3, "detail": "Check if file exists: (path: String) -> Bool", "insertText": "fileExists("}),
-                        // String utilities (bolt:string)
-                        json!({"label": "length", "kind": 3, "detail": "Get string length: (s: String) -> Integer", "insertText": "length("}),
-                        json!({"label": "concat", "kind": 3, "detail": "Concatenate strings: (a: String, b: String) -> String", "insertText": "concat("}),
-                        json!({"label": "indexOf", "kind": 3, "detail": "Find substring index: (s: String, substr: String) -> Integer", "insertText": "indexOf("}),
-                        json!({"label": "contains", "kind": 3, "detail": "Check if string contains substring: (s: String, substr: String) -> Bool", "insertText": "contains("}),
-                        json!({"label": "trim", "kind": 3, "detail": "Remove whitespace: (s: String) -> String", "insertText": "trim("}),
-                        // Built-in types
-                        json!({"label": "Integer", "kind": 7, "detail": "Integer type", "insertText": "Integer"}),
-                        json!({"label": "String", "kind": 7, "detail": "String type", "insertText": "String"}),
-                        json!({"label": "Bool", "kind": 7, "detail": "Boolean type", "insertText": "Bool"}),
-                        // Generic types and snippets
-                        json!({"label": "Array[T]", "kind": 7, "detail": "Generic array type", "insertText": "Array[T]"}),
-                        json!({"label": "Array[Integer]", "kind": 7, "detail": "Integer array", "insertText": "Array[Integer]"}),
-                        json!({"label": "Array[String]", "kind": 7, "detail": "String array", "insertText": "Array[String]"}),
-                        json!({"label": "Array[Bool]", "kind": 7, "detail": "Boolean array", "insertText": "Array[Bool]"}),
-                        // Generic type patterns
-                        json!({"label": "Result[T]", "kind": 7, "detail": "Generic result type", "insertText": "Result[T]"}),
-                        json!({"label": "Box[T]", "kind": 7, "detail": "Generic box type", "insertText": "Box[T]"}),
-                        // Common patterns
-                        json!({"label": "for in", "kind": 15, "detail": "For-in loop with Array[T]", "insertText": "for item in "}),
-                        json!({"label": "type def", "kind": 15, "detail": "Generic type definition", "insertText": "type Name[T] = { data: T }"}),
-                        // Module import patterns
-                        json!({"label": "import io", "kind": 15, "detail": "Import file I/O functions", "insertText": "import { readFile, writeFile } from \"bolt:io\""}),
-                        json!({"label": "import string", "kind": 15, "detail": "Import string utilities", "insertText": "import { length, concat, contains } from \"bolt:string\""}),
-                        json!({"label": "import stdio", "kind": 15, "detail": "Import stdio functions", "insertText": "import { print } from \"bolt:stdio\""}),
-                        // Native code patterns
-                        json!({"label": "native C", "kind": 15, "detail": "Native C function block", "insertText": "native \"C\" {\\n    export fun functionName(param: Type): ReturnType\\n}"}),
-                        // Literals
-                        json!({"label": "true", "kind": 12, "detail": "Boolean true", "insertText": "true"}),
-                        json!({"label": "false", "kind": 12, "detail": "Boolean false", "insertText": "false"}),
-                    ];
+                    self.spawn_request(msg.id, msg.params, Self::completion);
+                }
 
-                    let response = Message {
-                        jsonrpc: "2.0".to_string(),
-                        id: msg.id,
-                        method: None,
-                        params: None,
-                        result: Some(json!(items)),
-                        error: None,
-                    };
-                    eprintln!(
-                        "LSP: Sending completion response with {} items",
-                        items.len()
-                    );
-                    self.send_message(response);
+                "textDocument/definition" => {
+                    self.spawn_request(msg.id, msg.params, Self::definition);
                 }
 
                 "textDocument/hover" => {
                     eprintln!("LSP: Received hover request");
+                    self.spawn_request(msg.id, msg.params, Self::hover);
+                }
+
+                "textDocument/formatting" => {
+                    self.spawn_request(msg.id, msg.params, Self::formatting);
+                }
+
+                "workspace/didChangeConfiguration" => {
                     if let Some(params) = &msg.params {
-                        eprintln!(
-                            "LSP: Hover params: {}",
-                            serde_json::to_string_pretty(params).unwrap_or_default()
-                        );
-                        if let Some(uri) = params["textDocument"]["uri"].as_str() {
-                            if let Some(position) = params["position"].as_object() {
-                                if let Some(line) = position["line"].as_u64() {
-                                    if let Some(character) = position["character"].as_u64() {
-                                        eprintln!("LSP: Hover at {}:{}", line, character);
-                                        if let Some(doc) = self.documents.get(uri) {
-                                            let hover_info = self.get_hover_info(
-                                                doc,
-                                                line as usize,
-                                                character as usize,
-                                            );
-                                            let response = Message {
-                                                jsonrpc: "2.0".to_string(),
-                                                id: msg.id,
-                                                method: None,
-                                                params: None,
-                                                result: Some(json!({
-                                                    "contents": {
-                                                        "kind": "markdown",
-                                                        "value": hover_info
-                                                    }
-                                                })),
-                                                error: None,
-                                            };
-                                            eprintln!(
-                                                "LSP: Sending hover response: {}",
-                                                hover_info
-                                            );
-                                            self.send_message(response);
-                                            return;
-                                        } else {
-                                            eprintln!("LSP: Document not found in cache");
-                                        }
-                                    }
-                                }
-                            }
+                        self.apply_settings(&params["settings"]);
+                        // Re-check open documents under the new settings
+                        for (uri, text) in self.documents.clone() {
+                            self.publish_diagnostics(&uri, &text);
                         }
                     }
+                }
 
-                    eprintln!("LSP: Sending null hover response");
-                    // No hover info
-                    let response = Message {
-                        jsonrpc: "2.0".to_string(),
-                        id: msg.id,
-                        method: None,
-                        params: None,
-                        result: Some(Value::Null),
-                        error: None,
-                    };
-                    self.send_message(response);
+                "$/cancelRequest" => {
+                    if let Some(id) = msg.params.as_ref().map(|params| &params["id"]) {
+                        self.cancel_request(id);
+                    }
                 }
 
                 "shutdown" => {
+                    // Answer everything still in flight before acknowledging
+                    self.wait_for_requests();
                     let response = Message {
                         jsonrpc: "2.0".to_string(),
                         id: msg.id,
@@ -541,66 +566,413 @@ impl LspServer {
         }
     }
 
-    fn publish_diagnostics(&self, uri: &str, text: &str) {
-        let mut diagnostics = Vec::new();
+    fn load_index(&mut self, root: &Path) {
+        self.index = SymbolIndex::load(&root.join(INDEX_FILE)).map(Arc::new);
+        if let Some(index) = &self.index {
+            eprintln!("LSP: Loaded symbol index with {} files", index.files.len());
+        }
+    }
 
-        // Try to parse the document
-        let mut lexer = Lexer::new(text.to_string());
-        match lexer.tokenize() {
-            Ok(tokens) => {
-                let mut parser = Parser::new(tokens);
-                if let Err(e) = parser.parse() {
-                    // Parser error
-                    diagnostics.push(json!({
-                        "range": {
-                            "start": {"line": 0, "character": 0},
-                            "end": {"line": 0, "character": 0}
-                        },
-                        "severity": 1, // Error
-                        "message": format!("Parse error: {}", e)
-                    }));
+    // An invalid config is reported once and otherwise ignored, so editing
+    // it doesn't leave the server without diagnostics
+    fn load_lints(&mut self, root: &Path) {
+        let lints = LintConfig::load(root).unwrap_or_else(|e| {
+            eprintln!("LSP: Ignoring lint config: {}", e);
+            LintConfig::default()
+        });
+        self.lints = Arc::new(lints);
+    }
+
+    // Clients send either the `bolt` section or all settings, depending on
+    // how the section was registered
+    fn apply_settings(&mut self, settings: &Value) {
+        let section = settings.get("bolt").unwrap_or(settings);
+        let settings = match Settings::deserialize(section) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("LSP: Ignoring invalid settings: {}", e);
+                return;
+            }
+        };
+        eprintln!("LSP: Applying settings: {:?}", settings);
+        if settings.config_path != self.settings.config_path {
+            if let Some(dir) = settings
+                .config_path
+                .as_deref()
+                .and_then(|path| Path::new(path).parent())
+            {
+                self.load_index(dir);
+                self.load_lints(dir);
+            }
+        }
+        self.settings = Arc::new(settings);
+    }
+
+    fn formatting(&self, params: &Value) -> Value {
+        let Some(doc) = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri))
+        else {
+            return Value::Null;
+        };
+        let style = &self.settings.formatting;
+        let width = style
+            .indent_width
+            .or_else(|| params["options"]["tabSize"].as_u64().map(|n| n as usize))
+            .unwrap_or(4);
+        let use_tabs = style
+            .use_tabs
+            .or_else(|| {
+                params["options"]["insertSpaces"]
+                    .as_bool()
+                    .map(|spaces| !spaces)
+            })
+            .unwrap_or(false);
+        let indent = if use_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(width)
+        };
+        let formatted = format_document(doc, &indent);
+        if formatted == **doc {
+            return json!([]);
+        }
+        // One edit replacing the whole document
+        let positions = PositionMapper::new(doc, self.encoding);
+        let last_line = doc.split('\n').count() - 1;
+        let end = positions.character(last_line, positions.line(last_line).chars().count());
+        json!([{
+            "range": {
+                "start": {"line": 0, "character": 0},
+                "end": {"line": last_line, "character": end}
+            },
+            "newText": formatted
+        }])
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        // Enhanced completion items with generic type support
+        let items = vec![
+            // Keywords
+            json!({"label": "val", "kind": 14, "detail": "Immutable variable", "insertText": "val "}),
+            json!({"label": "var", "kind": 14, "detail": "Mutable variable", "insertText": "var "}),
+            json!({"label": "const", "kind": 14, "detail": "Compile-time constant", "insertText": "const "}),
+            json!({"label": "fun", "kind": 14, "detail": "Function", "insertText": "fun "}),
+            json!({"label": "type", "kind": 14, "detail": "Type definition", "insertText": "type "}),
+            json!({"label": "if", "kind": 14, "detail": "If statement", "insertText": "if "}),
+            json!({"label": "for", "kind": 14, "detail": "For loop", "insertText": "for "}),
+            json!({"label": "while", "kind": 14, "detail": "While loop", "insertText": "while "}),
+            json!({"label": "do", "kind": 14, "detail": "Do-while loop", "insertText": "do "}),
+            json!({"label": "import", "kind": 14, "detail": "Import", "insertText": "import "}),
+            json!({"label": "export", "kind": 14, "detail": "Export declaration", "insertText": "export "}),
+            json!({"label": "native", "kind": 14, "detail": "Native code block", "insertText": "native "}),
+            json!({"label": "return", "kind": 14, "detail": "Return statement", "insertText": "return "}),
+            json!({"label": "defer", "kind": 14, "detail": "Deferred cleanup", "insertText": "defer "}),
+            json!({"label": "guard", "kind": 14, "detail": "Early exit unless a condition holds", "insertText": "guard "}),
+            json!({"label": "unsafe", "kind": 14, "detail": "Block allowing inline asm", "insertText": "unsafe "}),
+            json!({"label": "asm", "kind": 14, "detail": "Inline GCC asm (inside unsafe)", "insertText": "asm "}),
+            json!({"label": "none", "kind": 14, "detail": "The absent optional value", "insertText": "none"}),
+            // Built-in functions
+            json!({"label": "print", "kind": 3, "detail": "Print function", "insertText": "print("}),
+            json!({"label": "println", "kind": 3, "detail": "Print function (same as print)", "insertText": "println("}),
+            json!({"label": "write", "kind": 3, "detail": "Print without a newline", "insertText": "write("}),
+            json!({"label": "format", "kind": 3, "detail": "Fill {} placeholders into a String", "insertText": "format("}),
+            json!({"label": "printf", "kind": 3, "detail": "Print a format without a newline", "insertText": "printf("}),
+            json!({"label": "panic", "kind": 3, "detail": "Stop with file:line and a message: (message: String)", "insertText": "panic("}),
+            json!({"label": "assert", "kind": 3, "detail": "Panic unless a condition holds (debug and --strict builds): (condition: Bool, message: String)", "insertText": "assert("}),
+            json!({"label": "exit", "kind": 3, "detail": "Stop the program with an exit status: (status: Integer)", "insertText": "exit("}),
+            json!({"label": "alloc", "kind": 3, "detail": "Heap memory for count values: alloc[T](count: Integer) -> ^T", "insertText": "alloc["}),
+            json!({"label": "realloc", "kind": 3, "detail": "Resize heap memory, keeping its values: realloc[T](block: ^T, count: Integer) -> ^T", "insertText": "realloc["}),
+            json!({"label": "free", "kind": 3, "detail": "Release memory from alloc or realloc: (block: ^T)", "insertText": "free("}),
+            // Standard library modules
+            json!({"label": "\"bolt:stdio\"", "kind": 9, "detail": "Standard I/O module", "insertText": "\"bolt:stdio\""}),
+            json!({"label": "\"bolt:math\"", "kind": 9, "detail": "Math utilities module", "insertText": "\"bolt:math\""}),
+            json!({"label": "\"bolt:io\"", "kind": 9, "detail": "File I/O operations module", "insertText": "\"bolt:io\""}),
+            json!({"label": "\"bolt:fs\"", "kind": 9, "detail": "Directory listing and glob module", "insertText": "\"bolt:fs\""}),
+            json!({"label": "\"bolt:string\"", "kind": 9, "detail": "String utilities module", "insertText": "\"bolt:string\""}),
+            json!({"label": "\"bolt:array\"", "kind": 9, "detail": "Array sorting and searching module", "insertText": "\"bolt:array\""}),
+            // File I/O functions (bolt:io)
+            json!({"label": "readFile", "kind": 3, "detail": "Read file contents: (path: String) -> String", "insertText": "readFile("}),
+            json!({"label": "writeFile", "kind": 3, "detail": "Write file contents: (path: String, content: String) -> Bool", "insertText": "writeFile("}),
+            json!({"label": "appendFile", "kind": 3, "detail": "Append to file: (path: String, content: String) -> Bool", "insertText": "appendFile("}),
+            json!({"label": "deleteFile", "kind": 3, "detail": "Delete file: (path: String) -> Bool", "insertText": "deleteFile("}),
+            json!({"label": "fileExists", "kind": 3, "detail": "Check if file exists: (path: String) -> Bool", "insertText": "fileExists("}),
+            json!({"label": "tempFile", "kind": 3, "detail": "Create a unique temporary file: (prefix: String) -> String", "insertText": "tempFile("}),
+            json!({"label": "tempDir", "kind": 3, "detail": "Create a unique temporary directory: () -> String", "insertText": "tempDir("}),
+            json!({"label": "removeTemp", "kind": 3, "detail": "Remove a temporary file or directory: (path: String) -> Bool", "insertText": "removeTemp("}),
+            // Directories (bolt:fs)
+            json!({"label": "listDir", "kind": 3, "detail": "Sorted entry names: (path: String) -> Array[String]", "insertText": "listDir("}),
+            json!({"label": "isDir", "kind": 3, "detail": "Check if a path is a directory: (path: String) -> Bool", "insertText": "isDir("}),
+            json!({"label": "glob", "kind": 3, "detail": "Paths matching *, ?, [a-z] and **: (pattern: String) -> Array[String]", "insertText": "glob("}),
+            json!({"label": "walk", "kind": 3, "detail": "Every file below a directory: (dir: String) -> Array[String]", "insertText": "walk("}),
+            // String utilities (bolt:string)
+            json!({"label": "length", "kind": 3, "detail": "Get string length: (s: String) -> Integer", "insertText": "length("}),
+            json!({"label": "concat", "kind": 3, "detail": "Concatenate strings: (a: String, b: String) -> String", "insertText": "concat("}),
+            json!({"label": "indexOf", "kind": 3, "detail": "Find substring index: (s: String, substr: String) -> Integer", "insertText": "indexOf("}),
+            json!({"label": "contains", "kind": 3, "detail": "Check if string contains substring: (s: String, substr: String) -> Bool", "insertText": "contains("}),
+            json!({"label": "trim", "kind": 3, "detail": "Remove whitespace: (s: String) -> String", "insertText": "trim("}),
+            json!({"label": "compare", "kind": 3, "detail": "Compare strings: (a: String, b: String) -> Integer", "insertText": "compare("}),
+            json!({"label": "naturalCompare", "kind": 3, "detail": "Compare strings, numbers by value: (a: String, b: String) -> Integer", "insertText": "naturalCompare("}),
+            json!({"label": "view", "kind": 3, "detail": "Substring without copying: (s: String, start: Integer, end: Integer) -> StrView", "insertText": "view("}),
+            // Array operations (bolt:array)
+            json!({"label": "sort", "kind": 3, "detail": "Sort in place: (arr: Array[T]) -> Array[T]", "insertText": "sort("}),
+            json!({"label": "sortBy", "kind": 3, "detail": "Stable sort by key: (arr: Array[T], key: (T) -> K) -> Array[T]", "insertText": "sortBy("}),
+            json!({"label": "binarySearch", "kind": 3, "detail": "Index in a sorted array or -1: (arr: Array[T], value: T) -> Integer", "insertText": "binarySearch("}),
+            json!({"label": "reverse", "kind": 3, "detail": "Reverse in place: (arr: Array[T]) -> Array[T]", "insertText": "reverse("}),
+            json!({"label": "indexOfValue", "kind": 3, "detail": "First index of a value or -1: (arr: Array[T], value: T) -> Integer", "insertText": "indexOfValue("}),
+            json!({"label": "filter", "kind": 3, "detail": "Keep matching elements: (arr: Array[T], keep: (T) -> Bool) -> Array[T]", "insertText": "filter("}),
+            json!({"label": "mapValues", "kind": 3, "detail": "Transform each element: (arr: Array[T], f: (T) -> U) -> Array[U]", "insertText": "mapValues("}),
+            json!({"label": "map", "kind": 3, "detail": "Transform each element: (arr: Array[T], f: (T) -> U) -> Array[U]", "insertText": "map("}),
+            json!({"label": "reduce", "kind": 3, "detail": "Fold into one value: (arr: Array[T], initial: U, f: (U, T) -> U) -> U", "insertText": "reduce("}),
+            json!({"label": "any", "kind": 3, "detail": "Whether some element matches: (arr: Array[T], keep: (T) -> Bool) -> Bool", "insertText": "any("}),
+            json!({"label": "all", "kind": 3, "detail": "Whether every element matches: (arr: Array[T], keep: (T) -> Bool) -> Bool", "insertText": "all("}),
+            json!({"label": "slice", "kind": 3, "detail": "Subrange without copying: (arr: Array[T], start: Integer, end: Integer) -> Slice[T]", "insertText": "slice("}),
+            // Random numbers (bolt:random)
+            json!({"label": "seedRandom", "kind": 3, "detail": "Restart the generator for a reproducible sequence: (seed: Integer)", "insertText": "seedRandom("}),
+            json!({"label": "randomInt", "kind": 3, "detail": "Uniform in min..max, both included: (min: Integer, max: Integer) -> Integer", "insertText": "randomInt("}),
+            json!({"label": "randomFloat", "kind": 3, "detail": "Uniform in [0, 1): () -> Float", "insertText": "randomFloat("}),
+            json!({"label": "randomNormal", "kind": 3, "detail": "Normally distributed: (mean: Float, stddev: Float) -> Float", "insertText": "randomNormal("}),
+            json!({"label": "shuffle", "kind": 3, "detail": "Shuffle in place: (arr: Array[T]) -> Array[T]", "insertText": "shuffle("}),
+            json!({"label": "choice", "kind": 3, "detail": "A random element, none if empty: (arr: Array[T]) -> T?", "insertText": "choice("}),
+            // Identifiers (bolt:uuid)
+            json!({"label": "uuid4", "kind": 3, "detail": "Random version 4 UUID: () -> String", "insertText": "uuid4("}),
+            json!({"label": "nanoid", "kind": 3, "detail": "Random URL-safe id: (length: Integer) -> String", "insertText": "nanoid("}),
+            // Built-in types
+            json!({"label": "Integer", "kind": 7, "detail": "Integer type", "insertText": "Integer"}),
+            json!({"label": "String", "kind": 7, "detail": "String type", "insertText": "String"}),
+            json!({"label": "Bool", "kind": 7, "detail": "Boolean type", "insertText": "Bool"}),
+            json!({"label": "Int8", "kind": 7, "detail": "8-bit signed integer", "insertText": "Int8"}),
+            json!({"label": "Int16", "kind": 7, "detail": "16-bit signed integer", "insertText": "Int16"}),
+            json!({"label": "Int32", "kind": 7, "detail": "32-bit signed integer", "insertText": "Int32"}),
+            json!({"label": "Int64", "kind": 7, "detail": "64-bit signed integer", "insertText": "Int64"}),
+            json!({"label": "UInt8", "kind": 7, "detail": "8-bit unsigned integer", "insertText": "UInt8"}),
+            json!({"label": "UInt16", "kind": 7, "detail": "16-bit unsigned integer", "insertText": "UInt16"}),
+            json!({"label": "UInt32", "kind": 7, "detail": "32-bit unsigned integer", "insertText": "UInt32"}),
+            json!({"label": "UInt64", "kind": 7, "detail": "64-bit unsigned integer", "insertText": "UInt64"}),
+            // Generic types and snippets
+            json!({"label": "Array[T]", "kind": 7, "detail": "Generic array type", "insertText": "Array[T]"}),
+            json!({"label": "Slice[T]", "kind": 7, "detail": "Non-owning view of part of an array", "insertText": "Slice[T]"}),
+            json!({"label": "StrView", "kind": 7, "detail": "Non-owning view of part of a string", "insertText": "StrView"}),
+            json!({"label": "Optional[T]", "kind": 7, "detail": "A T or none (also written T?)", "insertText": "Optional[T]"}),
+            json!({"label": "Array[Integer]", "kind": 7, "detail": "Integer array", "insertText": "Array[Integer]"}),
+            json!({"label": "Array[String]", "kind": 7, "detail": "String array", "insertText": "Array[String]"}),
+            json!({"label": "Array[Bool]", "kind": 7, "detail": "Boolean array", "insertText": "Array[Bool]"}),
+            // Generic type patterns
+            json!({"label": "Result[T]", "kind": 7, "detail": "Generic result type", "insertText": "Result[T]"}),
+            json!({"label": "Box[T]", "kind": 7, "detail": "Generic box type", "insertText": "Box[T]"}),
+            // Common patterns
+            json!({"label": "for in", "kind": 15, "detail": "For-in loop with Array[T]", "insertText": "for item in "}),
+            json!({"label": "type def", "kind": 15, "detail": "Generic type definition", "insertText": "type Name[T] = { data: T }"}),
+            // Module import patterns
+            json!({"label": "import io", "kind": 15, "detail": "Import file I/O functions", "insertText": "import { readFile, writeFile } from \"bolt:io\""}),
+            json!({"label": "import string", "kind": 15, "detail": "Import string utilities", "insertText": "import { length, concat, contains } from \"bolt:string\""}),
+            json!({"label": "import stdio", "kind": 15, "detail": "Import stdio functions", "insertText": "import { print } from \"bolt:stdio\""}),
+            // Native code patterns
+            json!({"label": "native C", "kind": 15, "detail": "Native C function block", "insertText": "native \"C\" {\\n    export fun functionName(param: Type): ReturnType\\n}"}),
+            // Literals
+            json!({"label": "true", "kind": 12, "detail": "Boolean true", "insertText": "true"}),
+            json!({"label": "false", "kind": 12, "detail": "Boolean false", "insertText": "false"}),
+        ];
+
+        // Names declared in the document and visible at the cursor
+        let mut items = items;
+        {
+            let uri = params["textDocument"]["uri"].as_str();
+            let line = params["position"]["line"].as_u64();
+            let character = params["position"]["character"].as_u64();
+            if let (Some(uri), Some(line), Some(character)) = (uri, line, character) {
+                if let Some(doc) = self.documents.get(uri) {
+                    let cursor = PositionMapper::new(doc, self.encoding)
+                        .location(line as usize, character as usize);
+                    items.extend(Self::symbol_completions(doc, &cursor));
                 }
             }
-            Err(e) => {
-                // Lexer error
-                diagnostics.push(json!({
+        }
+        json!(items)
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str();
+        let line = params["position"]["line"].as_u64();
+        let character = params["position"]["character"].as_u64();
+        match (uri, line, character) {
+            (Some(uri), Some(line), Some(character)) => self
+                .find_definition(uri, line as usize, character as usize)
+                .unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str();
+        let line = params["position"]["line"].as_u64();
+        let character = params["position"]["character"].as_u64();
+        let (Some(uri), Some(line), Some(character)) = (uri, line, character) else {
+            return Value::Null;
+        };
+        eprintln!("LSP: Hover at {}:{}", line, character);
+        let Some(doc) = self.documents.get(uri) else {
+            eprintln!("LSP: Document not found in cache");
+            return Value::Null;
+        };
+        let hover_info = self.get_hover_info(doc, line as usize, character as usize);
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": hover_info
+            }
+        })
+    }
+
+    // Answers a request on a worker from a snapshot of the server, so a slow
+    // request doesn't hold up the messages behind it. A request cancelled
+    // before its answer is sent gets the RequestCancelled error instead.
+    fn spawn_request(
+        &self,
+        id: Option<Value>,
+        params: Option<Value>,
+        handler: fn(&Self, &Value) -> Value,
+    ) {
+        let Some(id) = id else {
+            return;
+        };
+        let token = CancelToken::default();
+        self.requests
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), token.clone());
+        let server = self.clone();
+        self.workers.execute(move || {
+            let params = params.unwrap_or(Value::Null);
+            let result = (!token.is_cancelled()).then(|| handler(&server, &params));
+            let requests = server.requests.lock().unwrap();
+            let (result, error) = match result {
+                Some(result) if !token.is_cancelled() => (Some(result), None),
+                _ => (
+                    None,
+                    Some(json!({"code": REQUEST_CANCELLED, "message": "Request cancelled"})),
+                ),
+            };
+            server.send_message(Message {
+                jsonrpc: "2.0".to_string(),
+                id: Some(id.clone()),
+                method: None,
+                params: None,
+                result,
+                error,
+            });
+            drop(requests);
+            server.requests.lock().unwrap().remove(&id.to_string());
+        });
+    }
+
+    fn wait_for_requests(&self) {
+        while !self.requests.lock().unwrap().is_empty() {
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    fn cancel_request(&self, id: &Value) {
+        eprintln!("LSP: Cancelling request {}", id);
+        if let Some(token) = self.requests.lock().unwrap().get(&id.to_string()) {
+            token.cancel();
+        }
+    }
+
+    // Checks the document on a worker. A newer version of the same document
+    // cancels the check in flight, which stops at its next phase and
+    // publishes nothing.
+    fn publish_diagnostics(&self, uri: &str, text: &str) {
+        let token = CancelToken::default();
+        let previous = self
+            .diagnostics
+            .lock()
+            .unwrap()
+            .insert(uri.to_string(), token.clone());
+        if let Some(previous) = previous {
+            previous.cancel();
+        }
+        let server = self.clone();
+        let (uri, text) = (uri.to_string(), text.to_string());
+        self.workers
+            .execute(move || server.check_document(&uri, &text, &token));
+    }
+
+    // Errors are published as soon as they're known, with the lints from
+    // the last pause in typing; the new lints follow once the document has
+    // been left alone for `lint_delay_ms`, unless a newer version came first
+    fn check_document(&self, uri: &str, text: &str, token: &CancelToken) {
+        // The lexer and parser still panic on some malformed input; contain
+        // those panics so a half-typed document can't take the server down.
+        let positions = PositionMapper::new(text, self.encoding);
+        let parsed = std::panic::catch_unwind(|| {
+            Self::parse_diagnostics(text, &positions, &self.lints, &self.settings, token)
+        });
+        let (errors, mut hints) = match parsed {
+            Ok(Some(diagnostics)) => diagnostics,
+            Ok(None) => return, // Cancelled
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                    .unwrap_or_else(|| "unknown error".to_string());
+                let error = json!({
                     "range": {
                         "start": {"line": 0, "character": 0},
                         "end": {"line": 0, "character": 0}
                     },
                     "severity": 1, // Error
-                    "message": format!("Lexer error: {}", e)
-                }));
+                    "message": format!("Parse error: {}", message)
+                });
+                (vec![error], Vec::new())
             }
-        }
+        };
 
         // Check for common issues
         let lines: Vec<&str> = text.lines().collect();
         for (i, line) in lines.iter().enumerate() {
             // Check for print without import
-            if line.contains("print(") && !text.contains("bolt:stdio") {
-                diagnostics.push(json!({
+            if self.settings.lint_enabled("missing-stdio-import")
+                && line.contains("print(")
+                && !text.contains("bolt:stdio")
+            {
+                hints.push(json!({
                     "range": {
                         "start": {"line": i, "character": 0},
-                        "end": {"line": i, "character": line.len()}
+                        "end": {"line": i, "character": positions.character(i, line.chars().count())}
                     },
-                    "severity": 2, // Warning
+                    "severity": self.settings.lint_severity.code(),
+                    "code": "missing-stdio-import",
                     "message": "print function used but bolt:stdio not imported"
                 }));
             }
+        }
 
-            // Check for wrong assignment operator
-            if line.contains("val ") && line.contains(" = ") && !line.contains(" := ") {
-                diagnostics.push(json!({
-                    "range": {
-                        "start": {"line": i, "character": 0},
-                        "end": {"line": i, "character": line.len()}
-                    },
-                    "severity": 1, // Error
-                    "message": "Use := for variable declaration"
-                }));
+        let previous = self.hints.lock().unwrap().get(uri).cloned();
+        self.send_diagnostics(uri, &errors, &previous.unwrap_or_default(), token);
+
+        let server = self.clone();
+        let (uri, token) = (uri.to_string(), token.clone());
+        let delay = Duration::from_millis(self.settings.lint_delay_ms);
+        thread::spawn(move || {
+            thread::sleep(delay);
+            if !token.is_cancelled() {
+                server
+                    .hints
+                    .lock()
+                    .unwrap()
+                    .insert(uri.clone(), hints.clone());
+                server.send_diagnostics(&uri, &errors, &hints, &token);
             }
-        }
+        });
+    }
+
+    // A document's diagnostics replace the ones published before, so errors
+    // and lints always go together
+    fn send_diagnostics(&self, uri: &str, errors: &[Value], hints: &[Value], token: &CancelToken) {
+        let mut diagnostics: Vec<Value> = errors.iter().chain(hints).cloned().collect();
+        diagnostics.truncate(self.settings.max_diagnostics);
 
         let notification = Message {
             jsonrpc: "2.0".to_string(),
@@ -613,61 +985,237 @@ impl LspServer {
             result: None,
             error: None,
         };
-        self.send_message(notification);
+        // Checked under the lock a newer version cancels with, so a stale
+        // result can't be published after the newer one
+        let _pending = self.diagnostics.lock().unwrap();
+        if !token.is_cancelled() {
+            self.send_message(notification);
+        }
     }
 
-    fn send_message(&self, msg: Message) {
-        let content = serde_json::to_string(&msg).unwrap();
-        print!("Content-Length: {}\r\n\r\n{}", content.len(), content);
-        io::stdout().flush().unwrap();
+    // Definitions in the open document come from a fresh parse; anything
+    // else is answered from the workspace index without parsing other files
+    fn find_definition(&self, uri: &str, line: usize, character: usize) -> Option<Value> {
+        let document = self.documents.get(uri)?;
+        let positions = PositionMapper::new(document, self.encoding);
+        let cursor = positions.location(line, character);
+        let word = word_at(&positions, &cursor)?;
+
+        let text = document.to_string();
+        let parsed = std::panic::catch_unwind(move || {
+            let tokens = Lexer::new(text).tokenize().ok()?;
+            let mut parser = Parser::new(tokens);
+            parser.parse().ok()?;
+            Some(parser.into_symbol_table())
+        });
+        if let Ok(Some(symbol_table)) = parsed {
+            let local = symbol_table
+                .symbols_at(&cursor)
+                .into_iter()
+                .find(|symbol| symbol.name == word)
+                .and_then(|symbol| symbol.declared_at.clone());
+            if let Some(declared_at) = local {
+                return Some(lsp_location(uri, &positions, &declared_at, &word));
+            }
+        }
+
+        // The target file's positions come from its open document or, failing
+        // that, from disk
+        let definition = self.index.as_ref()?.find_definition(&word)?;
+        let target_uri = format!("file://{}", definition.path);
+        let target = match self.documents.get(&target_uri) {
+            Some(text) => text.to_string(),
+            None => std::fs::read_to_string(&definition.path).unwrap_or_default(),
+        };
+        let target_positions = PositionMapper::new(&target, self.encoding);
+        Some(lsp_location(
+            &target_uri,
+            &target_positions,
+            &definition.location,
+            &word,
+        ))
     }
 
-    fn get_hover_info(&self, document: &str, line: usize, character: usize) -> String {
-        eprintln!(
-            "LSP: Getting hover info at line {} character {}",
-            line, character
-        );
+    fn symbol_completions(text: &str, cursor: &SourceLocation) -> Vec<Value> {
+        let text = text.to_string();
+        let parsed = std::panic::catch_unwind(move || {
+            let tokens = Lexer::new(text).tokenize().ok()?;
+            let mut parser = Parser::new(tokens);
+            parser.parse().ok()?;
+            Some(parser.into_symbol_table())
+        });
+        let Ok(Some(symbol_table)) = parsed else {
+            return Vec::new();
+        };
 
-        let lines: Vec<&str> = document.lines().collect();
-        if line >= lines.len() {
-            return "No information available".to_string();
-        }
+        symbol_table
+            .symbols_at(cursor)
+            .into_iter()
+            .map(|symbol| {
+                let (kind, detail) = match FunctionSignature::of(symbol) {
+                    Some(signature) => (3, signature.to_string()),
+                    None => (6, format!("{}: {}", symbol.kind_name(), symbol.symbol_type)),
+                };
+                json!({"label": symbol.name, "kind": kind, "detail": detail, "insertText": symbol.name})
+            })
+            .collect()
+    }
 
-        let current_line = lines[line];
-        eprintln!("LSP: Current line: '{}'", current_line);
+    // The document's errors and its lints; None when cancelled between phases
+    fn parse_diagnostics(
+        text: &str,
+        positions: &PositionMapper,
+        lints: &LintConfig,
+        settings: &Settings,
+        token: &CancelToken,
+    ) -> Option<(Vec<Value>, Vec<Value>)> {
+        let mut diagnostics = Vec::new();
+        let mut hints = Vec::new();
 
-        // Find the word at the cursor position
-        if character >= current_line.len() {
-            return "No information available".to_string();
-        }
+        // Try to parse the document
+        let mut lexer = Lexer::new(text.to_string());
+        match lexer.tokenize() {
+            Ok(tokens) => {
+                if token.is_cancelled() {
+                    return None;
+                }
+                // What did parse is still checked, so type errors show up
+                // alongside syntax errors elsewhere in the document
+                let mut parser = Parser::new(tokens);
+                let program = parser.parse_recovering();
+                if token.is_cancelled() {
+                    return None;
+                }
+                let parsed_cleanly = parser.diagnostics().is_empty();
+                // Same rules the compiler applies to the entry module
+                let imported = imported_constants(&program);
+                match check_entry_program(&program)
+                    .and_then(|_| check_program_with_constants(&program, &imported))
+                {
+                    // Lints on a partly parsed program would be guesses
+                    Ok(_) if !parsed_cleanly => {}
+                    Ok(warnings) => {
+                        // bolt.toml's levels, then the editor's own switches
+                        for (level, warning) in lints.apply(warnings) {
+                            if !settings.lint_enabled(warning.lint) {
+                                continue;
+                            }
+                            let (severity, list) = match level {
+                                Level::Deny => (1, &mut diagnostics), // Fails the build
+                                _ => (settings.lint_severity.code(), &mut hints),
+                            };
+                            list.push(json!({
+                                "range": {
+                                    "start": {"line": 0, "character": 0},
+                                    "end": {"line": 0, "character": 0}
+                                },
+                                "severity": severity,
+                                "code": warning.lint,
+                                "message": warning.message
+                            }));
+                        }
+                    }
+                    Err(messages) => {
+                        for message in messages.lines() {
+                            diagnostics.push(json!({
+                                "range": {
+                                    "start": {"line": 0, "character": 0},
+                                    "end": {"line": 0, "character": 0}
+                                },
+                                "severity": 1, // Error
+                                "message": message
+                            }));
+                        }
+                    }
+                }
 
-        // Find word boundaries
-        let mut start = character;
-        let mut end = character;
+                // Recoverable parser diagnostics carry an exact span and fix-it;
+                // statements the parser gave up on, where it stopped
+                for diagnostic in parser.diagnostics() {
+                    if let ParseError::InvalidSyntax {
+                        message,
+                        location: Some(location),
+                    } = diagnostic
+                    {
+                        let (line, character) = positions.position(location);
+                        diagnostics.push(json!({
+                            "range": {
+                                "start": {"line": line, "character": character},
+                                "end": {"line": line, "character": character}
+                            },
+                            "severity": 1, // Error
+                            "message": format!("Parse error: {}", message)
+                        }));
+                    }
+                    if let ParseError::SuggestedFix { message, fix } = diagnostic {
+                        let (line, start) = positions.position(&fix.location);
+                        let end = positions.character(line, fix.location.column - 1 + fix.length);
+                        diagnostics.push(json!({
+                            "range": {
+                                "start": {"line": line, "character": start},
+                                "end": {"line": line, "character": end}
+                            },
+                            "severity": 1, // Error
+                            "message": message,
+                            "data": {"replacement": fix.replacement}
+                        }));
+                    }
+                }
+            }
+            Err(e) => {
+                // Lexer error
+                diagnostics.push(json!({
+                    "range": {
+                        "start": {"line": 0, "character": 0},
+                        "end": {"line": 0, "character": 0}
+                    },
+                    "severity": 1, // Error
+                    "message": format!("Lexer error: {}", e)
+                }));
+            }
+        }
 
-        let chars: Vec<char> = current_line.chars().collect();
+        Some((diagnostics, hints))
+    }
 
-        // Move start backwards to find beginning of word
-        while start > 0 && (chars[start - 1].is_alphanumeric() || chars[start - 1] == '_') {
-            start -= 1;
-        }
+    // Workers answer concurrently; holding the output lock keeps each
+    // message's header and body together
+    fn send_message(&self, msg: Message) {
+        let content = serde_json::to_string(&msg).unwrap();
+        let mut output = self.output.lock().unwrap();
+        write!(
+            output,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )
+        .unwrap();
+        output.flush().unwrap();
+    }
 
-        // Move end forwards to find end of word
-        while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
-            end += 1;
-        }
+    fn get_hover_info(&self, document: &str, line: usize, character: usize) -> String {
+        eprintln!(
+            "LSP: Getting hover info at line {} character {}",
+            line, character
+        );
 
-        if start == end {
+        let positions = PositionMapper::new(document, self.encoding);
+        let cursor = positions.location(line, character);
+        let Some(word) = word_at(&positions, &cursor) else {
             return "No information available".to_string();
-        }
-
-        let word: String = chars[start..end].iter().collect();
+        };
         eprintln!("LSP: Found word: '{}'", word);
 
         // Provide specific hover information based on the word
         match word.as_str() {
-            "print" => {
-                "**`print(value)`**\n\n*Built-in function*\n\nPrints a value to the console.\n\n**Examples:**\n```bolt\nprint(\"Hello, World!\")\nprint(42)\nprint(true)\n```".to_string()
+            "print" | "println" => {
+                "**`print(values...)`**\n\n*Built-in function*\n\nPrints its values to the console, separated by a space and followed by a newline. `println` is the same function.\n\n**Examples:**\n```bolt\nprint(\"Hello, World!\")\nprint(42)\nprint(\"x =\", x, \"ok:\", true)\n```".to_string()
+            }
+            "write" => {
+                "**`write(values...)`**\n\n*Built-in function*\n\nPrints its values to the console like `print`, without ending the line.\n\n**Example:**\n```bolt\nwrite(\"Loading\")\nwrite(\"...\")\nprint(\" done\")\n```".to_string()
+            }
+            "format" | "printf" => {
+                "**`format(template, values...)`**\n\n*Built-in function*\n\nFills each `{}` in the template with the next value, formatted like `print` would; `{{` and `}}` are literal braces. `printf` prints the result without ending the line.\n\n**Example:**\n```bolt\nval line := format(\"x = {}, ok = {}\", x, true)\nprintf(\"{}% done\\n\", 50)\n```".to_string()
             }
             "val" => {
                 "**`val`**\n\n*Keyword*\n\nDeclares an immutable variable (constant).\n\n**Syntax:**\n```bolt\nval name := value\nval name: Type = value\n```\n\n**Examples:**\n```bolt\nval message := \"Hello\"\nval count: Integer = 42\nval arr: Array[Integer] = Array[Integer] { ... }\n```".to_string()
@@ -678,6 +1226,9 @@ impl LspServer {
             "type" => {
                 "**`type`**\n\n*Keyword*\n\nDeclares a custom type or generic type.\n\n**Syntax:**\n```bolt\ntype TypeName = { field: Type }\ntype Generic[T] = { data: T }\n```\n\n**Examples:**\n```bolt\ntype Person = { name: String, age: Integer }\ntype Array[T] = { data: ^T, length: Integer }\ntype Result[T] = { value: T, isError: Bool }\n```".to_string()
             }
+            "interface" => {
+                "**`interface`**\n\n*Keyword*\n\nDeclares the methods a type must have. Types opt in with `implements`, and functions taking an interface are compiled once per concrete type passed to them.\n\n**Syntax:**\n```bolt\ninterface Printable {\n    fun toString(): String\n}\n\ntype Person implements Printable = { name: String }\n```".to_string()
+            }
             "fun" => {
                 "**`fun`**\n\n*Keyword*\n\nDeclares a function.\n\n**Syntax:**\n```bolt\nfun name(param: Type): ReturnType {\n    // function body\n    return value\n}\n```\n\n**Example:**\n```bolt\nfun greet(name: String): String {\n    return \"Hello, \" + name\n}\n```".to_string()
             }
@@ -691,13 +1242,28 @@ impl LspServer {
                 "**`import`**\n\n*Keyword*\n\nImports functions from modules.\n\n**Syntax:**\n```bolt\nimport { functionName } from \"module\"\n```\n\n**Examples:**\n```bolt\nimport { print } from \"bolt:stdio\"\nimport { readFile, writeFile } from \"bolt:io\"\nimport { length, concat } from \"bolt:string\"\n```".to_string()
             }
             "for" => {
-                "**`for`**\n\n*Keyword*\n\nLoop construct with multiple forms.\n\n**For-in loop:**\n```bolt\nfor item in array {\n    print(item)\n}\n```\n\n**Condition loop:**\n```bolt\nfor (condition) {\n    // code\n}\n```\n\n**Works with Array[T] types:**\n```bolt\nfor item in myGenericArray {\n    // item is correctly typed\n}\n```".to_string()
+                "**`for`**\n\n*Keyword*\n\nLoop construct with multiple forms.\n\n**For-in loop:**\n```bolt\nfor item in array {\n    print(item)\n}\n```\n\n**With index:**\n```bolt\nfor i, item in array {\n    print(i)\n}\n```\n\n**Condition loop:**\n```bolt\nfor condition {\n    // code\n}\n```\n\n**C-style loop:**\n```bolt\nfor (var i := 0; i < 10; i = i + 1) {\n    print(i)\n}\n```\n\n**Works with Array[T] types:**\n```bolt\nfor item in myGenericArray {\n    // item is correctly typed\n}\n```".to_string()
+            }
+            "while" => {
+                "**`while`**\n\n*Keyword*\n\nRepeats a block while a condition holds.\n\n**Syntax:**\n```bolt\nwhile condition {\n    // code\n}\n```\n\n**Example:**\n```bolt\nvar i := 0\nwhile i < 10 {\n    i = i + 1\n}\n```".to_string()
+            }
+            "do" => {
+                "**`do`**\n\n*Keyword*\n\nA loop whose body runs once before the condition is first checked.\n\n**Syntax:**\n```bolt\ndo {\n    // code\n} while (condition)\n```".to_string()
+            }
+            "defer" => {
+                "**`defer`**\n\n*Keyword*\n\nRuns a statement or block when the enclosing block exits. Deferred code also runs before every `return`, innermost first.\n\n**Syntax:**\n```bolt\ndefer print(\"done\")\n\ndefer {\n    // cleanup\n}\n```".to_string()
+            }
+            "guard" => {
+                "**`guard`**\n\n*Keyword*\n\nLeaves early unless a condition holds: the else block runs when it is false and must end with `return`.\n\n**Syntax:**\n```bolt\nguard count > 0 else {\n    return \"empty\"\n}\n```".to_string()
+            }
+            "none" => {
+                "**`none`**\n\n*Keyword*\n\nThe absent value of an optional type (`T?`).\n\n**Usage:**\n```bolt\nvar maybe: Integer? = none\nif (val n := maybe) {\n    print(n)\n} else {\n    print(\"nothing\")\n}\n```".to_string()
             }
             "Array" => {
                 "**`Array[T]`**\n\n*Generic Type*\n\nA generic array type that can hold elements of any type T.\n\n**Definition:**\n```bolt\ntype Array[T] = {\n    data: ^T,\n    length: Integer,\n    capacity: Integer\n}\n```\n\n**Usage:**\n```bolt\nval numbers: Array[Integer] = Array[Integer] { \n    data: &value, length: 1, capacity: 10 \n}\n\nfor item in numbers {\n    print(item)  // item is Integer\n}\n```".to_string()
             }
             "if" => {
-                "**`if`**\n\n*Keyword*\n\nConditional statement.\n\n**Syntax:**\n```bolt\nif condition {\n    // code\n} else if other_condition {\n    // code\n} else {\n    // code\n}\n```".to_string()
+                "**`if`**\n\n*Keyword*\n\nConditional statement.\n\n**Syntax:**\n```bolt\nif condition {\n    // code\n} else if other_condition {\n    // code\n} else {\n    // code\n}\n```\n\nParentheses around the condition are optional: `if (x > 0) { }` works too.".to_string()
             }
             "Integer" => {
                 "**`Integer`**\n\n*Built-in Type*\n\nA 64-bit signed integer type.\n\n**Usage:**\n```bolt\nval count: Integer = 42\nval numbers: Array[Integer] = ...\n```".to_string()
@@ -727,6 +1293,22 @@ impl LspServer {
             "fileExists" => {
                 "**`fileExists(path: String): Bool`**\n\n*File I/O Function*\n\nChecks if a file exists.\n\n**Usage:**\n```bolt\nimport { fileExists } from \"bolt:io\"\n\nif (fileExists(\"config.txt\")) {\n    // file exists, proceed\n}\n```".to_string()
             }
+            "tempFile" => {
+                "**`tempFile(prefix: String): String`**\n\n*File I/O Function*\n\nCreates an empty file with a unique name starting with `prefix` in the temp directory and returns its path. It is removed when the program exits.\n\n**Usage:**\n```bolt\nimport { tempFile, removeTemp } from \"bolt:io\"\n\nval path := tempFile(\"report-\")\ndefer removeTemp(path)\n```".to_string()
+            }
+            "tempDir" => {
+                "**`tempDir(): String`**\n\n*File I/O Function*\n\nCreates an empty directory with a unique name in the temp directory and returns its path. It is removed, with everything in it, when the program exits.\n\n**Usage:**\n```bolt\nimport { tempDir } from \"bolt:io\"\n\nval workspace := tempDir()\n```".to_string()
+            }
+            "removeTemp" => {
+                "**`removeTemp(path: String): Bool`**\n\n*File I/O Function*\n\nRemoves a path made by `tempFile` or `tempDir` (a directory with its contents) before the program exits. Any other path is left alone and gives false.\n\n**Usage:**\n```bolt\nimport { tempFile, removeTemp } from \"bolt:io\"\n\nval scratch := tempFile(\"scratch-\")\ndefer removeTemp(scratch)\n```".to_string()
+            }
+            // Directory functions (bolt:fs)
+            "glob" => {
+                "**`glob(pattern: String): Array[String]`**\n\n*File System Function*\n\nReturns the paths matching `pattern`, sorted. `*`, `?` and `[a-z]` match within one name and `**` matches any number of directories; hidden names only match a pattern that starts with `.`.\n\n**Usage:**\n```bolt\nimport { glob } from \"bolt:fs\"\n\nfor source in glob(\"src/**/*.bolt\") {\n    print(source)\n}\n```".to_string()
+            }
+            "walk" => {
+                "**`walk(dir: String): Array[String]`**\n\n*File System Function*\n\nReturns the path of every file below `dir`, depth first with each directory's entries in name order. Symlinked directories aren't followed.\n\n**Usage:**\n```bolt\nimport { walk } from \"bolt:fs\"\n\nfor path in walk(\"assets\") {\n    print(path)\n}\n```".to_string()
+            }
             // String utility functions (bolt:string)
             "length" => {
                 "**`length(s: String): Integer`**\n\n*String Utility Function*\n\nReturns the length of a string.\n\n**Usage:**\n```bolt\nimport { length } from \"bolt:string\"\n\nval text := \"Hello, World!\"\nval len := length(text)  // returns 13\n```".to_string()
@@ -743,6 +1325,12 @@ impl LspServer {
             "trim" => {
                 "**`trim(s: String): String`**\n\n*String Utility Function*\n\nRemoves leading and trailing whitespace.\n\n**Usage:**\n```bolt\nimport { trim } from \"bolt:string\"\n\nval text := \"  hello world  \"\nval trimmed := trim(text)  // returns \"hello world\"\n```".to_string()
             }
+            "compare" => {
+                "**`compare(a: String, b: String): Integer`**\n\n*String Utility Function*\n\nCompares two strings lexicographically: -1 if `a` sorts first, 1 if `b` does, 0 if equal.\n\n**Usage:**\n```bolt\nimport { compare } from \"bolt:string\"\n\nval order := compare(\"apple\", \"banana\")  // returns -1\n```".to_string()
+            }
+            "naturalCompare" => {
+                "**`naturalCompare(a: String, b: String): Integer`**\n\n*String Utility Function*\n\nLike `compare`, but runs of digits compare by numeric value, so `\"file2\"` sorts before `\"file10\"`.\n\n**Usage:**\n```bolt\nimport { naturalCompare } from \"bolt:string\"\n\nval order := naturalCompare(\"file10\", \"file2\")  // returns 1\n```".to_string()
+            }
             _ => {
                 // Check if it's a function by looking for function declarations
                 if let Some(func_info) = self.find_function_declaration(document, &word) {
@@ -1051,8 +1639,458 @@ impl LspServer {
     }
 }
 
+// Constants the document imports, if its modules load; otherwise a
+// constant defined with one is reported as unknown at compile time
+fn imported_constants(program: &Program) -> Vec<Constant> {
+    let mut modules = ModuleSystem::new();
+    modules
+        .resolve_imports(program)
+        .and_then(|_| modules.imported_constants(&program.statements))
+        .unwrap_or_default()
+}
+
+// The identifier under the cursor, if any
+fn word_at(positions: &PositionMapper, cursor: &SourceLocation) -> Option<String> {
+    let chars: Vec<char> = positions.line(cursor.line - 1).chars().collect();
+    let column = cursor.column - 1;
+    if column >= chars.len() {
+        return None;
+    }
+
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut start = column;
+    let mut end = column;
+    while start > 0 && is_word_char(chars[start - 1]) {
+        start -= 1;
+    }
+    while end < chars.len() && is_word_char(chars[end]) {
+        end += 1;
+    }
+
+    (start != end).then(|| chars[start..end].iter().collect())
+}
+
+fn uri_to_path(uri: &str) -> String {
+    uri.strip_prefix("file://").unwrap_or(uri).to_string()
+}
+
+// An LSP Location covering `word` where it starts at a source location
+fn lsp_location(
+    uri: &str,
+    positions: &PositionMapper,
+    location: &SourceLocation,
+    word: &str,
+) -> Value {
+    let (line, start) = positions.position(location);
+    let end = positions.character(line, location.column - 1 + word.chars().count());
+    json!({
+        "uri": uri,
+        "range": {
+            "start": {"line": line, "character": start},
+            "end": {"line": line, "character": end}
+        }
+    })
+}
+
+// Re-indents every line by its bracket depth and strips trailing whitespace.
+// A line starting with a closing bracket sits at the depth it closes to;
+// lines inside a block comment keep one extra space before their `*`.
+fn format_document(text: &str, indent: &str) -> String {
+    let mut formatted = String::new();
+    let mut depth: usize = 0;
+    let mut in_comment = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            formatted.push('\n');
+            continue;
+        }
+        let continues_comment = in_comment;
+        let mut change: isize = 0;
+        let mut chars = trimmed.chars().peekable();
+        let mut in_string = false;
+        while let Some(c) = chars.next() {
+            if in_comment {
+                if c == '*' && chars.peek() == Some(&'/') {
+                    chars.next();
+                    in_comment = false;
+                }
+            } else if in_string {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => in_string = false,
+                    _ => {}
+                }
+            } else {
+                match c {
+                    '"' => in_string = true,
+                    '/' if chars.peek() == Some(&'/') => break,
+                    '/' if chars.peek() == Some(&'*') => {
+                        chars.next();
+                        in_comment = true;
+                    }
+                    '{' | '(' | '[' => change += 1,
+                    '}' | ')' | ']' => change -= 1,
+                    _ => {}
+                }
+            }
+        }
+        let closes_first = !continues_comment && trimmed.starts_with(['}', ')', ']']);
+        let line_depth = if closes_first {
+            depth.saturating_sub(1)
+        } else {
+            depth
+        };
+        formatted.push_str(&indent.repeat(line_depth));
+        if continues_comment && trimmed.starts_with('*') {
+            formatted.push(' ');
+        }
+        formatted.push_str(trimmed);
+        formatted.push('\n');
+        depth = depth.saturating_add_signed(change);
+    }
+    if !text.ends_with('\n') {
+        formatted.pop();
+    }
+    formatted
+}
+
+// Reads one message's body. Headers may come in any case and order, with
+// blank lines between messages; Content-Type is optional but its charset
+// must be UTF-8. Partial reads just block until the rest arrives.
+// Returns Ok(None) at end of input.
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut content_length = None;
+    let mut charset = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break; // End of headers
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            eprintln!("LSP: Ignoring malformed header: '{}'", line);
+            continue;
+        };
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "content-length" => {
+                let length = value
+                    .parse::<usize>()
+                    .map_err(|_| invalid(format!("bad Content-Length '{}'", value)))?;
+                content_length = Some(length);
+            }
+            "content-type" => {
+                charset = value
+                    .split(';')
+                    .filter_map(|param| param.trim().split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+                    .map(|(_, charset)| charset.trim().trim_matches('"').to_lowercase());
+            }
+            _ => {}
+        }
+    }
+
+    let mut content = vec![0u8; content_length.unwrap_or(0)];
+    reader.read_exact(&mut content)?;
+    // "utf8" is still sent by older clients
+    if let Some(charset) = charset.filter(|c| c != "utf-8" && c != "utf8") {
+        return Err(invalid(format!("unsupported charset '{}'", charset)));
+    }
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|e| invalid(e.to_string()))
+}
+
+#[derive(Debug, PartialEq)]
+enum Transport {
+    Stdio,
+    Tcp(SocketAddr),
+}
+
+// `--tcp` takes a port, bound on localhost, or a full address to listen on
+fn parse_transport(args: &[String]) -> Result<Transport, String> {
+    let mut transport = Transport::Stdio;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stdio" => transport = Transport::Stdio,
+            "--tcp" => {
+                let target = args.next().ok_or("--tcp needs a port")?;
+                let address = match target.parse::<u16>() {
+                    Ok(port) => SocketAddr::from(([127, 0, 0, 1], port)),
+                    Err(_) => target
+                        .parse()
+                        .map_err(|_| format!("invalid --tcp port or address '{}'", target))?,
+                };
+                transport = Transport::Tcp(address);
+            }
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
+    }
+    Ok(transport)
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let transport = match parse_transport(&args) {
+        Ok(transport) => transport,
+        Err(e) => {
+            eprintln!("bolt-lsp: {}", e);
+            eprintln!("Usage: bolt-lsp [--stdio | --tcp <port|address>]");
+            std::process::exit(2);
+        }
+    };
+
     eprintln!("Bolt LSP Server starting...");
-    let mut server = LspServer::new();
-    server.run();
+    match transport {
+        Transport::Stdio => {
+            let mut server = LspServer::new(Box::new(io::stdout()));
+            server.run(BufReader::new(io::stdin().lock()));
+        }
+        Transport::Tcp(address) => {
+            let listener = TcpListener::bind(address).unwrap_or_else(|e| {
+                eprintln!("bolt-lsp: can't listen on {}: {}", address, e);
+                std::process::exit(1);
+            });
+            // Port 0 picks a free port, so report the one we got
+            eprintln!("LSP: Listening on {}", listener.local_addr().unwrap());
+            let (stream, peer) = listener.accept().unwrap();
+            eprintln!("LSP: Client connected from {}", peer);
+            let reader = BufReader::new(stream.try_clone().unwrap());
+            let mut server = LspServer::new(Box::new(stream));
+            server.run(reader);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_document_reindents_by_bracket_depth() {
+        let source = "fun main() {\n  if x > 0 {   \nprint(\"{\")\n      }\n\n/**\n* Doc\n*/\nval a := [\n1,\n]\n}\n";
+        let expected = "fun main() {\n\tif x > 0 {\n\t\tprint(\"{\")\n\t}\n\n\t/**\n\t * Doc\n\t */\n\tval a := [\n\t\t1,\n\t]\n}\n";
+        assert_eq!(format_document(source, "\t"), expected);
+        assert_eq!(
+            format_document("fun f() {\nreturn 1 // }\n}", "  "),
+            "fun f() {\n  return 1 // }\n}"
+        );
+    }
+
+    #[test]
+    fn test_settings_keep_defaults_for_missing_keys() {
+        let settings: Settings = serde_json::from_value(json!({
+            "disabledLints": ["missing-stdio-import"],
+            "formatting": {"useTabs": true}
+        }))
+        .unwrap();
+        assert!(!settings.lint_enabled("missing-stdio-import"));
+        assert!(settings.lint_enabled("other"));
+        assert_eq!(settings.max_diagnostics, 100);
+        assert_eq!(settings.formatting.use_tabs, Some(true));
+        assert_eq!(settings.formatting.indent_width, None);
+
+        let settings: Settings = serde_json::from_value(json!({"enableLints": false})).unwrap();
+        assert!(!settings.lint_enabled("other"));
+    }
+
+    #[test]
+    fn test_lints_are_hints_apart_from_errors() {
+        let settings: Settings = serde_json::from_value(json!({"lintSeverity": "hint"})).unwrap();
+        let check = |text: &str| {
+            LspServer::parse_diagnostics(
+                text,
+                &PositionMapper::new(text, PositionEncoding::Utf16),
+                &LintConfig::default(),
+                &settings,
+                &CancelToken::default(),
+            )
+            .unwrap()
+        };
+
+        let (errors, hints) = check("fun f(unused: Integer) {\n}\n");
+        assert!(errors.is_empty());
+        assert_eq!(hints[0]["code"], "unused-parameter");
+        assert_eq!(hints[0]["severity"], 4); // Hint
+        assert_eq!(Settings::default().lint_severity.code(), 3); // Information
+
+        // A type error fails the check, so there are no lints to show
+        let (errors, hints) = check("fun f(unused: Integer) {\n}\nval x: String = 1\n");
+        assert_eq!(errors.len(), 1);
+        assert!(hints.is_empty());
+    }
+
+    // Collects what the server writes, for the test to read back
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn messages(&self) -> Vec<Value> {
+            let bytes = self.0.lock().unwrap().clone();
+            let mut reader = BufReader::new(bytes.as_slice());
+            std::iter::from_fn(|| read_message(&mut reader).unwrap())
+                .map(|content| serde_json::from_str(&content).unwrap())
+                .collect()
+        }
+    }
+
+    fn send(server: &mut LspServer, message: Value) {
+        server.handle_message(serde_json::from_value(message).unwrap());
+    }
+
+    // Keeps every worker busy, so jobs queue up until the sender is dropped
+    fn hold_workers(server: &LspServer) -> mpsc::Sender<()> {
+        let (release, held) = mpsc::channel::<()>();
+        let held = Arc::new(Mutex::new(held));
+        for _ in 0..WORKER_COUNT {
+            let held = Arc::clone(&held);
+            server.workers.execute(move || {
+                let _ = held.lock().unwrap().recv();
+            });
+        }
+        release
+    }
+
+    // Returns once every job queued so far has finished
+    fn finish_jobs(server: &LspServer) {
+        let barrier = Arc::new(std::sync::Barrier::new(WORKER_COUNT + 1));
+        for _ in 0..WORKER_COUNT {
+            let barrier = Arc::clone(&barrier);
+            server.workers.execute(move || {
+                barrier.wait();
+            });
+        }
+        barrier.wait();
+    }
+
+    #[test]
+    fn test_cancelled_requests_answer_with_request_cancelled() {
+        let output = Output::default();
+        let mut server = LspServer::new(Box::new(output.clone()));
+        let release = hold_workers(&server);
+        send(
+            &mut server,
+            json!({"jsonrpc": "2.0", "id": 7, "method": "textDocument/formatting",
+                   "params": {"textDocument": {"uri": "file:///a.bolt"}, "options": {}}}),
+        );
+        send(
+            &mut server,
+            json!({"jsonrpc": "2.0", "method": "$/cancelRequest", "params": {"id": 7}}),
+        );
+        drop(release);
+        server.wait_for_requests();
+
+        let messages = output.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["id"], 7);
+        assert_eq!(messages[0]["error"]["code"], REQUEST_CANCELLED);
+        assert!(messages[0].get("result").is_none());
+    }
+
+    #[test]
+    fn test_a_newer_version_suppresses_the_stale_diagnostics() {
+        let output = Output::default();
+        let mut server = LspServer::new(Box::new(output.clone()));
+        server.settings = Arc::new(Settings {
+            lint_delay_ms: 0,
+            ..Settings::default()
+        });
+        let release = hold_workers(&server);
+        send(
+            &mut server,
+            json!({"jsonrpc": "2.0", "method": "textDocument/didOpen",
+                   "params": {"textDocument": {"uri": "file:///a.bolt", "languageId": "bolt",
+                                               "version": 1, "text": "val old: String = 1\n"}}}),
+        );
+        send(
+            &mut server,
+            json!({"jsonrpc": "2.0", "method": "textDocument/didChange",
+                   "params": {"textDocument": {"uri": "file:///a.bolt", "version": 2},
+                              "contentChanges": [{"text": "val new: Bool = 1\n"}]}}),
+        );
+        drop(release);
+        finish_jobs(&server);
+
+        // Errors go out first, the lints after the (zero) typing pause
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while output.messages().len() < 2 && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let messages = output.messages();
+        assert_eq!(messages.len(), 2);
+        for message in &messages {
+            assert_eq!(message["method"], "textDocument/publishDiagnostics");
+            let diagnostics = message["params"]["diagnostics"].to_string();
+            assert!(diagnostics.contains("Bool"), "{}", diagnostics);
+            assert!(!diagnostics.contains("String"), "{}", diagnostics);
+        }
+    }
+
+    // Hands out one byte per read, like a slow socket
+    struct Trickle<'a>(&'a [u8]);
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_read_message_handles_headers_and_partial_reads() {
+        let input = "content-length: 2\r\nContent-Type: application/vscode-jsonrpc; charset=utf8\r\n\r\n{}\r\n\
+                     X-Other: 1\r\nContent-Length: 4\r\n\r\nnull\
+                     Content-Length: 2\r\nContent-Type: text/plain; charset=latin1\r\n\r\n{}\
+                     Content-Length: 4";
+        let mut reader = BufReader::new(Trickle(input.as_bytes()));
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{}"));
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("null"));
+        let error = read_message(&mut reader).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_transport_flags() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(parse_transport(&[]), Ok(Transport::Stdio));
+        assert_eq!(parse_transport(&args(&["--stdio"])), Ok(Transport::Stdio));
+        assert_eq!(
+            parse_transport(&args(&["--tcp", "9257"])),
+            Ok(Transport::Tcp("127.0.0.1:9257".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_transport(&args(&["--tcp", "0.0.0.0:9257"])),
+            Ok(Transport::Tcp("0.0.0.0:9257".parse().unwrap()))
+        );
+        assert!(parse_transport(&args(&["--tcp"])).is_err());
+        assert!(parse_transport(&args(&["--tcp", "nowhere"])).is_err());
+    }
 }
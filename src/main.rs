@@ -1,36 +1,102 @@
-mod ast;
-mod c_codegen;
-mod error;
-mod lexer;
-mod module;
-mod parser;
-mod symbol_table;
-
-use clap::{Arg, Command as ClapCommand};
+use clap::{Arg, ArgMatches, Command as ClapCommand};
+use serde::Serialize;
+use std::collections::HashSet;
 use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use c_codegen::CCodeGen;
-use error::CompileError;
-use lexer::Lexer;
-use module::ModuleSystem;
-use parser::Parser;
+use bolt::ast::{Program, Statement};
+use bolt::c_codegen::{banner, library_header, CCodeGen};
+use bolt::checker::Warning;
+use bolt::compiler::Compiler;
+use bolt::diagnostics::{Diagnostic, Severity};
+use bolt::error::{CompileError, ParseError};
+use bolt::ice::{self, Ice, Stopped, REPRO_FILE};
+use bolt::index::{SymbolIndex, INDEX_FILE};
+use bolt::install::{default_prefix, install, path_guidance};
+use bolt::interpreter::{interpret, interpret_with_hot_reload};
+use bolt::ir::lower_program;
+use bolt::lexer::Lexer;
+use bolt::lint::{Level, LintConfig};
+use bolt::module::ModuleSystem;
+use bolt::parser::Parser;
+use bolt::stdlib;
+use bolt::symbol_table::SymbolTable;
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
+    let matches = cli().get_matches();
+    if let Err(e) = run(&matches) {
+        let max_errors = *matches.get_one::<usize>("max-errors").unwrap();
+        for line in e.report(max_errors) {
+            eprintln!("{}", line);
+        }
         std::process::exit(1);
     }
 }
 
-fn run() -> Result<(), CompileError> {
-    let matches = ClapCommand::new("bolt")
-        .version("0.1.0")
+fn cli() -> ClapCommand {
+    ClapCommand::new("bolt")
         .about("Bolt programming language compiler")
+        .disable_version_flag(true)
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            ClapCommand::new("run")
+                .about("Compile a program and run it")
+                .arg(
+                    Arg::new("input")
+                        .help("Input .bolt file, or - to read it from stdin")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("args")
+                        .help("Arguments passed to the program")
+                        .num_args(0..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            ClapCommand::new("check")
+                .about("Parse, type-check and lint a program without building it")
+                .arg(
+                    Arg::new("input")
+                        .help("Input .bolt file, or - to read it from stdin")
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            ClapCommand::new("install")
+                .about("Copy bolt, bolt-lsp and the standard library to a prefix")
+                .arg(
+                    Arg::new("prefix")
+                        .long("prefix")
+                        .help("Installation directory (default: ~/.bolt)"),
+                ),
+        )
+        .arg(
+            Arg::new("version")
+                .short('V')
+                .long("version")
+                .help("Print version")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .help("With --version, also print the commit and stdlib version")
+                .requires("version")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("input")
-                .help("Input .bolt file")
-                .required(true)
+                .help("Input .bolt file, or - to read it from stdin")
+                .required_unless_present("version")
                 .index(1),
         )
         .arg(
@@ -47,50 +113,227 @@ fn run() -> Result<(), CompileError> {
                 .help("Build in release mode")
                 .action(clap::ArgAction::SetTrue),
         )
-        .get_matches();
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .help("Keep runtime checks (slice bounds) in release builds")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("arena")
+                .long("arena")
+                .help("Allocate runtime strings from an arena that loops reset every iteration")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("lib")
+                .long("lib")
+                .help("Build a shared library (lib<output>.so) and its C header, whose runtime errors return to the caller")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-banner")
+                .long("no-banner")
+                .help("Leave the provenance comment (compiler, source file, build time) out of the generated C")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-timestamp")
+                .long("no-timestamp")
+                .help("Leave the build time out of the generated C's banner, so rebuilding gives identical output")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .value_name("file")
+                .help("Add this file's text (e.g. a license) to the generated C's banner")
+                .conflicts_with("no-banner"),
+        )
+        .arg(
+            Arg::new("index")
+                .long("index")
+                .help("Write a .bolt-index symbol index for the LSP after building")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("parse-only")
+                .long("parse-only")
+                .help("Only lex and parse the input, reporting whether it is valid syntax")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("emit-ir")
+                .long("emit-ir")
+                .help("Print the typed intermediate representation and exit")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("interpret")
+                .long("interpret")
+                .help("Run the program with the built-in interpreter instead of compiling it with gcc")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hot-reload")
+                .long("hot-reload")
+                .help("With --interpret, reload imported modules when their files change while the program runs")
+                .requires("interpret")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dump-symbols")
+                .long("dump-symbols")
+                .help("Print the symbol table as JSON and exit")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Print the build plan (modules, gcc command line, libraries, output paths) as JSON without building")
+                .conflicts_with_all(["parse-only", "emit-ir", "interpret", "dump-symbols"])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max-errors")
+                .long("max-errors")
+                .help("Print at most this many errors, then a count of the rest")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("20")
+                .global(true),
+        )
+}
+
+fn run(matches: &ArgMatches) -> Result<(), CompileError> {
+    if let Some(run_matches) = matches.subcommand_matches("run") {
+        return run_program(run_matches);
+    }
+    if let Some(check_matches) = matches.subcommand_matches("check") {
+        return check_only(check_matches);
+    }
+    if let Some(install_matches) = matches.subcommand_matches("install") {
+        return install_bolt(install_matches);
+    }
+    if matches.get_flag("version") {
+        print_version(matches.get_flag("verbose"));
+        return Ok(());
+    }
 
     let input_file = matches.get_one::<String>("input").unwrap();
     let output_file = matches.get_one::<String>("output").unwrap();
     let is_release = matches.get_flag("release");
+    let is_library = matches.get_flag("lib");
+
+    if matches.get_flag("parse-only") {
+        let source = read_source(input_file)?;
+        return parse_only(source);
+    }
 
     // Determine build mode and output directory
     let build_mode = if is_release { "release" } else { "debug" };
     let output_dir = format!("out/{}", build_mode);
 
-    // Create output directory if it doesn't exist
-    fs::create_dir_all(&output_dir).map_err(CompileError::IoError)?;
-
-    let full_output_path = format!("{}/{}", output_dir, output_file);
+    let full_output_path = if is_library {
+        format!("{}/lib{}.so", output_dir, output_file)
+    } else {
+        format!("{}/{}", output_dir, output_file)
+    };
+    let header_file = is_library.then(|| format!("{}/{}.h", output_dir, output_file));
 
     // Read the source file
-    let source = fs::read_to_string(input_file).map_err(CompileError::IoError)?;
+    let source = read_source(input_file)?;
+    let (ast, symbol_table) = parse_source(&source, input_file)?;
 
-    // Initialize module system
-    let mut module_system = ModuleSystem::new();
+    if matches.get_flag("dump-symbols") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&symbol_table.to_json()).unwrap()
+        );
+        return Ok(());
+    }
 
-    // Lexical analysis
-    let mut lexer = Lexer::new(source);
-    let tokens = lexer
-        .tokenize()
-        .map_err(|e| CompileError::CodegenError(format!("Lexer error: {}", e)))?;
+    let module_system = checked(&ast, input_file, &source)?;
 
-    // Parsing
-    let mut parser = Parser::new(tokens);
-    let ast = parser
-        .parse()
-        .map_err(|e| CompileError::CodegenError(format!("Parser error: {}", e)))?;
+    if matches.get_flag("emit-ir") {
+        let program = lower_program(&ast, &module_system).map_err(CompileError::CodegenError)?;
+        print!("{}", program);
+        return Ok(());
+    }
 
-    // Extract symbol table from parser
-    let symbol_table = parser.into_symbol_table();
+    if matches.get_flag("interpret") {
+        return interpret_program(
+            ast,
+            module_system,
+            input_file.clone(),
+            matches.get_flag("hot-reload"),
+        );
+    }
 
-    // Resolve imports and load modules
-    module_system
-        .resolve_imports(&ast)
-        .map_err(|e| CompileError::CodegenError(format!("Module resolution error: {}", e)))?;
+    let symbol_index = if matches.get_flag("index") {
+        Some(build_symbol_index(
+            input_file,
+            &source,
+            &ast,
+            &symbol_table,
+            &module_system,
+        ))
+    } else {
+        None
+    };
 
     // Code generation with module support and symbol table
     let mut codegen = CCodeGen::with_symbol_table(symbol_table);
-    let c_code = codegen.compile_program_with_modules(ast, &module_system);
+    codegen.set_string_arena(matches.get_flag("arena"));
+    codegen.set_strict(matches.get_flag("strict"));
+    codegen.set_library(is_library);
+    if !matches.get_flag("no-banner") {
+        codegen.set_banner(generated_banner(
+            input_file,
+            !matches.get_flag("no-timestamp"),
+            matches.get_one::<String>("header"),
+        )?);
+    }
+    let c_code = generate_c(&mut codegen, ast, &module_system, input_file, &source)?;
+    let c_file = format!("{}/{}.c", output_dir, output_file);
+
+    // Code generation is what finds the libraries to link, so it runs, but
+    // nothing is written and gcc isn't started
+    if matches.get_flag("dry-run") {
+        let mut modules: Vec<PlannedModule> = module_system
+            .module_paths()
+            .filter_map(|import| {
+                module_system
+                    .get_source(import)
+                    .map(|source| (import, source))
+            })
+            .map(|(import, source)| PlannedModule {
+                import: import.clone(),
+                path: source.file_path.clone(),
+                embedded: !Path::new(&source.file_path).is_file(),
+            })
+            .collect();
+        modules.sort_by(|a, b| a.import.cmp(&b.import));
+        let plan = BuildPlan {
+            input: input_file.clone(),
+            mode: build_mode,
+            modules,
+            c_file: c_file.clone(),
+            output: full_output_path.clone(),
+            header: header_file.clone(),
+            keeps_c_file: !is_release,
+            libraries: sorted_libraries(&codegen.required_libraries),
+            gcc: gcc_command_line(
+                &c_file,
+                &full_output_path,
+                &codegen.required_libraries,
+                is_release,
+                is_library,
+            ),
+            index: matches.get_flag("index").then(|| INDEX_FILE.to_string()),
+        };
+        println!("{}", serde_json::to_string_pretty(&plan).unwrap());
+        return Ok(());
+    }
 
     // Output generated C code for debugging (only in debug mode)
     if !is_release {
@@ -99,41 +342,559 @@ fn run() -> Result<(), CompileError> {
     }
 
     // Write C file to output directory
-    let c_file = format!("{}/{}.c", output_dir, output_file);
+    fs::create_dir_all(&output_dir).map_err(CompileError::IoError)?;
     fs::write(&c_file, c_code).map_err(CompileError::IoError)?;
 
-    // Compile with GCC (with optimizations in release mode)
-    let mut gcc_command = Command::new("gcc");
-    gcc_command.arg(&c_file).arg("-o").arg(&full_output_path);
+    compile_c(
+        &c_file,
+        &full_output_path,
+        &codegen.required_libraries,
+        is_release,
+        is_library,
+    )?;
+    if let Some(header_file) = &header_file {
+        fs::write(header_file, library_header(output_file)).map_err(CompileError::IoError)?;
+    }
 
-    // Add library linking flags for extern functions
-    for library in &codegen.required_libraries {
-        gcc_command.arg(&format!("-l{}", library));
+    if let Some(index) = symbol_index {
+        index
+            .write(Path::new(INDEX_FILE))
+            .map_err(CompileError::IoError)?;
+    }
+
+    // Clean up C file (only in release mode to keep debug artifacts)
+    if is_release {
+        fs::remove_file(&c_file).ok();
+    }
+
+    println!(
+        "Successfully compiled {} to {} ({})",
+        input_file, full_output_path, build_mode
+    );
+
+    Ok(())
+}
+
+// `-` reads the program from stdin, so `bolt run -` works at the end of a pipe
+fn read_source(input_file: &str) -> Result<String, CompileError> {
+    if input_file == "-" {
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .map_err(CompileError::IoError)?;
+        Ok(source)
+    } else {
+        fs::read_to_string(input_file).map_err(CompileError::IoError)
     }
+}
+
+// Parse errors don't end the run: the statements that did parse are still
+// type-checked, and every error from both phases is returned together
+fn parse_source(source: &str, path: &str) -> Result<(Program, SymbolTable), CompileError> {
+    let name = if path == "-" { "<stdin>" } else { path };
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    Compiler::new(&mut diagnostics)
+        .parse(source, name)
+        .ok_or_else(|| compile_errors(diagnostics))
+}
+
+fn compile_errors(diagnostics: Vec<Diagnostic>) -> CompileError {
+    CompileError::Multiple(
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| CompileError::CodegenError(diagnostic.message))
+            .collect(),
+    )
+}
+
+fn check_and_resolve(ast: &Program, input_file: &str) -> Result<ModuleSystem, CompileError> {
+    // Imports are resolved first: the program's constants may be defined
+    // with ones it imports. Views (Slice[T], StrView) may not be stored
+    // where they could outlive their data
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let Some(module_system) = Compiler::new(&mut diagnostics).resolve_and_check(ast) else {
+        return Err(compile_errors(diagnostics));
+    };
+    let warnings = diagnostics
+        .into_iter()
+        .filter_map(|diagnostic| {
+            Some(Warning {
+                lint: diagnostic.lint?,
+                message: diagnostic.message,
+            })
+        })
+        .collect();
+    report_lints(warnings, &lint_config(input_file)?)?;
+    Ok(module_system)
+}
+
+// check_and_resolve, reporting a panic as an internal compiler error
+fn checked(ast: &Program, input_file: &str, source: &str) -> Result<ModuleSystem, CompileError> {
+    match ice::catch("type checking", || check_and_resolve(ast, input_file)) {
+        Ok(result) => result,
+        Err(Stopped::UserError(message)) => Err(CompileError::CodegenError(message)),
+        Err(Stopped::Ice(crash)) => internal_error(crash, input_file, source),
+    }
+}
+
+// The program's C. A mistake in the program that only code generation
+// finds is an error like any other; any other panic is reported as an
+// internal compiler error in the function being compiled
+fn generate_c(
+    codegen: &mut CCodeGen,
+    ast: Program,
+    module_system: &ModuleSystem,
+    input_file: &str,
+    source: &str,
+) -> Result<String, CompileError> {
+    match ice::catch("code generation", || {
+        codegen.compile_program_with_modules(ast, module_system)
+    }) {
+        Ok(c_code) => Ok(c_code),
+        Err(Stopped::UserError(message)) => Err(CompileError::CodegenError(message)),
+        Err(Stopped::Ice(crash)) => {
+            let function = codegen.current_function().map(str::to_string);
+            internal_error(Ice { function, ..crash }, input_file, source)
+        }
+    }
+}
+
+// Print the crash report and exit like a Rust panic would, after writing
+// the smallest version of the program that still crashes the same way
+fn internal_error(crash: Ice, input_file: &str, source: &str) -> ! {
+    let minimized = ice::minimize(source, |candidate| {
+        crash_of(candidate).is_some_and(|other| other.same_as(&crash))
+    });
+    let repro = fs::write(REPRO_FILE, crash.repro(&minimized))
+        .ok()
+        .map(|_| REPRO_FILE);
+    let name = if input_file == "-" {
+        "<stdin>"
+    } else {
+        input_file
+    };
+    for line in crash.report(name, source, repro) {
+        eprintln!("{}", line);
+    }
+    std::process::exit(101);
+}
+
+// How compiling `source` crashes, if it does; warnings aren't reported and
+// a program that doesn't parse or check doesn't crash
+fn crash_of(source: &str) -> Option<Ice> {
+    let mut ignore = |_: Diagnostic| {};
+    let (ast, symbol_table) = Compiler::new(&mut ignore).parse(source, "<repro>")?;
+    let module_system = match ice::catch("type checking", || {
+        Compiler::new(&mut ignore).resolve_and_check(&ast)
+    }) {
+        Ok(module_system) => module_system?,
+        Err(Stopped::Ice(crash)) => return Some(crash),
+        Err(Stopped::UserError(_)) => return None,
+    };
+    let mut codegen = CCodeGen::with_symbol_table(symbol_table);
+    match ice::catch("code generation", || {
+        codegen.compile_program_with_modules(ast, &module_system)
+    }) {
+        Err(Stopped::Ice(crash)) => Some(crash),
+        _ => None,
+    }
+}
+
+// The [lints] levels of the bolt.toml or .boltlint nearest the input file
+// (or the current directory, for stdin)
+fn lint_config(input_file: &str) -> Result<LintConfig, CompileError> {
+    let dir = match Path::new(input_file).parent() {
+        Some(parent) if input_file != "-" && !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    LintConfig::load(dir).map_err(CompileError::CodegenError)
+}
+
+// Print the warnings the config doesn't allow; any denied one fails the build
+fn report_lints(
+    warnings: Vec<bolt::checker::Warning>,
+    config: &LintConfig,
+) -> Result<(), CompileError> {
+    let mut denied = 0;
+    for (level, warning) in config.apply(warnings) {
+        let label = match level {
+            Level::Deny => {
+                denied += 1;
+                "Error"
+            }
+            _ => "Warning",
+        };
+        eprintln!("{}: {} [{}]", label, warning.message, warning.lint);
+    }
+    match denied {
+        0 => Ok(()),
+        1 => Err(CompileError::CodegenError(
+            "1 denied lint (see [lints] in bolt.toml)".to_string(),
+        )),
+        n => Err(CompileError::CodegenError(format!(
+            "{} denied lints (see [lints] in bolt.toml)",
+            n
+        ))),
+    }
+}
+
+// `bolt check`: everything up to code generation, for editors and CI
+fn check_only(matches: &ArgMatches) -> Result<(), CompileError> {
+    let input_file = matches.get_one::<String>("input").unwrap();
+    let source = read_source(input_file)?;
+    let (ast, _) = parse_source(&source, input_file)?;
+    checked(&ast, input_file, &source)?;
+    println!("{}: no errors", input_file);
+    Ok(())
+}
+
+// What a build would do, printed as JSON by --dry-run so build systems
+// (Make, Bazel wrappers) can declare its inputs and outputs
+#[derive(Serialize)]
+struct BuildPlan {
+    input: String,
+    mode: &'static str, // "debug" or "release"
+    modules: Vec<PlannedModule>,
+    c_file: String,
+    output: String,
+    header: Option<String>, // The C header of a --lib build
+    keeps_c_file: bool,     // Release builds delete the C file after gcc
+    libraries: Vec<String>,
+    gcc: Vec<String>,      // The full command line, program first
+    index: Option<String>, // Written with --index
+}
+
+#[derive(Serialize)]
+struct PlannedModule {
+    import: String, // As written in the import, e.g. "bolt:stdio"
+    path: String,
+    embedded: bool, // Taken from the compiler's embedded stdlib, not from `path`
+}
+
+// Sorted, so the command line is the same from one run to the next
+fn sorted_libraries(libraries: &HashSet<String>) -> Vec<String> {
+    let mut libraries: Vec<String> = libraries.iter().cloned().collect();
+    libraries.sort();
+    libraries
+}
+
+// The gcc invocation for a C file (with optimizations in release mode),
+// building a shared library instead of an executable when `shared` is set
+fn gcc_command_line(
+    c_file: &str,
+    output_path: &str,
+    libraries: &HashSet<String>,
+    is_release: bool,
+    shared: bool,
+) -> Vec<String> {
+    let mut command = vec![
+        "gcc".to_string(),
+        c_file.to_string(),
+        "-o".to_string(),
+        output_path.to_string(),
+    ];
+    if shared {
+        command.extend(["-shared".to_string(), "-fPIC".to_string()]);
+    }
+
+    // Add library linking flags for extern functions
+    command.extend(
+        sorted_libraries(libraries)
+            .into_iter()
+            .map(|library| format!("-l{}", library)),
+    );
 
     if is_release {
-        gcc_command.arg("-O2").arg("-DNDEBUG");
+        command.extend(["-O2".to_string(), "-DNDEBUG".to_string()]);
     } else {
-        gcc_command.arg("-g").arg("-DDEBUG");
+        command.extend(["-g".to_string(), "-DDEBUG".to_string()]);
     }
+    command
+}
 
-    let status = gcc_command.status().map_err(CompileError::IoError)?;
+fn compile_c(
+    c_file: &str,
+    output_path: &str,
+    libraries: &HashSet<String>,
+    is_release: bool,
+    shared: bool,
+) -> Result<(), CompileError> {
+    let command = gcc_command_line(c_file, output_path, libraries, is_release, shared);
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .status()
+        .map_err(CompileError::IoError)?;
 
     if !status.success() {
         return Err(CompileError::CodegenError(
             "GCC compilation failed".to_string(),
         ));
     }
+    Ok(())
+}
 
-    // Clean up C file (only in release mode to keep debug artifacts)
-    if is_release {
-        fs::remove_file(&c_file).ok();
+// `bolt run`: a quiet debug build into out/debug, then the program runs with
+// the remaining arguments and bolt exits with its status. Together with the
+// lexer skipping a leading shebang, `#!/usr/bin/env -S bolt run` makes a
+// .bolt file an executable script.
+fn run_program(matches: &ArgMatches) -> Result<(), CompileError> {
+    let input_file = matches.get_one::<String>("input").unwrap();
+    let program_args: Vec<&String> = matches
+        .get_many::<String>("args")
+        .map(|args| args.collect())
+        .unwrap_or_default();
+
+    let source = read_source(input_file)?;
+    let (ast, symbol_table) = parse_source(&source, input_file)?;
+    let module_system = checked(&ast, input_file, &source)?;
+
+    let mut codegen = CCodeGen::with_symbol_table(symbol_table);
+    codegen.set_banner(generated_banner(input_file, true, None)?);
+    let c_code = generate_c(&mut codegen, ast, &module_system, input_file, &source)?;
+
+    let name = match input_file.as_str() {
+        "-" => "stdin".to_string(),
+        path => Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "script".to_string()),
+    };
+    let output_dir = "out/debug";
+    fs::create_dir_all(output_dir).map_err(CompileError::IoError)?;
+    let c_file = format!("{}/{}.c", output_dir, name);
+    let executable = format!("{}/{}", output_dir, name);
+    fs::write(&c_file, c_code).map_err(CompileError::IoError)?;
+    compile_c(
+        &c_file,
+        &executable,
+        &codegen.required_libraries,
+        false,
+        false,
+    )?;
+
+    let status = Command::new(&executable)
+        .args(program_args)
+        .status()
+        .map_err(CompileError::IoError)?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+// The provenance comment for the generated C. SOURCE_DATE_EPOCH, when set,
+// is the build time, as reproducible-build tooling expects
+fn generated_banner(
+    input_file: &str,
+    timestamp: bool,
+    header_file: Option<&String>,
+) -> Result<String, CompileError> {
+    let header = header_file
+        .map(fs::read_to_string)
+        .transpose()
+        .map_err(CompileError::IoError)?;
+    let generated_at = timestamp.then(|| {
+        std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.parse().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs())
+            })
+    });
+    let source = if input_file == "-" {
+        "stdin"
+    } else {
+        input_file
+    };
+    Ok(banner(source, generated_at, header.as_deref()))
+}
+
+fn print_version(verbose: bool) {
+    println!("bolt {}", env!("CARGO_PKG_VERSION"));
+    if verbose {
+        println!("commit: {}", env!("BOLT_COMMIT"));
+        println!(
+            "stdlib: {} ({} modules, embedded)",
+            stdlib::version(),
+            stdlib::SOURCES.len()
+        );
     }
+}
 
+// `bolt install`: the binaries are taken from the directory of the running
+// bolt, so `cargo build --release && ./target/release/bolt install` installs
+// (or updates) that build
+fn install_bolt(matches: &ArgMatches) -> Result<(), CompileError> {
+    let prefix = match matches.get_one::<String>("prefix") {
+        Some(prefix) => PathBuf::from(prefix),
+        None => default_prefix().ok_or_else(|| {
+            CompileError::CodegenError("HOME is not set; pass --prefix".to_string())
+        })?,
+    };
+    let exe = std::env::current_exe().map_err(CompileError::IoError)?;
+    let build_dir = exe.parent().unwrap_or(Path::new("."));
+
+    let installation = install(&prefix, build_dir).map_err(CompileError::IoError)?;
+    for binary in &installation.binaries {
+        println!("Installed {}", binary.display());
+    }
+    for name in &installation.missing {
+        eprintln!(
+            "Warning: {} was not found next to bolt; build it with `cargo build --bin {}`",
+            name, name
+        );
+    }
     println!(
-        "Successfully compiled {} to {} ({})",
-        input_file, full_output_path, build_mode
+        "Installed the standard library to {}",
+        installation.std_dir.display()
     );
+    if let Some(guidance) =
+        path_guidance(&installation.bin_dir, std::env::var_os("PATH").as_deref())
+    {
+        println!("{}", guidance);
+    }
+    Ok(())
+}
+
+// Lex and parse without compiling. The parser reports most syntax errors by
+// panicking, so a panic is caught and reported like any other parse error;
+// tests/grammar relies on this to tell rejected input from a crash.
+fn parse_only(source: String) -> Result<(), CompileError> {
+    let syntax_error = |message: String| {
+        CompileError::ParseError(ParseError::InvalidSyntax {
+            message,
+            location: None,
+        })
+    };
+
+    let result = ice::quietly(|| {
+        let tokens = Lexer::new(source).tokenize().map_err(syntax_error)?;
+        Parser::new(tokens).parse().map_err(syntax_error)
+    });
+
+    let program = match result {
+        Ok(parsed) => parsed?,
+        Err(payload) => return Err(syntax_error(ice::panic_message(payload.as_ref()))),
+    };
 
+    println!("Parsed {} top-level statements", program.statements.len());
     Ok(())
 }
+
+// Run the program in the interpreter. Its calls recurse on the Rust stack, so
+// it runs on a thread with room for deeply recursive Bolt programs.
+fn interpret_program(
+    ast: Program,
+    module_system: ModuleSystem,
+    input_file: String,
+    hot_reload: bool,
+) -> Result<(), CompileError> {
+    const STACK_SIZE: usize = 256 * 1024 * 1024;
+    let runner = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let stdout = std::io::stdout();
+            let mut out = stdout.lock();
+            let args = vec![input_file];
+            let result = if hot_reload {
+                interpret_with_hot_reload(&ast, module_system, args, &mut out)
+            } else {
+                interpret(&ast, &module_system, args, &mut out)
+            };
+            out.flush().ok();
+            result
+        })
+        .map_err(CompileError::IoError)?;
+    let status = runner
+        .join()
+        .unwrap_or_else(|_| Err("The interpreter crashed".to_string()))
+        .map_err(CompileError::RuntimeError)?;
+    // Like a compiled program, exit with the status main or exit() gave
+    if status != 0 {
+        std::process::exit(status);
+    }
+    Ok(())
+}
+
+// Index the entry file and every module it loaded, keyed by absolute path so
+// the LSP can match them against document URIs
+fn build_symbol_index(
+    input_file: &str,
+    source: &str,
+    program: &Program,
+    symbol_table: &SymbolTable,
+    module_system: &ModuleSystem,
+) -> SymbolIndex {
+    let absolute = |path: &str| {
+        fs::canonicalize(path)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| path.to_string())
+    };
+
+    let mut index = SymbolIndex::new();
+    let entry_exports = program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Function {
+                name,
+                exported: true,
+                ..
+            } => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    index.add_file(&absolute(input_file), source, symbol_table, entry_exports);
+
+    let mut module_paths: Vec<&String> = module_system.module_paths().collect();
+    module_paths.sort();
+    for module_path in module_paths {
+        if let Some(module) = module_system.get_source(module_path) {
+            let exports = module_system
+                .get_exports(module_path)
+                .map(|e| e.functions.clone())
+                .unwrap_or_default();
+            index.add_file(
+                &absolute(&module.file_path),
+                &module.source,
+                &module.symbol_table,
+                exports,
+            );
+        }
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcc_command_line_links_sorted_libraries() {
+        let libraries: HashSet<String> = ["pthread", "m"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            gcc_command_line("out/debug/app.c", "out/debug/app", &libraries, false, false),
+            vec![
+                "gcc",
+                "out/debug/app.c",
+                "-o",
+                "out/debug/app",
+                "-lm",
+                "-lpthread",
+                "-g",
+                "-DDEBUG"
+            ]
+        );
+        assert!(
+            gcc_command_line("app.c", "app", &HashSet::new(), true, false)
+                .ends_with(&["-O2".to_string(), "-DNDEBUG".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_gcc_command_line_builds_shared_library() {
+        let command = gcc_command_line("app.c", "libapp.so", &HashSet::new(), false, true);
+        assert_eq!(command[4..6], ["-shared", "-fPIC"]);
+    }
+}
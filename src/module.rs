@@ -1,9 +1,13 @@
-use crate::ast::{Program, Statement};
+use crate::ast::{Parameter, Program, Statement, Type};
+use crate::const_eval::{evaluate_constants_with, Constant};
 use crate::lexer::Lexer;
 use crate::parser::Parser;
+use crate::stdlib;
+use crate::symbol_table::SymbolTable;
 use std::collections::HashMap;
 use std::fs;
 
+/// Names a module exports, by kind
 #[derive(Debug, Clone)]
 pub struct ModuleExports {
     pub functions: Vec<String>,
@@ -11,11 +15,32 @@ pub struct ModuleExports {
     pub variables: Vec<String>,
 }
 
+/// Where a loaded module came from, kept for tooling such as the symbol index
+#[derive(Debug)]
+pub struct ModuleSource {
+    pub file_path: String,
+    pub source: String,
+    pub symbol_table: SymbolTable,
+}
+
+/// Declared parameters and return type of a function a module exports,
+/// whether written in Bolt or declared in a `native`/`extern` block
+#[derive(Debug, Clone)]
+pub struct FunctionSignature {
+    pub name: String,
+    pub params: Vec<Parameter>,
+    pub return_type: Option<Type>,
+}
+
+/// Loads the modules a program imports and answers questions about them.
+/// Modules are keyed by their path as written in the import (`bolt:math`,
+/// `./utils`), and each is loaded and parsed once however often it is imported.
 #[derive(Debug)]
 pub struct ModuleSystem {
     modules: HashMap<String, Program>,
     exports: HashMap<String, ModuleExports>,
     resolved_imports: HashMap<String, Vec<String>>,
+    sources: HashMap<String, ModuleSource>,
 }
 
 impl Default for ModuleSystem {
@@ -30,44 +55,125 @@ impl ModuleSystem {
             modules: HashMap::new(),
             exports: HashMap::new(),
             resolved_imports: HashMap::new(),
+            sources: HashMap::new(),
         }
     }
 
-    pub fn load_module(&mut self, module_path: &str) -> Result<(), String> {
-        if self.modules.contains_key(module_path) {
-            return Ok(()); // Already loaded
-        }
-
-        // Handle bolt: standard library prefix
-        let file_path = if module_path.starts_with("bolt:") {
-            let std_module = module_path.strip_prefix("bolt:").unwrap();
+    /// The file an import path refers to: `bolt:name` is `std/name.bolt`,
+    /// and other paths get a `.bolt` extension unless they already have one
+    pub fn resolve_path(module_path: &str) -> String {
+        if let Some(std_module) = module_path.strip_prefix("bolt:") {
             format!("std/{}.bolt", std_module)
         } else if module_path.ends_with(".bolt") {
             module_path.to_string()
         } else {
             format!("{}.bolt", module_path)
-        };
+        }
+    }
 
-        // Read the file
-        let content = fs::read_to_string(&file_path)
-            .map_err(|e| format!("Failed to read module '{}': {}", file_path, e))?;
+    /// Load, parse and record the exports of a module, unless it is already
+    /// loaded. Fails if the file can't be read, or if the module defines
+    /// `main` or runs top-level code, which only the entry module may do.
+    pub fn load_module(&mut self, module_path: &str) -> Result<(), String> {
+        if self.modules.contains_key(module_path) {
+            return Ok(()); // Already loaded
+        }
+
+        let file_path = Self::resolve_path(module_path);
+
+        // Read the file; without a std/ directory at hand (an installed
+        // bolt run elsewhere), `bolt:` modules come from the embedded stdlib
+        let content = match fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(e) => module_path
+                .strip_prefix("bolt:")
+                .and_then(stdlib::source)
+                .map(str::to_string)
+                .ok_or_else(|| format!("Failed to read module '{}': {}", file_path, e))?,
+        };
 
         // Parse the module
-        let mut lexer = Lexer::new(content);
+        let mut lexer = Lexer::new(content.clone());
         let tokens = lexer.tokenize()?;
-        let mut parser = Parser::new(tokens);
+        let mut parser = Parser::new(tokens).with_source_name(&file_path);
         let program = parser.parse()?;
+        let symbol_table = parser.into_symbol_table();
+
+        // Only the entry module runs code; imported modules just declare things
+        if defines_main(&program.statements) {
+            return Err(format!(
+                "Module '{}' defines main; only the entry module may define main",
+                module_path
+            ));
+        }
+        if program.statements.iter().any(is_top_level_code) {
+            return Err(format!(
+                "Module '{}' has top-level statements; only the entry module may run code outside functions",
+                module_path
+            ));
+        }
 
         // Extract exports from the module
         let exports = self.extract_exports(&program);
+        let imports: Vec<String> = program
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Import { module_path, .. } => Some(module_path.clone()),
+                _ => None,
+            })
+            .collect();
 
         // Store the module and its exports
         self.modules.insert(module_path.to_string(), program);
         self.exports.insert(module_path.to_string(), exports);
+        self.sources.insert(
+            module_path.to_string(),
+            ModuleSource {
+                file_path,
+                source: content,
+                symbol_table,
+            },
+        );
+
+        // What the module imports in turn, e.g. constants its own are
+        // defined with (stored first, so an import cycle stops here)
+        for import in imports {
+            self.load_module(&import)?;
+        }
 
         Ok(())
     }
 
+    /// Read and parse a loaded module again, replacing it, for hot reloading.
+    /// If the new version can't be loaded (including a syntax error the
+    /// parser panics on) the previous one stays in place.
+    pub fn reload_module(&mut self, module_path: &str) -> Result<(), String> {
+        let previous = (
+            self.modules.remove(module_path),
+            self.exports.remove(module_path),
+            self.sources.remove(module_path),
+        );
+        let loaded = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.load_module(module_path)
+        }))
+        .unwrap_or_else(|panic| {
+            Err(panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "unknown error".to_string()))
+        });
+        if loaded.is_err() {
+            if let (Some(program), Some(exports), Some(source)) = previous {
+                self.modules.insert(module_path.to_string(), program);
+                self.exports.insert(module_path.to_string(), exports);
+                self.sources.insert(module_path.to_string(), source);
+            }
+        }
+        loaded
+    }
+
     fn extract_exports(&self, program: &Program) -> ModuleExports {
         let mut exports = ModuleExports {
             functions: Vec::new(),
@@ -77,11 +183,20 @@ impl ModuleSystem {
 
         for statement in &program.statements {
             match statement {
+                Statement::Export { item } if defines_constant(&program.statements, item) => {
+                    exports.variables.push(item.clone());
+                }
                 Statement::Export { item } => {
-                    // For now, categorize all exports as functions
-                    // In a more sophisticated system, we'd track types of exported items
+                    // Everything else is categorized as a function for now
                     exports.functions.push(item.clone());
                 }
+                Statement::ConstDecl {
+                    name,
+                    exported: true,
+                    ..
+                } => {
+                    exports.variables.push(name.clone());
+                }
                 Statement::NativeBlock { functions, .. } => {
                     // Extract exported functions from native blocks
                     for native_func in functions {
@@ -98,11 +213,9 @@ impl ModuleSystem {
                         }
                     }
                 }
-                Statement::Function { name, exported, .. } => {
+                Statement::Function { name, exported, .. } if *exported => {
                     // Extract exported regular functions
-                    if *exported {
-                        exports.functions.push(name.clone());
-                    }
+                    exports.functions.push(name.clone());
                 }
                 _ => {}
             }
@@ -111,7 +224,12 @@ impl ModuleSystem {
         exports
     }
 
+    /// Load every module the entry program imports and record what each
+    /// import brings into scope. Importing the same module more than once
+    /// (e.g. `import { a } ...` and `import { b } ...`) accumulates the items.
     pub fn resolve_imports(&mut self, main_program: &Program) -> Result<(), String> {
+        check_entry_program(main_program)?;
+
         for statement in &main_program.statements {
             if let Statement::Import {
                 module_name,
@@ -147,21 +265,156 @@ impl ModuleSystem {
                 } else {
                     module_path.clone()
                 };
-                self.resolved_imports.insert(key, imported_items);
+                let resolved = self.resolved_imports.entry(key).or_default();
+                for item in imported_items {
+                    if !resolved.contains(&item) {
+                        resolved.push(item);
+                    }
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Items an import statement brought into scope: pass the namespace
+    /// for `import math from "bolt:math"`, or None for `import { .. } from`
+    pub fn imported_items(&self, module_path: &str, namespace: Option<&str>) -> Option<&[String]> {
+        let key = match namespace {
+            Some(namespace) => format!("{}:{}", namespace, module_path),
+            None => module_path.to_string(),
+        };
+        self.resolved_imports.get(&key).map(Vec::as_slice)
+    }
+
+    /// Signature of a function the module exports; None if the module isn't
+    /// loaded or doesn't export a function by that name
+    pub fn function_signature(&self, module_path: &str, name: &str) -> Option<FunctionSignature> {
+        if !self
+            .get_exports(module_path)?
+            .functions
+            .iter()
+            .any(|f| f == name)
+        {
+            return None;
+        }
+        let signature = |params: &[Parameter], return_type: &Option<Type>| FunctionSignature {
+            name: name.to_string(),
+            params: params.to_vec(),
+            return_type: return_type.clone(),
+        };
+        self.get_module(module_path)?
+            .statements
+            .iter()
+            .find_map(|statement| match statement {
+                Statement::Function {
+                    name: function,
+                    params,
+                    return_type,
+                    ..
+                } if function == name => Some(signature(params, return_type)),
+                Statement::NativeBlock { functions, .. } => functions
+                    .iter()
+                    .find(|f| f.name == name)
+                    .map(|f| signature(&f.params, &f.return_type)),
+                Statement::ExternBlock { functions, .. } => functions
+                    .iter()
+                    .find(|f| f.name == name)
+                    .map(|f| signature(&f.params, &f.return_type)),
+                _ => None,
+            })
+    }
+
+    /// Constants visible in a loaded module: the ones it imports, then its
+    /// own, all evaluated at compile time
+    pub fn module_constants(&self, module_path: &str) -> Result<Vec<Constant>, String> {
+        let module = self
+            .get_module(module_path)
+            .ok_or_else(|| format!("Module '{}' not found", module_path))?;
+        let mut visiting = vec![module_path.to_string()];
+        let mut constants = self.imported_constants_from(&module.statements, &mut visiting)?;
+        constants.extend(evaluate_constants_with(&module.statements, &constants)?);
+        Ok(constants)
+    }
+
+    /// Constants the `import { .. }` statements among `statements` bring in,
+    /// with their values folded through however many modules define them
+    pub fn imported_constants(&self, statements: &[Statement]) -> Result<Vec<Constant>, String> {
+        self.imported_constants_from(statements, &mut Vec::new())
+    }
+
+    // `visiting` holds the modules whose constants are being evaluated, to
+    // catch modules whose constants depend on each other
+    fn imported_constants_from(
+        &self,
+        statements: &[Statement],
+        visiting: &mut Vec<String>,
+    ) -> Result<Vec<Constant>, String> {
+        let mut imported = Vec::new();
+        for statement in statements {
+            let Statement::Import {
+                module_path,
+                items: Some(items),
+                ..
+            } = statement
+            else {
+                continue;
+            };
+            let Some(module) = self.get_module(module_path) else {
+                continue;
+            };
+            let names: Vec<&String> = items
+                .iter()
+                .filter(|item| defines_constant(&module.statements, item))
+                .collect();
+            if names.is_empty() {
+                continue;
+            }
+            let exported = &self.exports[module_path].variables;
+            if let Some(name) = names.iter().find(|name| !exported.contains(name)) {
+                return Err(format!(
+                    "Constant '{}' isn't exported by module '{}'; declare it with `export const`",
+                    name, module_path
+                ));
+            }
+            if visiting.contains(module_path) {
+                visiting.push(module_path.clone());
+                return Err(format!(
+                    "Constants of modules {} depend on each other",
+                    visiting.join(" -> ")
+                ));
+            }
+
+            visiting.push(module_path.clone());
+            let theirs = self.imported_constants_from(&module.statements, visiting)?;
+            let own = evaluate_constants_with(&module.statements, &theirs)?;
+            visiting.pop();
+            imported.extend(own.into_iter().filter(|c| names.contains(&&c.name)));
+        }
+        Ok(imported)
+    }
+
+    /// The parsed program of a loaded module
     pub fn get_module(&self, module_path: &str) -> Option<&Program> {
         self.modules.get(module_path)
     }
 
+    /// Everything a loaded module exports
     pub fn get_exports(&self, module_path: &str) -> Option<&ModuleExports> {
         self.exports.get(module_path)
     }
 
+    pub fn get_source(&self, module_path: &str) -> Option<&ModuleSource> {
+        self.sources.get(module_path)
+    }
+
+    /// Paths of every loaded module, as written in the import statements
+    pub fn module_paths(&self) -> impl Iterator<Item = &String> {
+        self.modules.keys()
+    }
+
+    /// Every function of every loaded module, exported or not, mapped to the
+    /// module that defines it
     pub fn get_all_functions(&self) -> HashMap<String, String> {
         let mut all_functions = HashMap::new();
 
@@ -191,3 +444,257 @@ impl ModuleSystem {
         all_functions
     }
 }
+
+/// Whether a top-level statement runs code rather than declaring something.
+/// Only the entry module may contain these.
+pub fn is_top_level_code(statement: &Statement) -> bool {
+    !matches!(
+        statement,
+        Statement::Function { .. }
+            | Statement::TypeDef { .. }
+            | Statement::Interface { .. }
+            | Statement::ConstDecl { .. }
+            | Statement::Import { .. }
+            | Statement::Export { .. }
+            | Statement::NativeBlock { .. }
+            | Statement::ExternBlock { .. }
+    )
+}
+
+fn defines_constant(statements: &[Statement], name: &str) -> bool {
+    statements
+        .iter()
+        .any(|s| matches!(s, Statement::ConstDecl { name: constant, .. } if constant == name))
+}
+
+pub fn defines_main(statements: &[Statement]) -> bool {
+    statements
+        .iter()
+        .any(|s| matches!(s, Statement::Function { name, .. } if name == "main"))
+}
+
+/// The entry module either defines `fun main` or runs its top-level
+/// statements as the program, not both.
+pub fn check_entry_program(program: &Program) -> Result<(), String> {
+    check_entry_statements(&program.statements)
+}
+
+pub fn check_entry_statements(statements: &[Statement]) -> Result<(), String> {
+    if defines_main(statements) && statements.iter().any(is_top_level_code) {
+        return Err(
+            "Top-level statements can't be mixed with `fun main`; move them into main".to_string(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Program {
+        let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    fn resolved(source: &str) -> Result<ModuleSystem, String> {
+        let mut modules = ModuleSystem::new();
+        modules.resolve_imports(&parse(source))?;
+        Ok(modules)
+    }
+
+    // A user module in its own temp directory, returned as an import path
+    fn user_module(name: &str, source: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("bolt_module_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.bolt", name));
+        fs::write(&path, source).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        assert_eq!(ModuleSystem::resolve_path("bolt:math"), "std/math.bolt");
+        assert_eq!(ModuleSystem::resolve_path("lib/utils"), "lib/utils.bolt");
+        assert_eq!(
+            ModuleSystem::resolve_path("lib/utils.bolt"),
+            "lib/utils.bolt"
+        );
+    }
+
+    #[test]
+    fn test_builtin_module_exports_and_signatures() {
+        let modules = resolved("import { max } from \"bolt:math\"\n").unwrap();
+        let exports = modules.get_exports("bolt:math").unwrap();
+        assert!(exports.functions.iter().any(|f| f == "max"));
+        assert!(exports.functions.iter().any(|f| f == "abs"));
+
+        let max = modules.function_signature("bolt:math", "max").unwrap();
+        let params: Vec<(&str, &Type)> = max
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), &p.param_type))
+            .collect();
+        assert_eq!(params, vec![("a", &Type::Integer), ("b", &Type::Integer)]);
+        assert_eq!(max.return_type, Some(Type::Integer));
+        assert!(modules.function_signature("bolt:math", "nope").is_none());
+        assert!(modules.function_signature("bolt:stdio", "print").is_none());
+    }
+
+    #[test]
+    fn test_native_signatures() {
+        let path = user_module(
+            "native_sig",
+            "native \"C\" {\n    export fun twice(x: Integer): Integer\n    fun hidden(): Integer\n}\n",
+        );
+        let modules = resolved(&format!("import {{ twice }} from \"{}\"\n", path)).unwrap();
+        let twice = modules.function_signature(&path, "twice").unwrap();
+        assert_eq!(twice.params.len(), 1);
+        assert_eq!(twice.return_type, Some(Type::Integer));
+        // Only exported functions have a public signature
+        assert!(modules.function_signature(&path, "hidden").is_none());
+    }
+
+    #[test]
+    fn test_selective_and_namespace_imports() {
+        let modules =
+            resolved("import { max } from \"bolt:math\"\nimport stdio from \"bolt:stdio\"\n")
+                .unwrap();
+        assert_eq!(
+            modules.imported_items("bolt:math", None),
+            Some(&["max".to_string()][..])
+        );
+        assert_eq!(modules.imported_items("bolt:math", Some("math")), None);
+
+        // A namespace import brings in everything the module exports
+        let stdio = modules.imported_items("bolt:stdio", Some("stdio")).unwrap();
+        assert!(stdio.iter().any(|item| item == "print"));
+        assert!(stdio.iter().any(|item| item == "println"));
+        assert!(stdio.iter().any(|item| item == "write"));
+        assert!(stdio.iter().any(|item| item == "format"));
+        assert_eq!(modules.imported_items("bolt:stdio", None), None);
+    }
+
+    #[test]
+    fn test_unknown_module_is_an_error() {
+        let error = resolved("import { f } from \"bolt:no_such_module\"\n").unwrap_err();
+        assert!(error.starts_with("Failed to read module 'std/no_such_module.bolt'"));
+    }
+
+    #[test]
+    fn test_duplicate_imports_load_once_and_merge_items() {
+        let modules =
+            resolved("import { max } from \"bolt:math\"\nimport { min, max } from \"bolt:math\"\n")
+                .unwrap();
+        assert_eq!(modules.module_paths().count(), 1);
+        assert_eq!(
+            modules.imported_items("bolt:math", None),
+            Some(&["max".to_string(), "min".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn test_modules_may_not_run_code() {
+        let path = user_module("runs_code", "val x := 1\n");
+        let error = resolved(&format!("import {{ x }} from \"{}\"\n", path)).unwrap_err();
+        assert!(error.contains("has top-level statements"));
+
+        let path = user_module("defines_main", "fun main() {\n}\n");
+        let error = resolved(&format!("import {{ main }} from \"{}\"\n", path)).unwrap_err();
+        assert!(error.contains("defines main"));
+    }
+
+    #[test]
+    fn test_interfaces_are_declarations() {
+        let entry = parse("interface Named {\n    fun name(): String\n}\nfun main() {\n}\n");
+        assert!(check_entry_statements(&entry.statements).is_ok());
+
+        let path = user_module(
+            "declares_interface",
+            "interface Named {\n    fun name(): String\n}\nexport fun one(): Integer {\n    return 1\n}\n",
+        );
+        assert!(resolved(&format!("import {{ one }} from \"{}\"\n", path)).is_ok());
+    }
+
+    #[test]
+    fn test_constants_fold_across_modules() {
+        let base = user_module("const_base", "export const PAGE: Integer = 512\n");
+        let config = user_module(
+            "const_config",
+            &format!(
+                "import {{ PAGE }} from \"{}\"\nexport const BUFFER := PAGE * 4\nconst PRIVATE := 1\n",
+                base
+            ),
+        );
+        let modules = resolved(&format!("import {{ BUFFER }} from \"{}\"\n", config)).unwrap();
+        // Modules the imported one imports are loaded too
+        assert_eq!(modules.module_paths().count(), 2);
+
+        let program = parse(&format!("import {{ BUFFER }} from \"{}\"\n", config));
+        let imported = modules.imported_constants(&program.statements).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].name, "BUFFER");
+        assert_eq!(
+            imported[0].value,
+            crate::const_eval::ConstValue::Integer(2048)
+        );
+        let names: Vec<String> = modules
+            .module_constants(&config)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, ["PAGE", "BUFFER", "PRIVATE"]);
+
+        let private = parse(&format!("import {{ PRIVATE }} from \"{}\"\n", config));
+        assert!(modules
+            .imported_constants(&private.statements)
+            .unwrap_err()
+            .contains("isn't exported"));
+    }
+
+    #[test]
+    fn test_constants_of_modules_may_not_depend_on_each_other() {
+        let a = user_module("const_cycle_a", "");
+        let b = user_module(
+            "const_cycle_b",
+            &format!("import {{ A }} from \"{}\"\nexport const B := A + 1\n", a),
+        );
+        fs::write(
+            &a,
+            format!("import {{ B }} from \"{}\"\nexport const A := B + 1\n", b),
+        )
+        .unwrap();
+        let source = format!("import {{ A }} from \"{}\"\n", a);
+        let modules = resolved(&source).unwrap();
+        assert!(modules
+            .imported_constants(&parse(&source).statements)
+            .unwrap_err()
+            .contains("depend on each other"));
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_version_on_error() {
+        let path = user_module(
+            "reloaded",
+            "export fun speed(): Integer {\n    return 1\n}\n",
+        );
+        let mut modules = resolved(&format!("import {{ speed }} from \"{}\"\n", path)).unwrap();
+
+        fs::write(
+            ModuleSystem::resolve_path(&path),
+            "export fun speed(): Integer {\n    return 2\n}\nexport fun boost(): Integer {\n    return 3\n}\n",
+        )
+        .unwrap();
+        modules.reload_module(&path).unwrap();
+        assert!(modules.function_signature(&path, "boost").is_some());
+
+        fs::write(
+            ModuleSystem::resolve_path(&path),
+            "export fun speed(: Integer {\n",
+        )
+        .unwrap();
+        assert!(modules.reload_module(&path).is_err());
+        assert!(modules.function_signature(&path, "boost").is_some());
+    }
+}
@@ -1,16 +1,29 @@
 use crate::ast::{
-    BinaryOperator, Expression, Field, NativeFunction, Parameter, Program, Statement, StructField,
-    Type, UnaryOperator,
+    AsmOperand, BinaryOperator, Expression, Field, IntKind, InterfaceMethod, InterpolationPart,
+    NativeFunction, Parameter, Program, Statement, StructField, Type, UnaryOperator,
 };
-use crate::lexer::{Token, TokenType};
-use crate::symbol_table::{ScopeKind, SymbolTable};
+use crate::error::{FixIt, ParseError, SourceLocation};
+use crate::lexer::{Lexer, StringPart, Token, TokenType};
+use crate::symbol_table::{method_function_name, ScopeKind, SymbolKind, SymbolTable};
+use std::panic::{self, AssertUnwindSafe};
 
 pub type ParseResult<T> = std::result::Result<T, String>;
 
+// The struct a method receiver belongs to; parse_receiver only accepts Custom
+fn receiver_type_name(receiver: &Parameter) -> String {
+    match &receiver.param_type {
+        Type::Custom(name) => name.clone(),
+        other => other.to_string(),
+    }
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     symbol_table: SymbolTable,
+    diagnostics: Vec<ParseError>, // Recoverable errors collected while parsing
+    allow_struct_literals: bool,  // False while parsing a loop condition like `while x {`
+    source_name: String,          // The file, for the locations panic and assert report
 }
 
 impl Parser {
@@ -19,9 +32,23 @@ impl Parser {
             tokens,
             current: 0,
             symbol_table: SymbolTable::new(),
+            diagnostics: Vec::new(),
+            allow_struct_literals: true,
+            source_name: "<input>".to_string(),
         }
     }
 
+    /// Name the file being parsed, as `panic` and `assert` report it
+    pub fn with_source_name(mut self, name: impl Into<String>) -> Self {
+        self.source_name = name.into();
+        self
+    }
+
+    /// Recoverable errors found during parsing (with fix-its where known)
+    pub fn diagnostics(&self) -> &[ParseError] {
+        &self.diagnostics
+    }
+
     /// Get a reference to the symbol table after parsing
     pub fn symbol_table(&self) -> &SymbolTable {
         &self.symbol_table
@@ -33,6 +60,21 @@ impl Parser {
     }
 
     pub fn parse(&mut self) -> ParseResult<Program> {
+        let program = self.parse_recovering();
+        if !self.diagnostics.is_empty() {
+            let messages: Vec<String> = self.diagnostics.iter().map(|d| d.to_string()).collect();
+            return Err(messages.join("\n"));
+        }
+
+        Ok(program)
+    }
+
+    /// Parse everything, even past errors: a statement the parser gives up
+    /// on (most syntax errors still panic) is recorded in `diagnostics()`
+    /// and parsing resumes at the next unindented line. The program holds
+    /// the statements that did parse, so it can still be type-checked for
+    /// more errors.
+    pub fn parse_recovering(&mut self) -> Program {
         let mut statements = Vec::new();
 
         while !self.is_at_end() {
@@ -41,21 +83,66 @@ impl Parser {
                 continue;
             }
 
-            statements.push(self.parse_statement());
+            let start = self.current;
+            let scope = self.symbol_table.current_scope();
+            match panic::catch_unwind(AssertUnwindSafe(|| self.parse_statement())) {
+                Ok(statement) => statements.push(statement),
+                Err(payload) => {
+                    let message = payload
+                        .downcast_ref::<String>()
+                        .cloned()
+                        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                        .unwrap_or_else(|| "unknown error".to_string());
+                    self.diagnostics.push(ParseError::InvalidSyntax {
+                        message,
+                        location: Some(self.current_location()),
+                    });
+                    // Scopes the failed statement opened were never closed
+                    while self.symbol_table.current_scope() != scope
+                        && self.symbol_table.exit_scope().is_ok()
+                    {}
+                    self.synchronize(start);
+                }
+            }
         }
 
-        Ok(Program { statements })
+        Program { statements }
+    }
+
+    // Skip to the next line starting in the first column (other than a
+    // closing brace), where a new top-level statement begins. Always moves
+    // past `start`, so a statement failing on its first token can't loop.
+    fn synchronize(&mut self, start: usize) {
+        if self.current == start {
+            self.advance();
+        }
+        while !self.is_at_end() {
+            let after_newline = self.tokens[self.current - 1].token_type == TokenType::Newline;
+            let token = self.peek();
+            if after_newline && token.column == 1 && token.token_type != TokenType::RightBrace {
+                return;
+            }
+            self.advance();
+        }
     }
 
     fn parse_statement(&mut self) -> Statement {
         match &self.peek().token_type {
             TokenType::Var => self.parse_var_decl(),
             TokenType::Val => self.parse_val_decl(),
+            TokenType::Const => self.parse_const_decl(false),
             TokenType::Type => self.parse_type_def(),
+            TokenType::Interface => self.parse_interface(),
             TokenType::If => self.parse_if_statement(),
             TokenType::For => self.parse_for_loop(),
+            TokenType::While => self.parse_while_loop(),
+            TokenType::Do => self.parse_do_while(),
             TokenType::Fun => self.parse_function(false),
             TokenType::Return => self.parse_return(),
+            TokenType::Defer => self.parse_defer(),
+            TokenType::Guard => self.parse_guard(),
+            TokenType::Unsafe => self.parse_unsafe(),
+            TokenType::Asm => self.parse_asm(),
             TokenType::Import => self.parse_import(),
             TokenType::Export => self.parse_export(),
             TokenType::Native => self.parse_native_block(),
@@ -65,57 +152,88 @@ impl Parser {
                 // Look ahead to see if it's an assignment
                 if matches!(self.peek().token_type, TokenType::Identifier(_)) {
                     let next_idx = self.current + 1;
-                    if next_idx < self.tokens.len()
-                        && self.tokens[next_idx].token_type == TokenType::Equal
-                    {
+                    let assign_op = self
+                        .tokens
+                        .get(next_idx)
+                        .and_then(|t| Self::assignment_operator(&t.token_type));
+                    if let Some(compound) = assign_op {
                         // It's an assignment
                         let name = match &self.advance().token_type {
                             TokenType::Identifier(n) => n.clone(),
                             _ => unreachable!(),
                         };
-                        self.advance(); // consume '='
-                        let value = self.parse_expression();
+                        self.advance(); // consume '=' or e.g. '+='
+                        let mut value = self.parse_expression();
+                        // `x += 1` is sugar for `x = x + 1`
+                        if let Some(operator) = compound {
+                            value = Expression::BinaryOp {
+                                left: Box::new(Expression::Identifier(name.clone())),
+                                operator,
+                                right: Box::new(value),
+                            };
+                        }
                         Statement::Assignment {
                             variable: name,
                             value,
                         }
                     } else {
-                        Statement::Expression(self.parse_expression())
+                        self.parse_expression_statement()
                     }
                 } else {
-                    Statement::Expression(self.parse_expression())
+                    self.parse_expression_statement()
+                }
+            }
+        }
+    }
+
+    // An expression evaluated for its effect. `panic(message)` and
+    // `assert(condition, message)` become statements that know their line;
+    // any other arity (like bolt:test's assert(condition)) is a plain call
+    fn parse_expression_statement(&mut self) -> Statement {
+        let location = format!("{}:{}", self.source_name, self.peek().line);
+        match self.parse_expression() {
+            Expression::FunctionCall { name, args } if name == "panic" && args.len() == 1 => {
+                let mut args = args.into_iter();
+                Statement::Panic {
+                    message: args.next().unwrap(),
+                    location,
+                }
+            }
+            Expression::FunctionCall { name, args } if name == "assert" && args.len() == 2 => {
+                let mut args = args.into_iter();
+                Statement::Assert {
+                    condition: args.next().unwrap(),
+                    message: args.next().unwrap(),
+                    location,
                 }
             }
+            expression => Statement::Expression(expression),
+        }
+    }
+
+    // `=` as Some(None), a compound assignment like `+=` as Some(its operator)
+    fn assignment_operator(token: &TokenType) -> Option<Option<BinaryOperator>> {
+        match token {
+            TokenType::Equal => Some(None),
+            TokenType::PlusEqual => Some(Some(BinaryOperator::Add)),
+            TokenType::MinusEqual => Some(Some(BinaryOperator::Subtract)),
+            TokenType::StarEqual => Some(Some(BinaryOperator::Multiply)),
+            TokenType::SlashEqual => Some(Some(BinaryOperator::Divide)),
+            TokenType::PercentEqual => Some(Some(BinaryOperator::Modulo)),
+            _ => None,
         }
     }
 
     fn parse_var_decl(&mut self) -> Statement {
         self.advance(); // consume 'var'
 
+        let name_location = self.current_location();
         let name = match &self.advance().token_type {
             TokenType::Identifier(name) => name.clone(),
             _ => panic!("Expected identifier after 'var'"),
         };
 
-        let mut type_annotation = None;
-        let value;
-
-        if self.peek().token_type == TokenType::Colon {
-            self.advance(); // consume ':'
-            type_annotation = Some(self.parse_type());
-
-            if self.peek().token_type == TokenType::Equal {
-                self.advance(); // consume '='
-                value = self.parse_expression();
-            } else {
-                panic!("Expected '=' after type annotation");
-            }
-        } else if self.peek().token_type == TokenType::ColonEqual {
-            self.advance(); // consume ':='
-            value = self.parse_expression();
-        } else {
-            panic!("Expected ':' or ':=' after variable name");
-        }
+        let (type_annotation, value) = self.parse_declaration_value();
 
         // Register variable in symbol table
         let var_type = if let Some(ref explicit_type) = type_annotation {
@@ -127,9 +245,9 @@ impl Parser {
         };
 
         // Register the variable as mutable in the symbol table
-        if let Err(e) = self
-            .symbol_table
-            .declare_variable(name.clone(), var_type, true, None)
+        if let Err(e) =
+            self.symbol_table
+                .declare_variable(name.clone(), var_type, true, Some(name_location))
         {
             panic!("Error declaring variable '{}': {}", name, e);
         }
@@ -144,30 +262,13 @@ impl Parser {
     fn parse_val_decl(&mut self) -> Statement {
         self.advance(); // consume 'val'
 
+        let name_location = self.current_location();
         let name = match &self.advance().token_type {
             TokenType::Identifier(name) => name.clone(),
             _ => panic!("Expected identifier after 'val'"),
         };
 
-        let mut type_annotation = None;
-        let value;
-
-        if self.peek().token_type == TokenType::Colon {
-            self.advance(); // consume ':'
-            type_annotation = Some(self.parse_type());
-
-            if self.peek().token_type == TokenType::Equal {
-                self.advance(); // consume '='
-                value = self.parse_expression();
-            } else {
-                panic!("Expected '=' after type annotation");
-            }
-        } else if self.peek().token_type == TokenType::ColonEqual {
-            self.advance(); // consume ':='
-            value = self.parse_expression();
-        } else {
-            panic!("Expected ':' or ':=' after variable name");
-        }
+        let (type_annotation, value) = self.parse_declaration_value();
 
         // Register variable in symbol table
         let var_type = if let Some(ref explicit_type) = type_annotation {
@@ -178,9 +279,9 @@ impl Parser {
         };
 
         // Register the variable as immutable in the symbol table
-        if let Err(e) = self
-            .symbol_table
-            .declare_variable(name.clone(), var_type, false, None)
+        if let Err(e) =
+            self.symbol_table
+                .declare_variable(name.clone(), var_type, false, Some(name_location))
         {
             panic!("Error declaring variable '{}': {}", name, e);
         }
@@ -192,38 +293,181 @@ impl Parser {
         }
     }
 
+    fn parse_const_decl(&mut self, exported: bool) -> Statement {
+        self.advance(); // consume 'const'
+
+        let mut name_location = self.current_location();
+        let mut name = match &self.advance().token_type {
+            TokenType::Identifier(name) => name.clone(),
+            _ => panic!("Expected identifier after 'const'"),
+        };
+        // A constant on a type: `const Limits.MAX := 10`
+        let namespace = if self.peek().token_type == TokenType::Dot {
+            self.advance(); // consume '.'
+            let type_name = std::mem::take(&mut name);
+            self.expect_type_namespace(&type_name);
+            name_location = self.current_location();
+            name = match &self.advance().token_type {
+                TokenType::Identifier(name) => name.clone(),
+                _ => panic!("Expected constant name after '{}.'", type_name),
+            };
+            Some(type_name)
+        } else {
+            None
+        };
+
+        let (type_annotation, value) = self.parse_declaration_value();
+
+        let const_type = type_annotation
+            .clone()
+            .unwrap_or_else(|| Type::Custom("inferred".to_string()));
+        let declared = match &namespace {
+            Some(type_name) => self.symbol_table.declare_associated_constant(
+                type_name,
+                &name,
+                const_type,
+                Some(name_location),
+            ),
+            None => self.symbol_table.declare_variable(
+                name.clone(),
+                const_type,
+                false,
+                Some(name_location),
+            ),
+        };
+        if let Err(e) = declared {
+            panic!("Error declaring constant '{}': {}", name, e);
+        }
+        if let Some(type_name) = &namespace {
+            name = method_function_name(type_name, &name);
+        }
+
+        Statement::ConstDecl {
+            name,
+            type_annotation,
+            value,
+            exported,
+        }
+    }
+
+    /// Parse the part of a `var`/`val` declaration after the name: either
+    /// `: Type = value` or `:= value`. Common beginner mistakes (`= value`,
+    /// `== value`, `: Type == value`) are recorded as diagnostics with a fix-it
+    /// and parsing continues as if the correct operator had been written.
+    fn parse_declaration_value(&mut self) -> (Option<Type>, Expression) {
+        if self.peek().token_type == TokenType::Colon {
+            self.advance(); // consume ':'
+            let type_annotation = self.parse_type();
+
+            match self.peek().token_type {
+                TokenType::Equal => {
+                    self.advance(); // consume '='
+                }
+                TokenType::EqualEqual => {
+                    let location = self.current_location();
+                    self.suggest_fix(
+                        "'==' is a comparison; use '=' to initialize a typed declaration",
+                        location,
+                        2,
+                        "=",
+                    );
+                    self.advance(); // consume '=='
+                }
+                _ => panic!("Expected '=' after type annotation"),
+            }
+
+            return (Some(type_annotation), self.parse_expression());
+        }
+
+        match self.peek().token_type {
+            TokenType::ColonEqual => {
+                self.advance(); // consume ':='
+            }
+            TokenType::Equal => {
+                let location = self.current_location();
+                self.suggest_fix(
+                    "Use ':=' to declare a variable with an inferred type",
+                    location,
+                    1,
+                    ":=",
+                );
+                self.advance(); // consume '='
+            }
+            TokenType::EqualEqual => {
+                let location = self.current_location();
+                self.suggest_fix(
+                    "'==' is a comparison; use ':=' to declare a variable",
+                    location,
+                    2,
+                    ":=",
+                );
+                self.advance(); // consume '=='
+            }
+            _ => panic!("Expected ':' or ':=' after variable name"),
+        }
+
+        (None, self.parse_expression())
+    }
+
+    // Optional `[T, K]` after the name of a generic type or function
+    fn parse_type_params(&mut self) -> Vec<String> {
+        let mut type_params = Vec::new();
+        if self.peek().token_type != TokenType::LeftBracket {
+            return type_params;
+        }
+        self.advance(); // consume '['
+
+        while self.peek().token_type != TokenType::RightBracket && !self.is_at_end() {
+            match &self.advance().token_type {
+                TokenType::Identifier(param_name) => {
+                    if type_params.contains(param_name) {
+                        panic!("Duplicate type parameter '{}'", param_name);
+                    }
+                    type_params.push(param_name.clone());
+                }
+                _ => panic!("Expected type parameter name"),
+            }
+
+            if self.peek().token_type == TokenType::Comma {
+                self.advance(); // consume ','
+            } else if self.peek().token_type != TokenType::RightBracket {
+                panic!("Expected ',' or ']' in type parameter list");
+            }
+        }
+
+        if self.peek().token_type != TokenType::RightBracket {
+            panic!("Expected ']' after type parameters");
+        }
+        self.advance(); // consume ']'
+        type_params
+    }
+
     fn parse_type_def(&mut self) -> Statement {
         self.advance(); // consume 'type'
 
+        let name_location = self.current_location();
         let name = match &self.advance().token_type {
             TokenType::Identifier(name) => name.clone(),
             _ => panic!("Expected identifier after 'type'"),
         };
 
         // Parse generic type parameters: type Name[T, K] = { ... }
-        let mut type_params = Vec::new();
-        if self.peek().token_type == TokenType::LeftBracket {
-            self.advance(); // consume '['
+        let type_params = self.parse_type_params();
 
-            while self.peek().token_type != TokenType::RightBracket && !self.is_at_end() {
+        // type Person implements Printable, Comparable = { ... }
+        let mut implements = Vec::new();
+        if self.peek().token_type == TokenType::Implements {
+            self.advance(); // consume 'implements'
+            loop {
                 match &self.advance().token_type {
-                    TokenType::Identifier(param_name) => {
-                        type_params.push(param_name.clone());
-                    }
-                    _ => panic!("Expected type parameter name"),
+                    TokenType::Identifier(interface) => implements.push(interface.clone()),
+                    _ => panic!("Expected interface name after 'implements'"),
                 }
-
-                if self.peek().token_type == TokenType::Comma {
-                    self.advance(); // consume ','
-                } else if self.peek().token_type != TokenType::RightBracket {
-                    panic!("Expected ',' or ']' in type parameter list");
+                if self.peek().token_type != TokenType::Comma {
+                    break;
                 }
+                self.advance(); // consume ','
             }
-
-            if self.peek().token_type != TokenType::RightBracket {
-                panic!("Expected ']' after type parameters");
-            }
-            self.advance(); // consume ']'
         }
 
         if self.peek().token_type != TokenType::Equal {
@@ -272,14 +516,204 @@ impl Parser {
         }
         self.advance(); // consume '}'
 
+        // Known type names let expressions tell `Box[Integer] { ... }` from `items[i]`
+        let definition = if type_params.is_empty() {
+            Type::Custom(name.clone())
+        } else {
+            Type::Generic {
+                name: name.clone(),
+                type_params: type_params.iter().cloned().map(Type::Custom).collect(),
+            }
+        };
+        if let Err(e) =
+            self.symbol_table
+                .declare_type(name.clone(), definition, Some(name_location))
+        {
+            panic!("Error declaring type '{}': {}", name, e);
+        }
+
         Statement::TypeDef {
             name,
             type_params,
             fields,
+            implements,
+        }
+    }
+
+    // interface Printable {
+    //     fun toString(): String
+    // }
+    fn parse_interface(&mut self) -> Statement {
+        self.advance(); // consume 'interface'
+
+        let name_location = self.current_location();
+        let name = match &self.advance().token_type {
+            TokenType::Identifier(name) => name.clone(),
+            _ => panic!("Expected identifier after 'interface'"),
+        };
+
+        if self.peek().token_type != TokenType::LeftBrace {
+            panic!("Expected '{{' after interface name");
+        }
+        self.advance(); // consume '{'
+
+        let mut methods: Vec<InterfaceMethod> = Vec::new();
+        while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
+            if self.peek().token_type == TokenType::Newline {
+                self.advance();
+                continue;
+            }
+
+            if self.peek().token_type != TokenType::Fun {
+                panic!(
+                    "Expected a method signature ('fun') in interface '{}'",
+                    name
+                );
+            }
+            self.advance(); // consume 'fun'
+            let method_name = match &self.advance().token_type {
+                TokenType::Identifier(name) => name.clone(),
+                _ => panic!("Expected method name"),
+            };
+            if methods.iter().any(|m| m.name == method_name) {
+                panic!(
+                    "Method '{}' is declared twice in interface '{}'",
+                    method_name, name
+                );
+            }
+            let (params, _) = self.parse_parameters();
+            let return_type = self.parse_return_type();
+            methods.push(InterfaceMethod {
+                name: method_name,
+                params,
+                return_type,
+            });
+        }
+
+        if self.peek().token_type != TokenType::RightBrace {
+            panic!("Expected '}}'");
         }
+        self.advance(); // consume '}'
+
+        // Parameters can then be declared as `item: Printable`
+        if let Err(e) = self.symbol_table.declare_type(
+            name.clone(),
+            Type::Custom(name.clone()),
+            Some(name_location),
+        ) {
+            panic!("Error declaring interface '{}': {}", name, e);
+        }
+
+        Statement::Interface { name, methods }
+    }
+
+    // Whether `name[...]` at `bracket` holds generic type arguments
+    // (`Box[Integer] { ... }`) rather than an index (`matrix[row]`). Variables
+    // and functions always index; otherwise the brackets must read as a type
+    // list, and either `name` or every type named in the list must be a type.
+    fn starts_generic_args(&self, name: &str, bracket: usize) -> bool {
+        if let Some(id) = self.symbol_table.lookup(name) {
+            let is_type = self
+                .symbol_table
+                .get_symbol(id)
+                .is_some_and(|symbol| matches!(symbol.kind, SymbolKind::Type { .. }));
+            if !is_type {
+                return false;
+            }
+        }
+        match self.scan_type_args(bracket) {
+            Some(type_names) => {
+                self.is_type_name(name) || type_names.iter().all(|n| self.is_type_name(n))
+            }
+            None => false,
+        }
+    }
+
+    // `alloc[T](count)` / `realloc[T](block, count)`, unless the program
+    // declares its own alloc. T may be a generic parameter, which isn't a type
+    // symbol, so this can't go through starts_generic_args.
+    fn starts_allocation(&self, name: &str, bracket: usize) -> bool {
+        matches!(name, "alloc" | "realloc")
+            && self.symbol_table.lookup(name).is_none()
+            && self.scan_type_args(bracket).is_some()
+    }
+
+    // The names in a bracketed type list starting at `bracket`, or None when
+    // the tokens can't be one (so `items[i + 1]` is never mistaken for types)
+    fn scan_type_args(&self, bracket: usize) -> Option<Vec<String>> {
+        let mut names = Vec::new();
+        let mut depth = 0;
+        for token in &self.tokens[bracket..] {
+            match &token.token_type {
+                TokenType::LeftBracket | TokenType::LeftParen => depth += 1,
+                TokenType::RightBracket | TokenType::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let closes_bracket = token.token_type == TokenType::RightBracket;
+                        return (closes_bracket && !names.is_empty()).then_some(names);
+                    }
+                }
+                TokenType::Identifier(name) => names.push(name.clone()),
+                TokenType::Comma | TokenType::Arrow | TokenType::Question | TokenType::Caret => {}
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    // A type declared in this file, whose namespace holds `Type.member`
+    // static functions and constants
+    fn is_declared_type(&self, name: &str) -> bool {
+        self.symbol_table
+            .lookup(name)
+            .and_then(|id| self.symbol_table.get_symbol(id))
+            .is_some_and(|symbol| matches!(symbol.kind, SymbolKind::Type { .. }))
+    }
+
+    fn expect_type_namespace(&self, name: &str) {
+        if !self.is_declared_type(name) {
+            panic!(
+                "'{}' isn't a type declared before this; static functions and constants belong to a type, like `fun Person.new()`",
+                name
+            );
+        }
+    }
+
+    fn is_type_name(&self, name: &str) -> bool {
+        const BUILTIN_TYPES: &[&str] = &[
+            "String", "Integer", "Float", "Bool", "Array", "Slice", "StrView", "Optional",
+        ];
+        if BUILTIN_TYPES.contains(&name) || IntKind::from_name(name).is_some() {
+            return true;
+        }
+        self.symbol_table
+            .lookup(name)
+            .and_then(|id| self.symbol_table.get_symbol(id))
+            .is_some_and(|symbol| matches!(symbol.kind, SymbolKind::Type { .. }))
     }
 
+    // A type, optionally followed by `?`: `Integer?` is Optional[Integer]
     fn parse_type(&mut self) -> Type {
+        let mut parsed = self.parse_non_optional_type();
+        loop {
+            // `Integer??` lexes as the coalescing operator
+            let depth = match self.peek().token_type {
+                TokenType::Question => 1,
+                TokenType::QuestionQuestion => 2,
+                _ => break,
+            };
+            self.advance(); // consume '?' or '??'
+            for _ in 0..depth {
+                parsed = Type::Generic {
+                    name: "Optional".to_string(),
+                    type_params: vec![parsed],
+                };
+            }
+        }
+        parsed
+    }
+
+    fn parse_non_optional_type(&mut self) -> Type {
         // Check for pointer type prefix (^)
         if self.peek().token_type == TokenType::Caret {
             self.advance(); // consume '^'
@@ -287,13 +721,66 @@ impl Parser {
             return Type::Pointer(Box::new(pointee_type));
         }
 
+        // Plain array type: [Integer]
+        if self.peek().token_type == TokenType::LeftBracket {
+            self.advance(); // consume '['
+            let element_type = self.parse_type();
+            if self.peek().token_type != TokenType::RightBracket {
+                panic!("Expected ']' after array element type");
+            }
+            self.advance(); // consume ']'
+            return Type::Array(Box::new(element_type));
+        }
+
+        // Function type: (Integer, Integer) -> Integer, or a tuple type
+        // (Integer, String) when no arrow follows
+        if self.peek().token_type == TokenType::LeftParen {
+            self.advance(); // consume '('
+
+            let mut params = Vec::new();
+            while self.peek().token_type != TokenType::RightParen && !self.is_at_end() {
+                params.push(self.parse_type());
+
+                if self.peek().token_type == TokenType::Comma {
+                    self.advance(); // consume ','
+                } else if self.peek().token_type != TokenType::RightParen {
+                    panic!("Expected ',' or ')' in function type parameter list");
+                }
+            }
+
+            if self.peek().token_type != TokenType::RightParen {
+                panic!("Expected ')' after function type parameters");
+            }
+            self.advance(); // consume ')'
+
+            if self.peek().token_type != TokenType::Arrow {
+                return match params.len() {
+                    0 => panic!("Expected '->' and a return type after function type parameters"),
+                    1 => params.pop().unwrap(), // (T) is just T
+                    _ => Type::Tuple(params),
+                };
+            }
+            self.advance(); // consume '->'
+
+            let return_type = self.parse_type();
+            return Type::Function {
+                params,
+                return_type: Box::new(return_type),
+            };
+        }
+
         let token = self.advance().clone();
         match &token.token_type {
             TokenType::Identifier(name) => {
                 match name.as_str() {
                     "String" => Type::String,
                     "Integer" => Type::Integer,
+                    "Float" => Type::Float,
                     "Bool" => Type::Bool,
+                    "Byte" => Type::SizedInt(IntKind::UInt8),
+                    sized if IntKind::from_name(sized).is_some() => {
+                        Type::SizedInt(IntKind::from_name(sized).unwrap())
+                    }
                     _ => {
                         // Check if this is a generic type like Array[T] or Map[K, V]
                         if self.peek().token_type == TokenType::LeftBracket {
@@ -334,7 +821,41 @@ impl Parser {
     }
 
     fn parse_expression(&mut self) -> Expression {
-        self.parse_logical_or()
+        let start = self.parse_logical_or();
+
+        // Ranges bind loosest: `0..n - 1` ends at n - 1
+        let inclusive = match self.peek().token_type {
+            TokenType::DotDot => false,
+            TokenType::DotDotEqual => true,
+            _ => return start,
+        };
+        self.advance(); // consume '..' or '..='
+        let end = self.parse_logical_or();
+        Expression::Range {
+            start: Box::new(start),
+            end: Box::new(end),
+            inclusive,
+        }
+    }
+
+    // The `${...}` part of an interpolated string is a complete expression
+    fn parse_interpolated_code(code: &str) -> Expression {
+        let tokens = Lexer::new(code.to_string())
+            .tokenize()
+            .unwrap_or_else(|e| panic!("Invalid string interpolation '{}': {}", code, e));
+        let mut parser = Parser::new(tokens);
+        if parser.is_at_end() {
+            panic!("Empty string interpolation '${{}}'");
+        }
+        let expr = parser.parse_expression();
+        if !parser.is_at_end() {
+            panic!(
+                "Unexpected {:?} in string interpolation '{}'",
+                parser.peek().token_type,
+                code
+            );
+        }
+        expr
     }
 
     fn parse_logical_or(&mut self) -> Expression {
@@ -370,7 +891,7 @@ impl Parser {
     }
 
     fn parse_comparison(&mut self) -> Expression {
-        let mut expr = self.parse_additive();
+        let mut expr = self.parse_coalesce();
 
         while matches!(
             self.peek().token_type,
@@ -390,7 +911,7 @@ impl Parser {
                 TokenType::GreaterEqual => BinaryOperator::GreaterEqual,
                 _ => unreachable!(),
             };
-            let right = self.parse_additive();
+            let right = self.parse_coalesce();
             expr = Expression::BinaryOp {
                 left: Box::new(expr),
                 operator,
@@ -401,6 +922,20 @@ impl Parser {
         expr
     }
 
+    // `??` binds looser than arithmetic but tighter than comparisons, and to
+    // the right: `a ?? b ?? 0` tries a, then b, then 0
+    fn parse_coalesce(&mut self) -> Expression {
+        let value = self.parse_additive();
+        if self.peek().token_type != TokenType::QuestionQuestion {
+            return value;
+        }
+        self.advance(); // consume '??'
+        Expression::Coalesce {
+            value: Box::new(value),
+            fallback: Box::new(self.parse_coalesce()),
+        }
+    }
+
     fn parse_additive(&mut self) -> Expression {
         let mut expr = self.parse_multiplicative();
 
@@ -422,7 +957,7 @@ impl Parser {
     }
 
     fn parse_multiplicative(&mut self) -> Expression {
-        let mut expr = self.parse_primary();
+        let mut expr = self.parse_cast();
 
         while matches!(
             self.peek().token_type,
@@ -434,7 +969,7 @@ impl Parser {
                 TokenType::Percent => BinaryOperator::Modulo,
                 _ => unreachable!(),
             };
-            let right = self.parse_primary();
+            let right = self.parse_cast();
             expr = Expression::BinaryOp {
                 left: Box::new(expr),
                 operator,
@@ -445,33 +980,110 @@ impl Parser {
         expr
     }
 
-    fn parse_primary(&mut self) -> Expression {
-        let mut expr = self.parse_primary_base();
+    // `as` binds tighter than `*` but looser than prefix operators, so
+    // `-x as Float` is `(-x) as Float` and `a * b as Float` casts only b
+    fn parse_cast(&mut self) -> Expression {
+        let mut expr = self.parse_unary();
+
+        while self.peek().token_type == TokenType::As {
+            self.advance(); // consume 'as'
+            let target = self.parse_type();
+            expr = Expression::Cast {
+                value: Box::new(expr),
+                target,
+            };
+        }
 
-        // Handle postfix operations like field access and array indexing
-        loop {
-            match self.peek().token_type {
-                TokenType::Dot => {
-                    self.advance(); // consume '.'
-                    let field_name = match &self.advance().token_type {
-                        TokenType::Identifier(name) => name.clone(),
-                        _ => panic!("Expected field name after '.'"),
-                    };
-                    expr = Expression::FieldAccess {
-                        object: Box::new(expr),
-                        field: field_name,
-                    };
-                }
-                TokenType::LeftBracket => {
-                    self.advance(); // consume '['
-                    let index = self.parse_expression();
-                    if self.peek().token_type != TokenType::RightBracket {
-                        panic!("Expected ']' after array index");
+        expr
+    }
+
+    // Prefix operators bind tighter than any binary operator but looser than
+    // postfix ones, so `!a == b` is `(!a) == b` and `!arr[0]` is `!(arr[0])`.
+    fn parse_unary(&mut self) -> Expression {
+        match self.peek().token_type {
+            TokenType::Bang => {
+                self.advance(); // consume '!'
+                let operand = self.parse_unary();
+                Expression::UnaryOp {
+                    operator: UnaryOperator::Not,
+                    operand: Box::new(operand),
+                }
+            }
+            // A '-' where an operand is expected negates; elsewhere it subtracts
+            TokenType::Minus => {
+                self.advance(); // consume '-'
+                let operand = self.parse_unary();
+                Expression::UnaryOp {
+                    operator: UnaryOperator::Negate,
+                    operand: Box::new(operand),
+                }
+            }
+            TokenType::Ampersand => {
+                self.advance(); // consume '&'
+                let operand = self.parse_unary();
+                Expression::AddressOf {
+                    operand: Box::new(operand),
+                }
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Expression {
+        let mut expr = self.parse_primary_base();
+
+        // Handle postfix operations like field access and array indexing
+        loop {
+            match self.peek().token_type {
+                TokenType::Dot => {
+                    self.advance(); // consume '.'
+                    let field_name = match &self.advance().token_type {
+                        TokenType::Identifier(name) => name.clone(),
+                        TokenType::Integer(index) => index.to_string(), // tuple field
+                        _ => panic!("Expected field name after '.'"),
+                    };
+                    expr = if self.peek().token_type == TokenType::LeftParen {
+                        Expression::MethodCall {
+                            object: Box::new(expr),
+                            method: field_name,
+                            args: self.parse_call_arguments(),
+                        }
+                    } else {
+                        Expression::FieldAccess {
+                            object: Box::new(expr),
+                            field: field_name,
+                        }
+                    };
+                }
+                TokenType::LeftBracket => {
+                    self.advance(); // consume '['
+                    let index = self.parse_delimited_expression();
+                    if self.peek().token_type != TokenType::RightBracket {
+                        panic!("Expected ']' after array index");
                     }
                     self.advance(); // consume ']'
-                    expr = Expression::ArrayAccess {
-                        array: Box::new(expr),
-                        index: Box::new(index),
+                    expr = match index {
+                        Expression::Range {
+                            start,
+                            end,
+                            inclusive,
+                        } => Expression::Slice {
+                            object: Box::new(expr),
+                            start,
+                            end: if inclusive {
+                                Box::new(Expression::BinaryOp {
+                                    left: end,
+                                    operator: BinaryOperator::Add,
+                                    right: Box::new(Expression::IntegerLiteral(1)),
+                                })
+                            } else {
+                                end
+                            },
+                        },
+                        index => Expression::ArrayAccess {
+                            array: Box::new(expr),
+                            index: Box::new(index),
+                        },
                     };
                 }
                 TokenType::Caret => {
@@ -480,6 +1092,16 @@ impl Parser {
                         operand: Box::new(expr),
                     };
                 }
+                TokenType::QuestionDot => {
+                    self.advance(); // consume '?.'
+                    let TokenType::Identifier(field) = self.advance().token_type.clone() else {
+                        panic!("Expected field name after '?.'");
+                    };
+                    expr = Expression::OptionalFieldAccess {
+                        object: Box::new(expr),
+                        field,
+                    };
+                }
                 _ => break,
             }
         }
@@ -489,30 +1111,44 @@ impl Parser {
 
     fn parse_primary_base(&mut self) -> Expression {
         match &self.peek().token_type {
-            TokenType::Bang => {
-                self.advance(); // consume '!'
-                let operand = self.parse_primary();
-                Expression::UnaryOp {
-                    operator: UnaryOperator::Not,
-                    operand: Box::new(operand),
-                }
-            }
-            TokenType::Ampersand => {
-                self.advance(); // consume '&'
-                let operand = self.parse_primary();
-                Expression::AddressOf {
-                    operand: Box::new(operand),
-                }
-            }
             TokenType::String(value) => {
                 let val = value.clone();
                 self.advance();
                 Expression::StringLiteral(val)
             }
+            TokenType::InterpolatedString(parts) => {
+                let parts = parts.clone();
+                self.advance();
+                Expression::StringInterpolation(
+                    parts
+                        .into_iter()
+                        .map(|part| match part {
+                            StringPart::Literal(text) => InterpolationPart::Literal(text),
+                            StringPart::Code(code) => {
+                                InterpolationPart::Expression(Self::parse_interpolated_code(&code))
+                            }
+                        })
+                        .collect(),
+                )
+            }
             TokenType::Integer(value) => {
                 let val = *value;
                 self.advance();
-                Expression::IntegerLiteral(val)
+                Expression::IntegerLiteral(val.into())
+            }
+            TokenType::Float(value) => {
+                let val = *value;
+                self.advance();
+                Expression::FloatLiteral(val)
+            }
+            // b'A' is the Byte 65
+            TokenType::Byte(value) => {
+                let byte = *value;
+                self.advance();
+                Expression::Cast {
+                    value: Box::new(Expression::IntegerLiteral(byte.into())),
+                    target: Type::SizedInt(IntKind::UInt8),
+                }
             }
             TokenType::Identifier(name) => {
                 let val = name.clone();
@@ -526,16 +1162,8 @@ impl Parser {
                     let saved_pos = self.current;
                     self.advance(); // consume '['
 
-                    // Try to determine if this is a type or expression
-                    // If the first token after [ is a type keyword or uppercase identifier, it's likely a generic
-                    let is_generic = match &self.peek().token_type {
-                        TokenType::Identifier(n) => {
-                            n.chars().next().map_or(false, |c| c.is_uppercase())
-                        }
-                        _ => false,
-                    };
-
-                    if is_generic {
+                    let allocation = self.starts_allocation(&val, saved_pos);
+                    if allocation || self.starts_generic_args(&val, saved_pos) {
                         // Parse as generic type
                         let mut type_args = Vec::new();
 
@@ -552,8 +1180,15 @@ impl Parser {
                         }
                         self.advance(); // consume ']'
 
-                        // Check if this is followed by a struct literal
-                        if self.peek().token_type == TokenType::LeftBrace {
+                        // Map[K, V] { key: value, ... } is a map, not a struct
+                        if allocation {
+                            self.parse_allocation(&val, type_args)
+                        } else if val == "Map" && self.peek().token_type == TokenType::LeftBrace {
+                            Expression::MapLiteral {
+                                type_args: Some(type_args),
+                                entries: self.parse_map_entries(),
+                            }
+                        } else if self.peek().token_type == TokenType::LeftBrace {
                             // Parse generic struct literal like Array[Integer] { ... }
                             self.advance(); // consume '{'
                             let mut fields = Vec::new();
@@ -610,45 +1245,44 @@ impl Parser {
                         Expression::Identifier(val)
                     }
                 } else if self.peek().token_type == TokenType::LeftParen {
-                    self.advance(); // consume '('
-                    let mut args = Vec::new();
-
-                    while self.peek().token_type != TokenType::RightParen && !self.is_at_end() {
-                        args.push(self.parse_expression());
-                        if self.peek().token_type == TokenType::Comma {
-                            self.advance(); // consume ','
-                        }
-                    }
-
-                    if self.peek().token_type != TokenType::RightParen {
-                        panic!("Expected ')' after function arguments");
-                    }
-                    self.advance(); // consume ')'
-
+                    let args = self.parse_call_arguments();
                     Expression::FunctionCall { name: val, args }
-                } else if self.peek().token_type == TokenType::Dot {
-                    // Handle namespace.function() calls
+                } else if self.peek().token_type == TokenType::Dot
+                    && !matches!(
+                        self.tokens.get(self.current + 1).map(|t| &t.token_type),
+                        Some(TokenType::Integer(_))
+                    )
+                {
+                    // Handle namespace.function() and value.method() calls; tuple.0 is
+                    // left to parse_primary
                     self.advance(); // consume '.'
                     let function_name = match &self.advance().token_type {
                         TokenType::Identifier(name) => name.clone(),
                         _ => panic!("Expected function name after '.'"),
                     };
 
-                    if self.peek().token_type == TokenType::LeftParen {
-                        self.advance(); // consume '('
-                        let mut args = Vec::new();
-
-                        while self.peek().token_type != TokenType::RightParen && !self.is_at_end() {
-                            args.push(self.parse_expression());
-                            if self.peek().token_type == TokenType::Comma {
-                                self.advance(); // consume ','
+                    if self.is_declared_type(&val) {
+                        // `Person.new(...)` and `Limits.MAX`: members of the
+                        // type's namespace, named like methods
+                        let name = method_function_name(&val, &function_name);
+                        if self.peek().token_type == TokenType::LeftParen {
+                            Expression::FunctionCall {
+                                name,
+                                args: self.parse_call_arguments(),
                             }
+                        } else {
+                            Expression::Identifier(name)
                         }
-
-                        if self.peek().token_type != TokenType::RightParen {
-                            panic!("Expected ')' after function arguments");
+                    } else if self.peek().token_type == TokenType::LeftParen
+                        && self.is_value_name(&val)
+                    {
+                        Expression::MethodCall {
+                            object: Box::new(Expression::Identifier(val)),
+                            method: function_name,
+                            args: self.parse_call_arguments(),
                         }
-                        self.advance(); // consume ')'
+                    } else if self.peek().token_type == TokenType::LeftParen {
+                        let args = self.parse_call_arguments();
 
                         Expression::NamespacedFunctionCall {
                             namespace: val,
@@ -662,7 +1296,9 @@ impl Parser {
                             field: function_name,
                         }
                     }
-                } else if self.peek().token_type == TokenType::LeftBrace {
+                } else if self.peek().token_type == TokenType::LeftBrace
+                    && self.allow_struct_literals
+                {
                     // Parse struct literal
                     self.advance(); // consume '{'
                     let mut fields = Vec::new();
@@ -714,6 +1350,10 @@ impl Parser {
                 self.advance();
                 Expression::BoolLiteral(true)
             }
+            TokenType::None => {
+                self.advance();
+                Expression::NoneLiteral
+            }
             TokenType::False => {
                 self.advance();
                 Expression::BoolLiteral(false)
@@ -723,7 +1363,7 @@ impl Parser {
                 let mut elements = Vec::new();
 
                 while self.peek().token_type != TokenType::RightBracket && !self.is_at_end() {
-                    elements.push(self.parse_expression());
+                    elements.push(self.parse_delimited_expression());
                     if self.peek().token_type == TokenType::Comma {
                         self.advance(); // consume ','
                     }
@@ -736,50 +1376,236 @@ impl Parser {
 
                 Expression::ArrayLiteral(elements)
             }
+            TokenType::LeftBrace if self.allow_struct_literals => Expression::MapLiteral {
+                type_args: None,
+                entries: self.parse_map_entries(),
+            },
+            TokenType::If => self.parse_if_expression(),
+            TokenType::LeftParen if self.at_lambda() => self.parse_lambda(),
             TokenType::LeftParen => {
+                // Struct literals are unambiguous again inside parentheses
                 self.advance(); // consume '('
+                let previous = self.allow_struct_literals;
+                self.allow_struct_literals = true;
                 let expr = self.parse_expression();
+
+                // A comma makes it a tuple: (1, "one"), or (1,) for one element
+                if self.peek().token_type == TokenType::Comma {
+                    let mut elements = vec![expr];
+                    while self.peek().token_type == TokenType::Comma {
+                        self.advance(); // consume ','
+                        if self.peek().token_type == TokenType::RightParen {
+                            break;
+                        }
+                        elements.push(self.parse_expression());
+                    }
+                    self.allow_struct_literals = previous;
+                    if self.peek().token_type != TokenType::RightParen {
+                        panic!("Expected ')' after tuple elements");
+                    }
+                    self.advance(); // consume ')'
+                    return Expression::TupleLiteral(elements);
+                }
+
+                self.allow_struct_literals = previous;
                 if self.peek().token_type != TokenType::RightParen {
                     panic!("Expected ')' after expression");
                 }
                 self.advance(); // consume ')'
-                expr
+                Expression::Grouping(Box::new(expr))
             }
             _ => panic!("Expected expression, got {:?}", self.peek().token_type),
         }
     }
 
-    fn parse_if_statement(&mut self) -> Statement {
-        self.advance(); // consume 'if'
+    // `(name: Type` or `() ->` starts a lambda rather than a parenthesized expression
+    fn at_lambda(&self) -> bool {
+        let token_after = |offset: usize| {
+            self.tokens
+                .get(self.current + offset)
+                .map(|token| &token.token_type)
+        };
+        match token_after(1) {
+            Some(TokenType::Identifier(_)) => matches!(token_after(2), Some(TokenType::Colon)),
+            Some(TokenType::RightParen) => {
+                matches!(token_after(2), Some(TokenType::Arrow | TokenType::Colon))
+            }
+            _ => false,
+        }
+    }
 
-        if self.peek().token_type != TokenType::LeftParen {
-            panic!("Expected '(' after 'if'");
+    // `{ key: value, ... }`, the entries of a map literal
+    fn parse_map_entries(&mut self) -> Vec<(Expression, Expression)> {
+        self.advance(); // consume '{'
+        let mut entries = Vec::new();
+        self.skip_newlines();
+        while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
+            let key = self.parse_delimited_expression();
+            if self.peek().token_type != TokenType::Colon {
+                panic!("Expected ':' after map key");
+            }
+            self.advance(); // consume ':'
+            let value = self.parse_delimited_expression();
+            entries.push((key, value));
+
+            self.skip_newlines();
+            if self.peek().token_type == TokenType::Comma {
+                self.advance(); // consume ','
+                self.skip_newlines();
+            } else if self.peek().token_type != TokenType::RightBrace {
+                panic!("Expected ',' or '}}' after map entry");
+            }
+        }
+        if self.peek().token_type != TokenType::RightBrace {
+            panic!("Expected '}}' after map entries");
         }
+        self.advance(); // consume '}'
+        entries
+    }
+
+    fn parse_lambda(&mut self) -> Expression {
+        let start = self.current_location();
         self.advance(); // consume '('
 
-        let condition = self.parse_expression();
+        let mut params = Vec::new();
+        let mut param_locations = Vec::new();
+        while self.peek().token_type != TokenType::RightParen && !self.is_at_end() {
+            param_locations.push(self.current_location());
+            let param_name = match &self.advance().token_type {
+                TokenType::Identifier(name) => name.clone(),
+                _ => panic!("Expected lambda parameter name"),
+            };
+            if self.peek().token_type != TokenType::Colon {
+                panic!(
+                    "Expected ':' and a type after lambda parameter '{}'",
+                    param_name
+                );
+            }
+            self.advance(); // consume ':'
+            params.push(Parameter {
+                name: param_name,
+                param_type: self.parse_type(),
+                default: None,
+            });
 
+            if self.peek().token_type == TokenType::Comma {
+                self.advance(); // consume ','
+            } else if self.peek().token_type != TokenType::RightParen {
+                panic!("Expected ',' or ')' in lambda parameter list");
+            }
+        }
         if self.peek().token_type != TokenType::RightParen {
-            panic!("Expected ')' after if condition");
+            panic!("Expected ')' after lambda parameters");
         }
         self.advance(); // consume ')'
 
+        let mut return_type = None;
+        if self.peek().token_type == TokenType::Colon {
+            self.advance(); // consume ':'
+            return_type = Some(self.parse_type());
+        }
+
+        if self.peek().token_type != TokenType::Arrow {
+            panic!("Expected '->' before lambda body");
+        }
+        self.advance(); // consume '->'
+
+        let scope_id = self.symbol_table.enter_scope(ScopeKind::Lambda);
+        for (param, location) in params.iter().zip(param_locations) {
+            if let Err(e) = self.symbol_table.declare_parameter(
+                param.name.clone(),
+                param.param_type.clone(),
+                Some(location),
+            ) {
+                panic!("Error declaring parameter '{}': {}", param.name, e);
+            }
+        }
+        let body = self.parse_expression();
+        let end = self.current_location();
+        self.symbol_table.set_scope_span(scope_id, start, end);
+        if let Err(e) = self.symbol_table.exit_scope() {
+            panic!("Error exiting lambda scope: {}", e);
+        }
+
+        Expression::Lambda {
+            params,
+            return_type,
+            body: Box::new(body),
+        }
+    }
+
+    fn parse_if_statement(&mut self) -> Statement {
+        let start = self.current_location();
+        self.advance(); // consume 'if'
+
+        // `if (cond) {` and `if cond {` are both accepted. Only the binding
+        // form needs its parentheses consumed here; any other parenthesized
+        // condition parses as a grouped expression and is unwrapped below.
+        let parenthesized_binding = self.peek().token_type == TokenType::LeftParen
+            && self
+                .tokens
+                .get(self.current + 1)
+                .is_some_and(|t| t.token_type == TokenType::Val);
+        if parenthesized_binding {
+            self.advance(); // consume '('
+        }
+
+        // `if (val name := optional)` unwraps into a scope of its own
+        let binding = if self.peek().token_type == TokenType::Val {
+            self.advance(); // consume 'val'
+            let name_location = self.current_location();
+            let name = match &self.advance().token_type {
+                TokenType::Identifier(name) => name.clone(),
+                _ => panic!("Expected identifier after 'val' in if condition"),
+            };
+            if self.peek().token_type != TokenType::ColonEqual {
+                panic!("Expected ':=' after '{}' in if condition", name);
+            }
+            self.advance(); // consume ':='
+            Some((name, name_location))
+        } else {
+            None
+        };
+
+        let condition = if parenthesized_binding {
+            let value = self.parse_delimited_expression();
+            if self.peek().token_type != TokenType::RightParen {
+                panic!("Expected ')' after if condition");
+            }
+            self.advance(); // consume ')'
+            value
+        } else {
+            match self.parse_condition_expression() {
+                Expression::Grouping(inner) => *inner,
+                condition => condition,
+            }
+        };
+
         if self.peek().token_type != TokenType::LeftBrace {
             panic!("Expected '{{' after if condition");
         }
         self.advance(); // consume '{'
 
-        let mut then_body = Vec::new();
-        while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
-            if self.peek().token_type == TokenType::Newline {
-                self.advance();
-                continue;
+        let binding_scope = binding.as_ref().map(|(name, location)| {
+            let scope_id = self.symbol_table.enter_scope(ScopeKind::IfStatement);
+            if let Err(e) = self.symbol_table.declare_variable(
+                name.clone(),
+                Type::Custom("inferred".to_string()),
+                false,
+                Some(location.clone()),
+            ) {
+                panic!("Error declaring variable '{}': {}", name, e);
+            }
+            scope_id
+        });
+
+        let then_body = self.parse_block_statements("if body");
+        if let Some(scope_id) = binding_scope {
+            let end = self.current_location();
+            self.symbol_table.set_scope_span(scope_id, start, end);
+            if let Err(e) = self.symbol_table.exit_scope() {
+                panic!("Error exiting if scope: {}", e);
             }
-            then_body.push(self.parse_statement());
-        }
-
-        if self.peek().token_type != TokenType::RightBrace {
-            panic!("Expected '}}'");
         }
         self.advance(); // consume '}'
 
@@ -793,18 +1619,7 @@ impl Parser {
             } else if self.peek().token_type == TokenType::LeftBrace {
                 self.advance(); // consume '{'
 
-                let mut statements = Vec::new();
-                while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
-                    if self.peek().token_type == TokenType::Newline {
-                        self.advance();
-                        continue;
-                    }
-                    statements.push(self.parse_statement());
-                }
-
-                if self.peek().token_type != TokenType::RightBrace {
-                    panic!("Expected '}}'");
-                }
+                let statements = self.parse_block_statements("else body");
                 self.advance(); // consume '}'
 
                 else_body = Some(statements);
@@ -813,10 +1628,18 @@ impl Parser {
             }
         }
 
-        Statement::If {
-            condition,
-            then_body,
-            else_body,
+        match binding {
+            Some((name, _)) => Statement::IfPresent {
+                name,
+                value: condition,
+                then_body,
+                else_body,
+            },
+            None => Statement::If {
+                condition,
+                then_body,
+                else_body,
+            },
         }
     }
 
@@ -828,17 +1651,7 @@ impl Parser {
             // Infinite loop: for {
             TokenType::LeftBrace => {
                 self.advance(); // consume '{'
-                let mut body = Vec::new();
-                while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
-                    if self.peek().token_type == TokenType::Newline {
-                        self.advance();
-                        continue;
-                    }
-                    body.push(self.parse_statement());
-                }
-                if self.peek().token_type != TokenType::RightBrace {
-                    panic!("Expected '}}' after for body");
-                }
+                let body = self.parse_block_statements("for body");
                 self.advance(); // consume '}'
 
                 // Infinite loop is just for (true)
@@ -869,7 +1682,7 @@ impl Parser {
 
                 if has_semicolon {
                     // C-style for loop: for (init; condition; update)
-                    panic!("C-style for loops not yet implemented");
+                    self.parse_c_style_for_loop()
                 } else {
                     // Condition-only loop: for (condition)
                     let condition = self.parse_expression();
@@ -884,166 +1697,362 @@ impl Parser {
                     }
                     self.advance(); // consume '{'
 
-                    let mut body = Vec::new();
-                    while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
-                        if self.peek().token_type == TokenType::Newline {
-                            self.advance();
-                            continue;
-                        }
-                        body.push(self.parse_statement());
-                    }
-
-                    if self.peek().token_type != TokenType::RightBrace {
-                        panic!("Expected '}}' after for body");
-                    }
+                    let body = self.parse_block_statements("for body");
                     self.advance(); // consume '}'
 
                     Statement::ForCondition { condition, body }
                 }
             }
-            // For-in loop: for item in items
-            TokenType::Identifier(variable) => {
-                let variable = variable.clone();
-                self.advance(); // consume identifier
-
-                if self.peek().token_type != TokenType::In {
-                    panic!("Expected 'in' after for loop variable");
+            // For-in loop: for item in items, or for i, item in items
+            TokenType::Identifier(_) if self.is_for_in_header() => {
+                let mut variable = self.loop_variable();
+                let mut index = None;
+                if self.peek().token_type == TokenType::Comma {
+                    self.advance(); // consume ','
+                    index = Some(variable);
+                    variable = self.loop_variable();
                 }
+
                 self.advance(); // consume 'in'
 
-                let iterable = self.parse_for_in_iterable();
+                // `for p in points {`: the brace opens the body, not a struct literal
+                let iterable = self.parse_condition_expression();
 
                 if self.peek().token_type != TokenType::LeftBrace {
                     panic!("Expected '{{' after for-in expression");
                 }
                 self.advance(); // consume '{'
 
-                let mut body = Vec::new();
-                while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
-                    if self.peek().token_type == TokenType::Newline {
-                        self.advance();
-                        continue;
-                    }
-                    body.push(self.parse_statement());
-                }
-
-                if self.peek().token_type != TokenType::RightBrace {
-                    panic!("Expected '}}' after for-in body");
-                }
+                let body = self.parse_block_statements("for-in body");
                 self.advance(); // consume '}'
 
                 Statement::ForIn {
+                    index,
                     variable,
                     iterable,
                     body,
                 }
             }
-            _ => panic!("Unexpected token after 'for'"),
+            // Condition loop without parentheses: for count < 10 { }
+            _ => self.parse_condition_loop("for"),
         }
     }
 
-    fn parse_for_in_iterable(&mut self) -> Expression {
-        match &self.peek().token_type {
-            TokenType::Identifier(name) => {
-                let val = name.clone();
-                self.advance();
-                Expression::Identifier(val)
+    // `item in` or `i, item in` after `for`
+    fn is_for_in_header(&self) -> bool {
+        let token = |offset: usize| {
+            self.tokens
+                .get(self.current + offset)
+                .map(|t| &t.token_type)
+        };
+        match token(1) {
+            Some(TokenType::In) => true,
+            Some(TokenType::Comma) => {
+                matches!(token(2), Some(TokenType::Identifier(_)))
+                    && token(3) == Some(&TokenType::In)
             }
-            TokenType::LeftBracket => {
-                self.advance(); // consume '['
-                let mut elements = Vec::new();
-
-                while self.peek().token_type != TokenType::RightBracket && !self.is_at_end() {
-                    elements.push(self.parse_expression());
-                    if self.peek().token_type == TokenType::Comma {
-                        self.advance(); // consume ','
-                    }
-                }
-
-                if self.peek().token_type != TokenType::RightBracket {
-                    panic!("Expected ']' after array elements");
-                }
-                self.advance(); // consume ']'
+            _ => false,
+        }
+    }
 
-                Expression::ArrayLiteral(elements)
-            }
-            _ => {
-                // For other expressions, use the full expression parser
-                // but this might still have the same issue
-                self.parse_logical_or()
-            }
+    fn loop_variable(&mut self) -> String {
+        match &self.advance().token_type {
+            TokenType::Identifier(name) => name.clone(),
+            _ => panic!("Expected a loop variable name"),
         }
     }
 
-    fn parse_function(&mut self, exported: bool) -> Statement {
-        self.advance(); // consume 'fun'
+    // Parses the rest of `for (init; condition; update) { ... }` after the '('.
+    // Each clause is optional. The loop variable lives in its own scope so
+    // several loops can declare the same counter.
+    fn parse_c_style_for_loop(&mut self) -> Statement {
+        let start = self.current_location();
+        let scope_id = self.symbol_table.enter_scope(ScopeKind::ForLoop);
 
-        let name = match &self.advance().token_type {
-            TokenType::Identifier(name) => name.clone(),
-            _ => panic!("Expected function name"),
+        let init = if self.peek().token_type == TokenType::Semicolon {
+            None
+        } else {
+            Some(Box::new(self.parse_statement()))
         };
+        if self.peek().token_type != TokenType::Semicolon {
+            panic!("Expected ';' after for loop initializer");
+        }
+        self.advance(); // consume ';'
 
-        if self.peek().token_type != TokenType::LeftParen {
-            panic!("Expected '(' after function name");
+        let condition = if self.peek().token_type == TokenType::Semicolon {
+            None
+        } else {
+            Some(self.parse_expression())
+        };
+        if self.peek().token_type != TokenType::Semicolon {
+            panic!("Expected ';' after for loop condition");
         }
-        self.advance(); // consume '('
+        self.advance(); // consume ';'
 
-        let mut params = Vec::new();
-        let mut param_types = Vec::new();
+        let update = if self.peek().token_type == TokenType::RightParen {
+            None
+        } else {
+            Some(Box::new(self.parse_statement()))
+        };
+        if self.peek().token_type != TokenType::RightParen {
+            panic!("Expected ')' after for loop update");
+        }
+        self.advance(); // consume ')'
 
-        while self.peek().token_type != TokenType::RightParen && !self.is_at_end() {
-            let param_name = match &self.advance().token_type {
-                TokenType::Identifier(name) => name.clone(),
-                _ => panic!("Expected parameter name"),
-            };
+        if self.peek().token_type != TokenType::LeftBrace {
+            panic!("Expected '{{' after for loop header");
+        }
+        self.advance(); // consume '{'
 
-            if self.peek().token_type != TokenType::Colon {
-                panic!("Expected ':' after parameter name");
-            }
-            self.advance(); // consume ':'
+        let body = self.parse_block_statements("for body");
+        let end = self.current_location();
+        self.advance(); // consume '}'
+        self.symbol_table.set_scope_span(scope_id, start, end);
 
-            let param_type = self.parse_type();
-            param_types.push(param_type.clone());
+        if let Err(e) = self.symbol_table.exit_scope() {
+            panic!("Error exiting for loop scope: {}", e);
+        }
 
-            params.push(Parameter {
-                name: param_name,
-                param_type,
-            });
+        Statement::ForLoop {
+            init,
+            condition,
+            update,
+            body,
+        }
+    }
 
-            if self.peek().token_type == TokenType::Comma {
-                self.advance(); // consume ','
+    fn parse_while_loop(&mut self) -> Statement {
+        self.advance(); // consume 'while'
+        self.parse_condition_loop("while")
+    }
+
+    // `do { body } while (cond)`: the body runs once before the condition is
+    // first checked
+    fn parse_do_while(&mut self) -> Statement {
+        self.advance(); // consume 'do'
+        if self.peek().token_type != TokenType::LeftBrace {
+            panic!("Expected '{{' after 'do'");
+        }
+        self.advance(); // consume '{'
+
+        let body = self.parse_block_statements("do body");
+        self.advance(); // consume '}'
+
+        if self.peek().token_type != TokenType::While {
+            panic!("Expected 'while' and a condition after do body");
+        }
+        self.advance(); // consume 'while'
+        let condition = self.parse_expression();
+
+        Statement::DoWhile { body, condition }
+    }
+
+    // The condition and body of `while cond { }` or `for cond { }`, after the
+    // keyword. `while (cond) { }` is accepted too; the parenthesized form
+    // simply parses as a grouped expression.
+    fn parse_condition_loop(&mut self, keyword: &str) -> Statement {
+        let condition = self.parse_condition_expression();
+
+        if self.peek().token_type != TokenType::LeftBrace {
+            panic!("Expected '{{' after {} condition", keyword);
+        }
+        self.advance(); // consume '{'
+
+        let body = self.parse_block_statements(&format!("{} body", keyword));
+        self.advance(); // consume '}'
+
+        Statement::ForCondition { condition, body }
+    }
+
+    // The statements of a block, from just after its '{' up to its '}',
+    // which is left for the caller. They get a scope of their own, so a
+    // declaration inside may shadow one outside but not one beside it.
+    fn parse_block_statements(&mut self, context: &str) -> Vec<Statement> {
+        let start = self.current_location();
+        let scope_id = self.symbol_table.enter_scope(ScopeKind::Block);
+
+        let mut body = Vec::new();
+        while self.peek().token_type != TokenType::RightBrace && !self.is_at_end() {
+            if self.peek().token_type == TokenType::Newline {
+                self.advance();
+                continue;
             }
+            body.push(self.parse_statement());
         }
 
-        if self.peek().token_type != TokenType::RightParen {
-            panic!("Expected ')' after parameters");
+        if self.peek().token_type != TokenType::RightBrace {
+            panic!("Expected '}}' after {}", context);
         }
-        self.advance(); // consume ')'
+        let end = self.current_location();
+        self.symbol_table.set_scope_span(scope_id, start, end);
+        if let Err(e) = self.symbol_table.exit_scope() {
+            panic!("Error exiting block scope: {}", e);
+        }
+        body
+    }
 
-        let mut return_type = None;
-        if self.peek().token_type == TokenType::Colon {
-            self.advance(); // consume ':'
-            return_type = Some(self.parse_type());
+    /// Parse `if cond { a } else { b }` in value position. The `else` branch is
+    /// required, and `else if` chains nest into the else value.
+    fn parse_if_expression(&mut self) -> Expression {
+        self.advance(); // consume 'if'
+        let condition = self.parse_condition_expression();
+        let then_value = self.parse_branch_value();
+
+        if self.peek().token_type != TokenType::Else {
+            panic!("An 'if' used as a value needs an 'else' branch");
+        }
+        self.advance(); // consume 'else'
+
+        let else_value = if self.peek().token_type == TokenType::If {
+            self.parse_if_expression()
+        } else {
+            self.parse_branch_value()
+        };
+
+        Expression::If {
+            condition: Box::new(condition),
+            then_value: Box::new(then_value),
+            else_value: Box::new(else_value),
+        }
+    }
+
+    // `{ value }`, one branch of an if expression
+    fn parse_branch_value(&mut self) -> Expression {
+        if self.peek().token_type != TokenType::LeftBrace {
+            panic!("Expected '{{' before if expression branch");
+        }
+        self.advance(); // consume '{'
+        self.skip_newlines();
+
+        let previous = self.allow_struct_literals;
+        self.allow_struct_literals = true;
+        let value = self.parse_expression();
+        self.allow_struct_literals = previous;
+
+        self.skip_newlines();
+        if self.peek().token_type != TokenType::RightBrace {
+            panic!("Expected '}}' after if expression branch");
+        }
+        self.advance(); // consume '}'
+        value
+    }
+
+    /// Parse an expression that is directly followed by a block. An identifier
+    /// followed by `{` is the start of the block here, not a struct literal.
+    fn parse_condition_expression(&mut self) -> Expression {
+        let previous = self.allow_struct_literals;
+        self.allow_struct_literals = false;
+        let condition = self.parse_expression();
+        self.allow_struct_literals = previous;
+        condition
+    }
+
+    /// Parse an expression inside `(...)` or `[...]`, where struct literals
+    /// are unambiguous again even within a condition.
+    fn parse_delimited_expression(&mut self) -> Expression {
+        let previous = self.allow_struct_literals;
+        self.allow_struct_literals = true;
+        let expr = self.parse_expression();
+        self.allow_struct_literals = previous;
+        expr
+    }
+
+    fn parse_function(&mut self, exported: bool) -> Statement {
+        let start = self.current_location();
+        self.advance(); // consume 'fun'
+
+        // A method: `fun (p: Person) greet()`
+        let receiver = if self.peek().token_type == TokenType::LeftParen {
+            Some(self.parse_receiver())
+        } else {
+            None
+        };
+
+        let mut name_location = self.current_location();
+        let mut method_name = match &self.advance().token_type {
+            TokenType::Identifier(name) => name.clone(),
+            _ => panic!("Expected function name"),
+        };
+        // A static function: `fun Person.new()`
+        let namespace = if receiver.is_none() && self.peek().token_type == TokenType::Dot {
+            self.advance(); // consume '.'
+            let type_name = std::mem::take(&mut method_name);
+            self.expect_type_namespace(&type_name);
+            name_location = self.current_location();
+            method_name = match &self.advance().token_type {
+                TokenType::Identifier(name) => name.clone(),
+                _ => panic!("Expected function name after '{}.'", type_name),
+            };
+            Some(type_name)
+        } else {
+            None
+        };
+        let name = match (&receiver, &namespace) {
+            (Some((receiver, _)), _) => {
+                method_function_name(&receiver_type_name(receiver), &method_name)
+            }
+            (None, Some(type_name)) => method_function_name(type_name, &method_name),
+            (None, None) => method_name.clone(),
+        };
+
+        // A generic function: `fun first[T](arr: Array[T]): T`
+        let type_params = self.parse_type_params();
+        if receiver.is_some() && !type_params.is_empty() {
+            panic!("Method '{}' can't have type parameters", name);
         }
 
+        let (params, param_locations) = self.parse_parameters();
+        let param_types = params.iter().map(|p| p.param_type.clone()).collect();
+        let return_type = self.parse_return_type();
+
         // Register function in symbol table
-        if let Err(e) =
-            self.symbol_table
-                .declare_function(name.clone(), param_types, return_type.clone(), None)
-        {
+        let declared = match (&receiver, &namespace) {
+            (Some((receiver, _)), _) => self.symbol_table.declare_method(
+                &receiver_type_name(receiver),
+                &method_name,
+                param_types,
+                return_type.clone(),
+                Some(name_location),
+            ),
+            (None, Some(type_name)) => self.symbol_table.declare_static_function(
+                type_name,
+                &method_name,
+                param_types,
+                return_type.clone(),
+                Some(name_location),
+            ),
+            (None, None) => self.symbol_table.declare_function(
+                name.clone(),
+                param_types,
+                return_type.clone(),
+                Some(name_location),
+            ),
+        };
+        if let Err(e) = declared {
             panic!("Error declaring function '{}': {}", name, e);
         }
 
         // Enter function scope
-        self.symbol_table
+        let scope_id = self
+            .symbol_table
             .enter_scope(ScopeKind::Function { name: name.clone() });
+        let receiver = receiver.map(|(receiver, location)| {
+            if let Err(e) = self.symbol_table.declare_parameter(
+                receiver.name.clone(),
+                receiver.param_type.clone(),
+                Some(location),
+            ) {
+                panic!("Error declaring receiver '{}': {}", receiver.name, e);
+            }
+            receiver
+        });
 
         // Register parameters in the function scope
-        for param in &params {
+        for (param, location) in params.iter().zip(param_locations) {
             if let Err(e) = self.symbol_table.declare_parameter(
                 param.name.clone(),
                 param.param_type.clone(),
-                None,
+                Some(location),
             ) {
                 panic!("Error declaring parameter '{}': {}", param.name, e);
             }
@@ -1066,7 +2075,9 @@ impl Parser {
         if self.peek().token_type != TokenType::RightBrace {
             panic!("Expected '}}'");
         }
+        let end = self.current_location();
         self.advance(); // consume '}'
+        self.symbol_table.set_scope_span(scope_id, start, end);
 
         // Exit function scope
         if let Err(e) = self.symbol_table.exit_scope() {
@@ -1075,6 +2086,8 @@ impl Parser {
 
         Statement::Function {
             name,
+            type_params,
+            receiver,
             params,
             return_type,
             body,
@@ -1082,6 +2095,180 @@ impl Parser {
         }
     }
 
+    // `(p: Person)` before a method name; the receiver must be a struct type
+    // `(a: Integer, b: String)` after a function name, with each
+    // parameter's location
+    fn parse_parameters(&mut self) -> (Vec<Parameter>, Vec<SourceLocation>) {
+        if self.peek().token_type != TokenType::LeftParen {
+            panic!("Expected '(' after function name");
+        }
+        self.advance(); // consume '('
+
+        let mut params = Vec::new();
+        let mut param_locations = Vec::new();
+
+        while self.peek().token_type != TokenType::RightParen && !self.is_at_end() {
+            param_locations.push(self.current_location());
+            let param_name = match &self.advance().token_type {
+                TokenType::Identifier(name) => name.clone(),
+                _ => panic!("Expected parameter name"),
+            };
+
+            if self.peek().token_type != TokenType::Colon {
+                panic!("Expected ':' after parameter name");
+            }
+            self.advance(); // consume ':'
+
+            let param_type = self.parse_type();
+            // `punctuation: String = "!"`
+            let default = if self.peek().token_type == TokenType::Equal {
+                self.advance(); // consume '='
+                Some(self.parse_delimited_expression())
+            } else {
+                None
+            };
+            params.push(Parameter {
+                name: param_name,
+                param_type,
+                default,
+            });
+
+            if self.peek().token_type == TokenType::Comma {
+                self.advance(); // consume ','
+            }
+        }
+
+        if self.peek().token_type != TokenType::RightParen {
+            panic!("Expected ')' after parameters");
+        }
+        self.advance(); // consume ')'
+        (params, param_locations)
+    }
+
+    // An optional `: Type` after a parameter list
+    // `: Unit` spells out what leaving the return type off means
+    fn parse_return_type(&mut self) -> Option<Type> {
+        if self.peek().token_type != TokenType::Colon {
+            return None;
+        }
+        self.advance(); // consume ':'
+        if self.peek().token_type == TokenType::Identifier("Unit".to_string()) {
+            self.advance(); // consume 'Unit'
+            return None;
+        }
+        Some(self.parse_type())
+    }
+
+    fn parse_receiver(&mut self) -> (Parameter, SourceLocation) {
+        self.advance(); // consume '('
+        let location = self.current_location();
+        let name = match &self.advance().token_type {
+            TokenType::Identifier(name) => name.clone(),
+            _ => panic!("Expected receiver name"),
+        };
+        if self.peek().token_type != TokenType::Colon {
+            panic!("Expected ':' after receiver name");
+        }
+        self.advance(); // consume ':'
+        let param_type = self.parse_type();
+        if !matches!(param_type, Type::Custom(_)) {
+            panic!(
+                "A method receiver must be a struct type, not {}",
+                param_type
+            );
+        }
+        if self.peek().token_type != TokenType::RightParen {
+            panic!("Expected ')' after method receiver");
+        }
+        self.advance(); // consume ')'
+        (
+            Parameter {
+                name,
+                param_type,
+                default: None,
+            },
+            location,
+        )
+    }
+
+    // The call after `alloc[T]` or `realloc[T]`; '(' is the current token
+    fn parse_allocation(&mut self, name: &str, type_args: Vec<Type>) -> Expression {
+        let resizes = name == "realloc";
+        let usage = if resizes {
+            "realloc[T](block, count)"
+        } else {
+            "alloc[T](count)"
+        };
+        let mut type_args = type_args.into_iter();
+        let element = match (type_args.next(), type_args.next()) {
+            (Some(element), None) => element,
+            _ => panic!("{} takes exactly one type argument: {}", name, usage),
+        };
+        if self.peek().token_type != TokenType::LeftParen {
+            panic!("Expected '(' after {}[...]: {}", name, usage);
+        }
+        let mut args = self.parse_call_arguments().into_iter();
+        let (block, count) = match (resizes, args.next(), args.next(), args.next()) {
+            (false, Some(count), None, None) => (None, count),
+            (true, Some(block), Some(count), None) => (Some(Box::new(block)), count),
+            _ => panic!("Wrong number of arguments to {}: {}", name, usage),
+        };
+        Expression::Allocate {
+            element,
+            block,
+            count: Box::new(count),
+        }
+    }
+
+    // `(a, b)` after a callee; the '(' is the current token
+    fn parse_call_arguments(&mut self) -> Vec<Expression> {
+        self.advance(); // consume '('
+        let mut args = Vec::new();
+        while self.peek().token_type != TokenType::RightParen && !self.is_at_end() {
+            // A named argument: `greet(name: "Ann")`
+            let named = match (
+                &self.peek().token_type,
+                self.tokens.get(self.current + 1).map(|t| &t.token_type),
+            ) {
+                (TokenType::Identifier(name), Some(TokenType::Colon)) => Some(name.clone()),
+                _ => None,
+            };
+            match named {
+                Some(name) => {
+                    self.advance(); // consume the name
+                    self.advance(); // consume ':'
+                    let value = self.parse_delimited_expression();
+                    args.push(Expression::NamedArgument {
+                        name,
+                        value: Box::new(value),
+                    });
+                }
+                None => args.push(self.parse_delimited_expression()),
+            }
+            if self.peek().token_type == TokenType::Comma {
+                self.advance(); // consume ','
+            }
+        }
+        if self.peek().token_type != TokenType::RightParen {
+            panic!("Expected ')' after function arguments");
+        }
+        self.advance(); // consume ')'
+        args
+    }
+
+    // Whether a name refers to a value, as opposed to a namespace or type
+    fn is_value_name(&self, name: &str) -> bool {
+        self.symbol_table
+            .lookup(name)
+            .and_then(|id| self.symbol_table.get_symbol(id))
+            .is_some_and(|symbol| {
+                matches!(
+                    symbol.kind,
+                    SymbolKind::Variable { .. } | SymbolKind::Parameter
+                )
+            })
+    }
+
     fn parse_return(&mut self) -> Statement {
         self.advance(); // consume 'return'
 
@@ -1097,6 +2284,160 @@ impl Parser {
         Statement::Return(value)
     }
 
+    fn parse_defer(&mut self) -> Statement {
+        self.advance(); // consume 'defer'
+
+        // `defer { ... }` defers a whole block, `defer stmt` a single statement
+        if self.peek().token_type != TokenType::LeftBrace {
+            let statement = self.parse_statement();
+            return Statement::Defer {
+                body: vec![statement],
+            };
+        }
+        self.advance(); // consume '{'
+
+        let body = self.parse_block_statements("defer body");
+        self.advance(); // consume '}'
+
+        Statement::Defer { body }
+    }
+
+    // `guard condition else { ...; return }` is `if !(condition) { ... }`.
+    // The else block must end by returning (or panicking), so the code after the guard can
+    // rely on the condition
+    fn parse_guard(&mut self) -> Statement {
+        self.advance(); // consume 'guard'
+        let condition = match self.parse_condition_expression() {
+            grouped @ Expression::Grouping(_) => grouped,
+            condition => Expression::Grouping(Box::new(condition)),
+        };
+        if self.peek().token_type != TokenType::Else {
+            panic!("Expected 'else' after guard condition");
+        }
+        self.advance(); // consume 'else'
+        if self.peek().token_type != TokenType::LeftBrace {
+            panic!("Expected '{{' after 'else' in guard");
+        }
+        self.advance(); // consume '{'
+
+        let body = self.parse_block_statements("guard body");
+        self.advance(); // consume '}'
+
+        if !matches!(
+            body.last(),
+            Some(Statement::Return(_) | Statement::Panic { .. })
+        ) {
+            panic!("A guard's else block must end with return or panic");
+        }
+        Statement::If {
+            condition: Expression::UnaryOp {
+                operator: UnaryOperator::Not,
+                operand: Box::new(condition),
+            },
+            then_body: body,
+            else_body: None,
+        }
+    }
+
+    fn parse_unsafe(&mut self) -> Statement {
+        self.advance(); // consume 'unsafe'
+        if self.peek().token_type != TokenType::LeftBrace {
+            panic!("Expected '{{' after 'unsafe'");
+        }
+        self.advance(); // consume '{'
+
+        let body = self.parse_block_statements("unsafe body");
+        self.advance(); // consume '}'
+
+        Statement::Unsafe { body }
+    }
+
+    // asm "template" followed by any of out(...), in(...) and clobber(...),
+    // in that order. Operands are variables, each optionally preceded by
+    // its constraint: out(total, "=a": high)
+    fn parse_asm(&mut self) -> Statement {
+        self.advance(); // consume 'asm'
+        let template = match &self.advance().token_type {
+            TokenType::String(template) => template.clone(),
+            _ => panic!("Expected a string with the assembly after 'asm'"),
+        };
+
+        let section = |parser: &mut Self, name: &str| -> bool {
+            let found = match &parser.peek().token_type {
+                TokenType::In => name == "in",
+                TokenType::Identifier(word) => word == name,
+                _ => false,
+            };
+            if found {
+                parser.advance();
+                if parser.advance().token_type != TokenType::LeftParen {
+                    panic!("Expected '(' after '{}' in asm", name);
+                }
+            }
+            found
+        };
+        let outputs = if section(self, "out") {
+            self.parse_asm_operands("+r")
+        } else {
+            Vec::new()
+        };
+        let inputs = if section(self, "in") {
+            self.parse_asm_operands("r")
+        } else {
+            Vec::new()
+        };
+        let mut clobbers = Vec::new();
+        if section(self, "clobber") {
+            while self.peek().token_type != TokenType::RightParen {
+                if !clobbers.is_empty() && self.advance().token_type != TokenType::Comma {
+                    panic!("Expected ',' between asm clobbers");
+                }
+                match &self.advance().token_type {
+                    TokenType::String(clobber) => clobbers.push(clobber.clone()),
+                    _ => panic!("Expected a string like \"cc\" or \"memory\" in asm clobbers"),
+                }
+            }
+            self.advance(); // consume ')'
+        }
+
+        Statement::Asm {
+            template,
+            outputs,
+            inputs,
+            clobbers,
+        }
+    }
+
+    // The operands up to and including ')'
+    fn parse_asm_operands(&mut self, default_constraint: &str) -> Vec<AsmOperand> {
+        let mut operands = Vec::new();
+        while self.peek().token_type != TokenType::RightParen {
+            if !operands.is_empty() && self.advance().token_type != TokenType::Comma {
+                panic!("Expected ',' between asm operands");
+            }
+            let constraint = match &self.peek().token_type {
+                TokenType::String(constraint) => {
+                    let constraint = constraint.clone();
+                    self.advance();
+                    if self.advance().token_type != TokenType::Colon {
+                        panic!("Expected ':' between an asm constraint and its variable");
+                    }
+                    constraint
+                }
+                _ => default_constraint.to_string(),
+            };
+            match &self.advance().token_type {
+                TokenType::Identifier(variable) => operands.push(AsmOperand {
+                    constraint,
+                    variable: variable.clone(),
+                }),
+                _ => panic!("Expected a variable as asm operand"),
+            }
+        }
+        self.advance(); // consume ')'
+        operands
+    }
+
     fn parse_import(&mut self) -> Statement {
         self.advance(); // consume 'import'
 
@@ -1174,13 +2515,14 @@ impl Parser {
                 // Parse the function with exported=true
                 self.parse_function(true)
             }
+            TokenType::Const => self.parse_const_decl(true),
             TokenType::Identifier(name) => {
                 // export item (existing functionality)
                 let item = name.clone();
                 self.advance(); // consume the identifier
                 Statement::Export { item }
             }
-            _ => panic!("Expected 'fun' or identifier after 'export'"),
+            _ => panic!("Expected 'fun', 'const' or identifier after 'export'"),
         }
     }
 
@@ -1269,6 +2611,7 @@ impl Parser {
                 params.push(Parameter {
                     name: param_name,
                     param_type,
+                    default: None,
                 });
             }
 
@@ -1364,6 +2707,7 @@ impl Parser {
             }
 
             let mut params = Vec::new();
+            let mut variadic = false;
 
             // Parse parameters
             while !matches!(self.peek().token_type, TokenType::RightParen) {
@@ -1374,6 +2718,19 @@ impl Parser {
                     }
                 }
 
+                // `fun dprintf(fd: Integer, format: String, ...)`: C varargs
+                if matches!(self.peek().token_type, TokenType::Ellipsis) {
+                    if params.is_empty() {
+                        panic!("'...' needs a parameter before it, like (format: String, ...)");
+                    }
+                    self.advance(); // consume '...'
+                    if !matches!(self.peek().token_type, TokenType::RightParen) {
+                        panic!("'...' must be the last parameter");
+                    }
+                    variadic = true;
+                    break;
+                }
+
                 let param_name = match &self.advance().token_type {
                     TokenType::Identifier(n) => n.clone(),
                     _ => panic!("Expected parameter name"),
@@ -1389,6 +2746,7 @@ impl Parser {
                 params.push(Parameter {
                     name: param_name,
                     param_type,
+                    default: None,
                 });
             }
 
@@ -1411,79 +2769,648 @@ impl Parser {
                             TokenType::String(lib_name) => Some(lib_name.clone()),
                             _ => panic!("Expected library name string after 'lib'"),
                         }
-                    } else {
-                        None
-                    }
-                } else {
-                    None
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            functions.push(ExternFunction {
+                name,
+                params,
+                return_type,
+                exported,
+                library,
+                variadic,
+            });
+        }
+
+        // Expect '}'
+        match &self.advance().token_type {
+            TokenType::RightBrace => {}
+            _ => panic!("Expected '}}' to close extern block"),
+        }
+
+        Statement::ExternBlock {
+            language,
+            functions,
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek().token_type == TokenType::Newline {
+            self.advance();
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn current_location(&self) -> SourceLocation {
+        let token = self.peek();
+        SourceLocation {
+            line: token.line,
+            column: token.column,
+        }
+    }
+
+    fn suggest_fix(
+        &mut self,
+        message: &str,
+        location: SourceLocation,
+        length: usize,
+        replacement: &str,
+    ) {
+        self.diagnostics.push(ParseError::SuggestedFix {
+            message: message.to_string(),
+            fix: FixIt {
+                location,
+                length,
+                replacement: replacement.to_string(),
+            },
+        });
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        &self.tokens[self.current - 1]
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::Eof
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse_type_from_string(input: &str) -> Type {
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.parse_type()
+    }
+
+    fn parse_statement_from_string(input: &str) -> Statement {
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.parse_statement()
+    }
+
+    fn parse_program_from_string(input: &str) -> (ParseResult<Program>, Vec<ParseError>) {
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let result = parser.parse();
+        let diagnostics = std::mem::take(&mut parser.diagnostics);
+        (result, diagnostics)
+    }
+
+    #[test]
+    fn test_missing_colon_equal_in_declaration() {
+        let (result, diagnostics) = parse_program_from_string("val x = 5\nvar y = 6");
+        assert!(result.is_err());
+        assert_eq!(diagnostics.len(), 2);
+
+        match &diagnostics[0] {
+            ParseError::SuggestedFix { message, fix } => {
+                assert!(message.contains("':='"));
+                assert_eq!(fix.location.line, 1);
+                assert_eq!(fix.location.column, 7);
+                assert_eq!(fix.length, 1);
+                assert_eq!(fix.replacement, ":=");
+            }
+            _ => panic!("Expected SuggestedFix diagnostic"),
+        }
+
+        match &diagnostics[1] {
+            ParseError::SuggestedFix { fix, .. } => assert_eq!(fix.location.line, 2),
+            _ => panic!("Expected SuggestedFix diagnostic"),
+        }
+    }
+
+    #[test]
+    fn test_parsing_resumes_after_a_failed_statement() {
+        let source = "val a := (1 +\nfun f() {\n    val b :=\n}\nval c := 3\nval d :=\n";
+        let mut parser = Parser::new(Lexer::new(source.to_string()).tokenize().unwrap());
+        let program = parser.parse_recovering();
+        let lines: Vec<usize> = parser
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| match diagnostic {
+                ParseError::InvalidSyntax {
+                    location: Some(location),
+                    ..
+                } => location.line,
+                other => panic!("Expected InvalidSyntax, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(lines, vec![1, 3, 6]);
+        // Only `val c` parsed, and the function's scope was closed again
+        assert!(matches!(
+            program.statements.as_slice(),
+            [Statement::ValDecl { name, .. }] if name == "c"
+        ));
+        assert_eq!(
+            parser.symbol_table().current_scope(),
+            parser.symbol_table().global_scope()
+        );
+    }
+
+    #[test]
+    fn test_double_equal_in_declaration() {
+        let (_, diagnostics) = parse_program_from_string("val x == 5");
+        assert_eq!(diagnostics.len(), 1);
+        match &diagnostics[0] {
+            ParseError::SuggestedFix { message, fix } => {
+                assert!(message.contains("comparison"));
+                assert_eq!(fix.length, 2);
+                assert_eq!(fix.replacement, ":=");
+            }
+            _ => panic!("Expected SuggestedFix diagnostic"),
+        }
+
+        let (_, diagnostics) = parse_program_from_string("val x: Integer == 5");
+        assert_eq!(diagnostics.len(), 1);
+        match &diagnostics[0] {
+            ParseError::SuggestedFix { fix, .. } => assert_eq!(fix.replacement, "="),
+            _ => panic!("Expected SuggestedFix diagnostic"),
+        }
+    }
+
+    #[test]
+    fn test_declaration_recovers_after_fix_it() {
+        // Parsing continues past the mistake so later statements are still seen
+        let mut lexer = Lexer::new("val x = 5\nval y := x".to_string());
+        let mut parser = Parser::new(lexer.tokenize().unwrap());
+        assert!(parser.parse().is_err());
+        assert_eq!(parser.diagnostics().len(), 1);
+        assert!(parser.symbol_table().lookup("y").is_some());
+    }
+
+    #[test]
+    fn test_blocks_open_scopes_for_shadowing() {
+        let source =
+            "val x := 1\nif x > 0 {\n    val x := \"inner\"\n}\nwhile x < 3 {\n    var x := 2\n}\n";
+        let mut parser = Parser::new(Lexer::new(source.to_string()).tokenize().unwrap());
+        let program = parser.parse_recovering();
+        assert!(parser.diagnostics().is_empty());
+        assert_eq!(program.statements.len(), 3);
+        let table = parser.symbol_table();
+        let outer = table
+            .lookup("x")
+            .and_then(|id| table.get_symbol(id))
+            .unwrap();
+        assert_eq!(outer.scope_id, table.global_scope());
+
+        // Two declarations in the same block still clash
+        let (_, diagnostics) =
+            parse_program_from_string("if true {\n    val y := 1\n    val y := 2\n}");
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    fn parse_expression_from_string(input: &str) -> Expression {
+        let mut lexer = Lexer::new(input.to_string());
+        let tokens = lexer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.parse_expression()
+    }
+
+    #[test]
+    fn test_parenthesized_expression_is_grouped() {
+        match parse_expression_from_string("(a + b) * c") {
+            Expression::BinaryOp {
+                left,
+                operator: BinaryOperator::Multiply,
+                ..
+            } => match *left {
+                Expression::Grouping(inner) => assert!(matches!(
+                    *inner,
+                    Expression::BinaryOp {
+                        operator: BinaryOperator::Add,
+                        ..
+                    }
+                )),
+                other => panic!("Expected Grouping, got {:?}", other),
+            },
+            other => panic!("Expected multiplication, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        match parse_expression_from_string(r#""total: ${a + b}!""#) {
+            Expression::StringInterpolation(parts) => {
+                assert_eq!(parts.len(), 3);
+                assert!(matches!(&parts[0], InterpolationPart::Literal(s) if s == "total: "));
+                assert!(matches!(
+                    &parts[1],
+                    InterpolationPart::Expression(Expression::BinaryOp {
+                        operator: BinaryOperator::Add,
+                        ..
+                    })
+                ));
+                assert!(matches!(&parts[2], InterpolationPart::Literal(s) if s == "!"));
+            }
+            other => panic!("Expected interpolation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected")]
+    fn test_string_interpolation_rejects_trailing_tokens() {
+        parse_expression_from_string(r#""${a b}""#);
+    }
+
+    #[test]
+    fn test_lambda_expression() {
+        match parse_expression_from_string("(acc: Integer, x: Integer) -> acc + x") {
+            Expression::Lambda {
+                params,
+                return_type,
+                body,
+            } => {
+                assert_eq!(params.len(), 2);
+                assert_eq!(params[1].param_type, Type::Integer);
+                assert!(return_type.is_none());
+                assert!(matches!(*body, Expression::BinaryOp { .. }));
+            }
+            other => panic!("Expected lambda, got {:?}", other),
+        }
+
+        match parse_expression_from_string("(x: Integer): Float -> x / 2.0") {
+            Expression::Lambda { return_type, .. } => assert_eq!(return_type, Some(Type::Float)),
+            other => panic!("Expected lambda, got {:?}", other),
+        }
+
+        // Plain parentheses are still a grouping
+        assert!(matches!(
+            parse_expression_from_string("(x)"),
+            Expression::Grouping(_)
+        ));
+    }
+
+    #[test]
+    fn test_not_binds_tighter_than_comparison() {
+        // `!a == b` is `(!a) == b`, as in C
+        match parse_expression_from_string("!a == b") {
+            Expression::BinaryOp {
+                left,
+                operator: BinaryOperator::Equal,
+                ..
+            } => assert!(matches!(*left, Expression::UnaryOp { .. })),
+            other => panic!("Expected comparison, got {:?}", other),
+        }
+
+        // Parentheses move the negation outside the comparison
+        match parse_expression_from_string("!(a == b)") {
+            Expression::UnaryOp { operand, .. } => {
+                assert!(matches!(*operand, Expression::Grouping(_)))
+            }
+            other => panic!("Expected negation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unary_operators_bind_looser_than_postfix() {
+        match parse_expression_from_string("!flags[0]") {
+            Expression::UnaryOp { operand, .. } => {
+                assert!(matches!(*operand, Expression::ArrayAccess { .. }))
+            }
+            other => panic!("Expected negation, got {:?}", other),
+        }
+
+        match parse_expression_from_string("!!done") {
+            Expression::UnaryOp { operand, .. } => {
+                assert!(matches!(*operand, Expression::UnaryOp { .. }))
+            }
+            other => panic!("Expected nested negation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_minus_negates_where_an_operand_is_expected() {
+        // `a - -b` subtracts a negation; `-a * b` is `(-a) * b`
+        match parse_expression_from_string("a - -b") {
+            Expression::BinaryOp {
+                operator: BinaryOperator::Subtract,
+                right,
+                ..
+            } => assert!(matches!(
+                *right,
+                Expression::UnaryOp {
+                    operator: UnaryOperator::Negate,
+                    ..
+                }
+            )),
+            other => panic!("Expected subtraction, got {:?}", other),
+        }
+        match parse_expression_from_string("-a * b") {
+            Expression::BinaryOp {
+                left,
+                operator: BinaryOperator::Multiply,
+                ..
+            } => assert!(matches!(*left, Expression::UnaryOp { .. })),
+            other => panic!("Expected multiplication, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_while_loop_parsing() {
+        match parse_statement_from_string("while i < 10 {\n    i = i + 1\n}") {
+            Statement::ForCondition { condition, body } => {
+                assert!(matches!(
+                    condition,
+                    Expression::BinaryOp {
+                        operator: BinaryOperator::Less,
+                        ..
+                    }
+                ));
+                assert_eq!(body.len(), 1);
+            }
+            other => panic!("Expected while loop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_do_while_parsing() {
+        match parse_statement_from_string("do {\n    n = n + 1\n} while (n < 3)") {
+            Statement::DoWhile { body, condition } => {
+                assert_eq!(body.len(), 1);
+                assert!(matches!(
+                    condition,
+                    Expression::Grouping(ref inner) if matches!(**inner, Expression::BinaryOp { .. })
+                ));
+            }
+            other => panic!("Expected do-while loop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_while_condition_is_not_a_struct_literal() {
+        // `running {` opens the loop body rather than a struct literal
+        match parse_statement_from_string("while running {\n}") {
+            Statement::ForCondition { condition, body } => {
+                assert!(matches!(condition, Expression::Identifier(ref n) if n == "running"));
+                assert!(body.is_empty());
+            }
+            other => panic!("Expected while loop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_range_parsing() {
+        match parse_statement_from_string("for i in 0..=n - 1 {\n}") {
+            Statement::ForIn { iterable, .. } => match iterable {
+                Expression::Range {
+                    start,
+                    end,
+                    inclusive,
+                } => {
+                    assert!(inclusive);
+                    assert!(matches!(*start, Expression::IntegerLiteral(0)));
+                    // The range binds looser than arithmetic
+                    assert!(matches!(
+                        *end,
+                        Expression::BinaryOp {
+                            operator: BinaryOperator::Subtract,
+                            ..
+                        }
+                    ));
                 }
-            } else {
-                None
-            };
+                other => panic!("Expected Range, got {:?}", other),
+            },
+            other => panic!("Expected for-in loop, got {:?}", other),
+        }
+    }
 
-            functions.push(ExternFunction {
-                name,
-                params,
-                return_type,
-                exported,
-                library,
-            });
+    #[test]
+    fn test_if_without_parentheses() {
+        match parse_statement_from_string("if ready {\n} else if (count > 2) {\n}") {
+            Statement::If {
+                condition,
+                else_body,
+                ..
+            } => {
+                assert!(matches!(condition, Expression::Identifier(ref n) if n == "ready"));
+                match else_body.as_deref() {
+                    // The grouping of a fully parenthesized condition is dropped
+                    Some([Statement::If { condition, .. }]) => {
+                        assert!(matches!(condition, Expression::BinaryOp { .. }))
+                    }
+                    other => panic!("Expected else if, got {:?}", other),
+                }
+            }
+            other => panic!("Expected If, got {:?}", other),
         }
+        assert!(matches!(
+            parse_statement_from_string("if val n := maybe {\n}"),
+            Statement::IfPresent { .. }
+        ));
+        assert!(matches!(
+            parse_statement_from_string("for count < 10 {\n}"),
+            Statement::ForCondition { .. }
+        ));
+    }
 
-        // Expect '}'
-        match &self.advance().token_type {
-            TokenType::RightBrace => {}
-            _ => panic!("Expected '}}' to close extern block"),
+    #[test]
+    fn test_for_in_iterable_is_not_a_struct_literal() {
+        match parse_statement_from_string("for p in shape.points {\n}") {
+            Statement::ForIn { iterable, .. } => {
+                assert!(
+                    matches!(iterable, Expression::FieldAccess { ref field, .. } if field == "points")
+                );
+            }
+            other => panic!("Expected for-in loop, got {:?}", other),
+        }
+        match parse_statement_from_string("for p in points { print(p) }") {
+            Statement::ForIn { iterable, body, .. } => {
+                assert!(matches!(iterable, Expression::Identifier(ref n) if n == "points"));
+                assert_eq!(body.len(), 1);
+            }
+            other => panic!("Expected for-in loop, got {:?}", other),
         }
+    }
 
-        Statement::ExternBlock {
-            language,
-            functions,
+    #[test]
+    fn test_for_in_with_index() {
+        match parse_statement_from_string("for i, name in names {\n}") {
+            Statement::ForIn {
+                index, variable, ..
+            } => {
+                assert_eq!(index.as_deref(), Some("i"));
+                assert_eq!(variable, "name");
+            }
+            other => panic!("Expected for-in loop, got {:?}", other),
         }
     }
 
-    fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+    #[test]
+    fn test_struct_literals_allowed_inside_delimiters_of_a_condition() {
+        match parse_statement_from_string("while contains(Point { x: 1 }, [Point { x: 2 }]) {\n}") {
+            Statement::ForCondition { condition, .. } => match condition {
+                Expression::FunctionCall { args, .. } => {
+                    assert!(matches!(args[0], Expression::StructLiteral { .. }));
+                    assert!(matches!(args[1], Expression::ArrayLiteral(_)));
+                }
+                other => panic!("Expected call, got {:?}", other),
+            },
+            other => panic!("Expected while loop, got {:?}", other),
+        }
     }
 
-    fn advance(&mut self) -> &Token {
-        if !self.is_at_end() {
-            self.current += 1;
+    #[test]
+    fn test_c_style_for_loop_parsing() {
+        match parse_statement_from_string("for (var i := 0; i < 10; i = i + 1) {\n    print(i)\n}")
+        {
+            Statement::ForLoop {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                assert!(
+                    matches!(init.as_deref(), Some(Statement::VarDecl { name, .. }) if name == "i")
+                );
+                assert!(matches!(
+                    condition,
+                    Some(Expression::BinaryOp {
+                        operator: BinaryOperator::Less,
+                        ..
+                    })
+                ));
+                assert!(matches!(
+                    update.as_deref(),
+                    Some(Statement::Assignment { .. })
+                ));
+                assert_eq!(body.len(), 1);
+            }
+            other => panic!("Expected ForLoop, got {:?}", other),
         }
-        &self.tokens[self.current - 1]
     }
 
-    fn is_at_end(&self) -> bool {
-        self.peek().token_type == TokenType::Eof
+    #[test]
+    fn test_c_style_for_loops_can_reuse_counter_name() {
+        let (result, _) = parse_program_from_string(
+            "for (var i := 0; i < 2; i = i + 1) {\n}\nfor (var i := 0; ; i = i + 1) {\n}",
+        );
+        let program = result.unwrap();
+        assert_eq!(program.statements.len(), 2);
+        assert!(matches!(
+            &program.statements[1],
+            Statement::ForLoop {
+                condition: None,
+                ..
+            }
+        ));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::lexer::Lexer;
+    #[test]
+    fn test_defer_parsing() {
+        match parse_statement_from_string("defer print(\"done\")") {
+            Statement::Defer { body } => {
+                assert_eq!(body.len(), 1);
+                assert!(matches!(body[0], Statement::Expression(_)));
+            }
+            other => panic!("Expected defer, got {:?}", other),
+        }
 
-    fn parse_type_from_string(input: &str) -> Type {
-        let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.tokenize().unwrap();
-        let mut parser = Parser::new(tokens);
-        parser.parse_type()
+        match parse_statement_from_string("defer {\n    print(1)\n    print(2)\n}") {
+            Statement::Defer { body } => assert_eq!(body.len(), 2),
+            other => panic!("Expected defer block, got {:?}", other),
+        }
     }
 
-    fn parse_statement_from_string(input: &str) -> Statement {
-        let mut lexer = Lexer::new(input.to_string());
-        let tokens = lexer.tokenize().unwrap();
-        let mut parser = Parser::new(tokens);
-        parser.parse_statement()
+    #[test]
+    fn test_guard_is_a_negated_if() {
+        match parse_statement_from_string(
+            "guard count > 0 else {\n    print(\"empty\")\n    return\n}",
+        ) {
+            Statement::If {
+                condition,
+                then_body,
+                else_body,
+            } => {
+                match condition {
+                    Expression::UnaryOp {
+                        operator: UnaryOperator::Not,
+                        operand,
+                    } => assert!(matches!(*operand, Expression::Grouping(_))),
+                    other => panic!("Expected a negated condition, got {:?}", other),
+                }
+                assert_eq!(then_body.len(), 2);
+                assert!(matches!(then_body[1], Statement::Return(None)));
+                assert!(else_body.is_none());
+            }
+            other => panic!("Expected guard to parse as if, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_static_members_use_method_names() {
+        let (result, diagnostics) = parse_program_from_string(
+            "type Point = { x: Integer }\n\
+             const Point.ORIGIN_X := 0\n\
+             fun Point.origin(): Point { return Point { x: Point.ORIGIN_X } }\n\
+             val p := Point.origin()",
+        );
+        assert!(diagnostics.is_empty());
+        let statements = result.unwrap().statements;
+        assert!(
+            matches!(&statements[1], Statement::ConstDecl { name, .. } if name == "Point_ORIGIN_X")
+        );
+        match &statements[2] {
+            Statement::Function {
+                name,
+                receiver,
+                body,
+                ..
+            } => {
+                assert_eq!(name, "Point_origin");
+                assert!(receiver.is_none());
+                assert!(format!("{:?}", body).contains("Identifier(\"Point_ORIGIN_X\")"));
+            }
+            other => panic!("Expected a static function, got {:?}", other),
+        }
+        assert!(matches!(
+            &statements[3],
+            Statement::ValDecl { value: Expression::FunctionCall { name, .. }, .. } if name == "Point_origin"
+        ));
     }
 
     #[test]
     fn test_basic_type_parsing() {
         assert!(matches!(parse_type_from_string("String"), Type::String));
         assert!(matches!(parse_type_from_string("Integer"), Type::Integer));
+        assert!(matches!(parse_type_from_string("Float"), Type::Float));
         assert!(matches!(parse_type_from_string("Bool"), Type::Bool));
     }
 
+    #[test]
+    fn test_float_declaration_parsing() {
+        match parse_statement_from_string("val ratio: Float = 0.25") {
+            Statement::ValDecl {
+                type_annotation,
+                value,
+                ..
+            } => {
+                assert!(matches!(type_annotation, Some(Type::Float)));
+                assert!(matches!(value, Expression::FloatLiteral(f) if f == 0.25));
+            }
+            _ => panic!("Expected ValDecl"),
+        }
+    }
+
     #[test]
     fn test_custom_type_parsing() {
         match parse_type_from_string("MyType") {
@@ -1560,6 +3487,204 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_function_type_parsing() {
+        match parse_type_from_string("(Integer, String) -> Bool") {
+            Type::Function {
+                params,
+                return_type,
+            } => {
+                assert_eq!(params, vec![Type::Integer, Type::String]);
+                assert_eq!(*return_type, Type::Bool);
+            }
+            other => panic!("Expected Function type, got {:?}", other),
+        }
+
+        // Callbacks can take and return other callbacks
+        match parse_type_from_string("((Integer) -> Integer) -> Integer") {
+            Type::Function { params, .. } => {
+                assert!(matches!(params[0], Type::Function { .. }));
+            }
+            other => panic!("Expected Function type, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_plain_array_type_parsing() {
+        assert_eq!(
+            parse_type_from_string("[Integer]"),
+            Type::Array(Box::new(Type::Integer))
+        );
+    }
+
+    #[test]
+    fn test_sized_integer_type_parsing() {
+        assert_eq!(
+            parse_type_from_string("Int8"),
+            Type::SizedInt(IntKind::Int8)
+        );
+        assert_eq!(
+            parse_type_from_string("UInt64"),
+            Type::SizedInt(IntKind::UInt64)
+        );
+        assert_eq!(
+            parse_type_from_string("Int128"),
+            Type::Custom("Int128".to_string())
+        );
+    }
+
+    #[test]
+    fn test_optional_type_parsing() {
+        let optional_integer = Type::Generic {
+            name: "Optional".to_string(),
+            type_params: vec![Type::Integer],
+        };
+        assert_eq!(parse_type_from_string("Integer?"), optional_integer);
+        assert_eq!(
+            parse_type_from_string("Optional[Integer]"),
+            optional_integer
+        );
+    }
+
+    #[test]
+    fn test_if_val_unwraps_optional() {
+        match parse_statement_from_string("if (val n := find()) { n } else { 0 }") {
+            Statement::IfPresent {
+                name,
+                value,
+                then_body,
+                else_body,
+            } => {
+                assert_eq!(name, "n");
+                assert!(matches!(value, Expression::FunctionCall { .. }));
+                assert_eq!(then_body.len(), 1);
+                assert!(else_body.is_some());
+            }
+            other => panic!("Expected IfPresent, got {:?}", other),
+        }
+        match parse_statement_from_string("var x: Integer? = none") {
+            Statement::VarDecl {
+                type_annotation,
+                value,
+                ..
+            } => {
+                assert_eq!(type_annotation, Some(parse_type_from_string("Integer?")));
+                assert!(matches!(value, Expression::NoneLiteral));
+            }
+            other => panic!("Expected VarDecl, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tuple_types_and_literals() {
+        assert_eq!(
+            parse_type_from_string("(Integer, String)"),
+            Type::Tuple(vec![Type::Integer, Type::String])
+        );
+        assert_eq!(parse_type_from_string("(Integer)"), Type::Integer);
+        match parse_expression_from_string("(1, \"one\")") {
+            Expression::TupleLiteral(elements) => assert_eq!(elements.len(), 2),
+            other => panic!("Expected TupleLiteral, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_expression_from_string("(1)"),
+            Expression::Grouping(_)
+        ));
+        match parse_expression_from_string("pair.0.1") {
+            Expression::FieldAccess { object, field } => {
+                assert_eq!(field, "1");
+                assert!(matches!(
+                    *object,
+                    Expression::FieldAccess { ref field, .. } if field == "0"
+                ));
+            }
+            other => panic!("Expected FieldAccess, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compound_assignment_desugars_to_binary_op() {
+        match parse_statement_from_string("count -= step * 2") {
+            Statement::Assignment { variable, value } => {
+                assert_eq!(variable, "count");
+                match value {
+                    Expression::BinaryOp {
+                        left,
+                        operator: BinaryOperator::Subtract,
+                        right,
+                    } => {
+                        assert!(matches!(*left, Expression::Identifier(ref n) if n == "count"));
+                        assert!(matches!(
+                            *right,
+                            Expression::BinaryOp {
+                                operator: BinaryOperator::Multiply,
+                                ..
+                            }
+                        ));
+                    }
+                    other => panic!("Expected subtraction, got {:?}", other),
+                }
+            }
+            other => panic!("Expected Assignment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_if_expression_parsing() {
+        match last_val_value(
+            "val s := if n > 0 { \"pos\" } else if n < 0 { \"neg\" } else { \"zero\" }",
+        ) {
+            Expression::If {
+                condition,
+                else_value,
+                ..
+            } => {
+                assert!(matches!(*condition, Expression::BinaryOp { .. }));
+                assert!(matches!(*else_value, Expression::If { .. }));
+            }
+            other => panic!("Expected If, got {:?}", other),
+        }
+        let missing_else = std::panic::catch_unwind(|| last_val_value("val x := if c { 1 }"));
+        assert!(missing_else.is_err());
+    }
+
+    // The value of the last `val` in a program
+    fn last_val_value(input: &str) -> Expression {
+        let (result, _) = parse_program_from_string(input);
+        match result.unwrap().statements.pop() {
+            Some(Statement::ValDecl { value, .. }) => value,
+            other => panic!("Expected ValDecl, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_index_by_uppercase_variable_is_array_access() {
+        let value = last_val_value("val matrix := [1, 2, 3]\nval R := 1\nval x := matrix[R]");
+        assert!(matches!(value, Expression::ArrayAccess { .. }));
+        // Index expressions never read as type lists
+        let value = last_val_value("val items := [1, 2]\nval x := items[Integer + 1]");
+        assert!(matches!(value, Expression::ArrayAccess { .. }));
+    }
+
+    #[test]
+    fn test_lowercase_generic_type_is_struct_literal() {
+        let value =
+            last_val_value("type pair[T] = { first: T }\nval p := pair[point] { first: 1 }");
+        match value {
+            Expression::StructLiteral {
+                type_name,
+                type_args,
+                ..
+            } => {
+                assert_eq!(type_name, "pair");
+                assert_eq!(type_args, Some(vec![Type::Custom("point".to_string())]));
+            }
+            other => panic!("Expected StructLiteral, got {:?}", other),
+        }
+        let value = last_val_value("val p := Box[Integer] { value: 1 }");
+        assert!(matches!(value, Expression::StructLiteral { .. }));
+    }
+
     #[test]
     fn test_generic_type_definition_parsing() {
         let input = "type Array[T] = { data: ^T, length: Integer }";
@@ -1568,6 +3693,7 @@ mod tests {
                 name,
                 type_params,
                 fields,
+                ..
             } => {
                 assert_eq!(name, "Array");
                 assert_eq!(type_params.len(), 1);
@@ -1592,6 +3718,243 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_generic_function_parsing() {
+        let input = "fun first[T](arr: Array[T]): T {\n    return arr.data[0]\n}";
+        match parse_statement_from_string(input) {
+            Statement::Function {
+                name,
+                type_params,
+                params,
+                return_type,
+                ..
+            } => {
+                assert_eq!(name, "first");
+                assert_eq!(type_params, vec!["T".to_string()]);
+                assert!(matches!(
+                    &params[0].param_type,
+                    Type::Generic { name, type_params } if name == "Array"
+                        && matches!(&type_params[0], Type::Custom(t) if t == "T")
+                ));
+                assert!(matches!(return_type, Some(Type::Custom(t)) if t == "T"));
+            }
+            _ => panic!("Expected Function statement"),
+        }
+    }
+
+    #[test]
+    fn test_default_parameters_and_named_arguments() {
+        let input = "fun greet(name: String, punctuation: String = \"!\") {\n    print(name)\n}";
+        match parse_statement_from_string(input) {
+            Statement::Function { params, .. } => {
+                assert!(params[0].default.is_none());
+                assert!(matches!(
+                    &params[1].default,
+                    Some(Expression::StringLiteral(s)) if s == "!"
+                ));
+            }
+            _ => panic!("Expected Function statement"),
+        }
+
+        match parse_statement_from_string("greet(\"Ann\", punctuation: \"?\")") {
+            Statement::Expression(Expression::FunctionCall { args, .. }) => {
+                assert!(matches!(&args[0], Expression::StringLiteral(s) if s == "Ann"));
+                assert!(matches!(
+                    &args[1],
+                    Expression::NamedArgument { name, value }
+                        if name == "punctuation"
+                            && matches!(value.as_ref(), Expression::StringLiteral(s) if s == "?")
+                ));
+            }
+            other => panic!("Expected a call, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_map_literals() {
+        match parse_statement_from_string("val ages := {\"ann\": 31, \"bob\": 27}") {
+            Statement::ValDecl {
+                value: Expression::MapLiteral { type_args, entries },
+                ..
+            } => {
+                assert!(type_args.is_none());
+                assert_eq!(entries.len(), 2);
+                assert!(matches!(&entries[1].0, Expression::StringLiteral(s) if s == "bob"));
+                assert!(matches!(&entries[1].1, Expression::IntegerLiteral(27)));
+            }
+            other => panic!("Expected a map literal, got {:?}", other),
+        }
+
+        match parse_statement_from_string("val squares := Map[Integer, Integer] {}") {
+            Statement::ValDecl {
+                value: Expression::MapLiteral { type_args, entries },
+                ..
+            } => {
+                assert_eq!(type_args, Some(vec![Type::Integer, Type::Integer]));
+                assert!(entries.is_empty());
+            }
+            other => panic!("Expected a map literal, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_range_index_is_a_slice() {
+        match parse_statement_from_string("val head := items[1..=n]") {
+            Statement::ValDecl {
+                value: Expression::Slice { object, start, end },
+                ..
+            } => {
+                assert!(matches!(*object, Expression::Identifier(ref name) if name == "items"));
+                assert!(matches!(*start, Expression::IntegerLiteral(1)));
+                // Inclusive ranges end one past their last index
+                assert!(matches!(
+                    *end,
+                    Expression::BinaryOp {
+                        operator: BinaryOperator::Add,
+                        ..
+                    }
+                ));
+            }
+            other => panic!("Expected a slice, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_statement_from_string("val x := items[2]"),
+            Statement::ValDecl {
+                value: Expression::ArrayAccess { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_as_binds_tighter_than_multiplication() {
+        match parse_statement_from_string("val r := a * b as Float") {
+            Statement::ValDecl {
+                value:
+                    Expression::BinaryOp {
+                        operator: BinaryOperator::Multiply,
+                        right,
+                        ..
+                    },
+                ..
+            } => assert!(matches!(
+                *right,
+                Expression::Cast {
+                    target: Type::Float,
+                    ..
+                }
+            )),
+            other => panic!("Expected a product with a cast operand, got {:?}", other),
+        }
+        match parse_statement_from_string("val p := &x as ^Byte") {
+            Statement::ValDecl {
+                value: Expression::Cast { value, target },
+                ..
+            } => {
+                assert!(matches!(*value, Expression::AddressOf { .. }));
+                assert_eq!(
+                    target,
+                    Type::Pointer(Box::new(Type::SizedInt(IntKind::UInt8)))
+                );
+            }
+            other => panic!("Expected a cast of &x, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_byte_literal_is_a_uint8_cast() {
+        match parse_statement_from_string("val newline := b'\\n'") {
+            Statement::ValDecl {
+                value: Expression::Cast { value, target },
+                ..
+            } => {
+                assert!(matches!(*value, Expression::IntegerLiteral(10)));
+                assert_eq!(target, Type::SizedInt(IntKind::UInt8));
+            }
+            other => panic!("Expected a Byte cast, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_panic_and_assert_record_file_and_line() {
+        let source = "val x := 1\n\nassert(x == 1, \"one\")\npanic(\"done\")\nassert(x == 1)\n";
+        let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+        let program = Parser::new(tokens)
+            .with_source_name("app.bolt")
+            .parse()
+            .unwrap();
+        match &program.statements[1] {
+            Statement::Assert { location, .. } => assert_eq!(location, "app.bolt:3"),
+            other => panic!("Expected an assert, got {:?}", other),
+        }
+        match &program.statements[2] {
+            Statement::Panic { location, .. } => assert_eq!(location, "app.bolt:4"),
+            other => panic!("Expected a panic, got {:?}", other),
+        }
+        // bolt:test's assert(condition) stays an ordinary call
+        assert!(matches!(
+            program.statements[3],
+            Statement::Expression(Expression::FunctionCall { .. })
+        ));
+    }
+
+    #[test]
+    fn test_alloc_and_realloc_take_one_type_argument() {
+        match parse_statement_from_string("val block := realloc[Integer](old, n * 2)") {
+            Statement::ValDecl {
+                value:
+                    Expression::Allocate {
+                        element,
+                        block: Some(block),
+                        count,
+                    },
+                ..
+            } => {
+                assert_eq!(element, Type::Integer);
+                assert!(matches!(*block, Expression::Identifier(ref name) if name == "old"));
+                assert!(matches!(*count, Expression::BinaryOp { .. }));
+            }
+            other => panic!("Expected a realloc, got {:?}", other),
+        }
+        let alloc = std::panic::catch_unwind(|| {
+            parse_statement_from_string("val b := alloc[Integer](1, 2)")
+        });
+        assert!(alloc.is_err());
+    }
+
+    #[test]
+    fn test_coalesce_groups_to_the_right_and_binds_looser_than_plus() {
+        match parse_statement_from_string("val r := a ?? b ?? 1 + 2") {
+            Statement::ValDecl {
+                value: Expression::Coalesce { value, fallback },
+                ..
+            } => {
+                assert!(matches!(*value, Expression::Identifier(ref name) if name == "a"));
+                match *fallback {
+                    Expression::Coalesce { value, fallback } => {
+                        assert!(matches!(*value, Expression::Identifier(ref name) if name == "b"));
+                        assert!(matches!(*fallback, Expression::BinaryOp { .. }));
+                    }
+                    other => panic!("Expected b ?? 1 + 2, got {:?}", other),
+                }
+            }
+            other => panic!("Expected a coalesce, got {:?}", other),
+        }
+        match parse_statement_from_string("val city := user?.address?.city") {
+            Statement::ValDecl {
+                value: Expression::OptionalFieldAccess { object, field },
+                ..
+            } => {
+                assert_eq!(field, "city");
+                assert!(matches!(
+                    *object,
+                    Expression::OptionalFieldAccess { ref field, .. } if field == "address"
+                ));
+            }
+            other => panic!("Expected an optional field access, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_multi_param_generic_definition() {
         let input = "type Map[K, V] = { keys: Array[K], values: Array[V] }";
@@ -1600,6 +3963,7 @@ mod tests {
                 name,
                 type_params,
                 fields,
+                ..
             } => {
                 assert_eq!(name, "Map");
                 assert_eq!(type_params.len(), 2);
@@ -1636,4 +4000,77 @@ mod tests {
             _ => panic!("Expected TypeDef statement"),
         }
     }
+
+    #[test]
+    fn test_method_declaration_and_calls() {
+        let source = "type Person = { name: String }\nfun (p: Person) greet(greeting: String): String {\n    return greeting + p.name\n}\nval ada := Person { name: \"Ada\" }\nval a := ada.greet(\"Hi \")\nval b := math.max(1, 2)\n";
+        let mut parser = Parser::new(Lexer::new(source.to_string()).tokenize().unwrap());
+        let program = parser.parse().unwrap();
+
+        match &program.statements[1] {
+            Statement::Function {
+                name,
+                receiver: Some(receiver),
+                params,
+                ..
+            } => {
+                assert_eq!(name, "Person_greet");
+                assert_eq!(receiver.name, "p");
+                assert_eq!(receiver.param_type, Type::Custom("Person".to_string()));
+                assert_eq!(params.len(), 1);
+            }
+            other => panic!("Expected a method, got {:?}", other),
+        }
+        // A call on a variable is a method call, on anything else a namespaced call
+        assert!(matches!(
+            &program.statements[3],
+            Statement::ValDecl { value: Expression::MethodCall { object, method, args }, .. }
+                if matches!(&**object, Expression::Identifier(n) if n == "ada")
+                    && method == "greet"
+                    && args.len() == 1
+        ));
+        assert!(matches!(
+            &program.statements[4],
+            Statement::ValDecl {
+                value: Expression::NamespacedFunctionCall { .. },
+                ..
+            }
+        ));
+        assert!(parser
+            .into_symbol_table()
+            .lookup_method("Person", "greet")
+            .is_some());
+    }
+
+    #[test]
+    fn test_interface_and_implements() {
+        let source = "interface Shape {\n    fun area(): Integer\n    fun scaled(factor: Integer): Integer\n}\ntype Square implements Shape, Printable = { side: Integer }\nfun total(shape: Shape): Integer {\n    return shape.area()\n}\n";
+        let program = Parser::new(Lexer::new(source.to_string()).tokenize().unwrap())
+            .parse()
+            .unwrap();
+
+        match &program.statements[0] {
+            Statement::Interface { name, methods } => {
+                assert_eq!(name, "Shape");
+                assert_eq!(methods.len(), 2);
+                assert_eq!(methods[0].name, "area");
+                assert!(methods[0].params.is_empty());
+                assert_eq!(methods[0].return_type, Some(Type::Integer));
+                assert_eq!(methods[1].params[0].name, "factor");
+            }
+            other => panic!("Expected an interface, got {:?}", other),
+        }
+        match &program.statements[1] {
+            Statement::TypeDef { implements, .. } => {
+                assert_eq!(implements, &["Shape", "Printable"]);
+            }
+            other => panic!("Expected a type definition, got {:?}", other),
+        }
+        // The interface is a type, so it can annotate parameters
+        assert!(matches!(
+            &program.statements[2],
+            Statement::Function { params, .. }
+                if params[0].param_type == Type::Custom("Shape".to_string())
+        ));
+    }
 }
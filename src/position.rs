@@ -0,0 +1,170 @@
+//! Mapping between LSP positions and source columns. Editors count a line's
+//! characters in UTF-16 code units (or UTF-8 bytes, if negotiated), while the
+//! lexer's `SourceLocation` counts chars, so the two differ on any line with
+//! non-ASCII text before the position.
+
+use crate::error::SourceLocation;
+
+/// How the `character` of an LSP position is counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Utf8,
+    Utf16,
+}
+
+impl PositionEncoding {
+    /// Pick from the client's `general.positionEncodings`. UTF-8 matches the
+    /// document strings without conversion, so it wins when offered; UTF-16
+    /// is the protocol default every client supports.
+    pub fn negotiate(offered: &[&str]) -> Self {
+        if offered.contains(&"utf-8") {
+            PositionEncoding::Utf8
+        } else {
+            PositionEncoding::Utf16
+        }
+    }
+
+    /// The name used in the `positionEncoding` server capability
+    pub fn name(self) -> &'static str {
+        match self {
+            PositionEncoding::Utf8 => "utf-8",
+            PositionEncoding::Utf16 => "utf-16",
+        }
+    }
+
+    fn units(self, c: char) -> usize {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+        }
+    }
+}
+
+/// Line starts of one document, for converting positions in either direction
+pub struct PositionMapper<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>, // Byte offset of each line
+    encoding: PositionEncoding,
+}
+
+impl<'a> PositionMapper<'a> {
+    pub fn new(text: &'a str, encoding: PositionEncoding) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            text,
+            line_starts,
+            encoding,
+        }
+    }
+
+    /// A line's text without its line ending; empty past the end of the document
+    pub fn line(&self, line: usize) -> &'a str {
+        let Some(&start) = self.line_starts.get(line) else {
+            return "";
+        };
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        let text = &self.text[start..end];
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    /// The 0-based char column of an LSP position's `character`. A position
+    /// inside a character (half a surrogate pair) maps to that character.
+    pub fn column(&self, line: usize, character: usize) -> usize {
+        let mut units = 0;
+        let mut columns = 0;
+        for c in self.line(line).chars() {
+            units += self.encoding.units(c);
+            if units > character {
+                return columns;
+            }
+            columns += 1;
+        }
+        // Past the end of the line every column counts as one unit
+        columns + (character - units.min(character))
+    }
+
+    /// The LSP `character` of a 0-based char column
+    pub fn character(&self, line: usize, column: usize) -> usize {
+        let text = self.line(line);
+        let units: usize = text
+            .chars()
+            .take(column)
+            .map(|c| self.encoding.units(c))
+            .sum();
+        units + column.saturating_sub(text.chars().count())
+    }
+
+    /// The source location (1-based) of an LSP position
+    pub fn location(&self, line: usize, character: usize) -> SourceLocation {
+        SourceLocation {
+            line: line + 1,
+            column: self.column(line, character) + 1,
+        }
+    }
+
+    /// The LSP `(line, character)` of a source location
+    pub fn position(&self, location: &SourceLocation) -> (usize, usize) {
+        let line = location.line.saturating_sub(1);
+        (
+            line,
+            self.character(line, location.column.saturating_sub(1)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_positions_count_surrogate_pairs() {
+        let text = "val s := \"😀é\"\nprint(s)";
+        let mapper = PositionMapper::new(text, PositionEncoding::Utf16);
+        // 😀 takes two UTF-16 units, é one
+        assert_eq!(mapper.character(0, 11), 12);
+        assert_eq!(mapper.character(0, 12), 13);
+        assert_eq!(mapper.column(0, 13), 12);
+        // The second half of the surrogate pair still points at 😀
+        assert_eq!(mapper.column(0, 11), 10);
+        assert_eq!(mapper.column(1, 6), 6);
+    }
+
+    #[test]
+    fn test_utf8_positions_count_bytes() {
+        let text = "é := 1";
+        let mapper = PositionMapper::new(text, PositionEncoding::Utf8);
+        assert_eq!(mapper.character(0, 2), 3);
+        assert_eq!(mapper.column(0, 3), 2);
+        let location = mapper.location(0, 3);
+        assert_eq!((location.line, location.column), (1, 3));
+        assert_eq!(mapper.position(&location), (0, 3));
+    }
+
+    #[test]
+    fn test_line_endings_and_positions_past_the_end() {
+        let mapper = PositionMapper::new("ab\r\nc", PositionEncoding::Utf16);
+        assert_eq!(mapper.line(0), "ab");
+        assert_eq!(mapper.line(1), "c");
+        assert_eq!(mapper.line(2), "");
+        assert_eq!(mapper.column(0, 5), 5);
+        assert_eq!(mapper.character(3, 4), 4);
+    }
+
+    #[test]
+    fn test_negotiate_prefers_utf8() {
+        assert_eq!(
+            PositionEncoding::negotiate(&["utf-16", "utf-8"]),
+            PositionEncoding::Utf8
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(&["utf-32"]),
+            PositionEncoding::Utf16
+        );
+        assert_eq!(PositionEncoding::negotiate(&[]), PositionEncoding::Utf16);
+    }
+}
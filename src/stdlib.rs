@@ -0,0 +1,72 @@
+//! The standard library sources, embedded in the compiler so an installed
+//! `bolt` can resolve `bolt:` imports without a checkout's std/ directory.
+//! A std/ directory next to the program still wins, so edits to it are
+//! picked up during development.
+
+/// Every `std/<name>.bolt`, by module name
+pub const SOURCES: &[(&str, &str)] = &[
+    ("array", include_str!("../std/array.bolt")),
+    ("array_generic", include_str!("../std/array_generic.bolt")),
+    ("fs", include_str!("../std/fs.bolt")),
+    ("io", include_str!("../std/io.bolt")),
+    ("iterate", include_str!("../std/iterate.bolt")),
+    ("map", include_str!("../std/map.bolt")),
+    ("math", include_str!("../std/math.bolt")),
+    ("random", include_str!("../std/random.bolt")),
+    ("simple", include_str!("../std/simple.bolt")),
+    ("stdio", include_str!("../std/stdio.bolt")),
+    ("string", include_str!("../std/string.bolt")),
+    ("test", include_str!("../std/test.bolt")),
+    ("uuid", include_str!("../std/uuid.bolt")),
+];
+
+/// The embedded source of `bolt:<name>`
+pub fn source(name: &str) -> Option<&'static str> {
+    SOURCES
+        .iter()
+        .find(|(module, _)| *module == name)
+        .map(|(_, source)| *source)
+}
+
+/// A hash of all the embedded sources, which tells apart two builds whose
+/// stdlib differs even when they share a version number
+pub fn version() -> String {
+    let all: String = SOURCES
+        .iter()
+        .map(|(name, source)| format!("{}\n{}\n", name, source))
+        .collect();
+    content_hash(&all)[..12].to_string()
+}
+
+// FNV-1a: stable across Rust versions, unlike std's DefaultHasher
+pub fn content_hash(source: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in source.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_every_std_file_is_embedded() {
+        let std_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("std");
+        let mut on_disk: Vec<String> = fs::read_dir(std_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "bolt"))
+            .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+            .collect();
+        on_disk.sort();
+        let embedded: Vec<&str> = SOURCES.iter().map(|(name, _)| *name).collect();
+        assert_eq!(on_disk, embedded);
+        assert!(source("math").unwrap().contains("export fun max"));
+        assert!(source("no_such_module").is_none());
+    }
+}
@@ -1,6 +1,9 @@
 use crate::ast::Type;
 use crate::error::{CompileError, ParseError, SourceLocation};
+#[cfg(feature = "lsp")]
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::fmt;
 
 pub type ScopeId = usize;
 pub type SymbolId = usize;
@@ -133,6 +136,78 @@ impl Symbol {
     pub fn mark_used(&mut self) {
         self.used = true;
     }
+
+    /// Short name of the symbol's kind, e.g. "variable" or "function"
+    pub fn kind_name(&self) -> &'static str {
+        match &self.kind {
+            SymbolKind::Variable { .. } => "variable",
+            SymbolKind::Function { .. } => "function",
+            SymbolKind::Type { .. } => "type",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Import { .. } => "import",
+        }
+    }
+
+    #[cfg(feature = "lsp")]
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "name": self.name,
+            "kind": self.kind_name(),
+            "type": self.symbol_type.to_string(),
+            "scope": self.scope_id,
+            "used": self.used,
+        });
+        if let Some(location) = &self.declared_at {
+            value["line"] = json!(location.line);
+            value["column"] = json!(location.column);
+        }
+        match &self.kind {
+            SymbolKind::Variable { mutable, .. } => value["mutable"] = json!(mutable),
+            SymbolKind::Function { .. } => {
+                if let Some(signature) = FunctionSignature::of(self) {
+                    value["signature"] = json!(signature.to_string());
+                }
+            }
+            SymbolKind::Import { module_path } => value["module"] = json!(module_path),
+            _ => {}
+        }
+        value
+    }
+}
+
+/// A function symbol's name and declared types, e.g. `fun add(Integer, Integer): Integer`
+#[derive(Debug, Clone)]
+pub struct FunctionSignature<'a> {
+    pub name: &'a str,
+    pub params: &'a [Type],
+    pub return_type: Option<&'a Type>,
+}
+
+impl<'a> FunctionSignature<'a> {
+    pub fn of(symbol: &'a Symbol) -> Option<Self> {
+        match &symbol.kind {
+            SymbolKind::Function {
+                params,
+                return_type,
+            } => Some(Self {
+                name: &symbol.name,
+                params,
+                return_type: return_type.as_ref(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FunctionSignature<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = self.params.iter().map(|t| t.to_string()).collect();
+        write!(f, "fun {}({})", self.name, params.join(", "))?;
+        if let Some(return_type) = self.return_type {
+            write!(f, ": {}", return_type)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -142,6 +217,7 @@ pub enum ScopeKind {
     Block,
     ForLoop,
     IfStatement,
+    Lambda,
     Module { path: String },
 }
 
@@ -153,6 +229,8 @@ pub struct Scope {
     pub children: Vec<ScopeId>,
     pub symbols: HashMap<String, SymbolId>,
     pub depth: usize,
+    pub start: Option<SourceLocation>, // Source span, when the parser recorded one
+    pub end: Option<SourceLocation>,
 }
 
 impl Scope {
@@ -165,6 +243,20 @@ impl Scope {
             children: Vec::new(),
             symbols: HashMap::new(),
             depth,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// Whether `location` falls inside this scope's span. The global scope
+    /// (and any scope without a span) contains everything.
+    pub fn contains(&self, location: &SourceLocation) -> bool {
+        match (&self.start, &self.end) {
+            (Some(start), Some(end)) => {
+                let position = (location.line, location.column);
+                (start.line, start.column) <= position && position <= (end.line, end.column)
+            }
+            _ => self.parent.is_none(),
         }
     }
 
@@ -188,6 +280,54 @@ impl Scope {
     }
 }
 
+/// Methods built into every `Array[T]`; `numbers.push(4)` grows `numbers` in place
+pub const ARRAY_METHODS: &[&str] = &["push", "pop", "insert", "get"];
+
+/// bolt:stdio's printing functions; the compiler turns them into printf and
+/// the interpreter writes to its output directly
+pub const PRINT_FUNCTIONS: &[&str] = &["print", "println", "write"];
+
+/// Whether a print function finishes the line; only `write` leaves it open
+pub fn ends_line(print_function: &str) -> bool {
+    print_function != "write"
+}
+
+/// bolt:stdio's formatting functions: `format("x = {}", x)` returns the
+/// String that `printf("x = {}", x)` prints
+pub const FORMAT_FUNCTIONS: &[&str] = &["format", "printf"];
+
+/// bolt:uuid's nanoid() symbols: 64 URL-safe characters, so one random byte
+/// picks one without bias
+pub const NANOID_ALPHABET: &str =
+    "useandom-26T198340PX75pxJACKVERYMINDBUSHWOLF_GQZbfghjklqvwyzrict";
+
+/// The text around the `{}` placeholders of a format string, so one piece
+/// more than there are placeholders; `{{` and `}}` are literal braces
+pub fn format_pieces(format: &str) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+                pieces.push(String::new());
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                pieces.last_mut().unwrap().push(c);
+            }
+            _ => pieces.last_mut().unwrap().push(c),
+        }
+    }
+    pieces
+}
+
+/// The function a method compiles to: `greet` on `Person` is `Person_greet`.
+/// Static functions and constants on a type (`Person.new`) are named the same way.
+pub fn method_function_name(type_name: &str, method: &str) -> String {
+    format!("{}_{}", type_name, method)
+}
+
 #[derive(Debug)]
 pub struct SymbolTable {
     scopes: Vec<Scope>,
@@ -196,6 +336,8 @@ pub struct SymbolTable {
     global_scope: ScopeId,
     next_scope_id: ScopeId,
     next_symbol_id: SymbolId,
+    methods: HashMap<String, HashMap<String, SymbolId>>, // Type name -> method name -> function
+    associated: HashMap<String, HashMap<String, SymbolId>>, // Type name -> static function or constant
 }
 
 impl Default for SymbolTable {
@@ -213,6 +355,8 @@ impl SymbolTable {
             global_scope: 0,
             next_scope_id: 0,
             next_symbol_id: 0,
+            methods: HashMap::new(),
+            associated: HashMap::new(),
         };
 
         // Create global scope
@@ -341,6 +485,87 @@ impl SymbolTable {
         Ok(symbol_id)
     }
 
+    /// Declare `fun (r: Type) method(...)`. It is a function named
+    /// `Type_method` (which is what codegen emits), also registered on the
+    /// type so `lookup_method` finds it.
+    pub fn declare_method(
+        &mut self,
+        type_name: &str,
+        method: &str,
+        params: Vec<Type>,
+        return_type: Option<Type>,
+        location: Option<SourceLocation>,
+    ) -> Result<SymbolId, CompileError> {
+        let symbol_id = self.declare_function(
+            method_function_name(type_name, method),
+            params,
+            return_type,
+            location,
+        )?;
+        self.methods
+            .entry(type_name.to_string())
+            .or_default()
+            .insert(method.to_string(), symbol_id);
+        Ok(symbol_id)
+    }
+
+    pub fn lookup_method(&self, type_name: &str, method: &str) -> Option<&Symbol> {
+        let symbol_id = *self.methods.get(type_name)?.get(method)?;
+        self.get_symbol(symbol_id)
+    }
+
+    /// Declare `fun Person.new(...)`, a function in the type's namespace
+    /// rather than on its values. It is a function named `Person_new`, found
+    /// by `lookup_associated` but not `lookup_method`.
+    pub fn declare_static_function(
+        &mut self,
+        type_name: &str,
+        function: &str,
+        params: Vec<Type>,
+        return_type: Option<Type>,
+        location: Option<SourceLocation>,
+    ) -> Result<SymbolId, CompileError> {
+        let symbol_id = self.declare_function(
+            method_function_name(type_name, function),
+            params,
+            return_type,
+            location,
+        )?;
+        self.associate(type_name, function, symbol_id);
+        Ok(symbol_id)
+    }
+
+    /// Declare `const Limits.MAX := ...`, a constant named `Limits_MAX`
+    pub fn declare_associated_constant(
+        &mut self,
+        type_name: &str,
+        constant: &str,
+        const_type: Type,
+        location: Option<SourceLocation>,
+    ) -> Result<SymbolId, CompileError> {
+        let symbol_id = self.declare_variable(
+            method_function_name(type_name, constant),
+            const_type,
+            false,
+            location,
+        )?;
+        self.associate(type_name, constant, symbol_id);
+        Ok(symbol_id)
+    }
+
+    fn associate(&mut self, type_name: &str, member: &str, symbol_id: SymbolId) {
+        self.associated
+            .entry(type_name.to_string())
+            .or_default()
+            .insert(member.to_string(), symbol_id);
+    }
+
+    /// A static function or constant declared in a type's namespace
+    pub fn lookup_associated(&self, type_name: &str, member: &str) -> Option<&Symbol> {
+        let symbol_id = *self.associated.get(type_name)?.get(member)?;
+        self.get_symbol(symbol_id)
+    }
+
     pub fn declare_type(
         &mut self,
         name: String,
@@ -473,10 +698,100 @@ impl SymbolTable {
             .collect()
     }
 
-    /// Convert symbol table to the legacy HashMap format for compatibility
+    /// Record the source span of a scope, e.g. from `fun` to its closing brace
+    pub fn set_scope_span(&mut self, id: ScopeId, start: SourceLocation, end: SourceLocation) {
+        if let Some(scope) = self.get_scope_mut(id) {
+            scope.start = Some(start);
+            scope.end = Some(end);
+        }
+    }
+
+    /// Look up a symbol by name as seen from `scope_id`, searching outwards
+    pub fn lookup_from(&self, name: &str, scope_id: ScopeId) -> Option<&Symbol> {
+        let mut scope = self.get_scope(scope_id);
+        while let Some(current) = scope {
+            if let Some(symbol_id) = current.get_symbol(name) {
+                return self.get_symbol(symbol_id);
+            }
+            scope = current.parent.and_then(|parent| self.get_scope(parent));
+        }
+        None
+    }
+
+    /// The innermost scope whose span contains `location`
+    pub fn scope_at(&self, location: &SourceLocation) -> ScopeId {
+        let mut scope_id = self.global_scope;
+        while let Some(child) = self.scopes[scope_id]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| {
+                let scope = &self.scopes[child];
+                scope.start.is_some() && scope.contains(location)
+            })
+        {
+            scope_id = child;
+        }
+        scope_id
+    }
+
+    /// Every symbol visible at `location`, innermost first. A shadowed
+    /// outer symbol is left out.
+    pub fn symbols_at(&self, location: &SourceLocation) -> Vec<&Symbol> {
+        let mut visible: Vec<&Symbol> = Vec::new();
+        let mut scope = self.get_scope(self.scope_at(location));
+        while let Some(current) = scope {
+            let mut symbols: Vec<&Symbol> = current
+                .symbols
+                .values()
+                .filter_map(|&id| self.get_symbol(id))
+                .filter(|symbol| !visible.iter().any(|seen| seen.name == symbol.name))
+                .collect();
+            symbols.sort_by_key(|symbol| symbol.name.clone());
+            visible.extend(symbols);
+            scope = current.parent.and_then(|parent| self.get_scope(parent));
+        }
+        visible
+    }
+
+    /// All declared functions with their signatures, in declaration order
+    pub fn functions(&self) -> impl Iterator<Item = FunctionSignature<'_>> {
+        self.symbols.iter().filter_map(FunctionSignature::of)
+    }
+
+    /// Serialize every scope and the symbols declared in it, for tooling
+    #[cfg(feature = "lsp")]
+    pub fn to_json(&self) -> Value {
+        let scopes: Vec<Value> = self
+            .scopes
+            .iter()
+            .map(|scope| {
+                let mut symbols: Vec<&Symbol> = scope
+                    .symbols
+                    .values()
+                    .filter_map(|&id| self.get_symbol(id))
+                    .collect();
+                symbols.sort_by_key(|symbol| symbol.name.clone());
+
+                json!({
+                    "id": scope.id,
+                    "kind": scope_kind_name(&scope.kind),
+                    "parent": scope.parent,
+                    "start": scope.start.as_ref().map(|l| json!({"line": l.line, "column": l.column})),
+                    "end": scope.end.as_ref().map(|l| json!({"line": l.line, "column": l.column})),
+                    "symbols": symbols.iter().map(|symbol| symbol.to_json()).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+
+        json!({ "scopes": scopes })
+    }
+
+    /// Convert symbol table to the legacy HashMap format for compatibility.
+    /// A name shadowed in an inner scope keeps its outermost declaration's type.
     /// TODO: Remove this once all codegen is updated
     pub fn to_legacy_variables(&self) -> HashMap<String, String> {
-        let mut variables = HashMap::new();
+        let mut variables: HashMap<String, (usize, String)> = HashMap::new();
 
         for symbol in &self.symbols {
             if matches!(
@@ -485,16 +800,40 @@ impl SymbolTable {
             ) {
                 let type_str = match &symbol.symbol_type {
                     Type::Integer => "int".to_string(),
+                    Type::Float => "float".to_string(),
                     Type::String => "char*".to_string(),
                     Type::Bool => "bool".to_string(),
                     Type::Custom(name) => name.clone(),
+                    Type::SizedInt(kind) => kind.name().to_string(),
                     _ => "unknown".to_string(),
                 };
-                variables.insert(symbol.name.clone(), type_str);
+                let depth = self.scopes[symbol.scope_id].depth;
+                match variables.get(&symbol.name) {
+                    Some((outer_depth, _)) if *outer_depth < depth => {}
+                    _ => {
+                        variables.insert(symbol.name.clone(), (depth, type_str));
+                    }
+                }
             }
         }
 
         variables
+            .into_iter()
+            .map(|(name, (_, type_str))| (name, type_str))
+            .collect()
+    }
+}
+
+#[cfg(feature = "lsp")]
+fn scope_kind_name(kind: &ScopeKind) -> String {
+    match kind {
+        ScopeKind::Global => "global".to_string(),
+        ScopeKind::Function { name } => format!("function {}", name),
+        ScopeKind::Block => "block".to_string(),
+        ScopeKind::ForLoop => "for".to_string(),
+        ScopeKind::IfStatement => "if".to_string(),
+        ScopeKind::Lambda => "lambda".to_string(),
+        ScopeKind::Module { path } => format!("module {}", path),
     }
 }
 
@@ -642,6 +981,61 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_method_declaration() {
+        let mut symbol_table = SymbolTable::new();
+        symbol_table
+            .declare_method("Person", "greet", vec![], Some(Type::String), None)
+            .unwrap();
+
+        let method = symbol_table.lookup_method("Person", "greet").unwrap();
+        assert_eq!(method.name, "Person_greet");
+        assert!(symbol_table.lookup("Person_greet").is_some());
+        assert!(symbol_table.lookup_method("Person", "wave").is_none());
+        assert!(symbol_table.lookup_method("Robot", "greet").is_none());
+
+        // Two types may have methods with the same name, one type may not
+        symbol_table
+            .declare_method("Robot", "greet", vec![], None, None)
+            .unwrap();
+        assert!(symbol_table
+            .declare_method("Person", "greet", vec![], None, None)
+            .is_err());
+    }
+
+    #[test]
+    fn test_static_functions_and_constants_on_types() {
+        let mut symbol_table = SymbolTable::new();
+        symbol_table
+            .declare_static_function(
+                "Person",
+                "new",
+                vec![Type::String],
+                Some(Type::Custom("Person".to_string())),
+                None,
+            )
+            .unwrap();
+        symbol_table
+            .declare_associated_constant("Person", "MAX_AGE", Type::Integer, None)
+            .unwrap();
+
+        let new = symbol_table.lookup_associated("Person", "new").unwrap();
+        assert_eq!(new.name, "Person_new");
+        assert!(matches!(new.kind, SymbolKind::Function { .. }));
+        assert_eq!(
+            symbol_table
+                .lookup_associated("Person", "MAX_AGE")
+                .unwrap()
+                .name,
+            "Person_MAX_AGE"
+        );
+        // Static functions aren't called on values
+        assert!(symbol_table.lookup_method("Person", "new").is_none());
+        assert!(symbol_table
+            .declare_method("Person", "new", vec![], None, None)
+            .is_err());
+    }
+
     #[test]
     fn test_legacy_variables_conversion() {
         let mut symbol_table = SymbolTable::new();
@@ -663,6 +1057,29 @@ mod tests {
         assert_eq!(legacy.get("flag"), Some(&"bool".to_string()));
     }
 
+    #[test]
+    fn test_block_scopes_shadow_without_leaking() {
+        let mut symbol_table = SymbolTable::new();
+        let outer = symbol_table
+            .declare_variable("x".to_string(), Type::Integer, false, None)
+            .unwrap();
+
+        symbol_table.enter_scope(ScopeKind::Block);
+        let inner = symbol_table
+            .declare_variable("x".to_string(), Type::String, false, None)
+            .unwrap();
+        assert_eq!(symbol_table.lookup("x"), Some(inner));
+        // Beside it in the same block is still a redeclaration
+        assert!(symbol_table
+            .declare_variable("x".to_string(), Type::Bool, false, None)
+            .is_err());
+        symbol_table.exit_scope().unwrap();
+
+        assert_eq!(symbol_table.lookup("x"), Some(outer));
+        let legacy = symbol_table.to_legacy_variables();
+        assert_eq!(legacy.get("x"), Some(&"int".to_string()));
+    }
+
     #[test]
     fn test_unused_symbols() {
         let mut symbol_table = SymbolTable::new();
@@ -681,4 +1098,90 @@ mod tests {
         assert_eq!(unused.len(), 1);
         assert_eq!(unused[0], unused_var);
     }
+
+    fn loc(line: usize, column: usize) -> SourceLocation {
+        SourceLocation { line, column }
+    }
+
+    #[test]
+    fn test_symbols_at_location() {
+        let mut symbol_table = SymbolTable::new();
+        symbol_table
+            .declare_variable("x".to_string(), Type::Integer, true, Some(loc(1, 5)))
+            .unwrap();
+        symbol_table
+            .declare_function(
+                "add".to_string(),
+                vec![Type::Integer, Type::Integer],
+                Some(Type::Integer),
+                Some(loc(2, 5)),
+            )
+            .unwrap();
+
+        let func_scope = symbol_table.enter_scope(ScopeKind::Function {
+            name: "add".to_string(),
+        });
+        symbol_table
+            .declare_parameter("x".to_string(), Type::String, Some(loc(2, 9)))
+            .unwrap();
+        symbol_table.set_scope_span(func_scope, loc(2, 1), loc(4, 1));
+        symbol_table.exit_scope().unwrap();
+
+        // Inside the function the parameter shadows the global
+        assert_eq!(symbol_table.scope_at(&loc(3, 5)), func_scope);
+        let inside = symbol_table.symbols_at(&loc(3, 5));
+        let x = inside.iter().find(|s| s.name == "x").unwrap();
+        assert_eq!(x.symbol_type, Type::String);
+        assert_eq!(inside.iter().filter(|s| s.name == "x").count(), 1);
+        assert!(inside.iter().any(|s| s.name == "add"));
+
+        // Outside it only the globals are visible
+        assert_eq!(
+            symbol_table.scope_at(&loc(6, 1)),
+            symbol_table.global_scope()
+        );
+        let outside = symbol_table.symbols_at(&loc(6, 1));
+        assert_eq!(outside.len(), 2);
+
+        let from_function = symbol_table.lookup_from("x", func_scope).unwrap();
+        assert!(matches!(from_function.kind, SymbolKind::Parameter));
+        assert!(symbol_table.lookup_from("missing", func_scope).is_none());
+    }
+
+    #[test]
+    fn test_function_signatures_and_json() {
+        let mut symbol_table = SymbolTable::new();
+        symbol_table
+            .declare_function(
+                "greet".to_string(),
+                vec![Type::String],
+                None,
+                Some(loc(1, 5)),
+            )
+            .unwrap();
+        symbol_table
+            .declare_variable("count".to_string(), Type::Integer, true, None)
+            .unwrap();
+
+        let signatures: Vec<String> = symbol_table.functions().map(|f| f.to_string()).collect();
+        assert_eq!(signatures, vec!["fun greet(String)"]);
+
+        #[cfg(feature = "lsp")]
+        {
+            let json = symbol_table.to_json();
+            let symbols = json["scopes"][0]["symbols"].as_array().unwrap();
+            assert_eq!(symbols.len(), 2);
+            assert_eq!(symbols[0]["name"], "count");
+            assert_eq!(symbols[0]["mutable"], true);
+            assert_eq!(symbols[1]["signature"], "fun greet(String)");
+            assert_eq!(symbols[1]["line"], 1);
+        }
+    }
+
+    #[test]
+    fn test_format_pieces_split_on_placeholders() {
+        assert_eq!(format_pieces("x = {}, y = {}"), vec!["x = ", ", y = ", ""]);
+        assert_eq!(format_pieces("{{}} {}"), vec!["{} ", ""]);
+        assert_eq!(format_pieces("plain"), vec!["plain"]);
+    }
 }
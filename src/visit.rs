@@ -0,0 +1,354 @@
+//! Read-only traversal of the AST.
+//!
+//! Implement `Visitor` and override the methods for the nodes you care
+//! about; each default walks into the node's children, so an override calls
+//! `walk_statement` / `walk_expression` itself to keep going deeper (or
+//! doesn't, to skip a subtree). The walks cover every variant, so a new
+//! one is visited without changes to existing visitors.
+
+use crate::ast::{Expression, InterpolationPart, Parameter, Program, Statement, Type};
+
+pub trait Visitor<'ast> {
+    fn visit_statement(&mut self, statement: &'ast Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expr: &'ast Expression) {
+        walk_expression(self, expr);
+    }
+
+    // Annotations, parameter and return types, cast targets and type arguments
+    fn visit_type(&mut self, _ty: &'ast Type) {}
+}
+
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program) {
+    walk_statements(visitor, &program.statements);
+}
+
+pub fn walk_statements<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    statements: &'ast [Statement],
+) {
+    for statement in statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+fn walk_signature<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    params: &'ast [Parameter],
+    return_type: &'ast Option<Type>,
+) {
+    for param in params {
+        visitor.visit_type(&param.param_type);
+        if let Some(default) = &param.default {
+            visitor.visit_expression(default);
+        }
+    }
+    if let Some(return_type) = return_type {
+        visitor.visit_type(return_type);
+    }
+}
+
+pub fn walk_statement<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    statement: &'ast Statement,
+) {
+    match statement {
+        Statement::VarDecl {
+            type_annotation,
+            value,
+            ..
+        }
+        | Statement::ValDecl {
+            type_annotation,
+            value,
+            ..
+        }
+        | Statement::ConstDecl {
+            type_annotation,
+            value,
+            ..
+        } => {
+            if let Some(annotation) = type_annotation {
+                visitor.visit_type(annotation);
+            }
+            visitor.visit_expression(value);
+        }
+        Statement::TypeDef { fields, .. } => {
+            for field in fields {
+                visitor.visit_type(&field.field_type);
+            }
+        }
+        Statement::Interface { methods, .. } => {
+            for method in methods {
+                walk_signature(visitor, &method.params, &method.return_type);
+            }
+        }
+        Statement::If {
+            condition: value,
+            then_body,
+            else_body,
+        }
+        | Statement::IfPresent {
+            value,
+            then_body,
+            else_body,
+            ..
+        } => {
+            visitor.visit_expression(value);
+            walk_statements(visitor, then_body);
+            if let Some(else_body) = else_body {
+                walk_statements(visitor, else_body);
+            }
+        }
+        Statement::ForIn {
+            iterable: value,
+            body,
+            ..
+        }
+        | Statement::ForCondition {
+            condition: value,
+            body,
+        } => {
+            visitor.visit_expression(value);
+            walk_statements(visitor, body);
+        }
+        Statement::DoWhile { body, condition } => {
+            walk_statements(visitor, body);
+            visitor.visit_expression(condition);
+        }
+        Statement::ForLoop {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            if let Some(init) = init {
+                visitor.visit_statement(init);
+            }
+            if let Some(condition) = condition {
+                visitor.visit_expression(condition);
+            }
+            if let Some(update) = update {
+                visitor.visit_statement(update);
+            }
+            walk_statements(visitor, body);
+        }
+        Statement::Function {
+            receiver,
+            params,
+            return_type,
+            body,
+            ..
+        } => {
+            if let Some(receiver) = receiver {
+                visitor.visit_type(&receiver.param_type);
+            }
+            walk_signature(visitor, params, return_type);
+            walk_statements(visitor, body);
+        }
+        Statement::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expression(value);
+            }
+        }
+        Statement::Defer { body } | Statement::Unsafe { body } => walk_statements(visitor, body),
+        Statement::Expression(value)
+        | Statement::Assignment { value, .. }
+        | Statement::Panic { message: value, .. } => visitor.visit_expression(value),
+        Statement::Assert {
+            condition, message, ..
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(message);
+        }
+        Statement::NativeBlock { functions, .. } => {
+            for function in functions {
+                walk_signature(visitor, &function.params, &function.return_type);
+            }
+        }
+        Statement::ExternBlock { functions, .. } => {
+            for function in functions {
+                walk_signature(visitor, &function.params, &function.return_type);
+            }
+        }
+        Statement::Import { .. } | Statement::Export { .. } | Statement::Asm { .. } => {}
+    }
+}
+
+pub fn walk_expression<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Expression) {
+    match expr {
+        Expression::ArrayLiteral(items)
+        | Expression::TupleLiteral(items)
+        | Expression::FunctionCall { args: items, .. }
+        | Expression::NamespacedFunctionCall { args: items, .. } => {
+            for item in items {
+                visitor.visit_expression(item);
+            }
+        }
+        Expression::MethodCall { object, args, .. } => {
+            visitor.visit_expression(object);
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::BinaryOp { left, right, .. }
+        | Expression::ArrayAccess {
+            array: left,
+            index: right,
+        }
+        | Expression::Range {
+            start: left,
+            end: right,
+            ..
+        }
+        | Expression::Coalesce {
+            value: left,
+            fallback: right,
+        } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::Slice { object, start, end } => {
+            for part in [object, start, end] {
+                visitor.visit_expression(part);
+            }
+        }
+        Expression::If {
+            condition,
+            then_value,
+            else_value,
+        } => {
+            for part in [condition, then_value, else_value] {
+                visitor.visit_expression(part);
+            }
+        }
+        Expression::StructLiteral {
+            type_args, fields, ..
+        } => {
+            for type_arg in type_args.iter().flatten() {
+                visitor.visit_type(type_arg);
+            }
+            for field in fields {
+                visitor.visit_expression(&field.value);
+            }
+        }
+        Expression::MapLiteral { type_args, entries } => {
+            for type_arg in type_args.iter().flatten() {
+                visitor.visit_type(type_arg);
+            }
+            for (key, value) in entries {
+                visitor.visit_expression(key);
+                visitor.visit_expression(value);
+            }
+        }
+        Expression::StringInterpolation(parts) => {
+            for part in parts {
+                if let InterpolationPart::Expression(value) = part {
+                    visitor.visit_expression(value);
+                }
+            }
+        }
+        Expression::Cast { value, target } => {
+            visitor.visit_expression(value);
+            visitor.visit_type(target);
+        }
+        Expression::Allocate {
+            element,
+            block,
+            count,
+        } => {
+            visitor.visit_type(element);
+            if let Some(block) = block {
+                visitor.visit_expression(block);
+            }
+            visitor.visit_expression(count);
+        }
+        Expression::Lambda {
+            params,
+            return_type,
+            body,
+        } => {
+            walk_signature(visitor, params, return_type);
+            visitor.visit_expression(body);
+        }
+        Expression::NamedArgument { value, .. }
+        | Expression::UnaryOp { operand: value, .. }
+        | Expression::FieldAccess { object: value, .. }
+        | Expression::OptionalFieldAccess { object: value, .. }
+        | Expression::AddressOf { operand: value }
+        | Expression::Dereference { operand: value }
+        | Expression::Grouping(value) => visitor.visit_expression(value),
+        Expression::StringLiteral(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::FloatLiteral(_)
+        | Expression::BoolLiteral(_)
+        | Expression::NoneLiteral
+        | Expression::Identifier(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{BinaryOperator, Parameter};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Program {
+        let tokens = Lexer::new(source.to_string()).tokenize().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    #[derive(Default)]
+    struct Names<'ast> {
+        identifiers: Vec<&'ast str>,
+        types: Vec<String>,
+    }
+
+    impl<'ast> Visitor<'ast> for Names<'ast> {
+        fn visit_expression(&mut self, expr: &'ast Expression) {
+            if let Expression::Identifier(name) = expr {
+                self.identifiers.push(name);
+            }
+            walk_expression(self, expr);
+        }
+
+        fn visit_type(&mut self, ty: &'ast Type) {
+            self.types.push(ty.to_string());
+        }
+    }
+
+    #[test]
+    fn test_walks_reach_nested_statements_and_expressions() {
+        let source = "fun scale(x: Integer, by: Integer = base): Float {\n\
+                      for item in items { total = total + item * by }\n\
+                      return (x ?? fallback) as Float\n\
+                      }";
+        let program = parse(source);
+        let mut names = Names::default();
+        walk_program(&mut names, &program);
+        assert_eq!(
+            names.identifiers,
+            ["base", "items", "total", "item", "by", "x", "fallback"]
+        );
+        assert_eq!(names.types, ["Integer", "Integer", "Float", "Float"]);
+    }
+
+    #[test]
+    fn test_builders_make_the_same_tree_the_parser_does() {
+        let built = Statement::function(
+            "double",
+            vec![Parameter::new("n", Type::Integer)],
+            Some(Type::optional(Type::Integer)),
+            vec![Statement::Return(Some(Expression::binary(
+                Expression::identifier("n"),
+                BinaryOperator::Multiply,
+                Expression::IntegerLiteral(2),
+            )))],
+        );
+        let parsed = parse("fun double(n: Integer): Integer? { return n * 2 }");
+        assert_eq!(format!("{:?}", [built]), format!("{:?}", parsed.statements));
+    }
+}
@@ -0,0 +1,115 @@
+//! Runs every snippet in tests/grammar through `bolt --parse-only` and checks
+//! it against the outcome tag on its first line:
+//!
+//! - `// expect: ok` means the snippet parses
+//! - `// expect: error <text>` means it is rejected with a parse error whose
+//!   message contains <text>
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+enum Expected {
+    Ok,
+    Error(String),
+}
+
+fn corpus() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/grammar");
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Can't read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bolt"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn expected_outcome(path: &Path) -> Expected {
+    let source = fs::read_to_string(path).unwrap();
+    let tag = source
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("// expect:"))
+        .map(str::trim)
+        .unwrap_or_else(|| panic!("{} has no `// expect:` tag", path.display()));
+    match tag.strip_prefix("error") {
+        Some(message) => Expected::Error(message.trim().to_string()),
+        None if tag == "ok" => Expected::Ok,
+        None => panic!("{}: unknown outcome tag '{}'", path.display(), tag),
+    }
+}
+
+#[test]
+fn test_grammar_corpus_matches_expected_outcomes() {
+    let files = corpus();
+    assert!(!files.is_empty(), "The grammar corpus is empty");
+
+    let mut failures = Vec::new();
+    for path in &files {
+        let output = Command::new(env!("CARGO_BIN_EXE_bolt"))
+            .arg("--parse-only")
+            .arg(path)
+            .output()
+            .expect("Failed to run bolt");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let name = path.file_name().unwrap().to_string_lossy();
+
+        match expected_outcome(path) {
+            Expected::Ok if !output.status.success() => failures.push(format!(
+                "{}: expected to parse, got: {}",
+                name,
+                stderr.trim()
+            )),
+            // A crash (e.g. exit code 101) is not a parse error
+            Expected::Error(message)
+                if !stderr.contains("Parse error:") || !stderr.contains(&message) =>
+            {
+                failures.push(format!(
+                    "{}: expected a parse error containing \"{}\", got: {}",
+                    name,
+                    message,
+                    if output.status.success() {
+                        "a successful parse".to_string()
+                    } else {
+                        stderr.trim().to_string()
+                    }
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "Grammar conformance failures:\n{}",
+        failures.join("\n")
+    );
+}
+
+// The corpus files need their tag on the first line, so a script's shebang
+// line is checked here, read from stdin the way `bolt run -` reads it
+#[test]
+fn test_shebang_script_parses_from_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bolt"))
+        .args(["--parse-only", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run bolt");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"#!/usr/bin/env -S bolt run\nval x := 1\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Parsed 1 top-level statements"));
+}
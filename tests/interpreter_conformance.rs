@@ -0,0 +1,169 @@
+//! Runs every program in tests/ with `bolt --interpret` and checks its output
+//! against the same tests/expected file the compiled program is held to.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Programs whose expected output can't match an interpreted run: those that
+// print argv[0] (the executable's path), those that read past the end of
+// an array, where the C output is whatever happened to be in memory, and
+// those using inline asm or extern C functions, which only the C backend
+// can run
+const SKIPPED: &[&str] = &[
+    "array_vs_regular_iteration_test",
+    "extern_c_printf_test",
+    "extern_printf_test",
+    "generic_for_in_test",
+    "getArgs_comprehensive_test",
+    "getArgs_test",
+    "inline_asm_test",
+    "main_args_test",
+    "mixed_features_test",
+];
+
+fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Can't read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bolt"))
+        .filter(|path| !SKIPPED.contains(&path.file_stem().unwrap().to_str().unwrap()))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_interpreter_matches_expected_output() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut failures = Vec::new();
+    let mut checked = 0;
+    for path in programs() {
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        let expected_file = root.join("tests/expected").join(format!("{}.txt", name));
+        let Ok(expected) = fs::read_to_string(&expected_file) else {
+            continue;
+        };
+        let output = Command::new(env!("CARGO_BIN_EXE_bolt"))
+            .current_dir(root)
+            .arg("--interpret")
+            .arg(&path)
+            .output()
+            .expect("Failed to run bolt");
+        checked += 1;
+
+        // Expected files hold the output without its final newline
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            failures.push(format!(
+                "{}: failed: {}",
+                name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        } else if stdout.trim_end_matches('\n') != expected.trim_end_matches('\n') {
+            failures.push(format!("{}: expected\n{}\ngot\n{}", name, expected, stdout));
+        }
+    }
+
+    assert!(checked > 0, "No test programs with expected output");
+    assert!(
+        failures.is_empty(),
+        "Interpreter output differs:\n{}",
+        failures.join("\n")
+    );
+}
+
+// Programs a checked (debug) build stops with a runtime error. The
+// interpreter must stop at the same point, after the same output.
+const RUNTIME_ERRORS: &[(&str, &str)] = &[
+    (
+        "add_overflow",
+        "var n := 2147483646\nfor i in 0..3 {\n    n += 1\n    print(n)\n}\n",
+    ),
+    (
+        "sub_overflow",
+        "fun less(a: Integer, b: Integer): Integer {\n    return a - b\n}\nprint(less(-2147483647, 1))\nprint(less(-2147483647, 2))\n",
+    ),
+    (
+        "mul_overflow",
+        "var n := 1\nfor i in 0..40 {\n    n = n * 2\n}\nprint(n)\n",
+    ),
+    (
+        "negate_overflow",
+        "fun negate(n: Integer): Integer {\n    return -n\n}\nprint(negate(2147483647))\nprint(negate(negate(2147483647) - 1))\n",
+    ),
+    (
+        "divide_overflow",
+        "var low := -2147483647\nlow -= 1\nvar divisor := -1\nprint(low % 2)\nprint(low / divisor)\n",
+    ),
+    (
+        "literal_overflow",
+        "print(-2147483648 % 2)\nprint(-2147483648 / -1)\n",
+    ),
+];
+
+#[test]
+fn test_interpreter_stops_on_the_runtime_errors_of_a_checked_build() {
+    let dir = std::env::temp_dir().join(format!("bolt-conformance-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut failures = Vec::new();
+    for (name, body) in RUNTIME_ERRORS {
+        let source = dir.join(format!("{}.bolt", name));
+        fs::write(
+            &source,
+            format!("import {{ print }} from \"bolt:stdio\"\n{}", body),
+        )
+        .unwrap();
+
+        let build = Command::new(env!("CARGO_BIN_EXE_bolt"))
+            .current_dir(&dir)
+            .arg(&source)
+            .args(["-o", name])
+            .output()
+            .expect("Failed to run bolt");
+        assert!(
+            build.status.success(),
+            "{} doesn't compile: {}",
+            name,
+            String::from_utf8_lossy(&build.stderr)
+        );
+        let compiled = Command::new(dir.join("out/debug").join(name))
+            .output()
+            .expect("Failed to run the compiled program");
+        let interpreted = Command::new(env!("CARGO_BIN_EXE_bolt"))
+            .arg("--interpret")
+            .arg(&source)
+            .output()
+            .expect("Failed to run bolt");
+
+        let stderr =
+            |output: &std::process::Output| String::from_utf8_lossy(&output.stderr).to_lowercase();
+        if compiled.status.success() || interpreted.status.success() {
+            failures.push(format!("{}: both runs should fail", name));
+        } else if compiled.stdout != interpreted.stdout {
+            failures.push(format!(
+                "{}: compiled printed\n{}\ninterpreted printed\n{}",
+                name,
+                String::from_utf8_lossy(&compiled.stdout),
+                String::from_utf8_lossy(&interpreted.stdout)
+            ));
+        } else if !stderr(&compiled).contains("overflow")
+            || !stderr(&interpreted).contains("overflow")
+        {
+            failures.push(format!(
+                "{}: expected an overflow error, got\n{}\n{}",
+                name,
+                stderr(&compiled),
+                stderr(&interpreted)
+            ));
+        }
+    }
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(
+        failures.is_empty(),
+        "Runtime errors differ:\n{}",
+        failures.join("\n")
+    );
+}
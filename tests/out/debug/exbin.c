@@ -0,0 +1,249 @@
+/*
+ * Generated by bolt 0.1.0 (commit 869e65d2c79c) from /root/crate/tests/zero_length_array_test.bolt
+ * at 2026-10-17 10:56:19 UTC
+ */
+
+#include <stdio.h>
+#include <stdint.h>
+#include <string.h>
+#include <stdlib.h>
+#include <stdarg.h>
+#include <time.h>
+#include <limits.h>
+
+#define BOLT_SMALL_STRING 24 // bytes, including the NUL
+#define BOLT_SMALL_SLAB 1024 // slots per slab
+
+typedef struct {
+    char bytes[BOLT_SMALL_STRING];
+} BoltSmallString;
+
+BoltSmallString* bolt_small_slab = NULL;
+int bolt_small_used = BOLT_SMALL_SLAB;
+
+void* bolt_string_alloc(size_t size) {
+    if (size > BOLT_SMALL_STRING) return malloc(size);
+    if (bolt_small_used == BOLT_SMALL_SLAB) {
+        bolt_small_slab = malloc(sizeof(BoltSmallString) * BOLT_SMALL_SLAB);
+        bolt_small_used = 0;
+    }
+    return bolt_small_slab[bolt_small_used++].bytes;
+}
+
+uint32_t bolt_random_state[4];
+int bolt_random_seeded = 0;
+
+uint64_t bolt_splitmix64(uint64_t* x) {
+    uint64_t z = (*x += 0x9E3779B97F4A7C15ULL);
+    z = (z ^ (z >> 30)) * 0xBF58476D1CE4E5B9ULL;
+    z = (z ^ (z >> 27)) * 0x94D049BB133111EBULL;
+    return z ^ (z >> 31);
+}
+
+void bolt_random_seed(uint64_t seed) {
+    uint64_t low = bolt_splitmix64(&seed);
+    uint64_t high = bolt_splitmix64(&seed);
+    bolt_random_state[0] = (uint32_t)low;
+    bolt_random_state[1] = (uint32_t)(low >> 32);
+    bolt_random_state[2] = (uint32_t)high;
+    bolt_random_state[3] = (uint32_t)(high >> 32);
+    bolt_random_seeded = 1;
+}
+
+uint32_t bolt_random_next(void) {
+    if (!bolt_random_seeded) {
+        bolt_random_seed((uint64_t)time(NULL) ^ (uint64_t)clock() ^ (uint64_t)(uintptr_t)&bolt_random_seeded);
+    }
+    uint32_t* s = bolt_random_state;
+    uint32_t product = s[1] * 5;
+    uint32_t result = ((product << 7) | (product >> 25)) * 9;
+    uint32_t t = s[1] << 9;
+    s[2] ^= s[0];
+    s[3] ^= s[1];
+    s[1] ^= s[2];
+    s[0] ^= s[3];
+    s[2] ^= t;
+    s[3] = (s[3] << 11) | (s[3] >> 21);
+    return result;
+}
+
+// Uniform in 0..bound-1 for bound <= 2^32, by multiplying instead of %
+uint32_t bolt_random_below(uint64_t bound) {
+    return (uint32_t)(((uint64_t)bolt_random_next() * bound) >> 32);
+}
+
+// Uniform in [0, 1) from 53 bits, all a double holds; two statements so
+// the draws happen in a fixed order
+double bolt_random_float(void) {
+    uint64_t high = bolt_random_next() >> 5;
+    uint64_t low = bolt_random_next() >> 6;
+    return (double)((high << 26) | low) / 9007199254740992.0;
+}
+
+// Bytes from the operating system, for identifiers that must not repeat
+// across runs; the generator above fills in if they can't be read
+void bolt_random_bytes(unsigned char* bytes, size_t count) {
+    FILE* source = fopen("/dev/urandom", "rb");
+    size_t filled = source ? fread(bytes, 1, count, source) : 0;
+    if (source) fclose(source);
+    for (size_t i = filled; i < count; i++) bytes[i] = (unsigned char)bolt_random_next();
+}
+
+_Noreturn void bolt_exit(int status) {
+    exit(status);
+}
+
+_Noreturn void bolt_panic(const char* location, const char* kind, const char* message) {
+    fflush(stdout);
+    fprintf(stderr, "%s: %s: %s\n", location, kind, message);
+    exit(1);
+}
+
+#ifdef DEBUG
+int bolt_add_int(int a, int b, const char* location) {
+    int result;
+    if (__builtin_add_overflow(a, b, &result)) bolt_panic(location, "runtime error", "integer overflow");
+    return result;
+}
+
+int bolt_sub_int(int a, int b, const char* location) {
+    int result;
+    if (__builtin_sub_overflow(a, b, &result)) bolt_panic(location, "runtime error", "integer overflow");
+    return result;
+}
+
+int bolt_mul_int(int a, int b, const char* location) {
+    int result;
+    if (__builtin_mul_overflow(a, b, &result)) bolt_panic(location, "runtime error", "integer overflow");
+    return result;
+}
+
+int bolt_div_int(int a, int b, const char* location) {
+    if (b == 0) bolt_panic(location, "runtime error", "division by zero");
+    if (a == INT_MIN && b == -1) bolt_panic(location, "runtime error", "integer overflow");
+    return a / b;
+}
+
+int bolt_mod_int(int a, int b, const char* location) {
+    if (b == 0) bolt_panic(location, "runtime error", "division by zero");
+    if (a == INT_MIN && b == -1) bolt_panic(location, "runtime error", "integer overflow");
+    return a % b;
+}
+
+_Noreturn void bolt_index_error(long long index, long long length, const char* location) {
+    char message[96];
+    snprintf(message, sizeof message, "index %lld is out of bounds for length %lld", index, length);
+    bolt_panic(location, "runtime error", message);
+}
+
+#define bolt_checked(operation, op, a, b) ({ __auto_type _bolt_left = (a); __auto_type _bolt_right = (b); _Generic(_bolt_left op _bolt_right, int: bolt_##operation##_int(_bolt_left, _bolt_right, __func__), default: _bolt_left op _bolt_right); })
+#define bolt_add(a, b) bolt_checked(add, +, a, b)
+#define bolt_sub(a, b) bolt_checked(sub, -, a, b)
+#define bolt_mul(a, b) bolt_checked(mul, *, a, b)
+#define bolt_div(a, b) bolt_checked(div, /, a, b)
+#define bolt_mod(a, b) bolt_checked(mod, %, a, b)
+#define bolt_neg(a) bolt_sub(0, a)
+#define bolt_index(index, length) ({ __auto_type _bolt_at = (index); if (_bolt_at < 0 || _bolt_at >= (length)) bolt_index_error(_bolt_at, (length), __func__); _bolt_at; })
+#define bolt_non_null(pointer) ({ __auto_type _bolt_pointer = (pointer); if (_bolt_pointer == NULL) bolt_panic(__func__, "runtime error", "null pointer dereference"); _bolt_pointer; })
+#else
+#define bolt_add(a, b) ((a) + (b))
+#define bolt_sub(a, b) ((a) - (b))
+#define bolt_mul(a, b) ((a) * (b))
+#define bolt_div(a, b) ((a) / (b))
+#define bolt_mod(a, b) ((a) % (b))
+#define bolt_neg(a) (-(a))
+#define bolt_index(index, length) (index)
+#define bolt_non_null(pointer) (pointer)
+#endif
+
+char* string_concat(const char* str1, const char* str2) {
+    size_t len1 = strlen(str1);
+    size_t len2 = strlen(str2);
+    char* result = bolt_string_alloc(len1 + len2 + 1);
+    strcpy(result, str1);
+    strcat(result, str2);
+    return result;
+}
+
+char* bolt_format(const char* format, ...) {
+    va_list args;
+    va_start(args, format);
+    va_list measure;
+    va_copy(measure, args);
+    int length = vsnprintf(NULL, 0, format, measure);
+    va_end(measure);
+    char* result = bolt_string_alloc(length + 1);
+    vsnprintf(result, length + 1, format, args);
+    va_end(args);
+    return result;
+}
+
+char* string_slice(const char* s, int start, int end) {
+    int length = (int)strlen(s);
+#ifdef DEBUG
+    if (start < 0 || end > length || start > end) {
+        fprintf(stderr, "Slice [%d..%d] is out of bounds for length %d\n", start, end, length);
+        exit(1);
+    }
+#endif
+    if (start < 0) start = 0;
+    if (end > length) end = length;
+    if (end < start) end = start;
+    char* result = bolt_string_alloc(end - start + 1);
+    memcpy(result, s + start, end - start);
+    result[end - start] = '\0';
+    return result;
+}
+
+char* toString(int value) {
+    char digits[16]; // enough for any 32-bit int
+    int length = snprintf(digits, sizeof digits, "%d", value);
+    char* result = bolt_string_alloc(length + 1);
+    memcpy(result, digits, length + 1);
+    return result;
+}
+
+char* floatToString(double value) {
+    int length = snprintf(NULL, 0, "%f", value);
+    char* result = bolt_string_alloc(length + 1);
+    snprintf(result, length + 1, "%f", value);
+    return result;
+}
+
+int bolt_argc;
+char** bolt_argv;
+
+char** getArgs() {
+    return bolt_argv;
+}
+
+int getArgsLength() {
+    return bolt_argc;
+}
+
+typedef struct {
+    int* data;
+    int length;
+    int capacity;
+} Array_Integer;
+
+int main(int argc, char* argv[]) {
+    bolt_argc = argc;
+    bolt_argv = argv;
+    printf("%s\n", "Testing zero-length arrays:");
+    int dummy = 0;
+    Array_Integer emptyIntArray = ((Array_Integer) {.data = (&dummy), .length = 0, .capacity = 0});
+    printf("%s\n", "Empty array length:");
+    printf("%d\n", emptyIntArray.length);
+    printf("%s\n", "Iterating empty array:");
+    int count = 0;
+    for (int _bolt_i_0 = 0; _bolt_i_0 < emptyIntArray.length; _bolt_i_0++) {
+        int item = emptyIntArray.data[_bolt_i_0];
+        count = bolt_add(count, 1);
+        printf("%s\n", "Should not print");
+    }
+    printf("%s\n", "Items processed:");
+    printf("%d\n", count);
+    printf("%s\n", "Test completed successfully");
+    return 0;
+}